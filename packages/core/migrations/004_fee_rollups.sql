@@ -0,0 +1,15 @@
+-- Migration 004: Fee rollups
+-- Per-bucket aggregates of fee_data_points. Each row carries a JSON-encoded
+-- quantile sketch so percentiles for long ranges can be answered by merging
+-- rollups instead of re-reading raw points.
+
+CREATE TABLE IF NOT EXISTS fee_rollups (
+    resolution   TEXT    NOT NULL,  -- bucket width, e.g. '1m'
+    bucket_start TEXT    NOT NULL,  -- RFC 3339, aligned to the resolution
+    sample_count INTEGER NOT NULL,
+    fee_sum      INTEGER NOT NULL,
+    min_fee      INTEGER NOT NULL,
+    max_fee      INTEGER NOT NULL,
+    sketch       TEXT    NOT NULL,
+    PRIMARY KEY (resolution, bucket_start)
+);
@@ -0,0 +1,20 @@
+-- Migration 005: Fee spikes
+-- One row per detected spike, upserted on every lifecycle event so the row
+-- always holds the latest peak and duration. end_time is NULL while open.
+
+CREATE TABLE IF NOT EXISTS fee_spikes (
+    id            TEXT    PRIMARY KEY,
+    severity      TEXT    NOT NULL,
+    strategy      TEXT    NOT NULL,
+    score         REAL    NOT NULL,
+    peak_fee      INTEGER NOT NULL,
+    baseline_fee  REAL    NOT NULL,
+    spike_ratio   REAL    NOT NULL,
+    start_time    TEXT    NOT NULL,
+    end_time      TEXT,
+    duration_secs INTEGER NOT NULL,
+    updated_at    TEXT    NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_fee_spikes_start_time
+    ON fee_spikes (start_time);
@@ -0,0 +1,12 @@
+-- Migration 006: Fee extreme periods
+-- Closed ExtremesTracker periods, reloaded at startup so min/max history
+-- survives restarts. lowest/highest hold JSON arrays of the top-N values.
+
+CREATE TABLE IF NOT EXISTS fee_extreme_periods (
+    period_start TEXT    PRIMARY KEY,
+    period_end   TEXT    NOT NULL,
+    min_fee      INTEGER NOT NULL,
+    max_fee      INTEGER NOT NULL,
+    lowest       TEXT    NOT NULL,
+    highest      TEXT    NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Migration 007: Engine snapshots
+-- Latest serialized state of the insights engine, keyed by name so a single
+-- row is replaced on every save. payload is the JSON-encoded snapshot.
+
+CREATE TABLE IF NOT EXISTS engine_snapshots (
+    name     TEXT    PRIMARY KEY,
+    version  INTEGER NOT NULL,
+    taken_at TEXT    NOT NULL,
+    payload  TEXT    NOT NULL
+);
@@ -0,0 +1,9 @@
+-- Migration 008: Insights snapshots
+-- Computed insights recorded alongside each engine snapshot so historical
+-- `?at=` queries can be answered without replaying raw points.
+-- payload is the JSON-encoded CurrentInsights.
+
+CREATE TABLE IF NOT EXISTS insights_snapshots (
+    taken_at TEXT PRIMARY KEY,
+    payload  TEXT NOT NULL
+);
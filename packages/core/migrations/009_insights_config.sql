@@ -0,0 +1,21 @@
+-- Migration 009: Runtime insights configuration
+-- insights_config holds the configuration applied through the admin API so
+-- it survives restarts; insights_config_changes is the append-only audit
+-- trail of every change. Configs are JSON-encoded InsightsConfig values.
+
+CREATE TABLE IF NOT EXISTS insights_config (
+    name       TEXT PRIMARY KEY,
+    updated_at TEXT NOT NULL,
+    payload    TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS insights_config_changes (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    changed_at      TEXT NOT NULL,
+    actor           TEXT,
+    previous_config TEXT NOT NULL,
+    new_config      TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_insights_config_changes_changed_at
+    ON insights_config_changes (changed_at);
@@ -0,0 +1,36 @@
+-- Migration 010: Open and close fees on rollups
+-- The first and last point of each bucket, so rollups can be served as
+-- OHLC candles. Ties on the timestamp keep the point merged last.
+
+ALTER TABLE fee_rollups ADD COLUMN open_fee  INTEGER;
+ALTER TABLE fee_rollups ADD COLUMN open_at   TEXT;
+ALTER TABLE fee_rollups ADD COLUMN close_fee INTEGER;
+ALTER TABLE fee_rollups ADD COLUMN close_at  TEXT;
+
+-- Backfill the 1m buckets that raw points still cover
+UPDATE fee_rollups
+SET open_at = (
+        SELECT p.timestamp FROM fee_data_points p
+        WHERE p.timestamp >= fee_rollups.bucket_start
+          AND p.timestamp < strftime('%Y-%m-%dT%H:%M:%S+00:00', fee_rollups.bucket_start, '+1 minute')
+        ORDER BY p.timestamp ASC, p.id ASC LIMIT 1
+    ),
+    open_fee = (
+        SELECT p.fee_amount FROM fee_data_points p
+        WHERE p.timestamp >= fee_rollups.bucket_start
+          AND p.timestamp < strftime('%Y-%m-%dT%H:%M:%S+00:00', fee_rollups.bucket_start, '+1 minute')
+        ORDER BY p.timestamp ASC, p.id ASC LIMIT 1
+    ),
+    close_at = (
+        SELECT p.timestamp FROM fee_data_points p
+        WHERE p.timestamp >= fee_rollups.bucket_start
+          AND p.timestamp < strftime('%Y-%m-%dT%H:%M:%S+00:00', fee_rollups.bucket_start, '+1 minute')
+        ORDER BY p.timestamp DESC, p.id DESC LIMIT 1
+    ),
+    close_fee = (
+        SELECT p.fee_amount FROM fee_data_points p
+        WHERE p.timestamp >= fee_rollups.bucket_start
+          AND p.timestamp < strftime('%Y-%m-%dT%H:%M:%S+00:00', fee_rollups.bucket_start, '+1 minute')
+        ORDER BY p.timestamp DESC, p.id DESC LIMIT 1
+    )
+WHERE resolution = '1m';
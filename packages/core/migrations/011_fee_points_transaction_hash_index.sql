@@ -0,0 +1,6 @@
+-- Migration 011: Transaction hash index
+-- Imports skip points whose transaction hash is already stored, looking
+-- each batch up by hash.
+
+CREATE INDEX IF NOT EXISTS idx_fee_data_points_transaction_hash
+    ON fee_data_points (transaction_hash);
@@ -1 +1,160 @@
+//! Fee spike alerts.
+//!
+//! Alert rules fire once per spike, the first time its severity reaches
+//! the rule's threshold. The poller evaluates every spike event against
+//! the enabled rules and hands each alert to [`webhook::dispatch`]; the
+//! backtest harness uses the same matching to count alerts in a replay.
+
 pub mod webhook;
+
+use crate::insights::types::{FeeSpike, SpikeSeverity};
+use crate::live::LiveFeed;
+use crate::repository::{AlertConfig, FeeRepository};
+
+use self::webhook::AlertPayload;
+
+/// Whether an alert rule fires for a spike: its threshold is at or below
+/// the spike's severity.
+pub fn rule_matches(rule: &AlertConfig, severity: &SpikeSeverity) -> bool {
+    SpikeSeverity::from_name(&rule.threshold)
+        .map(|threshold| severity_rank(severity) >= severity_rank(&threshold))
+        .unwrap_or(false)
+}
+
+/// Raise every rule's threshold to at least `min_severity`, the configured
+/// floor below which no alert fires. Rules already at or above it are kept.
+pub fn apply_min_severity(rules: Vec<AlertConfig>, min_severity: Option<&SpikeSeverity>) -> Vec<AlertConfig> {
+    let Some(min_severity) = min_severity else {
+        return rules;
+    };
+    rules
+        .into_iter()
+        .map(|mut rule| {
+            let below = SpikeSeverity::from_name(&rule.threshold)
+                .is_some_and(|threshold| severity_rank(&threshold) < severity_rank(min_severity));
+            if below {
+                rule.threshold = min_severity.as_str().to_string();
+            }
+            rule
+        })
+        .collect()
+}
+
+fn severity_rank(severity: &SpikeSeverity) -> u8 {
+    match severity {
+        SpikeSeverity::Minor => 0,
+        SpikeSeverity::Moderate => 1,
+        SpikeSeverity::Major => 2,
+        SpikeSeverity::Critical => 3,
+    }
+}
+
+/// Dispatch an alert for every enabled rule that `spike` now matches but
+/// did not at `previous`, its severity before this event (`None` for a new
+/// spike). Returns the number of alerts dispatched.
+pub async fn alert_spike(
+    spike: &FeeSpike,
+    previous: Option<&SpikeSeverity>,
+    rules: &[AlertConfig],
+    repository: &FeeRepository,
+    live: Option<&LiveFeed>,
+) -> usize {
+    let mut dispatched = 0;
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        let fired_before = previous.is_some_and(|severity| rule_matches(rule, severity));
+        if fired_before || !rule_matches(rule, &spike.severity) {
+            continue;
+        }
+        let payload = AlertPayload {
+            config_id: Some(rule.id),
+            severity: spike.severity.as_str().to_string(),
+            peak_fee: spike.peak_fee as i64,
+            baseline_fee: spike.baseline_fee,
+            spike_ratio: spike.spike_ratio,
+            webhook_url: rule.webhook_url.clone(),
+        };
+        webhook::dispatch(payload, repository, live).await;
+        dispatched += 1;
+    }
+    dispatched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    use crate::db::create_pool;
+    use crate::live::Topic;
+
+    fn rule(id: i64, threshold: &str, enabled: bool) -> AlertConfig {
+        AlertConfig {
+            id,
+            webhook_url: format!("https://hooks.example.com/{}", id),
+            threshold: threshold.to_string(),
+            enabled,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    fn spike(severity: SpikeSeverity) -> FeeSpike {
+        FeeSpike {
+            id: "spk_1".to_string(),
+            peak_fee: 5_000,
+            baseline_fee: 100.0,
+            spike_ratio: 50.0,
+            start_time: Utc::now(),
+            duration: Duration::seconds(30),
+            severity,
+            strategy: "threshold".to_string(),
+            score: 50.0,
+            end_time: None,
+        }
+    }
+
+    #[test]
+    fn alert_rules_fire_at_or_above_their_threshold() {
+        assert!(rule_matches(&rule(1, "Minor", true), &SpikeSeverity::Moderate));
+        assert!(rule_matches(&rule(1, "Major", true), &SpikeSeverity::Critical));
+        assert!(!rule_matches(&rule(1, "Major", true), &SpikeSeverity::Moderate));
+        assert!(!rule_matches(&rule(1, "bogus", true), &SpikeSeverity::Critical));
+    }
+
+    #[test]
+    fn min_severity_raises_lower_rule_thresholds() {
+        let rules = vec![rule(1, "Minor", true), rule(2, "Critical", true)];
+        let raised = apply_min_severity(rules.clone(), Some(&SpikeSeverity::Major));
+        assert_eq!(raised[0].threshold, "Major");
+        assert_eq!(raised[1].threshold, "Critical");
+        assert!(!rule_matches(&raised[0], &SpikeSeverity::Moderate));
+        assert_eq!(apply_min_severity(rules, None)[0].threshold, "Minor");
+    }
+
+    #[tokio::test]
+    async fn each_rule_fires_once_as_severity_rises() {
+        let repo = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let live = LiveFeed::new("testnet");
+        let minor = repo.insert_alert_config("https://hooks.example.com/minor", "Minor").await.unwrap();
+        let major = repo.insert_alert_config("https://hooks.example.com/major", "Major").await.unwrap();
+        let disabled = repo.insert_alert_config("https://hooks.example.com/off", "Minor").await.unwrap();
+        repo.update_alert_config(disabled, "Minor", false).await.unwrap();
+        let rules = repo.list_alert_configs().await.unwrap();
+
+        let opened = spike(SpikeSeverity::Moderate);
+        assert_eq!(alert_spike(&opened, None, &rules, &repo, Some(&live)).await, 1);
+
+        let escalated = spike(SpikeSeverity::Critical);
+        let fired = alert_spike(&escalated, Some(&opened.severity), &rules, &repo, Some(&live)).await;
+        assert_eq!(fired, 1);
+
+        let history = repo.query_alert_history(10, None, None).await.unwrap();
+        let mut fired_rules: Vec<_> = history.iter().filter_map(|event| event.config_id).collect();
+        fired_rules.sort();
+        assert_eq!(fired_rules, vec![minor, major]);
+
+        // Subscribers see the alert but never the webhook target
+        let published = live.snapshot(Topic::Alerts).unwrap().data;
+        assert_eq!(published["severity"], "Critical");
+        assert!(published.get("webhook_url").is_none());
+    }
+}
@@ -5,13 +5,13 @@
 //! persisted in the `alert_events` table via [`FeeRepository::log_alert_event`].
 //!
 //! This module is the integration point for Issue #31 (webhook delivery) and
-//! Issue #32 (alert history). The `dispatch` function is called by the
-//! scheduler / insights engine whenever a spike crosses an alert threshold.
-
-use std::sync::Arc;
+//! Issue #32 (alert history). The `dispatch` function is called through
+//! [`alert_spike`](super::alert_spike) whenever a spike crosses an alert
+//! threshold.
 
 use chrono::Utc;
 
+use crate::live::LiveFeed;
 use crate::repository::{AlertEvent, FeeRepository};
 
 /// Payload describing a triggered fee-spike alert.
@@ -31,15 +31,17 @@ pub struct AlertPayload {
     pub webhook_url: String,
 }
 
-/// Dispatch a webhook notification and log the outcome to the database.
+/// Dispatch a webhook notification, log the outcome to the database and
+/// publish it to the live feed's `alerts` topic when one is given.
 ///
 /// The HTTP client (`reqwest`) is not yet wired up in this stub — the
 /// `delivered` flag defaults to `false` until Issue #31 lands and the full
 /// HTTP POST is implemented.  The repository logging is fully functional.
-pub async fn dispatch(payload: AlertPayload, repository: Arc<FeeRepository>) {
+pub async fn dispatch(payload: AlertPayload, repository: &FeeRepository, live: Option<&LiveFeed>) {
     // TODO (Issue #31): perform the actual HTTP POST here and capture success.
     let delivered = false;
 
+    let triggered_at = Utc::now();
     let event = AlertEvent {
         id: None,
         config_id: payload.config_id,
@@ -49,7 +51,7 @@ pub async fn dispatch(payload: AlertPayload, repository: Arc<FeeRepository>) {
         spike_ratio: payload.spike_ratio,
         webhook_url: payload.webhook_url.clone(),
         delivered,
-        triggered_at: Utc::now().to_rfc3339(),
+        triggered_at: triggered_at.to_rfc3339(),
     };
 
     if let Err(err) = repository.log_alert_event(&event).await {
@@ -59,6 +61,9 @@ pub async fn dispatch(payload: AlertPayload, repository: Arc<FeeRepository>) {
             err
         );
     }
+    if let Some(live) = live {
+        live.publish_alert(&event, triggered_at);
+    }
 }
 
 #[cfg(test)]
@@ -69,7 +74,7 @@ mod tests {
     #[tokio::test]
     async fn dispatch_logs_event_to_database() {
         let pool = create_pool("sqlite::memory:").await.unwrap();
-        let repo = Arc::new(FeeRepository::new(pool));
+        let repo = FeeRepository::new(pool);
 
         let payload = AlertPayload {
             config_id: None,
@@ -80,7 +85,7 @@ mod tests {
             webhook_url: "https://hooks.example.com/test".to_string(),
         };
 
-        dispatch(payload, repo.clone()).await;
+        dispatch(payload, &repo, None).await;
 
         let events = repo.query_alert_history(10, None, None).await.unwrap();
         assert_eq!(events.len(), 1);
@@ -0,0 +1,452 @@
+//! Admin endpoints for changing the running service.
+//!
+//! Every route requires the configured admin key in the `X-Api-Key`
+//! header; without `ADMIN_API_KEY` set the routes answer 503.
+//!
+//! Routes:
+//! - `GET /admin/insights/config`         — the active insights configuration
+//! - `PUT /admin/insights/config`         — validate, persist and hot-swap a new one
+//! - `GET /admin/insights/config/history` — audit trail of changes, newest first
+//! - `POST /admin/import`                 — load fee data points from CSV or NDJSON
+
+use std::io::BufReader;
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+use utoipa::IntoParams;
+
+use crate::api::openapi::ErrorResponse;
+use crate::clock::SharedClock;
+use crate::config::Secret;
+use crate::import::{self, ImportError, ImportFormat, ImportReport};
+use crate::insights::{FeeInsightsEngine, InsightsConfig};
+use crate::repository::{FeeRepository, InsightsConfigChange};
+use crate::scheduler::rebuild_windows_from_rollups;
+
+/// Header carrying the admin key
+pub const API_KEY_HEADER: &str = "x-api-key";
+/// Optional header naming who made a change, recorded in the audit trail
+pub const ACTOR_HEADER: &str = "x-admin-actor";
+
+/// Default and maximum number of audit entries returned
+const DEFAULT_HISTORY_LIMIT: i64 = 50;
+const MAX_HISTORY_LIMIT: i64 = 500;
+
+/// Largest body `POST /admin/import` accepts; the `import` command has no limit
+const MAX_IMPORT_BYTES: usize = 256 * 1024 * 1024;
+
+/// Shared state for the admin API
+pub type AdminState = Arc<AdminApiState>;
+
+pub struct AdminApiState {
+    pub insights_engine: Arc<RwLock<FeeInsightsEngine>>,
+    pub repository: Arc<FeeRepository>,
+    pub api_key: Option<Secret>,
+    pub clock: SharedClock,
+}
+
+type ApiError = (StatusCode, Json<Value>);
+
+/// Create the admin API router
+pub fn create_admin_router(state: AdminApiState) -> Router {
+    Router::new()
+        .route(
+            "/admin/insights/config",
+            get(get_insights_config).put(put_insights_config),
+        )
+        .route("/admin/insights/config/history", get(get_insights_config_history))
+        .route(
+            "/admin/import",
+            post(import_fee_data).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+        )
+        .with_state(Arc::new(state))
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (status, Json(json!({ "error": message.into() })))
+}
+
+/// Check the `X-Api-Key` header against the configured admin key.
+fn authorize(state: &AdminApiState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let expected = state.api_key.as_ref().ok_or_else(|| {
+        error(StatusCode::SERVICE_UNAVAILABLE, "Admin API is disabled; set ADMIN_API_KEY")
+    })?;
+    let given = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    if constant_time_eq(given.as_bytes(), expected.expose().as_bytes()) {
+        Ok(())
+    } else {
+        Err(error(StatusCode::UNAUTHORIZED, "Missing or invalid API key"))
+    }
+}
+
+/// Who made a change, from the optional actor header.
+fn actor(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(ACTOR_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// `GET /admin/insights/config`
+#[utoipa::path(
+    get, path = "/admin/insights/config",
+    tag = "admin",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "The running insights configuration", body = InsightsConfig),
+        (status = 401, description = "Missing or invalid API key", body = ErrorResponse),
+        (status = 503, description = "Admin API is disabled", body = ErrorResponse),
+    )
+)]
+async fn get_insights_config(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+) -> Result<Json<InsightsConfig>, ApiError> {
+    authorize(&state, &headers)?;
+    let engine = state.insights_engine.read().await;
+    Ok(Json(engine.get_config().clone()))
+}
+
+/// `PUT /admin/insights/config`
+///
+/// The body is a complete `InsightsConfig`, as returned by `GET`. It is
+/// validated (422 on failure), recorded in the audit trail and stored so
+/// it is used again after a restart, then swapped into the running engine.
+/// The engine's write lock is held throughout, so concurrent changes apply
+/// one after the other and readers never see a half-applied config.
+#[utoipa::path(
+    put, path = "/admin/insights/config",
+    tag = "admin",
+    security(("api_key" = [])),
+    request_body = InsightsConfig,
+    params(("X-Admin-Actor" = Option<String>, Header, description = "Who is making the change, for the audit trail")),
+    responses(
+        (status = 200, description = "The recorded change", body = InsightsConfigChange),
+        (status = 401, description = "Missing or invalid API key", body = ErrorResponse),
+        (status = 422, description = "Invalid configuration", body = ErrorResponse),
+        (status = 503, description = "Admin API is disabled", body = ErrorResponse),
+    )
+)]
+async fn put_insights_config(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Json(config): Json<InsightsConfig>,
+) -> Result<Json<InsightsConfigChange>, ApiError> {
+    authorize(&state, &headers)?;
+    config
+        .validate()
+        .map_err(|e| error(StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    let now = state.clock.now();
+    let actor = actor(&headers);
+
+    let mut engine = state.insights_engine.write().await;
+    let previous = engine.get_config().clone();
+    let change = state
+        .repository
+        .save_insights_config(&previous, &config, actor, now)
+        .await
+        .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let uncovered = engine
+        .reconfigure(config)
+        .map_err(|e| error(StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+    // New or lengthened windows are filled from rollups, so they are not
+    // left partial until fresh data fills them
+    rebuild_windows_from_rollups(&mut engine, &state.repository, &uncovered).await;
+
+    tracing::info!(
+        "Insights configuration changed (audit #{}{})",
+        change.id,
+        actor.map(|a| format!(" by {}", a)).unwrap_or_default()
+    );
+
+    Ok(Json(change))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ConfigHistoryQuery {
+    pub limit: Option<i64>,
+}
+
+/// `GET /admin/insights/config/history`
+#[utoipa::path(
+    get, path = "/admin/insights/config/history",
+    tag = "admin",
+    security(("api_key" = [])),
+    params(ConfigHistoryQuery),
+    responses(
+        (status = 200, description = "Configuration changes, newest first", body = Vec<InsightsConfigChange>),
+        (status = 401, description = "Missing or invalid API key", body = ErrorResponse),
+        (status = 503, description = "Admin API is disabled", body = ErrorResponse),
+    )
+)]
+async fn get_insights_config_history(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Query(params): Query<ConfigHistoryQuery>,
+) -> Result<Json<Vec<InsightsConfigChange>>, ApiError> {
+    authorize(&state, &headers)?;
+    let limit = params.limit.unwrap_or(DEFAULT_HISTORY_LIMIT).clamp(1, MAX_HISTORY_LIMIT);
+
+    state
+        .repository
+        .list_insights_config_changes(limit)
+        .await
+        .map(Json)
+        .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    pub format: Option<ImportFormat>,
+}
+
+/// `POST /admin/import`
+///
+/// The body is fee data points as CSV or NDJSON, by `format` or else by
+/// its Content-Type, and may be sent with `Content-Encoding: gzip`. Rows
+/// that fail validation or are already stored are skipped and listed in
+/// the report rather than failing the request.
+#[utoipa::path(
+    post, path = "/admin/import",
+    tag = "admin",
+    security(("api_key" = [])),
+    params(ImportQuery, ("X-Admin-Actor" = Option<String>, Header, description = "Who is running the import, for the log")),
+    request_body(
+        description = "Fee data points as CSV or NDJSON, optionally gzipped",
+        content((String = "text/csv"), (String = "application/x-ndjson")),
+    ),
+    responses(
+        (status = 200, description = "What was imported and which rows were rejected", body = ImportReport),
+        (status = 400, description = "The body could not be read", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = ErrorResponse),
+        (status = 503, description = "Admin API is disabled", body = ErrorResponse),
+    )
+)]
+async fn import_fee_data(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Query(params): Query<ImportQuery>,
+    body: Bytes,
+) -> Result<Json<ImportReport>, ApiError> {
+    authorize(&state, &headers)?;
+    let format = params.format.unwrap_or_else(|| {
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if content_type.contains("json") {
+            ImportFormat::Ndjson
+        } else {
+            ImportFormat::Csv
+        }
+    });
+    let gzip = headers
+        .get(header::CONTENT_ENCODING)
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"gzip"));
+
+    let now = state.clock.now();
+    let result = if gzip {
+        let input = BufReader::new(GzDecoder::new(body.as_ref()));
+        import::import_points(&state.repository, input, format, now).await
+    } else {
+        import::import_points(&state.repository, body.as_ref(), format, now).await
+    };
+    let report = result.map_err(|err| match err {
+        ImportError::Read(_) => error(StatusCode::BAD_REQUEST, err.to_string()),
+        ImportError::Database(_) => error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+    })?;
+
+    tracing::info!(
+        "Imported {} fee data points ({} duplicates, {} rejected){}",
+        report.imported,
+        report.duplicates,
+        report.rejected,
+        actor(&headers).map(|a| format!(" by {}", a)).unwrap_or_default()
+    );
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use chrono::{Duration, Utc};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use crate::clock::system_clock;
+    use crate::db::create_pool;
+    use crate::insights::FeeDataPoint;
+
+    async fn make_app(
+        api_key: Option<&str>,
+    ) -> (Router, Arc<RwLock<FeeInsightsEngine>>, Arc<FeeRepository>) {
+        let repository = Arc::new(FeeRepository::new(create_pool("sqlite::memory:").await.unwrap()));
+        let engine = Arc::new(RwLock::new(FeeInsightsEngine::new(InsightsConfig::default())));
+        let app = create_admin_router(AdminApiState {
+            insights_engine: engine.clone(),
+            repository: repository.clone(),
+            api_key: api_key.map(Secret::new),
+            clock: system_clock(),
+        });
+        (app, engine, repository)
+    }
+
+    async fn send(app: Router, method: &str, uri: &str, key: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some(key) = key {
+            request = request.header(API_KEY_HEADER, key);
+        }
+        let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+        let resp = app.oneshot(request.body(body).unwrap()).await.unwrap();
+        let status = resp.status();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn requires_configured_api_key() {
+        let (app, _, _) = make_app(None).await;
+        let (status, _) = send(app, "GET", "/admin/insights/config", Some("k"), None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        let (app, _, _) = make_app(Some("k")).await;
+        let (status, _) = send(app.clone(), "GET", "/admin/insights/config", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(app.clone(), "GET", "/admin/insights/config", Some("wrong"), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, json) = send(app, "GET", "/admin/insights/config", Some("k"), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["spike_detection"]["threshold_multiplier"], 2.0);
+    }
+
+    #[tokio::test]
+    async fn put_swaps_engine_config_and_records_change() {
+        let (app, engine, repository) = make_app(Some("k")).await;
+        let now = Utc::now();
+        let points: Vec<FeeDataPoint> = (0..10)
+            .map(|i| FeeDataPoint {
+                fee_amount: 100 + i,
+                timestamp: now - Duration::minutes(30 - i as i64),
+                transaction_hash: format!("adm_{}", i),
+                ledger_sequence: i,
+            })
+            .collect();
+        engine.write().await.process_fee_data(&points).await.unwrap();
+        repository.insert_fee_points(&points).await.unwrap();
+        for resolution in crate::repository::ROLLUP_RESOLUTIONS {
+            repository.merge_rollups(resolution, &points).await.unwrap();
+        }
+
+        let mut config = InsightsConfig::with_window_specs(&["10m", "2h", "2d"]).unwrap();
+        config.spike_detection.threshold_multiplier = 3.0;
+        let (status, json) = send(
+            app.clone(),
+            "PUT",
+            "/admin/insights/config",
+            Some("k"),
+            Some(serde_json::to_value(&config).unwrap()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["previous_config"]["spike_detection"]["threshold_multiplier"], 2.0);
+
+        {
+            let engine = engine.read().await;
+            assert_eq!(engine.get_config().baseline_window, "2h");
+            // The 6h window's aggregates carry over into the 2h window
+            let averages = engine.get_rolling_averages();
+            assert_eq!(averages.windows["2h"].sample_count, 10);
+            // No current window covers two days, so it is rebuilt from rollups
+            assert_eq!(averages.windows["2d"].sample_count, 10);
+        }
+
+        let stored = repository.load_insights_config().await.unwrap().unwrap();
+        assert_eq!(stored.spike_detection.threshold_multiplier, 3.0);
+
+        let (status, json) = send(app, "GET", "/admin/insights/config/history", Some("k"), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn put_rejects_invalid_config_and_keeps_the_old_one() {
+        let (app, engine, repository) = make_app(Some("k")).await;
+        let config = InsightsConfig {
+            baseline_window: "missing".to_string(),
+            ..InsightsConfig::default()
+        };
+
+        let (status, _) = send(
+            app,
+            "PUT",
+            "/admin/insights/config",
+            Some("k"),
+            Some(serde_json::to_value(&config).unwrap()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_ne!(engine.read().await.get_config().baseline_window, "missing");
+        assert!(repository.load_insights_config().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn import_loads_csv_body_and_reports_rejections() {
+        let (app, _, repository) = make_app(Some("k")).await;
+        let now = Utc::now();
+        let body = format!(
+            "timestamp,fee_amount,transaction_hash,ledger_sequence\n{},120,imp_1,7\n{},0,imp_2,8\n",
+            (now - Duration::minutes(5)).to_rfc3339(),
+            (now - Duration::minutes(4)).to_rfc3339(),
+        );
+        let request = |key: Option<&str>| {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri("/admin/import")
+                .header("content-type", "text/csv");
+            if let Some(key) = key {
+                request = request.header(API_KEY_HEADER, key);
+            }
+            request.body(Body::from(body.clone())).unwrap()
+        };
+
+        let resp = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = app.oneshot(request(Some("k"))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let json: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["imported"], 1);
+        assert_eq!(json["rejected"], 1);
+        let stored = repository.fetch_since(now - Duration::hours(1)).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].transaction_hash, "imp_1");
+    }
+}
@@ -17,7 +17,9 @@ use axum::{
     Json,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
+use crate::api::openapi::ErrorResponse;
 use crate::repository::{AlertConfig, AlertEvent, FeeRepository, VALID_THRESHOLDS};
 
 /// Shared state for the alerts routes.
@@ -25,19 +27,19 @@ pub type AlertsState = Arc<FeeRepository>;
 
 // ---- Request / response shapes ----
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateAlertRequest {
     pub webhook_url: String,
     pub threshold: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateAlertRequest {
     pub threshold: Option<String>,
     pub enabled: Option<bool>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct CreateAlertResponse {
     pub id: i64,
 }
@@ -51,6 +53,15 @@ fn is_valid_threshold(t: &str) -> bool {
 // ---- Handlers ----
 
 /// `POST /alerts/config` — register a new webhook target.
+#[utoipa::path(
+    post, path = "/alerts/config",
+    tag = "alerts",
+    request_body = CreateAlertRequest,
+    responses(
+        (status = 201, description = "Alert registered", body = CreateAlertResponse),
+        (status = 400, description = "Invalid threshold", body = ErrorResponse),
+    )
+)]
 pub async fn create_alert(
     State(repo): State<AlertsState>,
     Json(body): Json<CreateAlertRequest>,
@@ -84,6 +95,13 @@ pub async fn create_alert(
 }
 
 /// `GET /alerts/config` — list all registered webhook configs.
+#[utoipa::path(
+    get, path = "/alerts/config",
+    tag = "alerts",
+    responses(
+        (status = 200, description = "All registered webhook configs", body = Vec<AlertConfig>),
+    )
+)]
 pub async fn list_alerts(
     State(repo): State<AlertsState>,
 ) -> Result<Json<Vec<AlertConfig>>, (StatusCode, Json<serde_json::Value>)> {
@@ -98,6 +116,17 @@ pub async fn list_alerts(
 }
 
 /// `PATCH /alerts/config/:id` — update threshold and/or enabled state.
+#[utoipa::path(
+    patch, path = "/alerts/config/{id}",
+    tag = "alerts",
+    params(("id" = i64, Path, description = "Alert config id")),
+    request_body = UpdateAlertRequest,
+    responses(
+        (status = 204, description = "Alert updated"),
+        (status = 400, description = "Invalid threshold", body = ErrorResponse),
+        (status = 404, description = "No alert config with this id", body = ErrorResponse),
+    )
+)]
 pub async fn update_alert(
     State(repo): State<AlertsState>,
     Path(id): Path<i64>,
@@ -155,6 +184,15 @@ pub async fn update_alert(
 }
 
 /// `DELETE /alerts/config/:id` — soft-delete by setting enabled = 0.
+#[utoipa::path(
+    delete, path = "/alerts/config/{id}",
+    tag = "alerts",
+    params(("id" = i64, Path, description = "Alert config id")),
+    responses(
+        (status = 204, description = "Alert disabled"),
+        (status = 404, description = "No alert config with this id", body = ErrorResponse),
+    )
+)]
 pub async fn delete_alert(
     State(repo): State<AlertsState>,
     Path(id): Path<i64>,
@@ -179,14 +217,15 @@ pub async fn delete_alert(
 
 // ---- Alert history ----
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct AlertHistoryQuery {
     pub limit: Option<i64>,
     pub severity: Option<String>,
     pub delivered: Option<bool>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AlertHistoryResponse {
     pub total: i64,
     pub items: Vec<AlertEvent>,
@@ -198,6 +237,15 @@ pub struct AlertHistoryResponse {
 /// - `limit`    — max items to return (default 20, clamped to 100)
 /// - `severity` — optional filter: Minor | Major | Critical
 /// - `delivered` — optional bool filter
+#[utoipa::path(
+    get, path = "/alerts/history",
+    tag = "alerts",
+    params(AlertHistoryQuery),
+    responses(
+        (status = 200, description = "Alert events, newest first", body = AlertHistoryResponse),
+        (status = 400, description = "Invalid severity", body = ErrorResponse),
+    )
+)]
 pub async fn get_alert_history(
     State(repo): State<AlertsState>,
     Query(params): Query<AlertHistoryQuery>,
@@ -0,0 +1,406 @@
+//! Conditional GET for the `/fees/*` and `/insights/*` routes.
+//!
+//! Responses carry an `ETag` and `Last-Modified` describing the data they
+//! were built from, and a `Cache-Control: max-age` lasting until that data
+//! is next refreshed. A request whose `If-None-Match` (or, without one,
+//! `If-Modified-Since`) still matches gets `304 Not Modified` without the
+//! handler running.
+//!
+//! `/fees/current` is versioned by when the Horizon fee stats were cached
+//! and fresh for the rest of the cache TTL. `/fees/history` and
+//! `/fees/candles` for an explicit range that ended a poll interval ago or
+//! more are versioned by that range and the retention tier serving it, and
+//! fresh until retention would move it to another tier, for at most a day.
+//! Everything else is built from polled data: versioned by the newest
+//! ledger in the fee store and the insights engine's last data or config
+//! change, and fresh until the next poll. ETags are weak, as a body may
+//! still differ in details such as a window ending at the time of the
+//! request.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tokio::sync::{watch, Mutex, RwLock};
+
+use crate::api::fees::CurrentFeeResponse;
+use crate::cache::ResponseCache;
+use crate::insights::FeeInsightsEngine;
+use crate::repository::ROLLUP_RESOLUTION_1M;
+use crate::scheduler::PollSettings;
+use crate::store::FeeHistoryStore;
+
+/// Format of HTTP dates in `Last-Modified` and `If-Modified-Since`.
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Longest `max-age` of a closed range, which an import can still fill in.
+const CLOSED_RANGE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Where the middleware reads data versions from.
+#[derive(Clone)]
+pub struct ConditionalState {
+    pub fee_cache: Arc<Mutex<ResponseCache<CurrentFeeResponse>>>,
+    pub fee_store: Arc<RwLock<FeeHistoryStore>>,
+    pub insights_engine: Arc<RwLock<FeeInsightsEngine>>,
+    /// Current poll interval, which changes on a config reload
+    pub poll_settings: watch::Receiver<PollSettings>,
+}
+
+/// The version of the data a response was built from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DataVersion {
+    etag: String,
+    last_modified: DateTime<Utc>,
+    max_age: Duration,
+}
+
+/// The range of a `/fees/history` or `/fees/candles` request.
+#[derive(Debug, Deserialize)]
+struct RangeQuery {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+impl ConditionalState {
+    async fn version(&self, uri: &Uri) -> Option<DataVersion> {
+        let path = uri.path();
+        if path.ends_with("/fees/current") {
+            return self.current_fees_version().await;
+        }
+        if path.ends_with("/fees/history") || path.ends_with("/fees/candles") {
+            if let Some(version) = self.closed_range_version(uri).await {
+                return Some(version);
+            }
+        }
+        let ledger = self.fee_store.read().await.get_last_n(1).pop();
+        let engine = self.insights_engine.read().await;
+        let last_modified = engine
+            .get_last_change()
+            .or_else(|| ledger.as_ref().map(|point| point.timestamp))?;
+        let interval = Duration::from_secs(self.poll_settings.borrow().poll_interval_seconds);
+        let since_update = (engine.clock().now() - last_modified).to_std().unwrap_or_default();
+
+        Some(DataVersion {
+            etag: format!(
+                "W/\"{}-{}\"",
+                ledger.map_or(0, |point| point.ledger_sequence),
+                last_modified.timestamp_millis()
+            ),
+            last_modified,
+            max_age: interval.saturating_sub(since_update),
+        })
+    }
+
+    /// Version of an explicit range no poll can add to any more, as it
+    /// ended at least a poll interval ago. `None` for any other request.
+    async fn closed_range_version(&self, uri: &Uri) -> Option<DataVersion> {
+        let Query(range) = Query::<RangeQuery>::try_from_uri(uri).ok()?;
+        let (from, to) = (range.from?, range.to?);
+        let now = self.insights_engine.read().await.clock().now();
+        let settings = self.poll_settings.borrow().clone();
+        let interval = chrono::Duration::seconds(settings.poll_interval_seconds as i64);
+        if from >= to || to + interval > now {
+            return None;
+        }
+
+        // The range reads differently once retention moves `from` to a
+        // coarser tier, so that tier is part of the version
+        let policy = &settings.retention;
+        let (tier, remaining) = if policy.covers_raw(from, now) {
+            ("raw", Some(from - policy.raw_cutoff(now)))
+        } else {
+            let tier = policy.finest_covering(ROLLUP_RESOLUTION_1M, from, now)?;
+            (tier, policy.rollup_cutoff(tier, now).map(|cutoff| from - cutoff))
+        };
+        let max_age = remaining
+            .and_then(|left| left.to_std().ok())
+            .map_or(CLOSED_RANGE_MAX_AGE, |left| left.min(CLOSED_RANGE_MAX_AGE));
+
+        Some(DataVersion {
+            etag: format!("W/\"{}-{}-{}\"", from.timestamp_millis(), to.timestamp_millis(), tier),
+            last_modified: to,
+            max_age,
+        })
+    }
+
+    async fn current_fees_version(&self) -> Option<DataVersion> {
+        let cache = self.fee_cache.lock().await;
+        let updated_at = cache.updated_at()?;
+        Some(DataVersion {
+            etag: format!("W/\"{}\"", updated_at.timestamp_millis()),
+            last_modified: updated_at,
+            max_age: cache.remaining_ttl().unwrap_or_default(),
+        })
+    }
+}
+
+/// Middleware answering conditional `GET`s and tagging fresh responses.
+pub async fn conditional_get(
+    State(state): State<ConditionalState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return next.run(request).await;
+    }
+    let uri = request.uri().clone();
+
+    // Read before the handler runs, so a version never claims newer data
+    // than the body holds
+    let version = state.version(&uri).await;
+    if let Some(version) = &version {
+        if not_modified(request.headers(), version) {
+            let mut response = StatusCode::NOT_MODIFIED.into_response();
+            set_headers(response.headers_mut(), version);
+            return response;
+        }
+    }
+
+    let mut response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    // The handler may have filled an empty cache
+    let version = match version {
+        Some(version) => Some(version),
+        None => state.version(&uri).await,
+    };
+    if let Some(version) = &version {
+        set_headers(response.headers_mut(), version);
+    }
+    response
+}
+
+fn set_headers(headers: &mut HeaderMap, version: &DataVersion) {
+    if let Ok(etag) = HeaderValue::from_str(&version.etag) {
+        headers.insert(header::ETAG, etag);
+    }
+    if let Ok(date) = HeaderValue::from_str(&http_date(version.last_modified)) {
+        headers.insert(header::LAST_MODIFIED, date);
+    }
+    if let Ok(cache_control) = HeaderValue::from_str(&format!("max-age={}", version.max_age.as_secs())) {
+        headers.insert(header::CACHE_CONTROL, cache_control);
+    }
+}
+
+/// Whether the client's copy is still current. `If-None-Match` takes
+/// precedence over `If-Modified-Since`, and is compared weakly.
+fn not_modified(headers: &HeaderMap, version: &DataVersion) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        let ours = opaque_tag(&version.etag);
+        return if_none_match
+            .to_str()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || opaque_tag(tag) == ours);
+    }
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_http_date)
+        .is_some_and(|since| version.last_modified.timestamp() <= since.timestamp())
+}
+
+fn opaque_tag(etag: &str) -> &str {
+    etag.strip_prefix("W/").unwrap_or(etag)
+}
+
+fn http_date(at: DateTime<Utc>) -> String {
+    at.format(HTTP_DATE_FORMAT).to_string()
+}
+
+fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::middleware::from_fn_with_state;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use crate::insights::{FeeDataPoint, InsightsConfig};
+    use crate::retention::RetentionPolicy;
+
+    fn state() -> ConditionalState {
+        let (_, poll_settings) = watch::channel(PollSettings {
+            poll_interval_seconds: 30,
+            retention: RetentionPolicy::default(),
+            alerts_enabled: true,
+            alerts_min_severity: None,
+        });
+        ConditionalState {
+            fee_cache: Arc::new(Mutex::new(ResponseCache::new(Duration::from_secs(5)))),
+            fee_store: Arc::new(RwLock::new(FeeHistoryStore::new(100))),
+            insights_engine: Arc::new(RwLock::new(FeeInsightsEngine::new(InsightsConfig::default()))),
+            poll_settings,
+        }
+    }
+
+    fn app(state: ConditionalState) -> Router {
+        Router::new()
+            .route("/insights", get(|| async { "insights" }))
+            .route("/v1/fees/history", get(|| async { "history" }))
+            .route("/v1/fees/current", get(|| async { "fees" }))
+            .layer(from_fn_with_state(state, conditional_get))
+    }
+
+    async fn poll(state: &ConditionalState, ledger: u64) {
+        let point = FeeDataPoint {
+            fee_amount: 100,
+            timestamp: Utc::now(),
+            transaction_hash: format!("tx{}", ledger),
+            ledger_sequence: ledger,
+        };
+        state.fee_store.write().await.push(point.clone());
+        state.insights_engine.write().await.process_fee_data(&[point]).await.unwrap();
+    }
+
+    async fn send(app: &Router, uri: &str, header: Option<(header::HeaderName, &str)>) -> Response {
+        let mut request = axum::http::Request::get(uri);
+        if let Some((name, value)) = header {
+            request = request.header(name, value);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn revalidates_against_the_polled_data_version() {
+        let state = state();
+        let app = app(state.clone());
+
+        // Nothing polled yet: no validators
+        let response = send(&app, "/insights", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::ETAG).is_none());
+
+        poll(&state, 7).await;
+        let response = send(&app, "/insights", None).await;
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert!(etag.starts_with("W/\"7-"));
+        let max_age = response.headers()[header::CACHE_CONTROL].to_str().unwrap().to_string();
+        assert!(max_age == "max-age=30" || max_age == "max-age=29", "{}", max_age);
+        let last_modified = response.headers()[header::LAST_MODIFIED].to_str().unwrap().to_string();
+
+        let response = send(&app, "/insights", Some((header::IF_NONE_MATCH, &etag))).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        let response = send(&app, "/insights", Some((header::IF_MODIFIED_SINCE, &last_modified))).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        // New data invalidates the client's copy
+        poll(&state, 8).await;
+        let response = send(&app, "/insights", Some((header::IF_NONE_MATCH, &etag))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag.as_str());
+        let response =
+            send(&app, "/insights", Some((header::IF_MODIFIED_SINCE, "Thu, 01 Jan 2015 00:00:00 GMT"))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn closed_ranges_are_versioned_by_the_range() {
+        let state = state();
+        let app = app(state.clone());
+        poll(&state, 7).await;
+
+        let to = Utc::now() - chrono::Duration::hours(1);
+        let from = to - chrono::Duration::hours(1);
+        let uri = format!(
+            "/v1/fees/history?from={}&to={}",
+            from.format("%Y-%m-%dT%H:%M:%SZ"),
+            to.format("%Y-%m-%dT%H:%M:%SZ")
+        );
+        let response = send(&app, &uri, None).await;
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert!(etag.ends_with("-raw\""), "{}", etag);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "max-age=86400");
+        assert_eq!(response.headers()[header::LAST_MODIFIED], http_date(to).as_str());
+
+        // Later polls cannot reach back into the range
+        poll(&state, 8).await;
+        let response = send(&app, &uri, Some((header::IF_NONE_MATCH, &etag))).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        // A range still open to the next poll follows the polled data
+        let open = format!("/v1/fees/history?from={}", from.format("%Y-%m-%dT%H:%M:%SZ"));
+        let response = send(&app, &open, None).await;
+        assert!(response.headers()[header::ETAG].to_str().unwrap().starts_with("W/\"8-"));
+    }
+
+    #[tokio::test]
+    async fn reconfiguring_the_engine_changes_the_version() {
+        let clock = crate::clock::ManualClock::new(Utc::now());
+        let state = ConditionalState {
+            insights_engine: Arc::new(RwLock::new(FeeInsightsEngine::with_clock(
+                InsightsConfig::default(),
+                clock.shared(),
+            ))),
+            ..state()
+        };
+        let app = app(state.clone());
+        poll(&state, 7).await;
+        let etag = send(&app, "/insights", None).await.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        clock.advance(chrono::Duration::seconds(5));
+        state
+            .insights_engine
+            .write()
+            .await
+            .reconfigure(InsightsConfig::default())
+            .unwrap();
+        let response = send(&app, "/insights", Some((header::IF_NONE_MATCH, &etag))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag.as_str());
+    }
+
+    #[tokio::test]
+    async fn current_fees_follow_the_response_cache() {
+        let state = state();
+        let app = app(state.clone());
+
+        let response = send(&app, "/v1/fees/current", None).await;
+        assert!(response.headers().get(header::ETAG).is_none());
+
+        state.fee_cache.lock().await.set(CurrentFeeResponse {
+            base_fee: "100".into(),
+            min_fee: "100".into(),
+            max_fee: "100".into(),
+            avg_fee: "100".into(),
+            percentiles: crate::api::fees::PercentileFees {
+                p10: "100".into(),
+                p25: "100".into(),
+                p50: "100".into(),
+                p75: "100".into(),
+                p90: "100".into(),
+                p95: "100".into(),
+            },
+        });
+        let response = send(&app, "/v1/fees/current", None).await;
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        let max_age = response.headers()[header::CACHE_CONTROL].to_str().unwrap().to_string();
+        assert!(max_age == "max-age=5" || max_age == "max-age=4", "{}", max_age);
+
+        let response = send(&app, "/v1/fees/current", Some((header::IF_NONE_MATCH, "\"other\", *"))).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let strong = etag.trim_start_matches("W/");
+        let response = send(&app, "/v1/fees/current", Some((header::IF_NONE_MATCH, strong))).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+}
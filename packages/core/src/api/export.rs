@@ -0,0 +1,182 @@
+//! `GET /fees/export` — bulk download of stored data.
+//!
+//! The response is streamed: rows are read and encoded a batch at a time
+//! while earlier batches are being sent, so a large range never has to fit
+//! in memory. Query parameters:
+//! - `dataset`    — `points` (default), `snapshots`, `rollups` or `alert_events`
+//! - `format`     — `csv` (default), `ndjson` or `parquet`
+//! - `from`, `to` — RFC 3339 range, `from` inclusive; defaults to the last 24 hours
+//! - `columns`    — comma-separated subset of the dataset's columns
+//! - `resolution` — rollups only: a single resolution, e.g. `1h`
+//! - `gzip`       — `true` to send the body with `Content-Encoding: gzip`
+
+use std::io;
+
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use utoipa::IntoParams;
+
+use crate::api::fees::FeesState;
+use crate::api::openapi::ErrorResponse;
+use crate::export::{ExportDataset, ExportFormat, ExportRequest, Exporter, DEFAULT_EXPORT_HOURS};
+use crate::repository::ROLLUP_RESOLUTIONS;
+
+/// Encoded batches buffered ahead of a slow client.
+const EXPORT_CHANNEL_CAPACITY: usize = 4;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    pub dataset: Option<String>,
+    pub format: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub columns: Option<String>,
+    pub resolution: Option<String>,
+    #[serde(default)]
+    pub gzip: bool,
+}
+
+type ApiError = (StatusCode, Json<Value>);
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message.into() })))
+}
+
+/// Parse a `dataset` or `format` value by the same names the CLI accepts.
+fn parse_choice<T: ValueEnum>(param: &str, value: Option<&str>, default: T) -> Result<T, ApiError> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+    T::from_str(value, true).map_err(|_| {
+        let names: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(|variant| variant.to_possible_value())
+            .map(|possible| possible.get_name().to_string())
+            .collect();
+        bad_request(format!(
+            "Unsupported {} '{}'. Must be one of: {}",
+            param,
+            value,
+            names.join(", ")
+        ))
+    })
+}
+
+/// `GET /fees/export` — stream a table's rows in `[from, to)`.
+#[utoipa::path(
+    get, path = "/fees/export",
+    tag = "fees",
+    params(ExportQuery),
+    responses(
+        (status = 200, description = "Rows as CSV, NDJSON or Parquet, optionally gzipped", content(
+            (String = "text/csv"),
+            (String = "application/x-ndjson"),
+            (Vec<u8> = "application/vnd.apache.parquet"),
+        )),
+        (status = 400, description = "Invalid dataset, format, columns or range", body = ErrorResponse),
+        (status = 503, description = "No database configured", body = ErrorResponse),
+    )
+)]
+pub async fn export_fees(
+    State(state): State<FeesState>,
+    Query(params): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let repository = state.repository.clone().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": "Export requires a database" })),
+        )
+    })?;
+
+    let dataset = parse_choice("dataset", params.dataset.as_deref(), ExportDataset::Points)?;
+    let format = parse_choice("format", params.format.as_deref(), ExportFormat::Csv)?;
+    let to = params.to.unwrap_or_else(|| state.clock.now());
+    let from = params.from.unwrap_or(to - Duration::hours(DEFAULT_EXPORT_HOURS));
+    if from >= to {
+        return Err(bad_request("from must be before to"));
+    }
+    let columns: Vec<String> = params
+        .columns
+        .as_deref()
+        .map(|list| list.split(',').filter(|name| !name.trim().is_empty()).map(str::to_string).collect())
+        .unwrap_or_default();
+    let columns = dataset.select_columns(&columns).map_err(bad_request)?;
+    if let Some(resolution) = &params.resolution {
+        if !ROLLUP_RESOLUTIONS.contains(&resolution.as_str()) {
+            return Err(bad_request(format!(
+                "Unsupported resolution '{}'. Must be one of: {}",
+                resolution,
+                ROLLUP_RESOLUTIONS.join(", ")
+            )));
+        }
+    }
+
+    let mut exporter = Exporter::new(ExportRequest {
+        dataset,
+        format,
+        from,
+        to,
+        columns,
+        gzip: params.gzip,
+        resolution: params.resolution,
+    })
+    .map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": err.to_string() })),
+        )
+    })?;
+
+    let (tx, rx) = mpsc::channel::<io::Result<Vec<u8>>>(EXPORT_CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        loop {
+            match exporter.next_chunk(&repository).await {
+                Ok(Some(chunk)) if chunk.is_empty() => {}
+                Ok(Some(chunk)) => {
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        tracing::debug!("Export of {} abandoned by the client", dataset.table());
+                        return;
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    // The body ends early, so the client sees a truncated transfer
+                    tracing::warn!("Export of {} failed: {}", dataset.table(), err);
+                    let _ = tx.send(Err(err)).await;
+                    return;
+                }
+            }
+        }
+        tracing::info!("Exported {} {} rows", exporter.rows(), dataset.table());
+    });
+
+    let filename = format!(
+        "{}_{}_{}.{}",
+        dataset.table(),
+        from.format("%Y%m%dT%H%M%SZ"),
+        to.format("%Y%m%dT%H%M%SZ"),
+        format.extension()
+    );
+    let mut response = Response::new(Body::from_stream(ReceiverStream::new(rx)));
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(format.content_type()));
+    if let Ok(disposition) = HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename)) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    if params.gzip {
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    }
+    Ok(response)
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -6,14 +7,24 @@ use axum::{
     http::StatusCode,
     Json,
 };
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tokio::sync::{Mutex, RwLock};
+use utoipa::{IntoParams, ToSchema};
 
+use crate::api::insights::insights_at;
+use crate::api::openapi::ErrorResponse;
 use crate::cache::ResponseCache;
+use crate::clock::SharedClock;
 use crate::error::AppError;
-use crate::insights::{FeeDataPoint, FeeInsightsEngine, TrendIndicator, TrendStrength};
+use crate::insights::{
+    format_duration, parse_duration, CurrentInsights, FeeDataPoint, FeeInsightsEngine, FeePercentiles,
+    QuantileSketch, TrendIndicator, TrendStrength,
+};
+use crate::point_in_time::PointInTimeInsights;
+use crate::repository::{FeeAmountStats, FeeRepository, FeeRollup, ROLLUP_RESOLUTION_1M, ROLLUP_RESOLUTIONS};
+use crate::retention::{RetentionPolicy, SharedRetention};
 use crate::services::horizon::HorizonClient;
 use crate::store::FeeHistoryStore;
 
@@ -52,9 +63,16 @@ pub struct FeesApiState {
     pub fee_cache: Arc<Mutex<ResponseCache<CurrentFeeResponse>>>,
     pub fee_store: Arc<RwLock<FeeHistoryStore>>,
     pub insights_engine: Option<Arc<RwLock<FeeInsightsEngine>>>,
+    /// Serves `/fees/trend?at=`; `None` without a database
+    pub point_in_time: Option<Arc<PointInTimeInsights>>,
+    /// Serves `/fees/history` ranges older than the in-memory store
+    pub repository: Option<Arc<FeeRepository>>,
+    /// Decides which rollup tier still covers an older range
+    pub retention: SharedRetention,
+    pub clock: SharedClock,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 pub struct PercentileFees {
     pub p10: String,
     pub p25: String,
@@ -64,7 +82,7 @@ pub struct PercentileFees {
     pub p95: String,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 pub struct CurrentFeeResponse {
     pub base_fee: String,
     pub min_fee: String,
@@ -73,142 +91,614 @@ pub struct CurrentFeeResponse {
     pub percentiles: PercentileFees,
 }
 
+#[utoipa::path(
+    get, path = "/fees/current",
+    tag = "fees",
+    responses(
+        (status = 200, description = "Current network fee stats", body = CurrentFeeResponse),
+        (status = 502, description = "Horizon could not be reached", body = ErrorResponse),
+        (status = 422, description = "Horizon sent a response that could not be parsed", body = ErrorResponse),
+    )
+)]
 pub async fn current_fees(
     State(state): State<FeesState>,
 ) -> Result<Json<CurrentFeeResponse>, AppError> {
-    {
-        let cache = state.fee_cache.lock().await;
-        if cache.is_fresh() {
-            if let Some(cached) = cache.get() {
-                return Ok(Json(cached));
-            }
-        }
-    }
-
     let provider = state
         .fee_stats_provider
-        .as_ref()
+        .as_deref()
         .ok_or_else(|| {
             AppError::Config("Fee stats provider missing from fees state".to_string())
         })?;
-    let fresh = provider.fetch_current_fees().await?;
+    cached_current_fees(provider, &state.fee_cache).await.map(Json)
+}
 
-    {
-        let mut cache = state.fee_cache.lock().await;
-        cache.set(fresh.clone());
+/// Current fees from `cache` while fresh, else fetched from `provider`
+/// and cached. Shared by `/fees/current` and the live feed.
+pub async fn cached_current_fees(
+    provider: &(dyn FeeStatsProvider + Send + Sync),
+    cache: &Mutex<ResponseCache<CurrentFeeResponse>>,
+) -> Result<CurrentFeeResponse, AppError> {
+    if let Some(cached) = cache.lock().await.get() {
+        return Ok(cached);
     }
 
-    Ok(Json(fresh))
+    let fresh = provider.fetch_current_fees().await?;
+    cache.lock().await.set(fresh.clone());
+    Ok(fresh)
 }
 
-#[derive(Debug, Deserialize)]
+/// Default and maximum number of points per `/fees/history` page.
+const DEFAULT_HISTORY_LIMIT: usize = 1_000;
+const MAX_HISTORY_LIMIT: usize = 10_000;
+
+/// Query for `/fees/history`.
+///
+/// The range is either `window` ending now (default `1h`), or `from`/`to`;
+/// a missing `to` means now and a missing `from` means one `window` before
+/// `to`. Both ends are inclusive.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct FeeHistoryQuery {
     pub window: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct FeeSummary {
     pub min: u64,
     pub max: u64,
     pub avg: f64,
-    pub p50: u64,
-    pub p95: u64,
+    /// p10 through p99, estimated from a quantile sketch (within 1%)
+    #[serde(flatten)]
+    pub percentiles: FeePercentiles,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct FeeHistoryResponse {
     pub window: String,
     pub from: DateTime<Utc>,
     pub to: DateTime<Utc>,
+    /// Points on this page
     pub data_points: usize,
+    /// Points in the whole range, across all pages
+    pub total: usize,
     pub fees: Vec<FeeDataPoint>,
+    /// Statistics over the whole range, the same on every page
     pub summary: FeeSummary,
+    /// `raw` when `total` and `summary` come from every point, otherwise
+    /// the rollup resolution they were merged from because raw points
+    /// from `from` onwards have been pruned. `fees` then only lists the
+    /// points still kept and `buckets` carries the range.
+    pub resolution: String,
+    /// The range as rollups at `resolution`, oldest first, the same on
+    /// every page; empty when `resolution` is `raw`
+    pub buckets: Vec<FeeCandle>,
+    /// Pass as `cursor`, with the same `from` and `to`, for the next page;
+    /// absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Position after the last point of a page: its timestamp and transaction
+/// hash, the order history pages are sorted by. Serialized as an opaque
+/// `<unix nanos>_<hash>` string.
+#[derive(Debug, Clone, PartialEq)]
+struct HistoryCursor {
+    timestamp: DateTime<Utc>,
+    transaction_hash: String,
+}
+
+impl HistoryCursor {
+    fn after(point: &FeeDataPoint) -> Self {
+        Self {
+            timestamp: point.timestamp,
+            transaction_hash: point.transaction_hash.clone(),
+        }
+    }
+
+    fn encode(&self) -> String {
+        let nanos = self.timestamp.timestamp_nanos_opt().unwrap_or_default();
+        format!("{}_{}", nanos, self.transaction_hash)
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        let (nanos, hash) = value.split_once('_')?;
+        Some(Self {
+            timestamp: DateTime::from_timestamp_nanos(nanos.parse().ok()?),
+            transaction_hash: hash.to_string(),
+        })
+    }
+
+    fn is_before(&self, point: &FeeDataPoint) -> bool {
+        (self.timestamp, self.transaction_hash.as_str())
+            < (point.timestamp, point.transaction_hash.as_str())
+    }
+}
+
+type ApiError = (StatusCode, Json<Value>);
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message.into() })))
 }
 
+fn internal_error(err: sqlx::Error) -> ApiError {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": err.to_string() })),
+    )
+}
+
+fn retention_policy(state: &FeesApiState) -> RetentionPolicy {
+    state.retention.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// `GET /fees/history` — fee data points in a range, a page at a time.
+///
+/// Served from the in-memory store when it reaches back to `from`, and
+/// from the database otherwise. Once raw points from `from` have been
+/// pruned, the totals and the `buckets` series come from the finest rollup
+/// tier still covering it.
+#[utoipa::path(
+    get, path = "/fees/history",
+    tag = "fees",
+    params(FeeHistoryQuery),
+    responses(
+        (status = 200, description = "A page of fee data points in the range", body = FeeHistoryResponse),
+        (status = 400, description = "Invalid window, range or cursor", body = ErrorResponse),
+    )
+)]
 pub async fn fee_history(
     State(state): State<FeesState>,
     Query(params): Query<FeeHistoryQuery>,
-) -> Result<Json<FeeHistoryResponse>, (StatusCode, Json<Value>)> {
+) -> Result<Json<FeeHistoryResponse>, ApiError> {
+    if params.window.is_some() && params.from.is_some() {
+        return Err(bad_request("Use either window or from, not both"));
+    }
     let window = params.window.unwrap_or_else(|| "1h".to_string());
-    let duration = parse_window(&window).ok_or_else(|| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(json!({ "error": format!("Unsupported window value: {}", window) })),
-        )
-    })?;
+    let duration = parse_duration(&window)
+        .ok_or_else(|| bad_request(format!("Unsupported window value: {}", window)))?;
+
+    let to = params.to.unwrap_or_else(|| state.clock.now());
+    let from = params.from.unwrap_or(to - duration);
+    if from >= to {
+        return Err(bad_request("from must be before to"));
+    }
+    let window = match params.from {
+        Some(_) => format_duration(to - from),
+        None => window,
+    };
+
+    let limit = params.limit.unwrap_or(DEFAULT_HISTORY_LIMIT).clamp(1, MAX_HISTORY_LIMIT);
+    let cursor = params
+        .cursor
+        .as_deref()
+        .map(|value| HistoryCursor::decode(value).ok_or_else(|| bad_request("Invalid cursor")))
+        .transpose()?;
 
-    let to = Utc::now();
-    let from = to - duration;
-    let fees = {
+    let in_memory = {
         let store = state.fee_store.read().await;
-        store.get_since(from)
+        match store.oldest_timestamp() {
+            Some(oldest) if oldest <= from => Some(store.get_between(from, to)),
+            _ => None,
+        }
+    };
+
+    // One extra point tells whether another page follows
+    let mut resolution = "raw".to_string();
+    let mut buckets = Vec::new();
+    let (mut fees, total, summary) = match (in_memory, &state.repository) {
+        (None, Some(repository)) => {
+            let after = cursor.as_ref().map(|c| (c.timestamp, c.transaction_hash.as_str()));
+            let page = repository
+                .fetch_page(from, to, after, limit as i64 + 1)
+                .await
+                .map_err(internal_error)?;
+
+            let policy = retention_policy(&state);
+            let now = state.clock.now();
+            let (total, summary) = match policy.finest_covering(ROLLUP_RESOLUTION_1M, from, now) {
+                Some(tier) if !policy.covers_raw(from, now) => {
+                    let rollups = repository
+                        .fetch_rollups(tier, from, to)
+                        .await
+                        .map_err(internal_error)?;
+                    resolution = tier.to_string();
+                    buckets = rollups.iter().map(FeeCandle::from).collect();
+                    summarize_rollups(&rollups)
+                }
+                _ => stored_summary(repository, from, to).await?,
+            };
+            (page, total, summary)
+        }
+        (in_memory, _) => {
+            let mut points = match in_memory {
+                Some(points) => points,
+                None => state.fee_store.read().await.get_between(from, to),
+            };
+            let amounts: Vec<u64> = points.iter().map(|p| p.fee_amount).collect();
+            let live_window = (params.from.is_none() && params.to.is_none()).then_some(duration);
+            let percentiles = match live_window {
+                Some(window) => window_percentiles(&state, window).await,
+                None => None,
+            }
+            .or_else(|| exact_percentiles(&amounts));
+            points.sort_by(|a, b| {
+                (a.timestamp, &a.transaction_hash).cmp(&(b.timestamp, &b.transaction_hash))
+            });
+            let page = points
+                .into_iter()
+                .filter(|point| cursor.as_ref().is_none_or(|c| c.is_before(point)))
+                .take(limit + 1)
+                .collect();
+            (page, amounts.len(), summarize_amounts(&amounts, percentiles))
+        }
+    };
+
+    let next_cursor = if fees.len() > limit {
+        fees.truncate(limit);
+        fees.last().map(|point| HistoryCursor::after(point).encode())
+    } else {
+        None
     };
-    let summary = compute_summary(&fees);
 
     Ok(Json(FeeHistoryResponse {
         window,
         from,
         to,
         data_points: fees.len(),
+        total,
         fees,
         summary,
+        resolution,
+        buckets,
+        next_cursor,
     }))
 }
 
-fn parse_window(value: &str) -> Option<Duration> {
-    match value {
-        "1h" => Some(Duration::hours(1)),
-        "6h" => Some(Duration::hours(6)),
-        "24h" => Some(Duration::hours(24)),
-        _ => None,
+/// Percentiles of the engine's own sketch for `window` ending now, when
+/// the engine keeps that window.
+async fn window_percentiles(state: &FeesApiState, window: chrono::Duration) -> Option<FeePercentiles> {
+    let engine = state.insights_engine.as_ref()?.read().await;
+    engine
+        .window_sketch(window)
+        .filter(|sketch| !sketch.is_empty())
+        .and_then(QuantileSketch::percentiles)
+}
+
+/// Count and summary of the stored points in `[from, to]`, every figure
+/// describing the same points. Count, min, max and average are aggregated
+/// in SQL. Percentiles come from the 1m rollups of the whole minutes in
+/// the range plus the raw points at its ragged ends when those rollups
+/// hold every point, else from one pass over the points ordered by fee.
+async fn stored_summary(
+    repository: &FeeRepository,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<(usize, FeeSummary), ApiError> {
+    let stats = repository
+        .fee_amount_stats(from, to)
+        .await
+        .map_err(internal_error)?;
+    if stats.count == 0 {
+        return Ok((0, empty_summary()));
+    }
+    let percentiles = match bucketed_sketch(repository, from, to).await? {
+        Some(sketch) if sketch.count() == stats.count => sketch
+            .percentiles()
+            .map(|percentiles| clamp_percentiles(percentiles, stats.min, stats.max)),
+        _ => ranked_percentiles(repository, from, to, stats.count).await?,
+    };
+    Ok((stats.count as usize, summarize_stats(&stats, percentiles)))
+}
+
+/// Sketch of the points in `[from, to]`: the merged 1m rollups of the
+/// whole minutes inside the range and the raw points before and after
+/// them. `None` when the range holds no whole minute.
+async fn bucketed_sketch(
+    repository: &FeeRepository,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Option<QuantileSketch>, ApiError> {
+    let width = parse_duration(ROLLUP_RESOLUTION_1M).unwrap_or_default().num_seconds().max(1);
+    let first = from.timestamp() + i64::from(from.timestamp_subsec_nanos() > 0);
+    let inner_from = DateTime::from_timestamp((first + width - 1).div_euclid(width) * width, 0);
+    let inner_to = DateTime::from_timestamp(to.timestamp().div_euclid(width) * width, 0);
+    let (Some(inner_from), Some(inner_to)) = (inner_from, inner_to) else {
+        return Ok(None);
+    };
+    if inner_from >= inner_to {
+        return Ok(None);
+    }
+
+    let rollups = repository
+        .fetch_rollups(ROLLUP_RESOLUTION_1M, inner_from, inner_to)
+        .await
+        .map_err(internal_error)?;
+    let mut sketch = merge_sketches(&rollups);
+    let ends = repository
+        .fetch_fee_amounts_outside(from, to, inner_from, inner_to)
+        .await
+        .map_err(internal_error)?;
+    for fee in ends {
+        sketch.insert(fee);
+    }
+    Ok(Some(sketch))
+}
+
+/// Keep sketch estimates, accurate to 1%, within the exact range.
+fn clamp_percentiles(p: FeePercentiles, min: u64, max: u64) -> FeePercentiles {
+    let clamp = |value: u64| value.clamp(min, max);
+    FeePercentiles {
+        p10: clamp(p.p10),
+        p25: clamp(p.p25),
+        p50: clamp(p.p50),
+        p75: clamp(p.p75),
+        p90: clamp(p.p90),
+        p95: clamp(p.p95),
+        p99: clamp(p.p99),
+    }
+}
+
+/// Percentiles in a summary, in `FeePercentiles` field order
+const PERCENTILES: [usize; 7] = [10, 25, 50, 75, 90, 95, 99];
+
+/// 1-based nearest rank of `percentile` among `count` values.
+fn nearest_rank(percentile: usize, count: usize) -> usize {
+    (percentile * count).div_ceil(100).max(1)
+}
+
+fn percentiles_from(values: &[u64]) -> Option<FeePercentiles> {
+    let [p10, p25, p50, p75, p90, p95, p99] = values.try_into().ok()?;
+    Some(FeePercentiles { p10, p25, p50, p75, p90, p95, p99 })
+}
+
+/// Nearest-rank percentiles of `amounts`.
+fn exact_percentiles(amounts: &[u64]) -> Option<FeePercentiles> {
+    let mut sorted = amounts.to_vec();
+    sorted.sort_unstable();
+    let values = PERCENTILES
+        .iter()
+        .map(|&percentile| sorted.get(nearest_rank(percentile, sorted.len()) - 1).copied())
+        .collect::<Option<Vec<_>>>()?;
+    percentiles_from(&values)
+}
+
+/// Nearest-rank percentiles of the `count` stored points in `[from, to]`,
+/// read in one pass ordered by fee.
+async fn ranked_percentiles(
+    repository: &FeeRepository,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    count: u64,
+) -> Result<Option<FeePercentiles>, ApiError> {
+    let ranks: Vec<u64> = PERCENTILES
+        .iter()
+        .map(|&percentile| nearest_rank(percentile, count as usize) as u64)
+        .collect();
+    let values = repository
+        .fee_amounts_at_ranks(from, to, &ranks)
+        .await
+        .map_err(internal_error)?;
+    Ok(percentiles_from(&values))
+}
+
+/// Exact count, min, max and average of `amounts` with the given percentiles.
+fn summarize_amounts(amounts: &[u64], percentiles: Option<FeePercentiles>) -> FeeSummary {
+    let (Some(min), Some(max), Some(percentiles)) =
+        (amounts.iter().min(), amounts.iter().max(), percentiles)
+    else {
+        return empty_summary();
+    };
+    FeeSummary {
+        min: *min,
+        max: *max,
+        avg: amounts.iter().sum::<u64>() as f64 / amounts.len() as f64,
+        percentiles,
+    }
+}
+
+/// Summary from SQL aggregates with the given percentiles.
+fn summarize_stats(stats: &FeeAmountStats, percentiles: Option<FeePercentiles>) -> FeeSummary {
+    match percentiles {
+        Some(percentiles) if stats.count > 0 => FeeSummary {
+            min: stats.min,
+            max: stats.max,
+            avg: stats.avg,
+            percentiles,
+        },
+        _ => empty_summary(),
+    }
+}
+
+fn merge_sketches(rollups: &[FeeRollup]) -> QuantileSketch {
+    let mut sketch = QuantileSketch::default();
+    for rollup in rollups {
+        if let Err(err) = sketch.merge(&rollup.sketch) {
+            tracing::warn!("Skipping {} rollup at {}: {}", rollup.resolution, rollup.bucket_start, err);
+        }
     }
+    sketch
 }
 
-fn compute_summary(fees: &[FeeDataPoint]) -> FeeSummary {
-    if fees.is_empty() {
-        return FeeSummary {
-            min: 0,
-            max: 0,
-            avg: 0.0,
+/// Point count and summary of the buckets' merged sketches.
+fn summarize_rollups(rollups: &[FeeRollup]) -> (usize, FeeSummary) {
+    let sketch = merge_sketches(rollups);
+    let min = rollups.iter().map(|r| r.min_fee).min().unwrap_or(0);
+    let max = rollups.iter().map(|r| r.max_fee).max().unwrap_or(0);
+    (sketch.count() as usize, summary_from_sketch(&sketch, min, max))
+}
+
+fn summary_from_sketch(sketch: &QuantileSketch, min: u64, max: u64) -> FeeSummary {
+    let (Some(avg), Some(percentiles)) = (sketch.mean(), sketch.percentiles()) else {
+        return empty_summary();
+    };
+
+    FeeSummary {
+        min,
+        max,
+        avg,
+        percentiles,
+    }
+}
+
+fn empty_summary() -> FeeSummary {
+    FeeSummary {
+        min: 0,
+        max: 0,
+        avg: 0.0,
+        percentiles: FeePercentiles {
+            p10: 0,
+            p25: 0,
             p50: 0,
+            p75: 0,
+            p90: 0,
             p95: 0,
-        };
+            p99: 0,
+        },
     }
+}
 
-    let mut values: Vec<u64> = fees.iter().map(|f| f.fee_amount).collect();
-    values.sort_unstable();
-    let sum: u64 = values.iter().sum();
-    let len = values.len();
+/// Most candles one `/fees/candles` request may cover.
+const MAX_CANDLES: i64 = 5_000;
+
+/// Query for `/fees/candles`. `resolution` defaults to `1h`, `to` to now
+/// and `from` to 24 hours before `to`.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FeeCandlesQuery {
+    pub resolution: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
 
-    FeeSummary {
-        min: values[0],
-        max: values[len - 1],
-        avg: sum as f64 / len as f64,
-        p50: percentile_nearest_rank(&values, 50),
-        p95: percentile_nearest_rank(&values, 95),
+/// Fee statistics for one time bucket.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FeeCandle {
+    pub bucket_start: DateTime<Utc>,
+    pub open: u64,
+    pub high: u64,
+    pub low: u64,
+    pub close: u64,
+    pub avg: f64,
+    pub p50: u64,
+    pub p95: u64,
+    /// Number of transactions in the bucket
+    pub count: u64,
+}
+
+impl From<&FeeRollup> for FeeCandle {
+    fn from(rollup: &FeeRollup) -> Self {
+        let avg = rollup.fee_sum as f64 / rollup.sample_count.max(1) as f64;
+        // Buckets recorded before open/close were tracked fall back to the average
+        let (open, close) = match (rollup.open_at, rollup.close_at) {
+            (Some(_), Some(_)) => (rollup.open_fee, rollup.close_fee),
+            _ => (avg.round() as u64, avg.round() as u64),
+        };
+        Self {
+            bucket_start: rollup.bucket_start,
+            open,
+            high: rollup.max_fee,
+            low: rollup.min_fee,
+            close,
+            avg,
+            p50: rollup.sketch.quantile(50.0).unwrap_or_default(),
+            p95: rollup.sketch.quantile(95.0).unwrap_or_default(),
+            count: rollup.sample_count,
+        }
     }
 }
 
-fn percentile_nearest_rank(sorted: &[u64], percentile: usize) -> u64 {
-    if sorted.is_empty() {
-        return 0;
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FeeCandlesResponse {
+    pub resolution: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Oldest first; buckets without transactions are omitted
+    pub candles: Vec<FeeCandle>,
+}
+
+/// `GET /fees/candles` — OHLC candles from the rollups the poller maintains.
+/// When the requested resolution has been pruned back past `from`, the
+/// finest coarser one still covering it is used and reported instead.
+/// `from` is rounded down to the start of its bucket.
+#[utoipa::path(
+    get, path = "/fees/candles",
+    tag = "fees",
+    params(FeeCandlesQuery),
+    responses(
+        (status = 200, description = "Candles, oldest first", body = FeeCandlesResponse),
+        (status = 400, description = "Invalid resolution or range", body = ErrorResponse),
+        (status = 503, description = "No database configured", body = ErrorResponse),
+    )
+)]
+pub async fn fee_candles(
+    State(state): State<FeesState>,
+    Query(params): Query<FeeCandlesQuery>,
+) -> Result<Json<FeeCandlesResponse>, ApiError> {
+    let repository = state.repository.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": "Candles require a database" })),
+        )
+    })?;
+
+    let resolution = params.resolution.unwrap_or_else(|| "1h".to_string());
+    if !ROLLUP_RESOLUTIONS.contains(&resolution.as_str()) {
+        return Err(bad_request(format!(
+            "Unsupported resolution '{}'. Must be one of: {}",
+            resolution,
+            ROLLUP_RESOLUTIONS.join(", ")
+        )));
     }
-    let n = sorted.len();
-    let rank = ((percentile * n).saturating_add(99) / 100).max(1);
-    sorted[rank - 1]
+
+    let now = state.clock.now();
+    let to = params.to.unwrap_or(now);
+    let from = params.from.unwrap_or(to - chrono::Duration::hours(24));
+    if from >= to {
+        return Err(bad_request("from must be before to"));
+    }
+    let resolution = retention_policy(&state)
+        .finest_covering(&resolution, from, now)
+        .map_or(resolution, str::to_string);
+    let width = parse_duration(&resolution).unwrap_or_default().num_seconds();
+    let from = DateTime::from_timestamp(from.timestamp().div_euclid(width) * width, 0).unwrap_or(from);
+    if (to - from).num_seconds() / width > MAX_CANDLES {
+        return Err(bad_request(format!(
+            "Range too large: at most {} {} candles per request",
+            MAX_CANDLES, resolution
+        )));
+    }
+
+    let rollups = repository
+        .fetch_rollups(&resolution, from, to)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(FeeCandlesResponse {
+        resolution,
+        from,
+        to,
+        candles: rollups.iter().map(FeeCandle::from).collect(),
+    }))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TrendChanges {
-    #[serde(rename = "1h_pct")]
-    pub one_h_pct: Option<f64>,
-    #[serde(rename = "6h_pct")]
-    pub six_h_pct: Option<f64>,
-    #[serde(rename = "24h_pct")]
-    pub twenty_four_h_pct: Option<f64>,
+/// Percentage change of the current average against each configured window,
+/// keyed by window duration, e.g. `"1h_pct"` or `"7d_pct"`.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+#[serde(transparent)]
+pub struct TrendChanges(pub BTreeMap<String, Option<f64>>);
+
+impl TrendChanges {
+    /// Get the change for a window duration label such as `"6h"`
+    pub fn get(&self, window_label: &str) -> Option<f64> {
+        self.0.get(&format!("{}_pct", window_label)).copied().flatten()
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct FeeTrendResponse {
     pub status: String,
     pub trend_strength: String,
@@ -218,24 +708,61 @@ pub struct FeeTrendResponse {
     pub last_updated: DateTime<Utc>,
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FeeTrendQuery {
+    pub at: Option<DateTime<Utc>>,
+}
+
+/// Fee trend from the live insights engine, or as of `at` when given.
+#[utoipa::path(
+    get, path = "/fees/trend",
+    tag = "fees",
+    params(FeeTrendQuery),
+    responses(
+        (status = 200, description = "Fee trend", body = FeeTrendResponse),
+        (status = 400, description = "`at` is in the future", body = ErrorResponse),
+        (status = 503, description = "`at` given without a database", body = ErrorResponse),
+    )
+)]
 pub async fn fee_trend(
     State(state): State<FeesState>,
-) -> Result<Json<FeeTrendResponse>, AppError> {
-    let engine = state
-        .insights_engine
-        .as_ref()
-        .ok_or_else(|| AppError::Config("Insights engine missing from fees state".to_string()))?;
-    let insights = engine.read().await.get_current_insights();
-    let averages = &insights.rolling_averages;
-    let current_avg = averages.short_term.value;
-
-    let changes = TrendChanges {
-        one_h_pct: percent_change(current_avg, &averages.short_term),
-        six_h_pct: percent_change(current_avg, &averages.medium_term),
-        twenty_four_h_pct: percent_change(current_avg, &averages.long_term),
+    Query(params): Query<FeeTrendQuery>,
+) -> Result<Json<FeeTrendResponse>, (StatusCode, Json<Value>)> {
+    let engine = state.insights_engine.as_ref().ok_or_else(|| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Insights engine missing from fees state" })),
+        )
+    })?;
+    let insights = match params.at {
+        Some(at) => {
+            insights_at(state.point_in_time.as_deref(), engine, state.clock.now(), at).await?
+        }
+        None => engine.read().await.get_current_insights(),
     };
 
-    Ok(Json(FeeTrendResponse {
+    Ok(Json(trend_response(&insights)))
+}
+
+fn trend_response(insights: &CurrentInsights) -> FeeTrendResponse {
+    let averages = &insights.rolling_averages;
+    let current_avg = averages.current_value();
+
+    let changes = TrendChanges(
+        averages
+            .by_duration()
+            .into_iter()
+            .map(|window_avg| {
+                (
+                    format!("{}_pct", window_avg.time_window.label()),
+                    percent_change(current_avg, window_avg),
+                )
+            })
+            .collect(),
+    );
+
+    FeeTrendResponse {
         status: trend_indicator_to_string(&insights.congestion_trends.current_trend),
         trend_strength: trend_strength_to_string(&insights.congestion_trends.trend_strength),
         changes,
@@ -245,7 +772,7 @@ pub async fn fee_trend(
             .predicted_duration
             .map(|d| d.num_minutes()),
         last_updated: insights.last_updated,
-    }))
+    }
 }
 
 fn percent_change(current_avg: f64, window_avg: &crate::insights::AverageResult) -> Option<f64> {
@@ -289,6 +816,7 @@ mod tests {
         routing::get,
         Router,
     };
+    use crate::clock::{system_clock, ManualClock};
     use crate::insights::InsightsConfig;
     use chrono::Duration as ChronoDuration;
     use tower::ServiceExt;
@@ -341,6 +869,10 @@ mod tests {
             fee_cache: default_cache(),
             fee_store: Arc::new(RwLock::new(store)),
             insights_engine: None,
+            point_in_time: None,
+            repository: None,
+            retention: RetentionPolicy::default().shared(),
+            clock: system_clock(),
         })
     }
 
@@ -350,6 +882,10 @@ mod tests {
             fee_cache: default_cache(),
             fee_store: Arc::new(RwLock::new(FeeHistoryStore::new(100))),
             insights_engine: Some(Arc::new(RwLock::new(engine))),
+            point_in_time: None,
+            repository: None,
+            retention: RetentionPolicy::default().shared(),
+            clock: system_clock(),
         })
     }
 
@@ -362,6 +898,10 @@ mod tests {
             fee_cache: Arc::new(Mutex::new(ResponseCache::new(ttl))),
             fee_store: Arc::new(RwLock::new(FeeHistoryStore::new(100))),
             insights_engine: None,
+            point_in_time: None,
+            repository: None,
+            retention: RetentionPolicy::default().shared(),
+            clock: system_clock(),
         })
     }
 
@@ -527,7 +1067,7 @@ mod tests {
 
     #[tokio::test]
     async fn fee_history_returns_data_points_and_summary_for_supported_windows() {
-        for window in ["1h", "6h", "24h"] {
+        for window in ["1h", "6h", "24h", "15m", "7d", "30d"] {
             let state = make_fee_state_with_points(test_points(10, 10));
             let app = Router::new()
                 .route("/fees/history", get(fee_history))
@@ -551,12 +1091,88 @@ mod tests {
             assert_eq!(payload.data_points, 10);
             assert_eq!(payload.summary.min, 100);
             assert_eq!(payload.summary.max, 1000);
+            assert!((495..=505).contains(&payload.summary.percentiles.p50));
+            assert!((990..=1010).contains(&payload.summary.percentiles.p99));
         }
     }
 
     #[tokio::test]
-    async fn fee_history_invalid_window_returns_400() {
+    async fn fee_history_percentiles_come_from_the_engine_window_sketch() {
+        // The engine saw 5000 stroop fees over the last hour; the store only
+        // holds 100..1000, so percentiles near 5000 can only be its sketch
+        let mut engine = FeeInsightsEngine::new(InsightsConfig::default());
+        let now = Utc::now();
+        let seen: Vec<FeeDataPoint> = (0..5)
+            .map(|i| FeeDataPoint {
+                fee_amount: 5000,
+                timestamp: now - chrono::Duration::minutes(i),
+                transaction_hash: format!("seen{}", i),
+                ledger_sequence: i as u64,
+            })
+            .collect();
+        engine.process_fee_data(&seen).await.unwrap();
         let state = make_fee_state_with_points(test_points(10, 10));
+        let state = Arc::new(FeesApiState {
+            insights_engine: Some(Arc::new(RwLock::new(engine))),
+            ..(*state).clone()
+        });
+
+        let (status, json) = get_history(state.clone(), "window=1h").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["summary"]["min"], 100);
+        assert!((4950..=5050).contains(&json["summary"]["p50"].as_u64().unwrap()));
+
+        // Any other range is not one of the engine's windows
+        let (_, json) = get_history(state, "window=2h").await;
+        assert_eq!(json["summary"]["p50"], 500);
+    }
+
+    #[tokio::test]
+    async fn fee_history_invalid_window_returns_400() {
+        for window in ["invalid", "0m", "15", "h"] {
+            let state = make_fee_state_with_points(test_points(10, 10));
+            let app = Router::new()
+                .route("/fees/history", get(fee_history))
+                .with_state(state);
+
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/fees/history?window={}", window))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "window {}", window);
+        }
+    }
+
+    #[tokio::test]
+    async fn fee_history_window_ends_at_state_clock() {
+        let as_of = Utc::now() - ChronoDuration::days(2);
+        let point = |fee_amount: u64, timestamp| FeeDataPoint {
+            fee_amount,
+            timestamp,
+            transaction_hash: format!("tx-{}", fee_amount),
+            ledger_sequence: 1,
+        };
+        let mut store = FeeHistoryStore::new(100);
+        store.push(point(100, as_of - ChronoDuration::minutes(90)));
+        store.push(point(200, as_of - ChronoDuration::minutes(30)));
+        store.push(point(300, Utc::now()));
+
+        let state = Arc::new(FeesApiState {
+            fee_stats_provider: None,
+            fee_cache: default_cache(),
+            fee_store: Arc::new(RwLock::new(store)),
+            insights_engine: None,
+            point_in_time: None,
+            repository: None,
+            retention: RetentionPolicy::default().shared(),
+            clock: ManualClock::new(as_of).shared(),
+        });
         let app = Router::new()
             .route("/fees/history", get(fee_history))
             .with_state(state);
@@ -564,16 +1180,324 @@ mod tests {
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/fees/history?window=invalid")
+                    .uri("/fees/history?window=1h")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: FeeHistoryResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload.to, as_of);
+        assert_eq!(payload.data_points, 1);
+        assert_eq!(payload.fees[0].fee_amount, 200);
+    }
+
+    async fn get_history(state: FeesState, query: &str) -> (StatusCode, Value) {
+        let app = Router::new()
+            .route("/fees/history", get(fee_history))
+            .with_state(state);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/fees/history?{}", query))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn fee_history_pages_through_range_with_cursor() {
+        let state = make_fee_state_with_points(test_points(10, 10));
+        let mut query = "window=1h&limit=4".to_string();
+        let mut fees = Vec::new();
+
+        loop {
+            let (status, json) = get_history(state.clone(), &query).await;
+            assert_eq!(status, StatusCode::OK);
+            // The summary covers the whole range on every page
+            assert_eq!(json["total"], 10);
+            assert_eq!(json["summary"]["max"], 1000);
+            fees.extend(json["fees"].as_array().unwrap().iter().map(|f| f["fee_amount"].as_u64().unwrap()));
+
+            match json["next_cursor"].as_str() {
+                Some(cursor) => {
+                    query = format!(
+                        "from={}&to={}&limit=4&cursor={}",
+                        json["from"].as_str().unwrap(),
+                        json["to"].as_str().unwrap(),
+                        cursor
+                    )
+                }
+                None => break,
+            }
+        }
+
+        assert_eq!(fees, (1..=10).map(|i| i * 100).collect::<Vec<u64>>());
+
+        let (status, _) = get_history(state.clone(), "cursor=bogus").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get_history(state, "window=1h&from=2024-01-01T00:00:00Z").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn fee_history_reads_older_ranges_from_database() {
+        let repository = Arc::new(FeeRepository::new(
+            crate::db::create_pool("sqlite::memory:").await.unwrap(),
+        ));
+        let old = Utc::now() - ChronoDuration::days(3);
+        let stored: Vec<FeeDataPoint> = (0..5)
+            .map(|i| FeeDataPoint {
+                fee_amount: 100 * (i + 1),
+                timestamp: old + ChronoDuration::minutes(i as i64),
+                transaction_hash: format!("old-{}", i),
+                ledger_sequence: i,
+            })
+            .collect();
+        repository.insert_fee_points(&stored).await.unwrap();
+
+        // Memory only holds the last few minutes
+        let mut store = FeeHistoryStore::new(100);
+        for point in test_points(3, 3) {
+            store.push(point);
+        }
+        let state = Arc::new(FeesApiState {
+            fee_stats_provider: None,
+            fee_cache: default_cache(),
+            fee_store: Arc::new(RwLock::new(store)),
+            insights_engine: None,
+            point_in_time: None,
+            repository: Some(repository),
+            retention: RetentionPolicy::default().shared(),
+            clock: system_clock(),
+        });
+
+        let from = (old - ChronoDuration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let to = (old + ChronoDuration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let (status, json) = get_history(state, &format!("from={}&to={}&limit=2", from, to)).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["window"], "2h");
+        assert_eq!(json["data_points"], 2);
+        assert_eq!(json["total"], 5);
+        assert_eq!(json["fees"][0]["transaction_hash"], "old-0");
+        assert_eq!(json["summary"]["min"], 100);
+        assert_eq!(json["summary"]["max"], 500);
+        assert_eq!(json["summary"]["avg"], 300.0);
+        // No rollups were built, so percentiles are ranked in SQL
+        assert_eq!(json["summary"]["p50"], 300);
+        assert_eq!(json["summary"]["p99"], 500);
+        assert!(json["next_cursor"].is_string());
+    }
+
+    #[tokio::test]
+    async fn stored_summaries_describe_only_points_in_range() {
+        let repository = Arc::new(FeeRepository::new(
+            crate::db::create_pool("sqlite::memory:").await.unwrap(),
+        ));
+        let now = Utc::now();
+        let minute = DateTime::from_timestamp((now - ChronoDuration::days(3)).timestamp() / 60 * 60, 0).unwrap();
+        let point = |fee_amount: u64, offset_secs: i64| FeeDataPoint {
+            fee_amount,
+            timestamp: minute + ChronoDuration::seconds(offset_secs),
+            transaction_hash: format!("tx-{}", fee_amount),
+            ledger_sequence: 1,
+        };
+        // The first and last minute buckets spill past both ends of the range
+        let points = vec![
+            point(9_000, -30),
+            point(100, -5),
+            point(200, 10),
+            point(300, 70),
+            point(400, 125),
+            point(8_000, 150),
+        ];
+        repository.insert_fee_points(&points).await.unwrap();
+        repository.merge_rollups(ROLLUP_RESOLUTION_1M, &points).await.unwrap();
+
+        let state = Arc::new(FeesApiState {
+            fee_stats_provider: None,
+            fee_cache: default_cache(),
+            fee_store: Arc::new(RwLock::new(FeeHistoryStore::new(100))),
+            insights_engine: None,
+            point_in_time: None,
+            repository: Some(repository),
+            retention: RetentionPolicy::default().shared(),
+            clock: system_clock(),
+        });
+
+        let format = |at: DateTime<Utc>| at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        for (from, to, total) in [
+            // Whole minutes plus ragged ends
+            (minute - ChronoDuration::seconds(10), minute + ChronoDuration::seconds(130), 4),
+            // Inside a single minute
+            (minute + ChronoDuration::seconds(5), minute + ChronoDuration::seconds(15), 1),
+        ] {
+            let (status, json) =
+                get_history(state.clone(), &format!("from={}&to={}", format(from), format(to))).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(json["total"], total);
+            let summary = &json["summary"];
+            let (min, max) = (summary["min"].as_u64().unwrap(), summary["max"].as_u64().unwrap());
+            assert!(max <= 400, "{}", summary);
+            for key in ["p10", "p50", "p99"] {
+                let value = summary[key].as_u64().unwrap();
+                assert!((min..=max).contains(&value), "{} = {} outside [{}, {}]", key, value, min, max);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn fee_queries_fall_back_to_coarser_tiers_past_retention() {
+        let repository = Arc::new(FeeRepository::new(
+            crate::db::create_pool("sqlite::memory:").await.unwrap(),
+        ));
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        // Past the 90 days of minute-level rollups; raw points long pruned
+        let old = DateTime::from_timestamp(1_690_000_200, 0).unwrap();
+        let points: Vec<FeeDataPoint> = (0..5)
+            .map(|i| FeeDataPoint {
+                fee_amount: 100 * (i + 1),
+                timestamp: old + ChronoDuration::minutes(i as i64),
+                transaction_hash: format!("old-{}", i),
+                ledger_sequence: i,
+            })
+            .collect();
+        for resolution in ROLLUP_RESOLUTIONS {
+            repository.merge_rollups(resolution, &points).await.unwrap();
+        }
+
+        let state = Arc::new(FeesApiState {
+            fee_stats_provider: None,
+            fee_cache: default_cache(),
+            fee_store: Arc::new(RwLock::new(FeeHistoryStore::new(100))),
+            insights_engine: None,
+            point_in_time: None,
+            repository: Some(repository),
+            retention: RetentionPolicy::default().shared(),
+            clock: ManualClock::new(now).shared(),
+        });
+
+        let from = (old - ChronoDuration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let to = (old + ChronoDuration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let (status, json) = get_history(state.clone(), &format!("from={}&to={}", from, to)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["resolution"], "1h");
+        assert_eq!(json["data_points"], 0);
+        assert_eq!(json["total"], 5);
+        assert_eq!(json["summary"]["min"], 100);
+        assert_eq!(json["summary"]["max"], 500);
+        // The pruned range is served as its rollup series
+        let buckets = json["buckets"].as_array().unwrap();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0]["count"], 5);
+        assert_eq!(buckets[0]["high"], 500);
+
+        let app = Router::new()
+            .route("/fees/candles", get(fee_candles))
+            .with_state(state);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/fees/candles?resolution=1m&from={}&to={}", from, to))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: FeeCandlesResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload.resolution, "1h");
+        assert_eq!(payload.candles.iter().map(|c| c.count).sum::<u64>(), 5);
+    }
+
+    #[tokio::test]
+    async fn fee_candles_report_ohlc_per_bucket() {
+        let repository = Arc::new(FeeRepository::new(
+            crate::db::create_pool("sqlite::memory:").await.unwrap(),
+        ));
+        let start = DateTime::from_timestamp(1_700_000_100, 0).unwrap(); // on a 5m boundary
+        let points: Vec<FeeDataPoint> = [(0, 300), (60, 100), (120, 900), (240, 200), (300, 500)]
+            .into_iter()
+            .map(|(offset, fee_amount)| FeeDataPoint {
+                fee_amount,
+                timestamp: start + ChronoDuration::seconds(offset),
+                transaction_hash: format!("tx-{}", offset),
+                ledger_sequence: 1,
+            })
+            .collect();
+        repository.merge_rollups("5m", &points).await.unwrap();
+
+        let state = Arc::new(FeesApiState {
+            fee_stats_provider: None,
+            fee_cache: default_cache(),
+            fee_store: Arc::new(RwLock::new(FeeHistoryStore::new(100))),
+            insights_engine: None,
+            point_in_time: None,
+            repository: Some(repository),
+            retention: RetentionPolicy::default().shared(),
+            clock: ManualClock::new(start + ChronoDuration::hours(1)).shared(),
+        });
+        let app = Router::new()
+            .route("/fees/candles", get(fee_candles))
+            .with_state(state.clone());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/fees/candles?resolution=5m")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: FeeCandlesResponse = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(payload.candles.len(), 2);
+        let first = &payload.candles[0];
+        assert_eq!(first.bucket_start, start);
+        assert_eq!((first.open, first.high, first.low, first.close), (300, 900, 100, 200));
+        assert_eq!(first.count, 4);
+        assert_eq!(first.avg, 375.0);
+        assert_eq!(payload.candles[1].open, 500);
+
+        let app = Router::new()
+            .route("/fees/candles", get(fee_candles))
+            .with_state(state);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/fees/candles?resolution=1m&from=2000-01-01T00:00:00Z")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[tokio::test]
+    async fn fee_candles_require_a_database() {
+        let app = Router::new()
+            .route("/fees/candles", get(fee_candles))
+            .with_state(make_fee_state_with_points(Vec::new()));
+        let response = app
+            .oneshot(Request::builder().uri("/fees/candles").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
     fn points_with_spike(high_fee: u64) -> Vec<FeeDataPoint> {
         let now = Utc::now();
         vec![
@@ -673,6 +1597,26 @@ mod tests {
         assert_eq!(payload.status, "Rising");
     }
 
+    #[tokio::test]
+    async fn fee_trend_at_requires_a_database() {
+        let state = make_fee_state_with_engine(FeeInsightsEngine::new(InsightsConfig::default()));
+        let app = Router::new()
+            .route("/fees/trend", get(fee_trend))
+            .with_state(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/fees/trend?at=2024-03-01T00:00:00Z")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
     #[test]
     fn trend_indicator_declining_serialises_to_human_readable_string() {
         assert_eq!(
@@ -728,8 +1672,9 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
         let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
         let payload: FeeTrendResponse = serde_json::from_slice(&body).unwrap();
-        assert!(payload.changes.one_h_pct.is_none());
-        assert!(payload.changes.six_h_pct.is_none());
-        assert!(payload.changes.twenty_four_h_pct.is_none());
+        assert_eq!(payload.changes.0.len(), 3);
+        assert!(payload.changes.get("1h").is_none());
+        assert!(payload.changes.get("6h").is_none());
+        assert!(payload.changes.get("24h").is_none());
     }
 }
@@ -1,5 +1,12 @@
 use axum::response::IntoResponse;
 
+#[utoipa::path(
+    get, path = "/health",
+    tag = "system",
+    responses(
+        (status = 200, description = "The server is up", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn health() -> impl IntoResponse {
     "ok"
 }
\ No newline at end of file
@@ -1,73 +1,292 @@
 //! Insights API endpoints
 
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
     http::StatusCode,
     response::Json,
     routing::get,
     Router,
 };
-use serde_json::Value;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::insights::{FeeInsightsEngine, CurrentInsights, RollingAverages, FeeExtremes, CongestionTrends};
+use crate::api::openapi::ErrorResponse;
+use crate::clock::SharedClock;
+use crate::insights::{AverageResult, FeeInsightsEngine, CurrentInsights, RollingAverages, FeeExtremes, CongestionTrends, ExtremeValue, FeeSpike, SpikeSeverity};
+use crate::insights::seasonality::{parse_utc_offset, rollup_resolution, BestWindow, SeasonalityProfile};
+use crate::point_in_time::PointInTimeInsights;
+use crate::repository::FeeRepository;
+
+/// Default number of weeks of history used for seasonality analysis
+const DEFAULT_SEASONALITY_WEEKS: u32 = 4;
+/// Upper bound on `weeks` to keep the rollup scan bounded
+const MAX_SEASONALITY_WEEKS: u32 = 52;
+/// Default and maximum number of spikes returned by `/insights/spikes`
+const DEFAULT_SPIKE_LIMIT: i64 = 100;
+const MAX_SPIKE_LIMIT: i64 = 500;
+/// Default and maximum number of periods returned by `/insights/extremes/history`
+const DEFAULT_EXTREME_PERIODS: usize = 7;
+const MAX_EXTREME_PERIODS: usize = 366;
 
 /// Shared state for the insights API
-pub type InsightsState = Arc<RwLock<FeeInsightsEngine>>;
+pub type InsightsState = Arc<InsightsApiState>;
+
+pub struct InsightsApiState {
+    pub insights_engine: Arc<RwLock<FeeInsightsEngine>>,
+    pub repository: Option<Arc<FeeRepository>>,
+    pub point_in_time: Option<Arc<PointInTimeInsights>>,
+    pub clock: SharedClock,
+}
 
-/// Create the insights API router
-pub fn create_insights_router(insights_engine: InsightsState) -> Router {
+/// Create the insights API router. `?at=` queries are answered by
+/// `point_in_time` and rejected without one.
+pub fn create_insights_router(
+    insights_engine: Arc<RwLock<FeeInsightsEngine>>,
+    repository: Option<Arc<FeeRepository>>,
+    point_in_time: Option<Arc<PointInTimeInsights>>,
+    clock: SharedClock,
+) -> Router {
     Router::new()
         .route("/insights", get(get_current_insights))
         .route("/insights/averages", get(get_rolling_averages))
         .route("/insights/extremes", get(get_extremes))
+        .route("/insights/extremes/history", get(get_extremes_history))
         .route("/insights/congestion", get(get_congestion_trends))
         .route("/insights/health", get(get_insights_health))
-        .with_state(insights_engine)
+        .route("/insights/seasonality", get(get_seasonality))
+        .route("/insights/seasonality/best-window", get(get_best_window))
+        .route("/insights/spikes", get(get_spikes))
+        .route("/insights/spikes/:id", get(get_spike))
+        .with_state(Arc::new(InsightsApiState {
+            insights_engine,
+            point_in_time,
+            repository,
+            clock,
+        }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InsightsQuery {
+    pub at: Option<DateTime<Utc>>,
 }
 
 /// Get current insights
+///
+/// Query params:
+/// - `at` — optional RFC 3339 instant; insights as they stood then are
+///   rebuilt from persisted data instead of read from the live engine
+#[utoipa::path(
+    get, path = "/insights",
+    tag = "insights",
+    params(InsightsQuery),
+    responses(
+        (status = 200, description = "Current insights", body = CurrentInsights),
+        (status = 400, description = "`at` is in the future", body = ErrorResponse),
+        (status = 503, description = "`at` given without a database", body = ErrorResponse),
+    )
+)]
 async fn get_current_insights(
-    State(engine): State<InsightsState>,
+    State(state): State<InsightsState>,
+    Query(params): Query<InsightsQuery>,
 ) -> Result<Json<CurrentInsights>, (StatusCode, Json<Value>)> {
-    let engine = engine.read().await;
-    let insights = engine.get_current_insights();
-    Ok(Json(insights))
+    let Some(at) = params.at else {
+        let engine = state.insights_engine.read().await;
+        return Ok(Json(engine.get_current_insights()));
+    };
+
+    insights_at(
+        state.point_in_time.as_deref(),
+        &state.insights_engine,
+        state.clock.now(),
+        at,
+    )
+    .await
+    .map(Json)
+}
+
+/// Insights as of `at`, built with the live engine's configuration.
+/// Shared by `/insights?at=` and `/fees/trend?at=`.
+pub(crate) async fn insights_at(
+    point_in_time: Option<&PointInTimeInsights>,
+    insights_engine: &RwLock<FeeInsightsEngine>,
+    now: DateTime<Utc>,
+    at: DateTime<Utc>,
+) -> Result<CurrentInsights, (StatusCode, Json<Value>)> {
+    if at > now {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "at must not be in the future" })),
+        ));
+    }
+    let point_in_time = point_in_time.ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": "Point-in-time insights require a database" })),
+        )
+    })?;
+
+    let config = insights_engine.read().await.get_config().clone();
+    point_in_time.insights_at(&config, at).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": e.to_string() })),
+        )
+    })
 }
 
 /// Get rolling averages
+#[utoipa::path(
+    get, path = "/insights/averages",
+    tag = "insights",
+    responses(
+        (status = 200, description = "Rolling averages keyed by window", body = BTreeMap<String, AverageResult>),
+    )
+)]
 async fn get_rolling_averages(
-    State(engine): State<InsightsState>,
+    State(state): State<InsightsState>,
 ) -> Result<Json<RollingAverages>, (StatusCode, Json<Value>)> {
-    let engine = engine.read().await;
+    let engine = state.insights_engine.read().await;
     let averages = engine.get_rolling_averages();
     Ok(Json(averages))
 }
 
 /// Get fee extremes
+#[utoipa::path(
+    get, path = "/insights/extremes",
+    tag = "insights",
+    responses(
+        (status = 200, description = "Extremes of the current period", body = FeeExtremes),
+    )
+)]
 async fn get_extremes(
-    State(engine): State<InsightsState>,
+    State(state): State<InsightsState>,
 ) -> Result<Json<FeeExtremes>, (StatusCode, Json<Value>)> {
-    let engine = engine.read().await;
+    let engine = state.insights_engine.read().await;
     let extremes = engine.get_extremes();
     Ok(Json(extremes))
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExtremesHistoryQuery {
+    pub periods: Option<usize>,
+    pub top: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExtremesHistoryResponse {
+    pub periods: usize,
+    /// Highest fee across all returned periods
+    pub highest: Option<ExtremeValue>,
+    /// Lowest fee across all returned periods
+    pub lowest: Option<ExtremeValue>,
+    /// Closed periods, most recent first
+    pub items: Vec<FeeExtremes>,
+}
+
+/// Get extremes for closed tracking periods
+///
+/// Query params:
+/// - `periods` — number of most recent periods (default 7, max 366)
+/// - `top`     — trim each period's lowest/highest lists to this many values
+///
+/// Reads persisted periods when a database is configured, falling back to
+/// the periods held in memory by the engine.
+#[utoipa::path(
+    get, path = "/insights/extremes/history",
+    tag = "insights",
+    params(ExtremesHistoryQuery),
+    responses(
+        (status = 200, description = "Extremes of closed periods, newest first", body = ExtremesHistoryResponse),
+        (status = 400, description = "Invalid parameters", body = ErrorResponse),
+    )
+)]
+async fn get_extremes_history(
+    State(state): State<InsightsState>,
+    Query(params): Query<ExtremesHistoryQuery>,
+) -> Result<Json<ExtremesHistoryResponse>, (StatusCode, Json<Value>)> {
+    let periods = params.periods.unwrap_or(DEFAULT_EXTREME_PERIODS);
+    if periods == 0 || periods > MAX_EXTREME_PERIODS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": format!("periods must be between 1 and {}", MAX_EXTREME_PERIODS)
+            })),
+        ));
+    }
+
+    let mut items = match &state.repository {
+        Some(repository) => repository
+            .fetch_extreme_periods(periods as i64)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({ "error": e.to_string() })),
+                )
+            })?,
+        None => state.insights_engine.read().await.get_extremes_history(periods),
+    };
+
+    if let Some(top) = params.top {
+        for item in &mut items {
+            item.lowest.truncate(top.max(1));
+            item.highest.truncate(top.max(1));
+        }
+    }
+
+    let highest = items
+        .iter()
+        .map(|item| &item.current_max)
+        .max_by_key(|value| value.value)
+        .cloned();
+    let lowest = items
+        .iter()
+        .map(|item| &item.current_min)
+        .min_by_key(|value| value.value)
+        .cloned();
+
+    Ok(Json(ExtremesHistoryResponse {
+        periods: items.len(),
+        highest,
+        lowest,
+        items,
+    }))
+}
+
 /// Get congestion trends
+#[utoipa::path(
+    get, path = "/insights/congestion",
+    tag = "insights",
+    responses(
+        (status = 200, description = "Congestion trends", body = CongestionTrends),
+    )
+)]
 async fn get_congestion_trends(
-    State(engine): State<InsightsState>,
+    State(state): State<InsightsState>,
 ) -> Result<Json<CongestionTrends>, (StatusCode, Json<Value>)> {
-    let engine = engine.read().await;
+    let engine = state.insights_engine.read().await;
     let trends = engine.get_congestion_trends();
     Ok(Json(trends))
 }
 
 /// Get insights engine health status
+#[utoipa::path(
+    get, path = "/insights/health",
+    tag = "insights",
+    responses(
+        (status = 200, description = "Engine status and configuration summary", body = Object),
+    )
+)]
 async fn get_insights_health(
-    State(engine): State<InsightsState>,
+    State(state): State<InsightsState>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let engine = engine.read().await;
+    let engine = state.insights_engine.read().await;
     
     let health_info = serde_json::json!({
         "status": "healthy",
@@ -80,4 +299,515 @@ async fn get_insights_health(
     });
     
     Ok(Json(health_info))
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SeasonalityQuery {
+    /// Weeks of history to analyse (default 4, max 52)
+    pub weeks: Option<u32>,
+    /// `UTC` or a fixed offset such as `+02:00` (default UTC). Named zones
+    /// such as `Europe/Berlin` are not supported, so daylight saving time
+    /// is not followed. Offsets that are not whole hours are profiled from
+    /// 15-minute rollups, which are kept for less time than hourly ones.
+    pub tz: Option<String>,
+    /// Length of the window `best-window` looks for, in hours (default 1, max 24)
+    pub hours: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BestWindowResponse {
+    pub timezone: String,
+    pub weeks: u32,
+    pub window_hours: usize,
+    pub best_window: Option<BestWindow>,
+}
+
+/// Get the hour-of-week fee profile over the last `weeks` weeks
+///
+/// Query params:
+/// - `weeks` — weeks of persisted history to analyse (default 4, max 52)
+/// - `tz`    — `UTC` or a fixed offset such as `+02:00` (default UTC);
+///   named zones are not supported
+///
+/// Built from hourly fee rollups, which are kept after raw points are
+/// pruned, so every requested week is covered.
+#[utoipa::path(
+    get, path = "/insights/seasonality",
+    tag = "insights",
+    params(SeasonalityQuery),
+    responses(
+        (status = 200, description = "Hour-of-week fee profile", body = SeasonalityProfile),
+        (status = 400, description = "Invalid `weeks` or `tz`", body = ErrorResponse),
+        (status = 503, description = "No database configured", body = ErrorResponse),
+    )
+)]
+async fn get_seasonality(
+    State(state): State<InsightsState>,
+    Query(params): Query<SeasonalityQuery>,
+) -> Result<Json<SeasonalityProfile>, (StatusCode, Json<Value>)> {
+    Ok(Json(load_seasonality_profile(&state, &params).await?))
+}
+
+/// Get the cheapest window of `hours` hours (default 1) starting within
+/// the next 24 hours, based on the historical hour-of-week pattern
+#[utoipa::path(
+    get, path = "/insights/seasonality/best-window",
+    tag = "insights",
+    params(SeasonalityQuery),
+    responses(
+        (status = 200, description = "Cheapest upcoming window, null without history", body = BestWindowResponse),
+        (status = 400, description = "Invalid `weeks`, `tz` or `hours`", body = ErrorResponse),
+        (status = 503, description = "No database configured", body = ErrorResponse),
+    )
+)]
+async fn get_best_window(
+    State(state): State<InsightsState>,
+    Query(params): Query<SeasonalityQuery>,
+) -> Result<Json<BestWindowResponse>, (StatusCode, Json<Value>)> {
+    let window_hours = params.hours.unwrap_or(1);
+    if window_hours == 0 || window_hours > 24 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "hours must be between 1 and 24" })),
+        ));
+    }
+
+    let profile = load_seasonality_profile(&state, &params).await?;
+    let best_window = profile.best_window(state.clock.now(), window_hours);
+
+    Ok(Json(BestWindowResponse {
+        timezone: profile.timezone,
+        weeks: profile.weeks,
+        window_hours,
+        best_window,
+    }))
+}
+
+async fn load_seasonality_profile(
+    state: &InsightsApiState,
+    params: &SeasonalityQuery,
+) -> Result<SeasonalityProfile, (StatusCode, Json<Value>)> {
+    let weeks = params.weeks.unwrap_or(DEFAULT_SEASONALITY_WEEKS);
+    if weeks == 0 || weeks > MAX_SEASONALITY_WEEKS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": format!("weeks must be between 1 and {}", MAX_SEASONALITY_WEEKS)
+            })),
+        ));
+    }
+
+    let offset = parse_utc_offset(params.tz.as_deref().unwrap_or("UTC")).map_err(|e| {
+        (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() })))
+    })?;
+
+    let repository = require_repository(state, "Seasonality analysis")?;
+
+    // Raw points are pruned after days, so read the rollups instead
+    let to = state.clock.now();
+    let from = to - Duration::weeks(weeks as i64);
+    let rollups = repository
+        .fetch_rollups(rollup_resolution(offset), from, to)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            )
+        })?;
+
+    Ok(SeasonalityProfile::from_rollups(
+        rollups.iter().map(|rollup| (rollup.bucket_start, &rollup.sketch)),
+        offset,
+        from,
+        to,
+        weeks,
+    ))
+}
+
+fn require_repository<'a>(
+    state: &'a InsightsApiState,
+    feature: &str,
+) -> Result<&'a FeeRepository, (StatusCode, Json<Value>)> {
+    state.repository.as_deref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": format!("{} requires a database", feature) })),
+        )
+    })
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SpikesQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub severity: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SpikesResponse {
+    pub count: usize,
+    pub items: Vec<FeeSpike>,
+}
+
+/// List persisted spikes, newest first
+///
+/// Query params:
+/// - `from`, `to` — RFC 3339 bounds; spikes overlapping the range are returned
+/// - `severity`   — optional filter: Minor | Moderate | Major | Critical
+/// - `limit`      — max items (default 100, clamped to 500)
+#[utoipa::path(
+    get, path = "/insights/spikes",
+    tag = "insights",
+    params(SpikesQuery),
+    responses(
+        (status = 200, description = "Spikes, newest first", body = SpikesResponse),
+        (status = 400, description = "Invalid range, severity or limit", body = ErrorResponse),
+        (status = 503, description = "No database configured", body = ErrorResponse),
+    )
+)]
+async fn get_spikes(
+    State(state): State<InsightsState>,
+    Query(params): Query<SpikesQuery>,
+) -> Result<Json<SpikesResponse>, (StatusCode, Json<Value>)> {
+    if let Some(severity) = params.severity.as_deref() {
+        if SpikeSeverity::from_name(severity).is_none() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": format!(
+                        "Invalid severity '{}'. Must be one of: Minor, Moderate, Major, Critical",
+                        severity
+                    )
+                })),
+            ));
+        }
+    }
+    if let (Some(from), Some(to)) = (params.from, params.to) {
+        if from >= to {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "from must be before to" })),
+            ));
+        }
+    }
+
+    let repository = require_repository(&state, "Spike history")?;
+    let limit = params.limit.unwrap_or(DEFAULT_SPIKE_LIMIT).clamp(1, MAX_SPIKE_LIMIT);
+    let items = repository
+        .query_spikes(params.from, params.to, params.severity.as_deref(), limit)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            )
+        })?;
+
+    Ok(Json(SpikesResponse {
+        count: items.len(),
+        items,
+    }))
+}
+
+/// Get a single persisted spike by ID
+#[utoipa::path(
+    get, path = "/insights/spikes/{id}",
+    tag = "insights",
+    params(("id" = i64, Path, description = "Spike id")),
+    responses(
+        (status = 200, description = "The spike", body = FeeSpike),
+        (status = 404, description = "No spike with this id", body = ErrorResponse),
+        (status = 503, description = "No database configured", body = ErrorResponse),
+    )
+)]
+async fn get_spike(
+    State(state): State<InsightsState>,
+    Path(id): Path<String>,
+) -> Result<Json<FeeSpike>, (StatusCode, Json<Value>)> {
+    let repository = require_repository(&state, "Spike history")?;
+    let spike = repository.get_spike(&id).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": e.to_string() })),
+        )
+    })?;
+
+    spike.map(Json).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("Spike '{}' not found", id) })),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::Request,
+    };
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use crate::clock::system_clock;
+    use crate::db::create_pool;
+    use crate::insights::{FeeDataPoint, InsightsConfig};
+    use crate::repository::ROLLUP_RESOLUTIONS;
+
+    async fn make_app(points: Vec<FeeDataPoint>) -> Router {
+        make_app_with_repo(points).await.0
+    }
+
+    async fn make_app_with_repo(points: Vec<FeeDataPoint>) -> (Router, Arc<FeeRepository>) {
+        let pool = create_pool("sqlite::memory:").await.unwrap();
+        let repo = Arc::new(FeeRepository::new(pool));
+        repo.insert_fee_points(&points).await.unwrap();
+        for resolution in ROLLUP_RESOLUTIONS {
+            repo.merge_rollups(resolution, &points).await.unwrap();
+        }
+
+        let engine = Arc::new(RwLock::new(FeeInsightsEngine::new(InsightsConfig::default())));
+        let point_in_time = Arc::new(PointInTimeInsights::new(repo.clone()));
+        (
+            create_insights_router(engine, Some(repo.clone()), Some(point_in_time), system_clock()),
+            repo,
+        )
+    }
+
+    async fn get(app: Router, uri: &str) -> (StatusCode, Value) {
+        let resp = app
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = resp.status();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn hourly_points(hours: i64) -> Vec<FeeDataPoint> {
+        let now = Utc::now();
+        (1..=hours)
+            .map(|h| FeeDataPoint {
+                fee_amount: 100 + h as u64,
+                timestamp: now - Duration::hours(h),
+                transaction_hash: format!("tx{}", h),
+                ledger_sequence: h as u64,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn seasonality_returns_168_buckets() {
+        let app = make_app(hourly_points(48)).await;
+        let (status, json) = get(app, "/insights/seasonality?weeks=1").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["buckets"].as_array().unwrap().len(), 168);
+        assert_eq!(json["total_transactions"], 48);
+        assert_eq!(json["timezone"], "UTC");
+    }
+
+    #[tokio::test]
+    async fn seasonality_accepts_offset_timezone() {
+        let app = make_app(hourly_points(2)).await;
+        let (status, json) = get(app, "/insights/seasonality?tz=%2B05:30").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["timezone"], "+05:30");
+    }
+
+    #[tokio::test]
+    async fn seasonality_rejects_invalid_parameters() {
+        let (status, _) = get(make_app(vec![]).await, "/insights/seasonality?weeks=0").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, json) = get(make_app(vec![]).await, "/insights/seasonality?tz=Europe/Berlin").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(json["error"].as_str().unwrap().contains("named zones are not supported"));
+    }
+
+    #[tokio::test]
+    async fn seasonality_covers_weeks_whose_raw_points_were_pruned() {
+        // Only the hourly rollup of a three-week-old fee is left
+        let (app, repo) = make_app_with_repo(vec![]).await;
+        let old = FeeDataPoint {
+            fee_amount: 500,
+            timestamp: Utc::now() - Duration::weeks(3),
+            transaction_hash: "old".to_string(),
+            ledger_sequence: 1,
+        };
+        repo.merge_rollups("1h", &[old]).await.unwrap();
+
+        let (status, json) = get(app.clone(), "/insights/seasonality?weeks=4").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["total_transactions"], 1);
+
+        let (_, json) = get(app, "/insights/seasonality?weeks=2").await;
+        assert_eq!(json["total_transactions"], 0);
+    }
+
+    #[tokio::test]
+    async fn seasonality_without_repository_returns_503() {
+        let engine = Arc::new(RwLock::new(FeeInsightsEngine::new(InsightsConfig::default())));
+        let app = create_insights_router(engine, None, None, system_clock());
+        let (status, _) = get(app, "/insights/seasonality").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn best_window_uses_last_weeks_pattern() {
+        // Seven days of hourly data covers every hour-of-week slot once
+        let app = make_app(hourly_points(24 * 7)).await;
+        let (status, json) = get(app, "/insights/seasonality/best-window?weeks=1&hours=2").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["window_hours"], 2);
+        assert!(json["best_window"]["start"].is_string());
+        assert!(json["best_window"]["expected_median_fee"].is_number());
+    }
+
+    #[tokio::test]
+    async fn best_window_is_null_without_history() {
+        let app = make_app(vec![]).await;
+        let (status, json) = get(app, "/insights/seasonality/best-window").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(json["best_window"].is_null());
+    }
+
+    fn make_spike(start: DateTime<Utc>, severity: SpikeSeverity) -> FeeSpike {
+        FeeSpike {
+            id: FeeSpike::id_for(start),
+            peak_fee: 800,
+            baseline_fee: 100.0,
+            spike_ratio: 8.0,
+            start_time: start,
+            duration: Duration::minutes(15),
+            severity,
+            strategy: "threshold".to_string(),
+            score: 8.0,
+            end_time: Some(start + Duration::minutes(15)),
+        }
+    }
+
+    #[tokio::test]
+    async fn spikes_lists_and_filters_persisted_spikes() {
+        let (app, repo) = make_app_with_repo(vec![]).await;
+        let t0 = DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z").unwrap().with_timezone(&Utc);
+        repo.upsert_spike(&make_spike(t0, SpikeSeverity::Minor), t0).await.unwrap();
+        repo.upsert_spike(&make_spike(t0 + Duration::days(1), SpikeSeverity::Critical), t0).await.unwrap();
+
+        let (status, json) = get(app.clone(), "/insights/spikes").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["count"], 2);
+
+        let (status, json) = get(app.clone(), "/insights/spikes?severity=Critical").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["count"], 1);
+        assert_eq!(json["items"][0]["severity"], "Critical");
+
+        let (status, json) = get(
+            app,
+            "/insights/spikes?from=2024-03-01T00:00:00Z&to=2024-03-02T00:00:00Z",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["count"], 1);
+        assert_eq!(json["items"][0]["id"], FeeSpike::id_for(t0));
+    }
+
+    #[tokio::test]
+    async fn spikes_rejects_invalid_parameters() {
+        let (status, _) = get(make_app(vec![]).await, "/insights/spikes?severity=Huge").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = get(
+            make_app(vec![]).await,
+            "/insights/spikes?from=2024-03-02T00:00:00Z&to=2024-03-01T00:00:00Z",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn spike_detail_returns_spike_or_404() {
+        let (app, repo) = make_app_with_repo(vec![]).await;
+        let start = Utc::now() - Duration::hours(1);
+        let spike = make_spike(start, SpikeSeverity::Major);
+        repo.upsert_spike(&spike, start).await.unwrap();
+
+        let (status, json) = get(app.clone(), &format!("/insights/spikes/{}", spike.id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["peak_fee"], 800);
+        assert_eq!(json["strategy"], "threshold");
+
+        let (status, _) = get(app, "/insights/spikes/spk_0").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn extremes_history_reads_persisted_periods() {
+        let (app, repo) = make_app_with_repo(vec![]).await;
+        let t0 = DateTime::parse_from_rfc3339("2024-03-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let value = |fee: u64, at: DateTime<Utc>| ExtremeValue {
+            value: fee,
+            timestamp: at,
+            transaction_hash: format!("tx{}", fee),
+        };
+        for (day, max) in [(0, 900u64), (1, 4_000), (2, 1_200)] {
+            let start = t0 + Duration::days(day);
+            repo.insert_extreme_period(&FeeExtremes {
+                current_min: value(100, start),
+                current_max: value(max, start),
+                period_start: start,
+                period_end: start + Duration::days(1),
+                lowest: vec![value(100, start), value(110, start)],
+                highest: vec![value(max, start), value(max - 1, start)],
+            })
+            .await
+            .unwrap();
+        }
+
+        let (status, json) = get(app.clone(), "/insights/extremes/history?periods=2&top=1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["periods"], 2);
+        assert_eq!(json["items"][0]["current_max"]["value"], 1_200);
+        assert_eq!(json["items"][0]["highest"].as_array().unwrap().len(), 1);
+        assert_eq!(json["highest"]["value"], 4_000);
+
+        let (status, _) = get(app, "/insights/extremes/history?periods=0").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn insights_at_rebuilds_past_state_and_rejects_future() {
+        let start = Utc::now() - Duration::days(2);
+        let points: Vec<FeeDataPoint> = (0..60)
+            .map(|i| FeeDataPoint {
+                fee_amount: if i < 30 { 100 } else { 700 },
+                timestamp: start + Duration::minutes(i),
+                transaction_hash: format!("at_{}", i),
+                ledger_sequence: i as u64,
+            })
+            .collect();
+        let (app, _) = make_app_with_repo(points).await;
+
+        let at = (start + Duration::minutes(20)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let (status, json) = get(app.clone(), &format!("/insights?at={}", at)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["extremes"]["current_max"]["value"], 100);
+
+        let (status, json) = get(app.clone(), "/insights").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(json["extremes"]["current_max"]["value"].is_u64());
+
+        let future = (Utc::now() + Duration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let (status, _) = get(app, &format!("/insights?at={}", future)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,31 @@
+//! `GET /metrics` — Prometheus metrics in the text exposition format.
+//!
+//! Left outside the API-key and rate limits so it can always be scraped.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::metrics::AppMetrics;
+
+#[utoipa::path(
+    get, path = "/metrics",
+    tag = "system",
+    responses(
+        (status = 200, description = "Every registered metric", body = String, content_type = "text/plain; version=0.0.4"),
+        (status = 500, description = "The metrics could not be rendered", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn metrics(State(metrics): State<Arc<AppMetrics>>) -> Response {
+    match metrics.render() {
+        Ok(body) => ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response(),
+        Err(err) => {
+            tracing::error!("Failed to render metrics: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, "metrics error").into_response()
+        }
+    }
+}
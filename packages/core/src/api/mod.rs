@@ -1,6 +1,13 @@
 pub mod health;
+pub mod metrics;
 pub mod fees;
 pub mod insights;
 pub mod alerts;
-
-
+pub mod admin;
+pub mod export;
+pub mod stream;
+pub mod ws;
+pub mod openapi;
+pub mod v1;
+pub mod conditional;
+pub mod rate_limit;
@@ -0,0 +1,312 @@
+//! `GET /openapi.json` and `GET /docs` — the API described as OpenAPI 3.
+//!
+//! The document is generated from the `#[utoipa::path]` annotations on the
+//! handlers and the `ToSchema` types they return. A copy is kept in
+//! `openapi.json` at the crate root; a test fails when the two differ, so
+//! changes to the API show up in review. Run the tests with
+//! `UPDATE_OPENAPI=1` to rewrite the copy.
+//!
+//! Handlers are annotated with their unversioned path. The document lists
+//! each under `/v1` as well, unless a `/v1` handler of its own exists, and
+//! marks the unversioned operations deprecated. Those `/v1` copies answer
+//! 400 to a `unit` parameter, which only the `/v1` handlers convert.
+
+use axum::{
+    response::{Html, IntoResponse},
+    Json,
+};
+use serde::Serialize;
+use utoipa::{
+    openapi::{
+        path::{Operation, PathItem},
+        security::{ApiKey, ApiKeyValue, SecurityScheme},
+        Content, Deprecated, Ref, RefOr, Response,
+    },
+    Modify, OpenApi, ToSchema,
+};
+
+use crate::api::admin::API_KEY_HEADER;
+use crate::api::v1::{API_PREFIX, UNIT_UNSUPPORTED};
+use crate::api::{admin, alerts, export, fees, health, insights, metrics, stream, v1, ws};
+
+/// Paths served only without a version prefix.
+const UNVERSIONED_PATHS: [&str; 2] = ["/health", "/metrics"];
+
+/// Body of every error response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Stellar Fee Tracker API",
+        description = "Stellar network fee stats, history and insights.",
+        license(name = "MIT")
+    ),
+    paths(
+        health::health,
+        metrics::metrics,
+        v1::current_fees,
+        v1::fee_history,
+        v1::fee_candles,
+        fees::current_fees,
+        fees::fee_history,
+        fees::fee_candles,
+        fees::fee_trend,
+        export::export_fees,
+        stream::fee_stream,
+        ws::live_feed,
+        insights::get_current_insights,
+        insights::get_rolling_averages,
+        insights::get_extremes,
+        insights::get_extremes_history,
+        insights::get_congestion_trends,
+        insights::get_insights_health,
+        insights::get_seasonality,
+        insights::get_best_window,
+        insights::get_spikes,
+        insights::get_spike,
+        alerts::create_alert,
+        alerts::list_alerts,
+        alerts::update_alert,
+        alerts::delete_alert,
+        alerts::get_alert_history,
+        admin::get_insights_config,
+        admin::put_insights_config,
+        admin::get_insights_config_history,
+        admin::import_fee_data,
+    ),
+    components(schemas(ErrorResponse)),
+    modifiers(&ApiKeyAuth, &VersionedPaths),
+    tags(
+        (name = "fees", description = "Current fees, history and exports"),
+        (name = "insights", description = "Averages, extremes, congestion, spikes and seasonality"),
+        (name = "alerts", description = "Webhook alerts"),
+        (name = "live", description = "Live feed over WebSocket or Server-Sent Events"),
+        (name = "admin", description = "Runtime configuration and data import"),
+        (name = "system", description = "Service health and metrics"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Declares the `api_key` scheme the admin routes require.
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+        );
+    }
+}
+
+/// Adds the `/v1` paths served by the unversioned handlers, with the 400
+/// they give a `unit` parameter, and deprecates the unversioned paths.
+struct VersionedPaths;
+
+impl Modify for VersionedPaths {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let paths = &mut openapi.paths.paths;
+        let legacy: Vec<String> = paths
+            .keys()
+            .filter(|path| !path.starts_with(API_PREFIX) && !UNVERSIONED_PATHS.contains(&path.as_str()))
+            .cloned()
+            .collect();
+
+        for path in legacy {
+            let Some(item) = paths.get_mut(&path) else {
+                continue;
+            };
+            let mut versioned = item.clone();
+            for operation in operations(item) {
+                operation.deprecated = Some(Deprecated::True);
+                // Operation ids must stay unique alongside the /v1 copy
+                operation.operation_id = operation.operation_id.take().map(|id| format!("legacy_{}", id));
+            }
+            for operation in operations(&mut versioned) {
+                document_unit_rejection(operation);
+            }
+            paths.entry(format!("{}{}", API_PREFIX, path)).or_insert(versioned);
+        }
+    }
+}
+
+fn document_unit_rejection(operation: &mut Operation) {
+    let responses = &mut operation.responses.responses;
+    match responses.get_mut("400") {
+        Some(RefOr::T(response)) => {
+            response.description = format!("{}; or {}", response.description, UNIT_UNSUPPORTED);
+        }
+        _ => {
+            let body = Content::new(Some(Ref::from_schema_name("ErrorResponse")));
+            responses.insert(
+                "400".to_string(),
+                RefOr::T(Response::builder().description(UNIT_UNSUPPORTED).content("application/json", body).build()),
+            );
+        }
+    }
+}
+
+fn operations(item: &mut PathItem) -> impl Iterator<Item = &mut Operation> {
+    [
+        &mut item.get,
+        &mut item.put,
+        &mut item.post,
+        &mut item.delete,
+        &mut item.options,
+        &mut item.head,
+        &mut item.patch,
+        &mut item.trace,
+    ]
+    .into_iter()
+    .filter_map(Option::as_mut)
+}
+
+/// `GET /openapi.json`
+pub async fn openapi_json() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
+
+const DOCS_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>Stellar Fee Tracker API</title>
+    <meta charset="utf-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1" />
+  </head>
+  <body>
+    <redoc spec-url="/openapi.json"></redoc>
+    <script src="https://cdn.redoc.ly/redoc/latest/bundles/redoc.standalone.js"></script>
+  </body>
+</html>
+"#;
+
+/// `GET /docs` — the document rendered with Redoc.
+pub async fn docs() -> Html<&'static str> {
+    Html(DOCS_PAGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+
+    #[test]
+    fn committed_spec_matches_the_code() {
+        let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(SNAPSHOT, &generated).unwrap();
+            return;
+        }
+        let committed = std::fs::read_to_string(SNAPSHOT).unwrap_or_default();
+        assert!(
+            committed == generated,
+            "openapi.json is out of date with the handlers; rerun the tests with UPDATE_OPENAPI=1 and commit the result"
+        );
+    }
+
+    #[test]
+    fn documents_the_main_responses() {
+        let spec = ApiDoc::openapi();
+        let schemas = &spec.components.as_ref().unwrap().schemas;
+        for name in ["CurrentFeeResponse", "FeeHistoryResponse", "CurrentInsights", "AlertHistoryResponse"] {
+            assert!(schemas.contains_key(name), "missing schema {}", name);
+        }
+        assert!(spec.paths.paths.contains_key("/v1/insights/spikes/{id}"));
+        assert!(schemas.contains_key("v1.CurrentFeeResponse"));
+    }
+
+    #[test]
+    fn unversioned_paths_are_deprecated_in_favour_of_v1() {
+        let spec = ApiDoc::openapi();
+        let paths = &spec.paths.paths;
+
+        let legacy = paths["/fees/current"].get.as_ref().unwrap();
+        assert!(matches!(legacy.deprecated, Some(Deprecated::True)));
+        assert_eq!(legacy.operation_id.as_deref(), Some("legacy_current_fees"));
+
+        // The /v1 handler is kept, not replaced by a copy of the legacy one
+        let current = paths["/v1/fees/current"].get.as_ref().unwrap();
+        assert!(current.deprecated.is_none());
+        assert_eq!(current.operation_id.as_deref(), Some("current_fees"));
+
+        let trend = paths["/v1/fees/trend"].get.as_ref().unwrap();
+        assert!(trend.deprecated.is_none());
+        // Copies of unversioned handlers refuse `unit`; the /v1 handlers take it
+        let RefOr::T(refused) = &trend.responses.responses["400"] else {
+            panic!("inline 400 response expected");
+        };
+        assert!(refused.description.ends_with(UNIT_UNSUPPORTED), "{}", refused.description);
+        assert!(paths["/health"].get.as_ref().unwrap().deprecated.is_none());
+        assert!(!paths.contains_key("/v1/health"));
+    }
+
+    /// Sources of the routers `serve` mounts
+    const ROUTER_SOURCES: [&str; 4] = [
+        include_str!("../main.rs"),
+        include_str!("v1.rs"),
+        include_str!("insights.rs"),
+        include_str!("admin.rs"),
+    ];
+
+    /// Routes that serve the document itself
+    const UNDOCUMENTED_PATHS: [&str; 2] = ["/openapi.json", "/docs"];
+
+    #[test]
+    fn every_routed_path_is_documented() {
+        let spec = ApiDoc::openapi();
+        let routed: Vec<String> = ROUTER_SOURCES
+            .iter()
+            .flat_map(|source| source.split(".route(").skip(1))
+            .filter_map(|call| call.trim_start().strip_prefix('"')?.split('"').next())
+            .map(|path| {
+                path.split('/')
+                    .map(|segment| match segment.strip_prefix(':') {
+                        Some(param) => format!("{{{}}}", param),
+                        None => segment.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .collect();
+        assert!(routed.iter().any(|path| path == "/metrics"));
+
+        for path in routed {
+            if UNDOCUMENTED_PATHS.contains(&path.as_str()) {
+                continue;
+            }
+            assert!(
+                spec.paths.paths.contains_key(&path)
+                    || spec.paths.paths.contains_key(&format!("{}{}", API_PREFIX, path)),
+                "{} is routed but not documented",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn path_parameters_appear_in_the_path() {
+        use utoipa::openapi::path::ParameterIn;
+
+        for (path, item) in ApiDoc::openapi().paths.paths {
+            let operations = [item.get, item.put, item.post, item.delete, item.patch];
+            for operation in operations.into_iter().flatten() {
+                for parameter in operation.parameters.unwrap_or_default() {
+                    if matches!(parameter.parameter_in, ParameterIn::Path) {
+                        assert!(
+                            path.contains(&format!("{{{}}}", parameter.name)),
+                            "{} documents `{}` as a path parameter",
+                            path,
+                            parameter.name
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
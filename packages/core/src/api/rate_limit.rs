@@ -0,0 +1,510 @@
+//! Token-bucket rate limiting for the API routes.
+//!
+//! Each route group has its own limit, written `120/1m`: a bucket holding
+//! 120 requests that refills evenly over a minute. Every client IP gets a
+//! bucket per group, and so does every `X-Api-Key` a request carries; a
+//! request spends a token from each of its buckets, so a key neither lifts
+//! the limit of the address using it nor escapes its own limit by moving
+//! between addresses. IPv6 clients are bucketed by their /64.
+//!
+//! Responses carry `X-RateLimit-Limit`, `X-RateLimit-Remaining` and
+//! `X-RateLimit-Reset` (seconds until the bucket is full again) for the
+//! emptier of the request's buckets. A request finding a bucket empty gets
+//! `429 Too Many Requests` with `Retry-After`, and is counted in
+//! `stellar_fee_tracker_rate_limited_requests_total`.
+//!
+//! The client address is the peer address, or with
+//! `RATE_LIMIT_TRUST_FORWARDED_FOR` the last `X-Forwarded-For` entry, as
+//! added by a reverse proxy in front of the service.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, RandomState};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+use crate::api::admin::API_KEY_HEADER;
+use crate::insights::parse_duration;
+use crate::metrics::AppMetrics;
+
+const LIMIT_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const REMAINING_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+const RESET_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// How often buckets that have refilled completely are dropped.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Routes sharing a limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RouteGroup {
+    /// `/fees/*` apart from the live stream
+    Fees,
+    /// `/insights/*`
+    Insights,
+    /// `/alerts/*`
+    Alerts,
+    /// `/ws` and `/fees/stream`, limiting new connections
+    Live,
+    /// `/admin/*`
+    Admin,
+}
+
+impl RouteGroup {
+    pub const ALL: [RouteGroup; 5] = [
+        RouteGroup::Fees,
+        RouteGroup::Insights,
+        RouteGroup::Alerts,
+        RouteGroup::Live,
+        RouteGroup::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteGroup::Fees => "fees",
+            RouteGroup::Insights => "insights",
+            RouteGroup::Alerts => "alerts",
+            RouteGroup::Live => "live",
+            RouteGroup::Admin => "admin",
+        }
+    }
+
+    /// Limit applied when none is configured.
+    fn default_limit(&self) -> RateLimit {
+        let requests = match self {
+            RouteGroup::Fees | RouteGroup::Insights => 120,
+            RouteGroup::Alerts => 30,
+            RouteGroup::Live | RouteGroup::Admin => 10,
+        };
+        RateLimit { requests, per: Duration::from_secs(60) }
+    }
+}
+
+impl FromStr for RouteGroup {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        RouteGroup::ALL
+            .into_iter()
+            .find(|group| group.as_str() == value.trim())
+            .ok_or_else(|| {
+                let names: Vec<&str> = RouteGroup::ALL.iter().map(RouteGroup::as_str).collect();
+                format!("Unknown route group '{}'. Must be one of: {}", value, names.join(", "))
+            })
+    }
+}
+
+/// `requests` per `per`, which is also the most a client can burst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub per: Duration,
+}
+
+impl RateLimit {
+    fn tokens_per_second(&self) -> f64 {
+        self.requests as f64 / self.per.as_secs_f64()
+    }
+
+    /// Time for a bucket to regain `tokens`.
+    fn time_to_refill(&self, tokens: f64) -> Duration {
+        Duration::from_secs_f64(tokens.max(0.0) / self.tokens_per_second())
+    }
+}
+
+/// Parses `120/1m`; `off` disables the limit.
+pub fn parse_rate_limit(value: &str) -> Result<Option<RateLimit>, String> {
+    let value = value.trim();
+    if value == "off" {
+        return Ok(None);
+    }
+    let invalid = || format!("Invalid rate limit '{}'. Expected e.g. 120/1m or off", value);
+    let (requests, per) = value.split_once('/').ok_or_else(invalid)?;
+    let requests: u32 = requests.trim().parse().map_err(|_| invalid())?;
+    let per = parse_duration(per)
+        .and_then(|per| per.to_std().ok())
+        .ok_or_else(invalid)?;
+    if requests == 0 || per.is_zero() {
+        return Err(invalid());
+    }
+    Ok(Some(RateLimit { requests, per }))
+}
+
+/// The limit of every route group; `None` leaves a group unlimited.
+pub fn default_rate_limits() -> BTreeMap<RouteGroup, Option<RateLimit>> {
+    RouteGroup::ALL
+        .into_iter()
+        .map(|group| (group, Some(group.default_limit())))
+        .collect()
+}
+
+/// Who a bucket belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Client {
+    Ip(IpAddr),
+    /// Hash of the key, so buckets do not hold key material
+    ApiKey(u64),
+}
+
+impl Client {
+    fn label(&self) -> &'static str {
+        match self {
+            Client::Ip(_) => "ip",
+            Client::ApiKey(_) => "api_key",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.tokens_per_second()).min(limit.requests as f64);
+        self.updated = now;
+    }
+}
+
+/// The outcome of one request against its buckets.
+#[derive(Debug, Clone, PartialEq)]
+struct Decision {
+    limit: u32,
+    remaining: u32,
+    reset: Duration,
+    /// Set when the request is refused, with the client whose bucket was empty
+    rejected: Option<(Client, Duration)>,
+}
+
+struct Buckets {
+    by_client: HashMap<(RouteGroup, Client), Bucket>,
+    last_sweep: Instant,
+}
+
+/// Buckets of every client, shared by all the rate-limited routes.
+pub struct RateLimiter {
+    limits: BTreeMap<RouteGroup, Option<RateLimit>>,
+    trust_forwarded_for: bool,
+    buckets: Mutex<Buckets>,
+    key_hasher: RandomState,
+    metrics: Option<Arc<AppMetrics>>,
+}
+
+impl RateLimiter {
+    /// Groups missing from `limits` are unlimited.
+    pub fn new(limits: BTreeMap<RouteGroup, Option<RateLimit>>, trust_forwarded_for: bool) -> Self {
+        Self {
+            limits,
+            trust_forwarded_for,
+            buckets: Mutex::new(Buckets { by_client: HashMap::new(), last_sweep: Instant::now() }),
+            key_hasher: RandomState::new(),
+            metrics: None,
+        }
+    }
+
+    /// Count rejected requests in `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<AppMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// The buckets `request` spends from: its address, then its key.
+    fn clients(&self, request: &Request) -> Vec<Client> {
+        let mut clients = vec![Client::Ip(bucket_address(self.client_ip(request)))];
+        if let Some(key) = request.headers().get(API_KEY_HEADER) {
+            clients.push(Client::ApiKey(self.key_hasher.hash_one(key.as_bytes())));
+        }
+        clients
+    }
+
+    fn client_ip(&self, request: &Request) -> IpAddr {
+        let forwarded = self
+            .trust_forwarded_for
+            .then(|| request.headers().get(FORWARDED_FOR_HEADER))
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        forwarded
+            .or_else(|| {
+                request
+                    .extensions()
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| addr.ip())
+            })
+            // Without connection info every request shares one bucket
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    }
+
+    /// Take a token from each of `clients`' buckets, or from none of them
+    /// if any is empty. `None` when the group is unlimited.
+    fn check(&self, group: RouteGroup, clients: &[Client], now: Instant) -> Option<Decision> {
+        let limit = self.limits.get(&group).copied().flatten()?;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if now.saturating_duration_since(buckets.last_sweep) >= SWEEP_INTERVAL {
+            buckets.sweep(&self.limits, now);
+        }
+
+        let full = Bucket { tokens: limit.requests as f64, updated: now };
+        let mut current: Vec<Bucket> = clients
+            .iter()
+            .map(|client| {
+                let mut bucket = buckets.by_client.get(&(group, *client)).copied().unwrap_or(full);
+                bucket.refill(&limit, now);
+                bucket
+            })
+            .collect();
+
+        let empty = clients
+            .iter()
+            .zip(&current)
+            .find(|(_, bucket)| bucket.tokens < 1.0)
+            .map(|(client, bucket)| (*client, limit.time_to_refill(1.0 - bucket.tokens)));
+        if empty.is_none() {
+            for bucket in &mut current {
+                bucket.tokens -= 1.0;
+            }
+        }
+        for (client, bucket) in clients.iter().zip(&current) {
+            buckets.by_client.insert((group, *client), *bucket);
+        }
+
+        let lowest = current.iter().map(|bucket| bucket.tokens).fold(limit.requests as f64, f64::min);
+        Some(Decision {
+            limit: limit.requests,
+            remaining: lowest.max(0.0) as u32,
+            reset: limit.time_to_refill(limit.requests as f64 - lowest),
+            rejected: empty,
+        })
+    }
+}
+
+impl Buckets {
+    /// Drop buckets that have refilled, as a new one would start full.
+    fn sweep(&mut self, limits: &BTreeMap<RouteGroup, Option<RateLimit>>, now: Instant) {
+        self.by_client.retain(|(group, _), bucket| {
+            limits
+                .get(group)
+                .copied()
+                .flatten()
+                .is_some_and(|limit| now.saturating_duration_since(bucket.updated) < limit.per)
+        });
+        self.last_sweep = now;
+    }
+}
+
+/// Bucket IPv6 clients by /64, the smallest block usually given to one site.
+fn bucket_address(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(Ipv6Addr::from(u128::from(v6) & !(u64::MAX as u128))),
+        },
+        v4 => v4,
+    }
+}
+
+/// Middleware state: the shared limiter and the group of the routes it wraps.
+#[derive(Clone)]
+pub struct RateLimitState {
+    pub limiter: Arc<RateLimiter>,
+    pub group: RouteGroup,
+}
+
+/// Middleware limiting the request rate of each client.
+pub async fn rate_limit(
+    State(state): State<RateLimitState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let clients = state.limiter.clients(&request);
+    let Some(decision) = state.limiter.check(state.group, &clients, Instant::now()) else {
+        return next.run(request).await;
+    };
+
+    let mut response = match decision.rejected {
+        Some((client, retry_after)) => {
+            if let Some(metrics) = &state.limiter.metrics {
+                metrics
+                    .rate_limited_total
+                    .with_label_values(&[state.group.as_str(), client.label()])
+                    .inc();
+            }
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(json!({ "error": "Rate limit exceeded" })),
+            )
+                .into_response();
+            response.headers_mut().insert(
+                axum::http::header::RETRY_AFTER,
+                HeaderValue::from(whole_seconds(retry_after)),
+            );
+            response
+        }
+        None => next.run(request).await,
+    };
+    set_headers(response.headers_mut(), &decision);
+    response
+}
+
+fn set_headers(headers: &mut HeaderMap, decision: &Decision) {
+    headers.insert(LIMIT_HEADER, HeaderValue::from(decision.limit));
+    headers.insert(REMAINING_HEADER, HeaderValue::from(decision.remaining));
+    headers.insert(RESET_HEADER, HeaderValue::from(whole_seconds(decision.reset)));
+}
+
+/// Seconds rounded up, so a client waiting that long finds a token.
+fn whole_seconds(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::middleware::from_fn_with_state;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn limiter(limit: &str) -> RateLimiter {
+        RateLimiter::new(BTreeMap::from([(RouteGroup::Fees, parse_rate_limit(limit).unwrap())]), false)
+    }
+
+    fn ip(last: u8) -> Client {
+        Client::Ip(IpAddr::V4(Ipv4Addr::new(192, 0, 2, last)))
+    }
+
+    #[test]
+    fn parses_limits() {
+        assert_eq!(
+            parse_rate_limit("120/1m").unwrap(),
+            Some(RateLimit { requests: 120, per: Duration::from_secs(60) })
+        );
+        assert_eq!(parse_rate_limit(" off ").unwrap(), None);
+        for invalid in ["120", "0/1m", "10/0s", "ten/1m", "10/soon"] {
+            assert!(parse_rate_limit(invalid).is_err(), "{}", invalid);
+        }
+        assert_eq!("live".parse::<RouteGroup>().unwrap(), RouteGroup::Live);
+        assert!("stream".parse::<RouteGroup>().is_err());
+    }
+
+    #[test]
+    fn buckets_empty_then_refill_evenly() {
+        let limiter = limiter("2/10s");
+        let start = Instant::now();
+
+        let first = limiter.check(RouteGroup::Fees, &[ip(1)], start).unwrap();
+        assert_eq!((first.limit, first.remaining), (2, 1));
+        assert_eq!(whole_seconds(first.reset), 5);
+        let second = limiter.check(RouteGroup::Fees, &[ip(1)], start).unwrap();
+        assert_eq!(second.remaining, 0);
+
+        let refused = limiter.check(RouteGroup::Fees, &[ip(1)], start).unwrap();
+        let (client, retry_after) = refused.rejected.unwrap();
+        assert_eq!(client, ip(1));
+        assert_eq!(whole_seconds(retry_after), 5);
+        assert_eq!(whole_seconds(refused.reset), 10);
+
+        // Other clients and unlimited groups are unaffected
+        assert!(limiter.check(RouteGroup::Fees, &[ip(2)], start).unwrap().rejected.is_none());
+        assert!(limiter.check(RouteGroup::Insights, &[ip(1)], start).is_none());
+
+        let later = limiter.check(RouteGroup::Fees, &[ip(1)], start + Duration::from_secs(5)).unwrap();
+        assert!(later.rejected.is_none());
+        assert_eq!(later.remaining, 0);
+    }
+
+    #[test]
+    fn api_keys_have_their_own_bucket_alongside_the_address() {
+        let limiter = limiter("1/1m");
+        let now = Instant::now();
+        let key = Client::ApiKey(7);
+
+        assert!(limiter.check(RouteGroup::Fees, &[ip(1), key], now).unwrap().rejected.is_none());
+        // The key is spent even from another address
+        let refused = limiter.check(RouteGroup::Fees, &[ip(2), key], now).unwrap();
+        assert_eq!(refused.rejected.unwrap().0, key);
+        // A refused request takes nothing, so the second address still has its token
+        assert!(limiter.check(RouteGroup::Fees, &[ip(2)], now).unwrap().rejected.is_none());
+        // A new key does not lift the address's limit
+        let refused = limiter.check(RouteGroup::Fees, &[ip(1), Client::ApiKey(8)], now).unwrap();
+        assert_eq!(refused.rejected.unwrap().0, ip(1));
+    }
+
+    #[test]
+    fn refilled_buckets_are_swept() {
+        let limiter = limiter("5/10s");
+        let start = Instant::now();
+        limiter.check(RouteGroup::Fees, &[ip(1)], start);
+        limiter.check(RouteGroup::Fees, &[ip(2)], start + SWEEP_INTERVAL - Duration::from_secs(5));
+        limiter.check(RouteGroup::Fees, &[ip(3)], start + SWEEP_INTERVAL);
+
+        let buckets = limiter.buckets.lock().unwrap();
+        let mut clients: Vec<Client> = buckets.by_client.keys().map(|(_, client)| *client).collect();
+        clients.sort_by_key(|client| format!("{:?}", client));
+        assert_eq!(clients, vec![ip(2), ip(3)]);
+    }
+
+    #[test]
+    fn ipv6_clients_share_a_bucket_per_64() {
+        let a: IpAddr = "2001:db8:1:2::1".parse().unwrap();
+        let b: IpAddr = "2001:db8:1:2:ffff::9".parse().unwrap();
+        let c: IpAddr = "2001:db8:1:3::1".parse().unwrap();
+        assert_eq!(bucket_address(a), bucket_address(b));
+        assert_ne!(bucket_address(a), bucket_address(c));
+        let mapped: IpAddr = "::ffff:192.0.2.1".parse().unwrap();
+        assert_eq!(bucket_address(mapped), IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+    }
+
+    #[tokio::test]
+    async fn refuses_with_retry_after_and_counts_rejections() {
+        let metrics = Arc::new(AppMetrics::new().unwrap());
+        let limiter = RateLimiter::new(
+            BTreeMap::from([(RouteGroup::Fees, parse_rate_limit("1/1m").unwrap())]),
+            true,
+        )
+        .with_metrics(metrics.clone());
+        let app = Router::new().route("/fees/current", get(|| async { "fees" })).layer(
+            from_fn_with_state(
+                RateLimitState { limiter: Arc::new(limiter), group: RouteGroup::Fees },
+                rate_limit,
+            ),
+        );
+        let send = |forwarded_for: &'static str| {
+            let request = axum::http::Request::get("/fees/current")
+                .header(FORWARDED_FOR_HEADER, forwarded_for)
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        let response = send("198.51.100.7, 192.0.2.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[LIMIT_HEADER], "1");
+        assert_eq!(response.headers()[REMAINING_HEADER], "0");
+        assert_eq!(response.headers()[RESET_HEADER], "60");
+
+        let response = send("192.0.2.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[axum::http::header::RETRY_AFTER], "60");
+        assert_eq!(metrics.rate_limited_total.with_label_values(&["fees", "ip"]).get(), 1.0);
+
+        // The proxy's entry, not the client-supplied one, picks the bucket
+        let response = send("192.0.2.1, 192.0.2.2").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,308 @@
+//! `GET /fees/stream` — live feed as Server-Sent Events.
+//!
+//! For clients that cannot use `/ws`. After every poll the stream carries
+//! the new fee stats (`fees.current`) and insights (`insights`) as events
+//! named after their topic, whose data is the same JSON message `/ws`
+//! sends. `topics` picks other live feed topics instead, comma-separated,
+//! and `network` works as it does for `/ws`.
+//!
+//! Every event has an id. A client reconnecting with `Last-Event-ID` is
+//! sent the events it missed while they are still kept, and otherwise the
+//! current state of each topic, as is a new client. A keep-alive comment
+//! goes out every [`SSE_KEEP_ALIVE_INTERVAL`] so idle proxies don't close
+//! the connection.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use utoipa::IntoParams;
+
+use crate::api::openapi::ErrorResponse;
+use crate::api::ws::LiveState;
+use crate::live::{LiveFeed, LiveMessage, Topic};
+
+/// How often a comment is sent on an otherwise idle stream.
+pub const SSE_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Topics streamed when none are asked for.
+const DEFAULT_STREAM_TOPICS: [Topic; 2] = [Topic::FeesCurrent, Topic::Insights];
+
+/// Events buffered ahead of a slow client before it falls behind the feed.
+const SSE_CHANNEL_CAPACITY: usize = 16;
+
+type ApiError = (StatusCode, Json<Value>);
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StreamQuery {
+    pub topics: Option<String>,
+    pub network: Option<String>,
+}
+
+/// Event id of `message`: the feed's start time and the message id, so an
+/// id from before a restart is not mistaken for a current one.
+fn event_id(feed: &LiveFeed, message: &LiveMessage) -> String {
+    format!("{}-{}", feed.started_at().timestamp_millis(), message.id)
+}
+
+/// The message id in a `Last-Event-ID` sent by this feed.
+fn parse_event_id(feed: &LiveFeed, event_id: &str) -> Option<u64> {
+    let (started_at, id) = event_id.trim().split_once('-')?;
+    if started_at.parse::<i64>().ok()? != feed.started_at().timestamp_millis() {
+        return None;
+    }
+    id.parse().ok()
+}
+
+/// `GET /fees/stream` — stream live feed messages as they are published.
+#[utoipa::path(
+    get, path = "/fees/stream",
+    tag = "live",
+    params(StreamQuery, ("Last-Event-ID" = Option<String>, Header, description = "Id of the last event received, to resume after it")),
+    responses(
+        (status = 200, description = "Server-Sent Events named after their topic", content_type = "text/event-stream"),
+        (status = 400, description = "Unknown topic or another network", body = ErrorResponse),
+    )
+)]
+pub async fn fee_stream(
+    State(feed): State<LiveState>,
+    Query(params): Query<StreamQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let names: Vec<&str> = params
+        .topics
+        .as_deref()
+        .map(|list| list.split(',').filter(|name| !name.trim().is_empty()).collect())
+        .unwrap_or_default();
+    let mut topics = feed
+        .parse_topics(&names, params.network.as_deref())
+        .map_err(|err| (StatusCode::BAD_REQUEST, Json(json!({ "error": err }))))?;
+    if topics.is_empty() {
+        topics = DEFAULT_STREAM_TOPICS.to_vec();
+    }
+    let resume_after = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_event_id(&feed, value));
+
+    let (tx, rx) = mpsc::channel(SSE_CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        // Subscribe before catching up so nothing published meanwhile is lost
+        let mut updates = feed.subscribe();
+        let mut stream = EventStream {
+            tx,
+            feed,
+            topics,
+            sent: HashMap::new(),
+        };
+
+        let caught_up = match resume_after.and_then(|id| stream.feed.replay_after(id)) {
+            Some(missed) => stream.send_all(&missed).await,
+            None => stream.send_snapshots().await,
+        };
+        if caught_up.is_err() {
+            return;
+        }
+
+        loop {
+            let outcome = tokio::select! {
+                update = updates.recv() => match update {
+                    Ok(message) => stream.send(&message).await,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::debug!("Fee stream client lagged by {} updates", skipped);
+                        stream.send_snapshots().await
+                    }
+                    Err(RecvError::Closed) => Err(Disconnect),
+                },
+                _ = stream.tx.closed() => Err(Disconnect),
+            };
+            if outcome.is_err() {
+                break;
+            }
+        }
+    });
+
+    let sse = Sse::new(ReceiverStream::new(rx))
+        .keep_alive(KeepAlive::new().interval(SSE_KEEP_ALIVE_INTERVAL));
+    let mut response = sse.into_response();
+    // Stops nginx and similar proxies from buffering the stream
+    response
+        .headers_mut()
+        .insert(HeaderName::from_static("x-accel-buffering"), HeaderValue::from_static("no"));
+    Ok(response)
+}
+
+/// The client has gone away.
+struct Disconnect;
+
+struct EventStream {
+    tx: mpsc::Sender<Result<Event, Infallible>>,
+    feed: LiveState,
+    topics: Vec<Topic>,
+    /// Id of the last message sent per topic; older ones are skipped
+    sent: HashMap<Topic, u64>,
+}
+
+impl EventStream {
+    async fn send(&mut self, message: &LiveMessage) -> Result<(), Disconnect> {
+        if !self.topics.contains(&message.topic)
+            || self.sent.get(&message.topic).is_some_and(|sent| message.id <= *sent)
+        {
+            return Ok(());
+        }
+        let event = match Event::default().event(message.topic.as_str()).json_data(message) {
+            Ok(event) => event.id(event_id(&self.feed, message)),
+            Err(err) => {
+                tracing::warn!("Failed to encode {} event: {}", message.topic, err);
+                return Ok(());
+            }
+        };
+        self.tx.send(Ok(event)).await.map_err(|_| Disconnect)?;
+        self.sent.insert(message.topic, message.id);
+        Ok(())
+    }
+
+    async fn send_all(&mut self, messages: &[Arc<LiveMessage>]) -> Result<(), Disconnect> {
+        for message in messages {
+            self.send(message).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_snapshots(&mut self) -> Result<(), Disconnect> {
+        let snapshots: Vec<LiveMessage> = self
+            .topics
+            .iter()
+            .filter_map(|topic| self.feed.snapshot(*topic))
+            .collect();
+        for snapshot in &snapshots {
+            self.send(snapshot).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::{routing::get, Router};
+    use chrono::Utc;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use crate::insights::{FeeInsightsEngine, InsightsConfig};
+
+    fn app(feed: LiveState) -> Router {
+        Router::new().route("/fees/stream", get(fee_stream)).with_state(feed)
+    }
+
+    fn publish_insights(feed: &LiveFeed) -> u64 {
+        let insights = FeeInsightsEngine::new(InsightsConfig::default()).get_current_insights();
+        feed.publish(Topic::Insights, serde_json::to_value(insights).unwrap(), Utc::now())
+    }
+
+    /// Read the body until `events` events have arrived.
+    async fn read_events(body: &mut Body, events: usize) -> String {
+        let mut text = String::new();
+        while text.matches("\nid: ").count() < events {
+            let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+                .await
+                .expect("event within 5s")
+                .unwrap()
+                .unwrap();
+            if let Ok(data) = frame.into_data() {
+                text.push_str(std::str::from_utf8(&data).unwrap());
+            }
+        }
+        text
+    }
+
+    #[tokio::test]
+    async fn streams_snapshot_then_updates_with_event_ids() {
+        let feed = Arc::new(LiveFeed::new("testnet"));
+        let first = publish_insights(&feed);
+
+        let response = app(feed.clone())
+            .oneshot(Request::get("/fees/stream").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let mut body = response.into_body();
+
+        let text = read_events(&mut body, 1).await;
+        assert!(text.contains("event: insights"));
+        assert!(text.contains(&format!("id: {}-{}", feed.started_at().timestamp_millis(), first)));
+
+        let second = publish_insights(&feed);
+        let text = read_events(&mut body, 1).await;
+        assert!(text.contains(&format!("-{}\n", second)));
+        assert!(text.contains("\"network\":\"testnet\""));
+    }
+
+    #[tokio::test]
+    async fn resumes_after_last_event_id() {
+        let feed = Arc::new(LiveFeed::new("testnet"));
+        let first = publish_insights(&feed);
+        feed.publish(Topic::FeesPoints, json!([]), Utc::now());
+        let second = publish_insights(&feed);
+        let third = publish_insights(&feed);
+
+        let last_event_id = format!("{}-{}", feed.started_at().timestamp_millis(), first);
+        let response = app(feed.clone())
+            .oneshot(
+                Request::get("/fees/stream")
+                    .header("last-event-id", last_event_id)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let text = read_events(&mut response.into_body(), 2).await;
+        assert!(text.contains(&format!("-{}\n", second)));
+        assert!(text.contains(&format!("-{}\n", third)));
+        // Not a subscribed topic
+        assert!(!text.contains("fees.points"));
+
+        // An id from another process falls back to the latest snapshot
+        let response = app(feed.clone())
+            .oneshot(
+                Request::get("/fees/stream?topics=insights")
+                    .header("last-event-id", format!("1-{}", first))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let text = read_events(&mut response.into_body(), 1).await;
+        assert!(text.contains(&format!("-{}\n", third)));
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_topics() {
+        let feed = Arc::new(LiveFeed::new("testnet"));
+        let response = app(feed)
+            .oneshot(Request::get("/fees/stream?topics=fees").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,573 @@
+//! `/v1` — the versioned API.
+//!
+//! Every route is served under `/v1`. The fee routes there report amounts
+//! as JSON numbers, never strings, in the `unit` the client asks for:
+//! whole stroops by default, or XLM with `unit=xlm`. Each response names
+//! its unit. The remaining routes are the unversioned handlers mounted
+//! under the prefix, as their amounts are already numbers of stroops;
+//! they refuse `unit` with 400 rather than ignore it.
+//!
+//! The unversioned routes keep working but are deprecated: their responses
+//! carry `Deprecation` and `Sunset` headers and a `Link` to the `/v1` route
+//! replacing them.
+
+use std::borrow::Cow;
+
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{IntoParams, PartialSchema, ToSchema};
+
+use crate::api::export::export_fees;
+use crate::api::fees::{self, FeeCandlesQuery, FeeHistoryQuery, FeesState};
+use crate::api::openapi::ErrorResponse;
+use crate::error::AppError;
+use crate::insights::{self, FeeDataPoint};
+
+/// Path prefix of the versioned API.
+pub const API_PREFIX: &str = "/v1";
+
+/// `Deprecation` header on unversioned routes: deprecated since
+/// 2026-10-18T00:00:00Z, as an RFC 9745 structured date.
+pub const LEGACY_DEPRECATION: &str = "@1792281600";
+
+/// `Sunset` header on unversioned routes: the date they may be removed.
+pub const LEGACY_SUNSET: &str = "Fri, 30 Apr 2027 00:00:00 GMT";
+
+/// Why a route without unit conversion refused a request.
+pub const UNIT_UNSUPPORTED: &str =
+    "unit is only supported on /v1/fees/current, /v1/fees/history and /v1/fees/candles; amounts here are in stroops";
+
+/// Stroops in one XLM.
+pub const STROOPS_PER_XLM: u64 = 10_000_000;
+
+type ApiError = (axum::http::StatusCode, Json<Value>);
+
+/// Unit fee amounts are reported in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeUnit {
+    #[default]
+    Stroops,
+    Xlm,
+}
+
+impl FeeUnit {
+    /// A whole number of stroops in this unit.
+    pub fn amount(self, stroops: u64) -> FeeAmount {
+        match self {
+            FeeUnit::Stroops => FeeAmount::Whole(stroops),
+            FeeUnit::Xlm => FeeAmount::Decimal(stroops as f64 / STROOPS_PER_XLM as f64),
+        }
+    }
+
+    /// A possibly fractional number of stroops, such as an average, in this unit.
+    pub fn average(self, stroops: f64) -> FeeAmount {
+        match self {
+            FeeUnit::Stroops => FeeAmount::Decimal(stroops),
+            FeeUnit::Xlm => FeeAmount::Decimal(stroops / STROOPS_PER_XLM as f64),
+        }
+    }
+}
+
+/// A fee in a response's `unit`. Whole stroops serialize as integers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum FeeAmount {
+    Whole(u64),
+    Decimal(f64),
+}
+
+impl PartialSchema for FeeAmount {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::Number)
+            .description(Some("Fee in the response's `unit`"))
+            .into()
+    }
+}
+
+impl ToSchema for FeeAmount {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("FeeAmount")
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UnitQuery {
+    /// `stroops` (default) or `xlm`
+    #[serde(default)]
+    #[param(inline)]
+    pub unit: FeeUnit,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = v1::PercentileFees)]
+pub struct PercentileFees {
+    pub p10: FeeAmount,
+    pub p25: FeeAmount,
+    pub p50: FeeAmount,
+    pub p75: FeeAmount,
+    pub p90: FeeAmount,
+    pub p95: FeeAmount,
+}
+
+/// Horizon fee stats for recent ledgers.
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = v1::CurrentFeeResponse)]
+pub struct CurrentFeeResponse {
+    pub unit: FeeUnit,
+    pub base_fee: FeeAmount,
+    pub min_fee: FeeAmount,
+    pub max_fee: FeeAmount,
+    pub avg_fee: FeeAmount,
+    pub percentiles: PercentileFees,
+}
+
+impl CurrentFeeResponse {
+    /// Horizon's string amounts, parsed and converted to `unit`.
+    pub fn new(fees: &fees::CurrentFeeResponse, unit: FeeUnit) -> Result<Self, AppError> {
+        let amount = |name: &str, value: &str| {
+            value.trim().parse::<u64>().map(|stroops| unit.amount(stroops)).map_err(|_| {
+                AppError::Parse(format!("Horizon {} is not a number of stroops: '{}'", name, value))
+            })
+        };
+        let p = &fees.percentiles;
+        Ok(Self {
+            unit,
+            base_fee: amount("base_fee", &fees.base_fee)?,
+            min_fee: amount("min_fee", &fees.min_fee)?,
+            max_fee: amount("max_fee", &fees.max_fee)?,
+            avg_fee: amount("avg_fee", &fees.avg_fee)?,
+            percentiles: PercentileFees {
+                p10: amount("p10", &p.p10)?,
+                p25: amount("p25", &p.p25)?,
+                p50: amount("p50", &p.p50)?,
+                p75: amount("p75", &p.p75)?,
+                p90: amount("p90", &p.p90)?,
+                p95: amount("p95", &p.p95)?,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = v1::FeePoint)]
+pub struct FeePoint {
+    pub fee_amount: FeeAmount,
+    pub timestamp: DateTime<Utc>,
+    pub transaction_hash: String,
+    pub ledger_sequence: u64,
+}
+
+impl FeePoint {
+    fn new(point: FeeDataPoint, unit: FeeUnit) -> Self {
+        Self {
+            fee_amount: unit.amount(point.fee_amount),
+            timestamp: point.timestamp,
+            transaction_hash: point.transaction_hash,
+            ledger_sequence: point.ledger_sequence,
+        }
+    }
+}
+
+/// Percentiles estimated from a quantile sketch (within 1%).
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = v1::FeePercentiles)]
+pub struct FeePercentiles {
+    pub p10: FeeAmount,
+    pub p25: FeeAmount,
+    pub p50: FeeAmount,
+    pub p75: FeeAmount,
+    pub p90: FeeAmount,
+    pub p95: FeeAmount,
+    pub p99: FeeAmount,
+}
+
+impl FeePercentiles {
+    fn new(p: &insights::FeePercentiles, unit: FeeUnit) -> Self {
+        Self {
+            p10: unit.amount(p.p10),
+            p25: unit.amount(p.p25),
+            p50: unit.amount(p.p50),
+            p75: unit.amount(p.p75),
+            p90: unit.amount(p.p90),
+            p95: unit.amount(p.p95),
+            p99: unit.amount(p.p99),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = v1::FeeSummary)]
+pub struct FeeSummary {
+    pub min: FeeAmount,
+    pub max: FeeAmount,
+    pub avg: FeeAmount,
+    pub percentiles: FeePercentiles,
+}
+
+/// A page of `/v1/fees/history`, as for `/fees/history`.
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = v1::FeeHistoryResponse)]
+pub struct FeeHistoryResponse {
+    pub unit: FeeUnit,
+    pub window: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub data_points: usize,
+    pub total: usize,
+    pub fees: Vec<FeePoint>,
+    pub summary: FeeSummary,
+    pub resolution: String,
+    pub buckets: Vec<FeeCandle>,
+    pub next_cursor: Option<String>,
+}
+
+impl FeeHistoryResponse {
+    pub fn new(history: fees::FeeHistoryResponse, unit: FeeUnit) -> Self {
+        let summary = &history.summary;
+        Self {
+            unit,
+            window: history.window,
+            from: history.from,
+            to: history.to,
+            data_points: history.data_points,
+            total: history.total,
+            fees: history.fees.into_iter().map(|point| FeePoint::new(point, unit)).collect(),
+            summary: FeeSummary {
+                min: unit.amount(summary.min),
+                max: unit.amount(summary.max),
+                avg: unit.average(summary.avg),
+                percentiles: FeePercentiles::new(&summary.percentiles, unit),
+            },
+            resolution: history.resolution,
+            buckets: history.buckets.into_iter().map(|candle| FeeCandle::new(candle, unit)).collect(),
+            next_cursor: history.next_cursor,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = v1::FeeCandle)]
+pub struct FeeCandle {
+    pub bucket_start: DateTime<Utc>,
+    pub open: FeeAmount,
+    pub high: FeeAmount,
+    pub low: FeeAmount,
+    pub close: FeeAmount,
+    pub avg: FeeAmount,
+    pub p50: FeeAmount,
+    pub p95: FeeAmount,
+    /// Number of transactions in the bucket
+    pub count: u64,
+}
+
+impl FeeCandle {
+    fn new(candle: fees::FeeCandle, unit: FeeUnit) -> Self {
+        Self {
+            bucket_start: candle.bucket_start,
+            open: unit.amount(candle.open),
+            high: unit.amount(candle.high),
+            low: unit.amount(candle.low),
+            close: unit.amount(candle.close),
+            avg: unit.average(candle.avg),
+            p50: unit.amount(candle.p50),
+            p95: unit.amount(candle.p95),
+            count: candle.count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = v1::FeeCandlesResponse)]
+pub struct FeeCandlesResponse {
+    pub unit: FeeUnit,
+    pub resolution: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Oldest first; buckets without transactions are omitted
+    pub candles: Vec<FeeCandle>,
+}
+
+impl FeeCandlesResponse {
+    pub fn new(candles: fees::FeeCandlesResponse, unit: FeeUnit) -> Self {
+        Self {
+            unit,
+            resolution: candles.resolution,
+            from: candles.from,
+            to: candles.to,
+            candles: candles.candles.into_iter().map(|candle| FeeCandle::new(candle, unit)).collect(),
+        }
+    }
+}
+
+/// Create the `/fees/*` routes of the versioned API, to be nested under
+/// [`API_PREFIX`].
+pub fn create_fees_router(state: FeesState) -> Router {
+    Router::new()
+        .route("/fees/current", get(current_fees))
+        .route("/fees/history", get(fee_history))
+        .route("/fees/candles", get(fee_candles))
+        .route("/fees/trend", get(fees::fee_trend).layer(middleware::from_fn(reject_unit)))
+        .route("/fees/export", get(export_fees).layer(middleware::from_fn(reject_unit)))
+        .with_state(state)
+}
+
+/// `GET /v1/fees/current`
+#[utoipa::path(
+    get, path = "/v1/fees/current",
+    tag = "fees",
+    params(UnitQuery),
+    responses(
+        (status = 200, description = "Current network fee stats", body = CurrentFeeResponse),
+        (status = 502, description = "Horizon could not be reached", body = ErrorResponse),
+        (status = 422, description = "Horizon sent a response that could not be parsed", body = ErrorResponse),
+    )
+)]
+pub async fn current_fees(
+    State(state): State<FeesState>,
+    Query(params): Query<UnitQuery>,
+) -> Result<Json<CurrentFeeResponse>, AppError> {
+    let Json(current) = fees::current_fees(State(state)).await?;
+    CurrentFeeResponse::new(&current, params.unit).map(Json)
+}
+
+/// `GET /v1/fees/history` — as `/fees/history`, with amounts in `unit`.
+#[utoipa::path(
+    get, path = "/v1/fees/history",
+    tag = "fees",
+    params(FeeHistoryQuery, UnitQuery),
+    responses(
+        (status = 200, description = "A page of fee data points in the range", body = FeeHistoryResponse),
+        (status = 400, description = "Invalid window, range or cursor", body = ErrorResponse),
+    )
+)]
+pub async fn fee_history(
+    State(state): State<FeesState>,
+    Query(params): Query<FeeHistoryQuery>,
+    Query(unit): Query<UnitQuery>,
+) -> Result<Json<FeeHistoryResponse>, ApiError> {
+    let Json(history) = fees::fee_history(State(state), Query(params)).await?;
+    Ok(Json(FeeHistoryResponse::new(history, unit.unit)))
+}
+
+/// `GET /v1/fees/candles` — as `/fees/candles`, with amounts in `unit`.
+#[utoipa::path(
+    get, path = "/v1/fees/candles",
+    tag = "fees",
+    params(FeeCandlesQuery, UnitQuery),
+    responses(
+        (status = 200, description = "Candles, oldest first", body = FeeCandlesResponse),
+        (status = 400, description = "Invalid resolution or range", body = ErrorResponse),
+        (status = 503, description = "No database configured", body = ErrorResponse),
+    )
+)]
+pub async fn fee_candles(
+    State(state): State<FeesState>,
+    Query(params): Query<FeeCandlesQuery>,
+    Query(unit): Query<UnitQuery>,
+) -> Result<Json<FeeCandlesResponse>, ApiError> {
+    let Json(candles) = fees::fee_candles(State(state), Query(params)).await?;
+    Ok(Json(FeeCandlesResponse::new(candles, unit.unit)))
+}
+
+/// Middleware for the `/v1` routes that always report stroops: refuses a
+/// `unit` query parameter instead of silently ignoring it.
+pub async fn reject_unit(request: Request, next: Next) -> Response {
+    let has_unit = request
+        .uri()
+        .query()
+        .is_some_and(|query| query.split('&').any(|pair| pair.split('=').next() == Some("unit")));
+    if has_unit {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": UNIT_UNSUPPORTED }))).into_response();
+    }
+    next.run(request).await
+}
+
+/// Middleware for the unversioned routes: marks responses deprecated and
+/// links the `/v1` route replacing them.
+pub async fn deprecate_legacy(request: Request, next: Next) -> Response {
+    let successor = format!("<{}{}>; rel=\"successor-version\"", API_PREFIX, request.uri().path());
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static(LEGACY_DEPRECATION),
+    );
+    headers.insert(HeaderName::from_static("sunset"), HeaderValue::from_static(LEGACY_SUNSET));
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.append(header::LINK, link);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration as StdDuration;
+
+    use async_trait::async_trait;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tokio::sync::{Mutex, RwLock};
+    use tower::ServiceExt;
+
+    use crate::api::fees::{FeeStatsProvider, FeesApiState};
+    use crate::cache::ResponseCache;
+    use crate::clock::system_clock;
+    use crate::retention::RetentionPolicy;
+    use crate::store::FeeHistoryStore;
+
+    struct FixedFees(fees::CurrentFeeResponse);
+
+    #[async_trait]
+    impl FeeStatsProvider for FixedFees {
+        async fn fetch_current_fees(&self) -> Result<fees::CurrentFeeResponse, AppError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn horizon_fees(base_fee: &str) -> fees::CurrentFeeResponse {
+        fees::CurrentFeeResponse {
+            base_fee: base_fee.into(),
+            min_fee: "100".into(),
+            max_fee: "5000000".into(),
+            avg_fee: "250".into(),
+            percentiles: fees::PercentileFees {
+                p10: "100".into(),
+                p25: "100".into(),
+                p50: "150".into(),
+                p75: "300".into(),
+                p90: "500".into(),
+                p95: "800".into(),
+            },
+        }
+    }
+
+    fn state(base_fee: &str, points: Vec<FeeDataPoint>) -> FeesState {
+        let mut store = FeeHistoryStore::new(100);
+        for point in points {
+            store.push(point);
+        }
+        Arc::new(FeesApiState {
+            fee_stats_provider: Some(Arc::new(FixedFees(horizon_fees(base_fee)))),
+            fee_cache: Arc::new(Mutex::new(ResponseCache::new(StdDuration::from_secs(5)))),
+            fee_store: Arc::new(RwLock::new(store)),
+            insights_engine: None,
+            point_in_time: None,
+            repository: None,
+            retention: RetentionPolicy::default().shared(),
+            clock: system_clock(),
+        })
+    }
+
+    fn app(state: FeesState) -> Router {
+        let legacy = Router::new()
+            .route("/fees/current", get(fees::current_fees))
+            .with_state(state.clone())
+            .layer(middleware::from_fn(deprecate_legacy));
+        Router::new().nest(API_PREFIX, create_fees_router(state)).merge(legacy)
+    }
+
+    async fn get_json(app: Router, uri: &str) -> (StatusCode, Value) {
+        let response = app.oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn current_fees_are_numbers_in_the_requested_unit() {
+        let app = app(state("100", Vec::new()));
+
+        let (status, json) = get_json(app.clone(), "/v1/fees/current").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["unit"], "stroops");
+        assert_eq!(json["base_fee"], 100);
+        assert_eq!(json["max_fee"], 5_000_000);
+        assert_eq!(json["percentiles"]["p95"], 800);
+
+        let (_, json) = get_json(app.clone(), "/v1/fees/current?unit=xlm").await;
+        assert_eq!(json["unit"], "xlm");
+        assert_eq!(json["base_fee"], 0.00001);
+        assert_eq!(json["max_fee"], 0.5);
+
+        let (status, _) = get_json(app, "/v1/fees/current?unit=lumens").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn unparseable_horizon_amounts_are_reported() {
+        let (status, json) = get_json(app(state("n/a", Vec::new())), "/v1/fees/current").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(json["error"].as_str().unwrap().contains("base_fee"));
+    }
+
+    #[tokio::test]
+    async fn history_converts_points_and_summary() {
+        let point = FeeDataPoint {
+            fee_amount: 200,
+            timestamp: Utc::now() - chrono::Duration::minutes(1),
+            transaction_hash: "tx".into(),
+            ledger_sequence: 7,
+        };
+        let (status, json) = get_json(app(state("100", vec![point])), "/v1/fees/history?unit=xlm").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["unit"], "xlm");
+        assert_eq!(json["fees"][0]["fee_amount"], 0.00002);
+        assert_eq!(json["summary"]["min"], 0.00002);
+        assert!(json["summary"]["percentiles"]["p99"].is_number());
+    }
+
+    #[tokio::test]
+    async fn routes_without_unit_conversion_refuse_unit() {
+        let app = app(state("100", Vec::new()));
+
+        let (status, json) = get_json(app.clone(), "/v1/fees/trend?unit=xlm").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"], UNIT_UNSUPPORTED);
+        let (status, _) = get_json(app.clone(), "/v1/fees/export?dataset=points&unit=stroops").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Other parameters still reach the handler
+        let (status, _) = get_json(app, "/v1/fees/trend?units=xlm").await;
+        assert_ne!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn legacy_routes_are_marked_deprecated() {
+        let app = app(state("100", Vec::new()));
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/fees/current").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["deprecation"], LEGACY_DEPRECATION);
+        assert_eq!(response.headers()["sunset"], LEGACY_SUNSET);
+        assert_eq!(response.headers()["link"], "</v1/fees/current>; rel=\"successor-version\"");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["base_fee"], "100");
+
+        let response = app
+            .oneshot(Request::get("/v1/fees/current").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert!(response.headers().get("deprecation").is_none());
+    }
+}
@@ -0,0 +1,343 @@
+//! `GET /ws` — live feed over WebSocket.
+//!
+//! Clients pick topics with JSON text frames:
+//! - `{"op": "subscribe", "topics": ["fees.current", "spikes"], "network": "testnet"}`
+//! - `{"op": "unsubscribe", "topics": ["spikes"]}`
+//!
+//! or up front with `/ws?topics=fees.current,spikes&network=testnet`.
+//! `network` is optional and must match the network this server tracks.
+//!
+//! Every frame from the server is a JSON object with a `type`:
+//! - `subscribed` / `unsubscribed` — acknowledges a request
+//! - `snapshot` — current state of a topic, sent on subscribe
+//! - `update`   — a change published after a scheduler tick or spike event
+//! - `lagged`   — `skipped` updates were dropped because the client read
+//!   too slowly; fresh snapshots of its topics follow
+//! - `error`    — a request was not understood; the connection stays open
+//!
+//! The server pings every [`WS_HEARTBEAT_INTERVAL`] and closes connections
+//! that have sent nothing, not even a pong, for two intervals. A client
+//! that stops reading is dropped once a send blocks for [`WS_SEND_TIMEOUT`].
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::StatusCode,
+    response::Response,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{self, Instant};
+use utoipa::IntoParams;
+
+use crate::api::openapi::ErrorResponse;
+use crate::live::{LiveFeed, LiveMessage, Topic};
+
+/// Shared state type for the live feed route.
+pub type LiveState = Arc<LiveFeed>;
+
+/// How often the server pings each client.
+pub const WS_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Longest a single send may block before the client is dropped.
+pub const WS_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+type ApiError = (StatusCode, Json<Value>);
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WsQuery {
+    /// Comma-separated topics to subscribe to on connect
+    pub topics: Option<String>,
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum ClientFrame {
+    Subscribe {
+        topics: Vec<String>,
+        network: Option<String>,
+    },
+    Unsubscribe {
+        topics: Vec<String>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerFrame<'a> {
+    Subscribed { topics: Vec<Topic> },
+    Unsubscribed { topics: Vec<Topic> },
+    Snapshot(&'a LiveMessage),
+    Update(&'a LiveMessage),
+    Lagged { skipped: u64 },
+    Error { error: String },
+}
+
+/// The client is gone or too slow; the session ends.
+struct Disconnect;
+
+/// `GET /ws` — upgrade to a live feed session.
+#[utoipa::path(
+    get, path = "/ws",
+    tag = "live",
+    params(WsQuery),
+    responses(
+        (status = 101, description = "Switched to a WebSocket live feed session"),
+        (status = 400, description = "Unknown topic or another network", body = ErrorResponse),
+    )
+)]
+pub async fn live_feed(
+    ws: WebSocketUpgrade,
+    State(feed): State<LiveState>,
+    Query(params): Query<WsQuery>,
+) -> Result<Response, ApiError> {
+    let names: Vec<&str> = params
+        .topics
+        .as_deref()
+        .map(|list| list.split(',').filter(|name| !name.trim().is_empty()).collect())
+        .unwrap_or_default();
+    let topics = feed
+        .parse_topics(&names, params.network.as_deref())
+        .map_err(|err| (StatusCode::BAD_REQUEST, Json(json!({ "error": err }))))?;
+
+    Ok(ws.on_upgrade(move |socket| run_session(socket, feed, topics)))
+}
+
+async fn run_session(socket: WebSocket, feed: LiveState, topics: Vec<Topic>) {
+    // Subscribe before any snapshot is taken so no update falls in between
+    let mut updates = feed.subscribe();
+    let mut session = Session {
+        socket,
+        feed,
+        topics: BTreeSet::new(),
+    };
+    if !topics.is_empty() && session.subscribe(topics).await.is_err() {
+        return;
+    }
+
+    let mut heartbeat = time::interval_at(Instant::now() + WS_HEARTBEAT_INTERVAL, WS_HEARTBEAT_INTERVAL);
+    let mut last_seen = Instant::now();
+
+    loop {
+        let outcome = tokio::select! {
+            incoming = session.socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    last_seen = Instant::now();
+                    session.handle_frame(&text).await
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => Err(Disconnect),
+                Some(Ok(_)) => {
+                    last_seen = Instant::now();
+                    Ok(())
+                }
+            },
+
+            update = updates.recv() => match update {
+                Ok(message) if session.topics.contains(&message.topic) => {
+                    session.send(&ServerFrame::Update(&message)).await
+                }
+                Ok(_) => Ok(()),
+                Err(RecvError::Lagged(skipped)) => session.resync(skipped).await,
+                Err(RecvError::Closed) => Err(Disconnect),
+            },
+
+            _ = heartbeat.tick() => {
+                if last_seen.elapsed() > WS_HEARTBEAT_INTERVAL * 2 {
+                    tracing::debug!("Closing live feed connection after missed heartbeats");
+                    Err(Disconnect)
+                } else {
+                    session.send_message(Message::Ping(Vec::new())).await
+                }
+            }
+        };
+        if outcome.is_err() {
+            break;
+        }
+    }
+}
+
+struct Session {
+    socket: WebSocket,
+    feed: LiveState,
+    topics: BTreeSet<Topic>,
+}
+
+impl Session {
+    async fn handle_frame(&mut self, text: &str) -> Result<(), Disconnect> {
+        let frame = match serde_json::from_str::<ClientFrame>(text) {
+            Ok(frame) => frame,
+            Err(err) => {
+                return self
+                    .send(&ServerFrame::Error { error: format!("Invalid request: {}", err) })
+                    .await
+            }
+        };
+        let parsed = match &frame {
+            ClientFrame::Subscribe { topics, network } => self.feed.parse_topics(topics, network.as_deref()),
+            ClientFrame::Unsubscribe { topics } => self.feed.parse_topics(topics, None),
+        };
+        let topics = match parsed {
+            Ok(topics) => topics,
+            Err(error) => return self.send(&ServerFrame::Error { error }).await,
+        };
+
+        match frame {
+            ClientFrame::Subscribe { .. } => self.subscribe(topics).await,
+            ClientFrame::Unsubscribe { .. } => {
+                for topic in &topics {
+                    self.topics.remove(topic);
+                }
+                self.send(&ServerFrame::Unsubscribed { topics }).await
+            }
+        }
+    }
+
+    /// Add `topics` and send a snapshot of each.
+    async fn subscribe(&mut self, topics: Vec<Topic>) -> Result<(), Disconnect> {
+        self.topics.extend(topics.iter().copied());
+        self.send(&ServerFrame::Subscribed { topics: topics.clone() }).await?;
+        self.send_snapshots(&topics).await
+    }
+
+    /// Tell a lagging client what it missed, then bring it up to date.
+    async fn resync(&mut self, skipped: u64) -> Result<(), Disconnect> {
+        tracing::debug!("Live feed client lagged by {} updates", skipped);
+        self.send(&ServerFrame::Lagged { skipped }).await?;
+        let topics: Vec<Topic> = self.topics.iter().copied().collect();
+        self.send_snapshots(&topics).await
+    }
+
+    async fn send_snapshots(&mut self, topics: &[Topic]) -> Result<(), Disconnect> {
+        for topic in topics {
+            if let Some(snapshot) = self.feed.snapshot(*topic) {
+                self.send(&ServerFrame::Snapshot(&snapshot)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn send(&mut self, frame: &ServerFrame<'_>) -> Result<(), Disconnect> {
+        let text = serde_json::to_string(frame).map_err(|_| Disconnect)?;
+        self.send_message(Message::Text(text)).await
+    }
+
+    async fn send_message(&mut self, message: Message) -> Result<(), Disconnect> {
+        match time::timeout(WS_SEND_TIMEOUT, self.socket.send(message)).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(_)) => Err(Disconnect),
+            Err(_) => {
+                tracing::debug!("Dropping live feed client that stopped reading");
+                Err(Disconnect)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use chrono::Utc;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::{connect_async, tungstenite::Message as ClientMessage};
+
+    use crate::insights::FeeDataPoint;
+
+    async fn serve(feed: LiveState) -> String {
+        let app = Router::new().route("/ws", get(live_feed)).with_state(feed);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("ws://{}/ws", addr)
+    }
+
+    fn point(hash: &str) -> FeeDataPoint {
+        FeeDataPoint {
+            fee_amount: 100,
+            timestamp: Utc::now(),
+            transaction_hash: hash.to_string(),
+            ledger_sequence: 1,
+        }
+    }
+
+    async fn next_json<S>(client: &mut S) -> Value
+    where
+        S: StreamExt<Item = Result<ClientMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+    {
+        loop {
+            let message = time::timeout(Duration::from_secs(5), client.next())
+                .await
+                .expect("frame within 5s")
+                .unwrap()
+                .unwrap();
+            if let ClientMessage::Text(text) = message {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn sends_snapshot_on_subscribe_then_updates() {
+        let feed = Arc::new(LiveFeed::new("testnet"));
+        feed.publish_tick(&[point("before")], None, Utc::now()).await;
+        let url = serve(feed.clone()).await;
+
+        let (mut client, _) = connect_async(format!("{}?topics=fees.points", url)).await.unwrap();
+        let frame = next_json(&mut client).await;
+        assert_eq!(frame["type"], "subscribed");
+        assert_eq!(frame["topics"], json!(["fees.points"]));
+        let frame = next_json(&mut client).await;
+        assert_eq!(frame["type"], "snapshot");
+        assert_eq!(frame["network"], "testnet");
+        assert_eq!(frame["data"][0]["transaction_hash"], "before");
+
+        feed.publish_tick(&[point("after")], None, Utc::now()).await;
+        let frame = next_json(&mut client).await;
+        assert_eq!(frame["type"], "update");
+        assert_eq!(frame["topic"], "fees.points");
+        assert_eq!(frame["data"][0]["transaction_hash"], "after");
+
+        // Unsubscribed topics are not delivered
+        client
+            .send(ClientMessage::Text(r#"{"op":"unsubscribe","topics":["fees.points"]}"#.into()))
+            .await
+            .unwrap();
+        assert_eq!(next_json(&mut client).await["type"], "unsubscribed");
+        feed.publish_tick(&[point("ignored")], None, Utc::now()).await;
+        client
+            .send(ClientMessage::Text(r#"{"op":"subscribe","topics":["spikes"]}"#.into()))
+            .await
+            .unwrap();
+        assert_eq!(next_json(&mut client).await["type"], "subscribed");
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_topics_and_other_networks() {
+        let feed = Arc::new(LiveFeed::new("testnet"));
+        let url = serve(feed).await;
+
+        assert!(connect_async(format!("{}?topics=fees.points&network=mainnet", url)).await.is_err());
+
+        let (mut client, _) = connect_async(url).await.unwrap();
+        client
+            .send(ClientMessage::Text(r#"{"op":"subscribe","topics":["fees"]}"#.into()))
+            .await
+            .unwrap();
+        let frame = next_json(&mut client).await;
+        assert_eq!(frame["type"], "error");
+        assert!(frame["error"].as_str().unwrap().contains("Unknown topic 'fees'"));
+
+        client.send(ClientMessage::Text("not json".into())).await.unwrap();
+        assert_eq!(next_json(&mut client).await["type"], "error");
+    }
+}
@@ -0,0 +1,381 @@
+//! Backtesting harness.
+//!
+//! Replays a range of persisted `fee_data_points` through fresh insights
+//! engines — one with the current configuration and one with a candidate
+//! configuration — and reports the spikes each would have detected and the
+//! alerts the configured alert rules would have fired.
+//!
+//! Points are fed through a [`Replayer`] in batches of one poll interval,
+//! so every window sees the data as it did live.
+//! The hour-of-week baseline is not rebuilt during a replay; both runs fall
+//! back to the rolling average baseline.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::{apply_min_severity, rule_matches};
+use crate::cli::BacktestArgs;
+use crate::insights::config::{ConfigOverrides, SpikeStrategy};
+use crate::insights::replay::Replayer;
+use crate::insights::types::{format_duration, parse_duration, FeeDataPoint, FeeSpike, SpikeSeverity};
+use crate::insights::InsightsConfig;
+use crate::repository::{AlertConfig, FeeRepository};
+
+/// Parse the candidate overrides given on the `backtest` command line.
+pub fn overrides_from_args(args: &BacktestArgs) -> Result<ConfigOverrides, String> {
+    let minimum_spike_duration = args
+        .min_spike_duration
+        .as_deref()
+        .map(|value| {
+            parse_duration(value).ok_or_else(|| format!("Invalid --min-spike-duration: {}", value))
+        })
+        .transpose()?;
+    let strategy = args
+        .strategy
+        .as_deref()
+        .map(|json| {
+            serde_json::from_str(json).map_err(|e| format!("Invalid --strategy: {}", e))
+        })
+        .transpose()?;
+
+    Ok(ConfigOverrides {
+        threshold_multiplier: args.threshold_multiplier,
+        minimum_spike_duration,
+        windows: args.windows.clone(),
+        strategy,
+        ..ConfigOverrides::default()
+    })
+}
+
+/// The settings of a run that matter when reading a report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigSummary {
+    pub windows: Vec<String>,
+    pub baseline_window: String,
+    pub threshold_multiplier: f64,
+    pub minimum_spike_duration: String,
+    pub strategy: SpikeStrategy,
+}
+
+impl ConfigSummary {
+    fn of(config: &InsightsConfig) -> Self {
+        Self {
+            windows: config.time_windows.iter().map(|w| w.name.clone()).collect(),
+            baseline_window: config.baseline_window.clone(),
+            threshold_multiplier: config.spike_detection.threshold_multiplier,
+            minimum_spike_duration: format_duration(config.spike_detection.minimum_spike_duration),
+            strategy: config.spike_detection.strategy.clone(),
+        }
+    }
+}
+
+/// Outcome of replaying the range through one configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestRun {
+    pub config: ConfigSummary,
+    /// Detected spikes in their final state, oldest first
+    pub spikes: Vec<FeeSpike>,
+    pub spikes_by_severity: BTreeMap<String, usize>,
+    /// Alerts the enabled alert rules would have fired, keyed by spike severity
+    pub alerts_by_severity: BTreeMap<String, usize>,
+    pub total_alerts: usize,
+    /// Batches the engine rejected, e.g. for lack of a baseline
+    pub failed_batches: usize,
+}
+
+/// Differences between the candidate and current runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestDiff {
+    pub spikes: i64,
+    pub alerts: i64,
+    pub alerts_by_severity: BTreeMap<String, i64>,
+    /// IDs of spikes only the current configuration detects
+    pub only_current: Vec<String>,
+    /// IDs of spikes only the candidate configuration detects
+    pub only_candidate: Vec<String>,
+}
+
+/// Full backtest report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub points_replayed: usize,
+    pub alert_rules: usize,
+    pub current: BacktestRun,
+    pub candidate: BacktestRun,
+    pub diff: BacktestDiff,
+}
+
+/// Replay `from..to` from the repository through both configurations.
+/// Alert rules fire no lower than `alerts_min_severity`, as they do live.
+pub async fn run_backtest(
+    repository: &FeeRepository,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    current: InsightsConfig,
+    candidate: InsightsConfig,
+    batch: Duration,
+    alerts_min_severity: Option<&SpikeSeverity>,
+) -> Result<BacktestReport, sqlx::Error> {
+    let points = repository.fetch_range(from, to).await?;
+    let rules: Vec<AlertConfig> = repository
+        .list_alert_configs()
+        .await?
+        .into_iter()
+        .filter(|rule| rule.enabled)
+        .collect();
+    let rules = apply_min_severity(rules, alerts_min_severity);
+
+    let current = replay(&points, current, batch, &rules).await;
+    let candidate = replay(&points, candidate, batch, &rules).await;
+    let diff = diff_runs(&current, &candidate);
+
+    Ok(BacktestReport {
+        from,
+        to,
+        points_replayed: points.len(),
+        alert_rules: rules.len(),
+        current,
+        candidate,
+        diff,
+    })
+}
+
+/// Replay points, oldest first, through a fresh engine in batches of `batch`.
+pub async fn replay(
+    points: &[FeeDataPoint],
+    config: InsightsConfig,
+    batch: Duration,
+    rules: &[AlertConfig],
+) -> BacktestRun {
+    let summary = ConfigSummary::of(&config);
+    let start = points.first().map(|p| p.timestamp).unwrap_or_else(Utc::now);
+    let mut replayer = Replayer::new(config, start, batch);
+
+    let mut spikes: BTreeMap<String, FeeSpike> = BTreeMap::new();
+    replayer
+        .replay(points, |engine| {
+            for event in engine.take_spike_events() {
+                spikes.insert(event.spike.id.clone(), event.spike);
+            }
+        })
+        .await;
+
+    let mut spikes: Vec<FeeSpike> = spikes.into_values().collect();
+    spikes.sort_by_key(|spike| spike.start_time);
+
+    let mut spikes_by_severity = BTreeMap::new();
+    let mut alerts_by_severity = BTreeMap::new();
+    for spike in &spikes {
+        let severity = spike.severity.as_str().to_string();
+        *spikes_by_severity.entry(severity.clone()).or_insert(0) += 1;
+        let alerts = rules.iter().filter(|rule| rule_matches(rule, &spike.severity)).count();
+        if alerts > 0 {
+            *alerts_by_severity.entry(severity).or_insert(0) += alerts;
+        }
+    }
+
+    BacktestRun {
+        config: summary,
+        total_alerts: alerts_by_severity.values().sum(),
+        spikes,
+        spikes_by_severity,
+        alerts_by_severity,
+        failed_batches: replayer.failed_batches(),
+    }
+}
+
+fn diff_runs(current: &BacktestRun, candidate: &BacktestRun) -> BacktestDiff {
+    let current_ids: BTreeSet<&str> = current.spikes.iter().map(|s| s.id.as_str()).collect();
+    let candidate_ids: BTreeSet<&str> = candidate.spikes.iter().map(|s| s.id.as_str()).collect();
+
+    let severities: BTreeSet<&String> = current
+        .alerts_by_severity
+        .keys()
+        .chain(candidate.alerts_by_severity.keys())
+        .collect();
+    let alerts_by_severity = severities
+        .into_iter()
+        .map(|severity| {
+            let before = current.alerts_by_severity.get(severity).copied().unwrap_or(0) as i64;
+            let after = candidate.alerts_by_severity.get(severity).copied().unwrap_or(0) as i64;
+            (severity.clone(), after - before)
+        })
+        .collect();
+
+    BacktestDiff {
+        spikes: candidate.spikes.len() as i64 - current.spikes.len() as i64,
+        alerts: candidate.total_alerts as i64 - current.total_alerts as i64,
+        alerts_by_severity,
+        only_current: current_ids.difference(&candidate_ids).map(|id| id.to_string()).collect(),
+        only_candidate: candidate_ids.difference(&current_ids).map(|id| id.to_string()).collect(),
+    }
+}
+
+impl BacktestReport {
+    /// Render the report as a Markdown document.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Backtest {} – {}\n", self.from.to_rfc3339(), self.to.to_rfc3339());
+        let _ = writeln!(
+            out,
+            "{} fee points replayed against {} enabled alert rule(s).\n",
+            self.points_replayed, self.alert_rules
+        );
+
+        let _ = writeln!(out, "| | Current | Candidate | Change |");
+        let _ = writeln!(out, "|---|---|---|---|");
+        let _ = writeln!(
+            out,
+            "| Threshold multiplier | {} | {} | |",
+            self.current.config.threshold_multiplier, self.candidate.config.threshold_multiplier
+        );
+        let _ = writeln!(
+            out,
+            "| Windows | {} | {} | |",
+            self.current.config.windows.join(", "),
+            self.candidate.config.windows.join(", ")
+        );
+        let _ = writeln!(
+            out,
+            "| Spikes | {} | {} | {:+} |",
+            self.current.spikes.len(),
+            self.candidate.spikes.len(),
+            self.diff.spikes
+        );
+        let _ = writeln!(
+            out,
+            "| Alerts | {} | {} | {:+} |",
+            self.current.total_alerts, self.candidate.total_alerts, self.diff.alerts
+        );
+        for (severity, change) in &self.diff.alerts_by_severity {
+            let _ = writeln!(
+                out,
+                "| Alerts ({}) | {} | {} | {:+} |",
+                severity,
+                self.current.alerts_by_severity.get(severity).copied().unwrap_or(0),
+                self.candidate.alerts_by_severity.get(severity).copied().unwrap_or(0),
+                change
+            );
+        }
+
+        for (title, run) in [("Current", &self.current), ("Candidate", &self.candidate)] {
+            let _ = writeln!(out, "\n## {} spikes\n", title);
+            if run.spikes.is_empty() {
+                let _ = writeln!(out, "None detected.");
+                continue;
+            }
+            let _ = writeln!(out, "| ID | Start | Severity | Peak | Baseline | Duration |");
+            let _ = writeln!(out, "|---|---|---|---|---|---|");
+            for spike in &run.spikes {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} | {:.1} | {} |",
+                    spike.id,
+                    spike.start_time.to_rfc3339(),
+                    spike.severity.as_str(),
+                    spike.peak_fee,
+                    spike.baseline_fee,
+                    format_duration(spike.duration.max(Duration::seconds(1)))
+                );
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_pool;
+
+    fn spike_series(start: DateTime<Utc>) -> Vec<FeeDataPoint> {
+        // Ten minutes of 100 stroop fees with a 5x and a 2.5x burst in between
+        (0..40)
+            .map(|i| {
+                let fee_amount = match i {
+                    15..=17 => 500,
+                    30..=32 => 250,
+                    _ => 100,
+                };
+                FeeDataPoint {
+                    fee_amount,
+                    timestamp: start + Duration::seconds(i * 15),
+                    transaction_hash: format!("bt_{}", i),
+                    ledger_sequence: i as u64,
+                }
+            })
+            .collect()
+    }
+
+    fn zero_duration_config() -> InsightsConfig {
+        let mut config = InsightsConfig::default();
+        config.spike_detection.minimum_spike_duration = Duration::zero();
+        config
+    }
+
+    #[tokio::test]
+    async fn lower_threshold_detects_more_spikes_and_alerts() {
+        let repo = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let start = Utc::now() - Duration::days(2);
+        repo.insert_fee_points(&spike_series(start)).await.unwrap();
+        repo.insert_alert_config("https://hooks.example.com/a", "Minor").await.unwrap();
+
+        let current = zero_duration_config();
+        let candidate = ConfigOverrides {
+            threshold_multiplier: Some(1.5),
+            ..ConfigOverrides::default()
+        }
+        .apply(&current)
+        .unwrap();
+
+        let report = run_backtest(
+            &repo,
+            start - Duration::minutes(1),
+            start + Duration::hours(1),
+            current,
+            candidate,
+            Duration::minutes(1),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.points_replayed, 40);
+        assert_eq!(report.alert_rules, 1);
+        assert_eq!(report.current.spikes.len(), 1);
+        assert_eq!(report.candidate.spikes.len(), 2);
+        assert_eq!(report.diff.spikes, 1);
+        assert_eq!(report.diff.alerts, 1);
+        assert_eq!(report.diff.only_candidate.len(), 1);
+        assert!(report.diff.only_current.is_empty());
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| Spikes | 1 | 2 | +1 |"));
+    }
+
+    #[test]
+    fn overrides_replace_windows_but_keep_spike_settings() {
+        let base = zero_duration_config();
+        let config = ConfigOverrides {
+            windows: vec!["10m".to_string(), "2h".to_string(), "2d".to_string()],
+            ..ConfigOverrides::default()
+        }
+        .apply(&base)
+        .unwrap();
+
+        assert_eq!(config.baseline_window, "2h");
+        assert_eq!(config.spike_detection.minimum_spike_duration, Duration::zero());
+
+        let invalid = ConfigOverrides {
+            threshold_multiplier: Some(0.0),
+            ..ConfigOverrides::default()
+        };
+        assert!(invalid.apply(&base).is_err());
+    }
+}
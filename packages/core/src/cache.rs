@@ -1,9 +1,15 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
 use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
+
 /// In-memory TTL cache for a single clonable response value.
 pub struct ResponseCache<T: Clone> {
     value: Option<T>,
     cached_at: Option<Instant>,
+    /// Wall-clock time of `cached_at`, for Last-Modified headers
+    updated_at: Option<DateTime<Utc>>,
     ttl: Duration,
 }
 
@@ -12,6 +18,7 @@ impl<T: Clone> ResponseCache<T> {
         Self {
             value: None,
             cached_at: None,
+            updated_at: None,
             ttl,
         }
     }
@@ -28,11 +35,25 @@ impl<T: Clone> ResponseCache<T> {
     pub fn set(&mut self, value: T) {
         self.value = Some(value);
         self.cached_at = Some(Instant::now());
+        self.updated_at = Some(Utc::now());
     }
 
     pub fn invalidate(&mut self) {
         self.value = None;
         self.cached_at = None;
+        self.updated_at = None;
+    }
+
+    /// When the cached value was stored, while it is still fresh.
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at.filter(|_| self.is_fresh())
+    }
+
+    /// How much longer the cached value stays fresh.
+    pub fn remaining_ttl(&self) -> Option<Duration> {
+        self.cached_at
+            .filter(|_| self.is_fresh())
+            .map(|cached_at| self.ttl.saturating_sub(cached_at.elapsed()))
     }
 
     pub fn is_fresh(&self) -> bool {
@@ -43,6 +64,58 @@ impl<T: Clone> ResponseCache<T> {
     }
 }
 
+/// In-memory TTL cache holding up to `capacity` values by key.
+/// When full, the oldest insertion is evicted.
+pub struct KeyedCache<K, V: Clone> {
+    entries: HashMap<K, (V, Instant)>,
+    order: VecDeque<K>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> KeyedCache<K, V> {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+            ttl,
+        }
+    }
+
+    /// Returns the value for `key` only when still within TTL.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.entries
+            .get(key)
+            .filter(|(_, cached_at)| cached_at.elapsed() <= self.ttl)
+            .map(|(value, _)| value.clone())
+    }
+
+    pub fn set(&mut self, key: K, value: V) {
+        if self.entries.insert(key.clone(), (value, Instant::now())).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn invalidate(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -73,6 +146,22 @@ mod tests {
         assert!(!cache.is_fresh());
     }
 
+    #[test]
+    fn reports_update_time_and_remaining_ttl_while_fresh() {
+        let mut cache = ResponseCache::new(Duration::from_secs(60));
+        assert!(cache.updated_at().is_none());
+        assert!(cache.remaining_ttl().is_none());
+
+        let before = Utc::now();
+        cache.set(42_u64);
+        assert!(cache.updated_at().unwrap() >= before);
+        let remaining = cache.remaining_ttl().unwrap();
+        assert!(remaining <= Duration::from_secs(60) && remaining > Duration::from_secs(59));
+
+        cache.invalidate();
+        assert!(cache.updated_at().is_none());
+    }
+
     #[test]
     fn invalidate_clears_cached_value() {
         let mut cache = ResponseCache::new(Duration::from_secs(5));
@@ -82,4 +171,21 @@ mod tests {
         assert!(cache.get().is_none());
         assert!(!cache.is_fresh());
     }
+
+    #[test]
+    fn keyed_cache_evicts_oldest_and_expires_entries() {
+        let mut cache = KeyedCache::new(2, Duration::from_millis(30));
+        cache.set("a", 1_u64);
+        cache.set("b", 2);
+        cache.set("a", 10);
+        cache.set("c", 3);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&"a").is_none());
+        assert_eq!(cache.get(&"b"), Some(2));
+        assert_eq!(cache.get(&"c"), Some(3));
+
+        thread::sleep(Duration::from_millis(40));
+        assert!(cache.get(&"c").is_none());
+    }
 }
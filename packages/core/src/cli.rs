@@ -1,24 +1,239 @@
 // CLI module placeholder
 // CLI module placeholder
-use clap::Parser;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::export::{ExportDataset, ExportFormat};
+use crate::import::ImportFormat;
 
 /// Stellar Fee Tracker CLI arguments
-#[derive(Debug, Parser)]
+#[derive(Debug, Clone, Default, Parser)]
 #[command(
     name = "stellar-fee-tracker",
     version,
     about = "Real-time insights into Stellar network transaction fees"
 )]
 pub struct Cli {
+    /// TOML configuration file; environment variables and flags override it
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
     /// Stellar network to use (testnet or mainnet)
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub network: Option<String>,
 
     /// Horizon API base URL
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub horizon_url: Option<String>,
 
     /// Fee polling interval in seconds
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub poll_interval: Option<u64>,
+
+    /// Window whose average is the spike baseline, e.g. `1h`
+    #[arg(long, global = true)]
+    pub baseline_window: Option<String>,
+
+    /// Spike baseline mode as JSON, e.g. `{"HourOfWeek":{"weeks":4,"min_samples":3}}`
+    #[arg(long, global = true)]
+    pub baseline_mode: Option<String>,
+
+    /// Spike severity cutoffs as JSON, e.g. `{"moderate":4.0,"major":6.0,"critical":10.0}`
+    #[arg(long, global = true)]
+    pub severity_cutoffs: Option<String>,
+
+    /// Period the congestion trend is measured over, e.g. `30m`
+    #[arg(long, global = true)]
+    pub congestion_window: Option<String>,
+
+    /// Lowest spike severity any alert rule fires at, e.g. `Major`
+    #[arg(long, global = true)]
+    pub alerts_min_severity: Option<String>,
+
+    /// Command to run; defaults to `serve`
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Run the API server and fee poller (the default)
+    Serve,
+
+    /// Fetch fee data from Horizon once and print the resulting insights
+    PollOnce(PollOnceArgs),
+
+    /// Apply pending database migrations, or show their status
+    Migrate {
+        #[command(subcommand)]
+        action: Option<MigrateAction>,
+    },
+
+    /// Database maintenance
+    #[command(subcommand)]
+    Db(DbCommand),
+
+    /// Write stored fee data, snapshots, rollups or alert events to stdout or a file
+    Export(ExportArgs),
+
+    /// Load fee data points from a CSV or NDJSON file
+    Import(ImportArgs),
+
+    /// Configuration tools
+    #[command(subcommand)]
+    Config(ConfigCommand),
+
+    /// Read insights from the database without starting the server
+    #[command(subcommand)]
+    Insights(InsightsCommand),
+
+    /// Replay stored fee data through an alternate insights configuration
+    /// and compare the spikes and alerts against the current one
+    Backtest(BacktestArgs),
+}
+
+/// Arguments for `poll-once`
+#[derive(Debug, Clone, Args)]
+pub struct PollOnceArgs {
+    /// Store the fetched points in the database, as the poller would
+    #[arg(long)]
+    pub persist: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand)]
+pub enum MigrateAction {
+    /// Apply pending migrations (the default)
+    Run,
+    /// List every migration and when it was applied
+    Status,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum DbCommand {
+    /// Delete fee data points and insights snapshots older than the retention period
+    Prune(PruneArgs),
+}
+
+/// Arguments for `db prune`
+#[derive(Debug, Clone, Args)]
+pub struct PruneArgs {
+    /// Age beyond which rows are deleted, e.g. `30d`; defaults to the configured retention
+    #[arg(long)]
+    pub older_than: Option<String>,
+
+    /// Report how many rows would be deleted without deleting them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Arguments for `import`
+#[derive(Debug, Clone, Args)]
+pub struct ImportArgs {
+    /// File to read, or `-` for stdin
+    pub input: PathBuf,
+
+    /// Input format; guessed from the file extension by default
+    #[arg(long, value_enum)]
+    pub format: Option<ImportFormat>,
+
+    /// The input is gzipped; assumed for `.gz` files
+    #[arg(long)]
+    pub gzip: bool,
+}
+
+/// Arguments for `export`
+#[derive(Debug, Clone, Args)]
+pub struct ExportArgs {
+    /// Table to export
+    #[arg(long, value_enum, default_value_t = ExportDataset::Points)]
+    pub dataset: ExportDataset,
+
+    /// Start of the range (RFC 3339); defaults to 24 hours before `--to`
+    #[arg(long)]
+    pub from: Option<DateTime<Utc>>,
+
+    /// End of the range, exclusive (RFC 3339); defaults to now
+    #[arg(long)]
+    pub to: Option<DateTime<Utc>>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub format: ExportFormat,
+
+    /// Columns to include, comma-separated; defaults to all of them
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// Only rollups at this resolution, e.g. 1h
+    #[arg(long)]
+    pub resolution: Option<String>,
+
+    /// Gzip the output
+    #[arg(long)]
+    pub gzip: bool,
+
+    /// File to write; defaults to stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand)]
+pub enum ConfigCommand {
+    /// Validate the configuration and print the resolved settings
+    Check,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum InsightsCommand {
+    /// Print the current insights, or those at `--at`
+    Show(InsightsShowArgs),
+}
+
+/// Arguments for `insights show`
+#[derive(Debug, Clone, Args)]
+pub struct InsightsShowArgs {
+    /// Past instant (RFC 3339) to rebuild the insights at
+    #[arg(long)]
+    pub at: Option<DateTime<Utc>>,
+}
+
+/// Arguments for `backtest`. Options left unset keep the current value.
+#[derive(Debug, Clone, Args)]
+pub struct BacktestArgs {
+    /// Start of the replay range (RFC 3339); defaults to the raw retention
+    /// period (`STORAGE_RETENTION_DAYS`) before `--to`
+    #[arg(long)]
+    pub from: Option<DateTime<Utc>>,
+
+    /// End of the replay range (RFC 3339); defaults to now
+    #[arg(long)]
+    pub to: Option<DateTime<Utc>>,
+
+    /// Spike threshold multiplier to test
+    #[arg(long)]
+    pub threshold_multiplier: Option<f64>,
+
+    /// Minimum spike duration to test, e.g. `2m`
+    #[arg(long)]
+    pub min_spike_duration: Option<String>,
+
+    /// Rolling average windows to test, e.g. `5m,1h,24h`
+    #[arg(long, value_delimiter = ',')]
+    pub windows: Vec<String>,
+
+    /// Spike strategy to test as JSON, e.g. `{"type":"z_score","window":30,"threshold":3.0}`
+    #[arg(long)]
+    pub strategy: Option<String>,
+
+    /// Report format
+    #[arg(long, value_enum, default_value_t = ReportFormat::Markdown)]
+    pub format: ReportFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Json,
+    Markdown,
 }
@@ -0,0 +1,381 @@
+//! One-off subcommands.
+//!
+//! Each command runs to completion and prints its result to stdout, so it
+//! can be piped; logs go to stderr. Errors are returned as messages for
+//! `main` to report before exiting non-zero.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::backtest;
+use crate::cli::{
+    BacktestArgs, ExportArgs, ImportArgs, InsightsShowArgs, MigrateAction, PollOnceArgs, PruneArgs,
+    ReportFormat,
+};
+use crate::clock::SharedClock;
+use crate::config::Config;
+use crate::db;
+use crate::export::{self, ExportRequest, Exporter};
+use crate::import::{self, ImportFormat};
+use crate::insights::types::parse_duration;
+use crate::insights::{FeeDataProvider, FeeInsightsEngine, HorizonFeeDataProvider, InsightsConfig};
+use crate::point_in_time::PointInTimeInsights;
+use crate::reload::Reloader;
+use crate::repository::{FeeRepository, ROLLUP_RESOLUTIONS};
+use crate::retention::{self, RetentionReport};
+use crate::scheduler;
+use crate::services::horizon::HorizonClient;
+use crate::store::{FeeHistoryStore, DEFAULT_CAPACITY};
+
+/// How far back persisted points are replayed when restoring the engine.
+pub const REHYDRATION_HOURS: i64 = 24;
+
+/// The insights configuration to run with: the one applied through the
+/// admin API if one is stored and still valid, otherwise the configured one.
+pub async fn effective_insights_config(
+    config: &Config,
+    repository: &FeeRepository,
+) -> Result<InsightsConfig, String> {
+    let configured = config
+        .insights_config()
+        .map_err(|err| format!("Invalid insights configuration: {}", err))?;
+
+    match repository.load_insights_config().await {
+        Ok(Some(stored)) => match stored.validate() {
+            Ok(()) => {
+                tracing::info!("Using insights configuration applied through the admin API");
+                // Points arrive at the poller's rate whatever was stored
+                Ok(InsightsConfig {
+                    polling_interval: configured.polling_interval,
+                    ..stored
+                })
+            }
+            Err(err) => {
+                tracing::warn!("Ignoring stored insights configuration: {}", err);
+                Ok(configured)
+            }
+        },
+        Ok(None) => Ok(configured),
+        Err(err) => {
+            tracing::warn!("Failed to load stored insights configuration: {}", err);
+            Ok(configured)
+        }
+    }
+}
+
+/// Build an insights engine and restore its state from the database: the
+/// latest snapshot plus newer points, and the closed extremes periods.
+pub async fn restore_insights_engine(
+    config: &Config,
+    repository: &FeeRepository,
+    clock: SharedClock,
+) -> Result<Arc<RwLock<FeeInsightsEngine>>, String> {
+    let insights_config = effective_insights_config(config, repository).await?;
+    let extremes_to_keep = insights_config.extremes.historical_periods_to_keep as i64;
+    let since = clock.now() - Duration::hours(REHYDRATION_HOURS);
+    let engine = Arc::new(RwLock::new(FeeInsightsEngine::with_clock(insights_config, clock)));
+
+    let replayed = scheduler::rehydrate_insights_engine(&engine, repository, since).await;
+    tracing::info!("Replayed {} fee data points through the insights engine", replayed);
+
+    match repository.fetch_extreme_periods(extremes_to_keep).await {
+        Ok(periods) if !periods.is_empty() => {
+            let count = periods.len();
+            engine.write().await.restore_extremes_history(periods);
+            tracing::info!("Restored {} extremes periods from database", count);
+        }
+        Ok(_) => {}
+        Err(err) => tracing::warn!("Failed to restore extremes history: {}", err),
+    }
+
+    Ok(engine)
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// `config check` — the configuration has already been loaded and
+/// validated by the time this runs, so only the result is printed.
+pub fn config_check(config: &Config) -> Result<(), String> {
+    println!("Configuration is valid");
+    println!("{:#?}", config);
+    Ok(())
+}
+
+/// `migrate` / `migrate status`
+pub async fn migrate(config: &Config, action: MigrateAction) -> Result<(), String> {
+    let pool = db::connect(&config.database_url)
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", config.database_url, e))?;
+
+    if action == MigrateAction::Run {
+        db::run_migrations(&pool)
+            .await
+            .map_err(|e| format!("Migration failed: {}", e))?;
+    }
+
+    let status = db::migration_status(&pool).await.map_err(|e| e.to_string())?;
+    for migration in &status {
+        let applied = migration
+            .applied_at
+            .map(|at| at.to_rfc3339())
+            .unwrap_or_else(|| "pending".to_string());
+        println!("{:>4}  {:<40} {}", migration.version, migration.description, applied);
+    }
+    Ok(())
+}
+
+/// `poll-once` — fetch from Horizon once, feed the restored engine and
+/// print its insights. Nothing is written unless `--persist` is given.
+pub async fn poll_once(
+    config: &Config,
+    repository: Arc<FeeRepository>,
+    clock: SharedClock,
+    args: &PollOnceArgs,
+) -> Result<(), String> {
+    let client = HorizonClient::with_timeout(
+        config.horizon_url.clone(),
+        StdDuration::from_secs(config.horizon_timeout_seconds),
+    );
+    let provider: Arc<dyn FeeDataProvider + Send + Sync> =
+        Arc::new(HorizonFeeDataProvider::new(client));
+    let store = Arc::new(RwLock::new(FeeHistoryStore::new(DEFAULT_CAPACITY)));
+    let engine = restore_insights_engine(config, &repository, clock).await?;
+
+    let fetched = scheduler::poll_once(
+        &provider,
+        &store,
+        &engine,
+        config.retry_attempts,
+        config.base_retry_delay_ms,
+        args.persist.then_some(repository.as_ref()),
+        &Reloader::poll_settings_of(config),
+        None,
+        None,
+    )
+    .await;
+    if !fetched {
+        return Err(format!("No fee data fetched from {}", config.horizon_url));
+    }
+
+    if args.persist {
+        scheduler::save_engine_snapshot(&engine, &repository).await;
+    }
+
+    let insights = engine.read().await.get_current_insights();
+    print_json(&insights)
+}
+
+/// `insights show` — insights restored from the database, or rebuilt as
+/// of `--at`.
+pub async fn insights_show(
+    config: &Config,
+    repository: Arc<FeeRepository>,
+    clock: SharedClock,
+    args: &InsightsShowArgs,
+) -> Result<(), String> {
+    if let Some(at) = args.at {
+        if at > clock.now() {
+            return Err("--at must not be in the future".to_string());
+        }
+        let insights_config = effective_insights_config(config, &repository).await?;
+        let insights = PointInTimeInsights::new(repository)
+            .with_snapshot_interval(config.snapshot_interval_seconds)
+            .insights_at(&insights_config, at)
+            .await
+            .map_err(|e| format!("Failed to rebuild insights: {}", e))?;
+        return print_json(&insights);
+    }
+
+    let engine = restore_insights_engine(config, &repository, clock).await?;
+    let engine = engine.read().await;
+    if engine.get_last_update().is_none() {
+        return Err("No fee data in the database yet".to_string());
+    }
+    print_json(&engine.get_current_insights())
+}
+
+#[derive(Debug, Serialize)]
+struct PruneReport {
+    cutoff: chrono::DateTime<Utc>,
+    dry_run: bool,
+    #[serde(flatten)]
+    pruned: RetentionReport,
+}
+
+/// `db prune` — apply the configured retention tiers as the poller would.
+/// `--older-than` replaces the raw retention; rollups are still downsampled
+/// from raw points before those are deleted.
+pub async fn db_prune(
+    config: &Config,
+    repository: &FeeRepository,
+    clock: SharedClock,
+    args: &PruneArgs,
+) -> Result<(), String> {
+    let mut policy = config.retention_policy();
+    if let Some(spec) = &args.older_than {
+        let age = parse_duration(spec)
+            .filter(|age| *age > Duration::zero())
+            .ok_or_else(|| format!("Invalid --older-than '{}'; expected e.g. 30d or 12h", spec))?;
+        policy.raw = age;
+    }
+    let now = clock.now();
+
+    let pruned = if args.dry_run {
+        retention::preview_retention(repository, &policy, now).await
+    } else {
+        retention::apply_retention(repository, &policy, now).await
+    }
+    .map_err(|e| e.to_string())?;
+
+    print_json(&PruneReport {
+        cutoff: policy.raw_cutoff(now),
+        dry_run: args.dry_run,
+        pruned,
+    })
+}
+
+/// `export` — rows of a table in `[from, to)`, written a batch at a time.
+pub async fn export(
+    repository: &FeeRepository,
+    clock: SharedClock,
+    args: &ExportArgs,
+) -> Result<(), String> {
+    let to = args.to.unwrap_or_else(|| clock.now());
+    let from = args.from.unwrap_or(to - Duration::hours(export::DEFAULT_EXPORT_HOURS));
+    if from >= to {
+        return Err("--from must be before --to".to_string());
+    }
+    if let Some(resolution) = &args.resolution {
+        if !ROLLUP_RESOLUTIONS.contains(&resolution.as_str()) {
+            return Err(format!(
+                "Invalid --resolution '{}'. Must be one of: {}",
+                resolution,
+                ROLLUP_RESOLUTIONS.join(", ")
+            ));
+        }
+    }
+
+    let mut exporter = Exporter::new(ExportRequest {
+        dataset: args.dataset,
+        format: args.format,
+        from,
+        to,
+        columns: args.dataset.select_columns(&args.columns)?,
+        gzip: args.gzip,
+        resolution: args.resolution.clone(),
+    })
+    .map_err(|e| format!("Export failed: {}", e))?;
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
+    while let Some(chunk) = exporter
+        .next_chunk(repository)
+        .await
+        .map_err(|e| format!("Export failed: {}", e))?
+    {
+        out.write_all(&chunk).map_err(|e| format!("Export failed: {}", e))?;
+    }
+    out.flush().map_err(|e| format!("Export failed: {}", e))?;
+
+    tracing::info!("Exported {} {} rows", exporter.rows(), args.dataset.table());
+    Ok(())
+}
+
+/// `import` — load fee data points and print the import report.
+pub async fn import(
+    repository: &FeeRepository,
+    clock: SharedClock,
+    args: &ImportArgs,
+) -> Result<(), String> {
+    let from_stdin = args.input.as_os_str() == "-";
+    let format = args.format.unwrap_or_else(|| ImportFormat::from_path(&args.input));
+    let gzip = args.gzip || args.input.extension().is_some_and(|ext| ext == "gz");
+
+    let input: Box<dyn Read> = if from_stdin {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(
+            File::open(&args.input)
+                .map_err(|e| format!("Cannot open {}: {}", args.input.display(), e))?,
+        )
+    };
+    let input: Box<dyn BufRead> = if gzip {
+        Box::new(BufReader::new(GzDecoder::new(input)))
+    } else {
+        Box::new(BufReader::new(input))
+    };
+
+    let report = import::import_points(repository, input, format, clock.now())
+        .await
+        .map_err(|e| e.to_string())?;
+    tracing::info!(
+        "Imported {} fee data points; {} duplicates skipped, {} rows rejected",
+        report.imported,
+        report.duplicates,
+        report.rejected
+    );
+    print_json(&report)
+}
+
+/// `backtest` — print the report in the requested format.
+pub async fn backtest(
+    config: &Config,
+    repository: &FeeRepository,
+    args: &BacktestArgs,
+) -> Result<(), String> {
+    let current = effective_insights_config(config, repository).await?;
+    let candidate = backtest::overrides_from_args(args)
+        .and_then(|overrides| overrides.apply(&current).map_err(|e| e.to_string()))?;
+
+    // Raw points older than the retention period have been pruned, so the
+    // default range stops there and an explicit older start is flagged
+    let now = Utc::now();
+    let raw_retention = config.retention_policy().raw;
+    let to = args.to.unwrap_or(now);
+    let from = args.from.unwrap_or(to - raw_retention);
+    if from >= to {
+        return Err("--from must be before --to".to_string());
+    }
+    if from < now - raw_retention {
+        tracing::warn!(
+            "--from is older than the {}d raw retention; pruned points are missing from the replay",
+            raw_retention.num_days()
+        );
+    }
+
+    let batch = Duration::seconds(config.poll_interval_seconds.max(1) as i64);
+    let report = backtest::run_backtest(
+        repository,
+        from,
+        to,
+        current,
+        candidate,
+        batch,
+        config.alerts_min_severity.as_ref(),
+    )
+    .await
+    .map_err(|err| format!("Backtest failed: {}", err))?;
+
+    match args.format {
+        ReportFormat::Json => print_json(&report),
+        ReportFormat::Markdown => {
+            print!("{}", report.to_markdown());
+            io::stdout().flush().map_err(|e| e.to_string())
+        }
+    }
+}
@@ -1,22 +1,98 @@
+//! Service configuration.
+//!
+//! Settings are layered: built-in defaults, then the TOML file given by
+//! `--config`, then environment variables, then CLI flags. Secrets can be
+//! read from a file named by the matching `*_FILE` variable (or
+//! `*_file` key) so they stay out of the environment and the config file.
+//! Every invalid setting is reported at once rather than one per restart.
+
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
 
+use serde::de::DeserializeOwned;
+
+use crate::api::rate_limit::{default_rate_limits, parse_rate_limit, RateLimit, RouteGroup};
 use crate::cli::Cli;
+use crate::config_file::FileConfig;
+use crate::insights::config::{BaselineMode, ConfigOverrides, SpikeStrategy};
+use crate::insights::strategy::SeverityCutoffs;
+use crate::insights::types::SpikeSeverity;
+use crate::insights::{parse_duration, InsightsConfig};
+use crate::repository::ROLLUP_RESOLUTIONS;
+use crate::retention::{RetentionPolicy, DEFAULT_RAW_RETENTION_DAYS};
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub stellar_network: StellarNetwork,
     pub horizon_url: String,
+    /// Per-request timeout for Horizon calls
+    pub horizon_timeout_seconds: u64,
     pub poll_interval_seconds: u64,
     pub cache_ttl_seconds: u64,
     pub api_port: u16,
     pub allowed_origins: Vec<String>,
+    /// Token-bucket limit of each route group; `None` leaves it unlimited
+    pub rate_limits: BTreeMap<RouteGroup, Option<RateLimit>>,
+    /// Rate limit by the last `X-Forwarded-For` address rather than the
+    /// peer, for when a reverse proxy sits in front of the service
+    pub rate_limit_trust_forwarded_for: bool,
     pub retry_attempts: u32,
     pub base_retry_delay_ms: u64,
     pub database_url: String,
+    /// Days raw fee data points are kept
     pub storage_retention_days: u64,
+    /// Days rollups are kept per resolution, for every maintained
+    /// resolution; 0 keeps a tier forever
+    pub rollup_retention_days: BTreeMap<String, u64>,
+    /// How often the insights engine state is snapshotted to the database
+    pub snapshot_interval_seconds: u64,
+    /// Rolling average windows such as `5m,1h,24h,7d`; empty means the
+    /// built-in short/medium/long-term windows
+    pub insights_windows: Vec<String>,
+    /// Window whose average is the spike baseline; defaults to the middle one
+    pub insights_baseline_window: Option<String>,
+    pub insights_baseline_mode: Option<BaselineMode>,
+    pub insights_threshold_multiplier: Option<f64>,
+    pub insights_minimum_spike_duration: Option<chrono::Duration>,
+    pub insights_congestion_window: Option<chrono::Duration>,
+    pub insights_strategy: Option<SpikeStrategy>,
+    pub insights_severity_cutoffs: Option<SeverityCutoffs>,
+    pub extremes_tracking_period: Option<chrono::Duration>,
+    pub extremes_periods_to_keep: Option<usize>,
+    pub extremes_top_n: Option<usize>,
+    /// Whether the poller dispatches alerts for spikes matching the alert rules
+    pub alerts_enabled: bool,
+    /// Lowest severity any alert rule fires at, whatever its own threshold
+    pub alerts_min_severity: Option<SpikeSeverity>,
+    /// Key required in `X-Api-Key` by the `/admin` routes; unset disables them
+    pub admin_api_key: Option<Secret>,
+    /// The `--config` file these settings were read from, re-read on reload
+    pub config_file: Option<PathBuf>,
 }
 
-#[derive(Debug, Clone)]
+/// A configuration value that must not appear in logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StellarNetwork {
     Testnet,
     Mainnet,
@@ -31,15 +107,114 @@ impl StellarNetwork {
             StellarNetwork::Mainnet => "https://horizon.stellar.org",
         }
     }
+
+    /// Name as given in `STELLAR_NETWORK`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StellarNetwork::Testnet => "testnet",
+            StellarNetwork::Mainnet => "mainnet",
+        }
+    }
+}
+
+/// Resolves one layer at a time and collects every problem on the way.
+struct Resolver<'a> {
+    env: &'a dyn Fn(&str) -> Option<String>,
+    errors: Vec<String>,
+}
+
+impl Resolver<'_> {
+    /// CLI value, else the parsed env var, else the file value.
+    fn layered<T: FromStr>(&mut self, cli: Option<T>, key: &str, file: Option<T>) -> Option<T> {
+        if cli.is_some() {
+            return cli;
+        }
+        match (self.env)(key) {
+            Some(raw) => match raw.trim().parse() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    self.errors.push(format!("Invalid {}: {}", key, raw));
+                    None
+                }
+            },
+            None => file,
+        }
+    }
+
+    /// CLI value, else the env var, else the file value, as a duration
+    /// such as `30m`.
+    fn duration(&mut self, cli: Option<String>, key: &str, file: Option<String>) -> Option<chrono::Duration> {
+        let raw = cli.or_else(|| (self.env)(key)).or(file)?;
+        let parsed = parse_duration(raw.trim());
+        if parsed.is_none() {
+            self.errors.push(format!("Invalid {}: {}", key, raw));
+        }
+        parsed
+    }
+
+    /// CLI value, else the env var, both given as JSON, else the file value.
+    fn json<T: DeserializeOwned>(&mut self, cli: Option<String>, key: &str, file: Option<T>) -> Option<T> {
+        match cli.or_else(|| (self.env)(key)) {
+            Some(json) => match serde_json::from_str(&json) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    self.errors.push(format!("Invalid {}: {}", key, e));
+                    None
+                }
+            },
+            None => file,
+        }
+    }
+
+    /// A secret from `key`, the file named by `{key}_FILE`, the config
+    /// file's value or the file named by its `*_file` key, in that order.
+    fn secret(&mut self, key: &str, file: Option<String>, file_path: Option<PathBuf>) -> Option<Secret> {
+        let file_key = format!("{}_FILE", key);
+        let (value, source) = if let Some(value) = (self.env)(key) {
+            (Some(value), None)
+        } else if let Some(path) = (self.env)(&file_key) {
+            (read_secret(&path), Some((file_key, path)))
+        } else if let Some(value) = file {
+            (Some(value), None)
+        } else if let Some(path) = file_path {
+            let path = path.display().to_string();
+            (read_secret(&path), Some((format!("{} file", key), path)))
+        } else {
+            (None, None)
+        };
+
+        match (value, source) {
+            (None, Some((name, path))) => {
+                self.errors.push(format!("Cannot read {} {}", name, path));
+                None
+            }
+            (value, _) => value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .map(Secret::new),
+        }
+    }
+}
+
+fn read_secret(path: &str) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
+
+fn split_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
 }
 
 impl Config {
-    /// Build configuration from CLI flags and environment variables.
+    /// Build configuration from the `--config` file, environment variables
+    /// and CLI flags.
     ///
     /// `HORIZON_URL` is optional — when omitted it defaults to the well-known
     /// public Horizon endpoint for the selected `STELLAR_NETWORK`.
     pub fn from_sources(cli: &Cli) -> Result<Self, String> {
-        Self::from_sources_with_env(cli, &std::collections::HashMap::new())
+        Self::from_sources_with_env(cli, &HashMap::new())
     }
 
     /// Like `from_sources` but allows injecting env var overrides — used in tests
@@ -47,96 +222,331 @@ impl Config {
     #[cfg(test)]
     pub fn from_sources_with_overrides(
         cli: &Cli,
-        overrides: &std::collections::HashMap<&str, &str>,
+        overrides: &HashMap<&str, &str>,
     ) -> Result<Self, String> {
         Self::from_sources_with_env(cli, &overrides.iter().map(|(k, v)| (*k, *v)).collect())
     }
 
-    fn from_sources_with_env(
-        cli: &Cli,
-        overrides: &std::collections::HashMap<&str, &str>,
-    ) -> Result<Self, String> {
+    fn from_sources_with_env(cli: &Cli, overrides: &HashMap<&str, &str>) -> Result<Self, String> {
+        let file = match &cli.config {
+            Some(path) => FileConfig::load(path)?,
+            None => FileConfig::default(),
+        };
         let get = |key: &str| -> Option<String> {
             overrides
                 .get(key)
                 .map(|v| v.to_string())
                 .or_else(|| env::var(key).ok())
         };
+        Self::resolve(cli, file, &get)
+    }
+
+    fn resolve(
+        cli: &Cli,
+        file: FileConfig,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self, String> {
+        let mut r = Resolver { env, errors: Vec::new() };
+        let FileConfig { horizon, poller, api, database, insights, extremes, alerts } = file;
 
         // -------- Network --------
         let network_raw = cli
             .network
             .clone()
-            .or_else(|| get("STELLAR_NETWORK"))
-            .ok_or("STELLAR_NETWORK is required")?;
-
-        let stellar_network = match network_raw.as_str() {
-            "testnet" => StellarNetwork::Testnet,
-            "mainnet" => StellarNetwork::Mainnet,
-            other => return Err(format!("Invalid STELLAR_NETWORK: {}", other)),
+            .or_else(|| env("STELLAR_NETWORK"))
+            .or(horizon.network);
+        let stellar_network = match network_raw.as_deref() {
+            Some("testnet") => Some(StellarNetwork::Testnet),
+            Some("mainnet") => Some(StellarNetwork::Mainnet),
+            Some(other) => {
+                r.errors.push(format!("Invalid STELLAR_NETWORK: {}", other));
+                None
+            }
+            None => {
+                r.errors.push("STELLAR_NETWORK is required".to_string());
+                None
+            }
         };
 
-        // -------- Horizon URL --------
+        // -------- Horizon --------
         let horizon_url = cli
             .horizon_url
             .clone()
-            .or_else(|| get("HORIZON_URL"))
-            .unwrap_or_else(|| stellar_network.default_horizon_url().to_string());
+            .or_else(|| env("HORIZON_URL"))
+            .or(horizon.url);
+        let horizon_timeout_seconds = r
+            .layered(None, "HORIZON_TIMEOUT_SECONDS", horizon.timeout_seconds)
+            .unwrap_or(10);
 
         // -------- Poll Interval --------
-        let poll_interval_seconds = cli
-            .poll_interval
-            .or_else(|| get("POLL_INTERVAL_SECONDS")?.parse().ok())
-            .ok_or("POLL_INTERVAL_SECONDS is required and must be a number")?;
-
-        // -------- API Port --------
-        let api_port = get("API_PORT")
-            .and_then(|v| v.parse::<u16>().ok())
-            .unwrap_or(8080);
-
-        // -------- Cache TTL --------
-        let cache_ttl_seconds = get("CACHE_TTL_SECONDS")
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(5);
-
-        // -------- Allowed Origins --------
-        let allowed_origins = get("ALLOWED_ORIGINS")
-            .unwrap_or_else(|| "http://localhost:3000".to_string())
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
+        let poll_interval_seconds = r.layered(cli.poll_interval, "POLL_INTERVAL_SECONDS", poller.interval_seconds);
+        match poll_interval_seconds {
+            None => r.errors.push("POLL_INTERVAL_SECONDS is required and must be a number".to_string()),
+            Some(0) => r.errors.push("POLL_INTERVAL_SECONDS must be at least 1".to_string()),
+            Some(_) => {}
+        }
 
-        // -------- Retry config --------
-        let retry_attempts = get("RETRY_ATTEMPTS")
-            .and_then(|v| v.parse::<u32>().ok())
-            .unwrap_or(3);
+        // -------- API --------
+        let api_port = r.layered(None, "API_PORT", api.port).unwrap_or(8080);
+        let cache_ttl_seconds = r.layered(None, "CACHE_TTL_SECONDS", api.cache_ttl_seconds).unwrap_or(5);
+        let allowed_origins = env("ALLOWED_ORIGINS")
+            .map(|raw| split_list(&raw))
+            .or(api.allowed_origins)
+            .unwrap_or_else(|| vec!["http://localhost:3000".to_string()]);
+        for invalid in allowed_origins
+            .iter()
+            .filter(|o| o.parse::<axum::http::HeaderValue>().is_err())
+        {
+            r.errors.push(format!("Invalid ALLOWED_ORIGINS entry: {}", invalid));
+        }
+        let mut rate_limits = default_rate_limits();
+        let rate_limit_overrides = match env("RATE_LIMITS") {
+            Some(raw) => parse_rate_limits(&raw).unwrap_or_else(|err| {
+                r.errors.push(err);
+                BTreeMap::new()
+            }),
+            None => api.rate_limits.unwrap_or_default(),
+        };
+        for (group, limit) in rate_limit_overrides {
+            match (group.parse::<RouteGroup>(), parse_rate_limit(&limit)) {
+                (Ok(group), Ok(limit)) => {
+                    rate_limits.insert(group, limit);
+                }
+                (Err(err), _) | (_, Err(err)) => r.errors.push(format!("Invalid RATE_LIMITS entry: {}", err)),
+            }
+        }
+        let rate_limit_trust_forwarded_for = r
+            .layered(None, "RATE_LIMIT_TRUST_FORWARDED_FOR", api.trust_forwarded_for)
+            .unwrap_or(false);
 
-        let base_retry_delay_ms = get("BASE_RETRY_DELAY_MS")
-            .and_then(|v| v.parse::<u64>().ok())
+        // -------- Retry config --------
+        let retry_attempts = r.layered(None, "RETRY_ATTEMPTS", horizon.retry_attempts).unwrap_or(3);
+        let base_retry_delay_ms = r
+            .layered(None, "BASE_RETRY_DELAY_MS", horizon.base_retry_delay_ms)
             .unwrap_or(1000);
 
-        // -------- Database URL --------
-        let database_url = get("DATABASE_URL")
+        // -------- Database --------
+        let database_url = env("DATABASE_URL")
+            .or(database.url)
             .unwrap_or_else(|| "sqlite://stellar_fees.db".to_string());
+        let storage_retention_days = r
+            .layered(None, "STORAGE_RETENTION_DAYS", database.retention_days)
+            .unwrap_or(DEFAULT_RAW_RETENTION_DAYS);
+        if storage_retention_days == 0 {
+            r.errors.push("STORAGE_RETENTION_DAYS must be at least 1".to_string());
+        }
+        let mut rollup_retention_days = RetentionPolicy::default().rollup_days;
+        let rollup_overrides = match env("ROLLUP_RETENTION_DAYS") {
+            Some(raw) => parse_rollup_retention(&raw).unwrap_or_else(|err| {
+                r.errors.push(err);
+                BTreeMap::new()
+            }),
+            None => database.rollup_retention_days.unwrap_or_default(),
+        };
+        for (resolution, days) in rollup_overrides {
+            if !ROLLUP_RESOLUTIONS.contains(&resolution.as_str()) {
+                r.errors.push(format!(
+                    "Invalid ROLLUP_RETENTION_DAYS resolution '{}'. Must be one of: {}",
+                    resolution,
+                    ROLLUP_RESOLUTIONS.join(", ")
+                ));
+            } else if days > 0 && days < storage_retention_days {
+                // Raw points would be pruned after their rollups, leaving a gap
+                r.errors.push(format!(
+                    "ROLLUP_RETENTION_DAYS for {} must be 0 or at least STORAGE_RETENTION_DAYS ({})",
+                    resolution, storage_retention_days
+                ));
+            } else {
+                rollup_retention_days.insert(resolution, days);
+            }
+        }
 
-        // -------- Storage retention --------
-        let storage_retention_days = get("STORAGE_RETENTION_DAYS")
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(7);
+        // -------- Engine snapshots --------
+        let snapshot_interval_seconds = r
+            .layered(None, "SNAPSHOT_INTERVAL_SECONDS", poller.snapshot_interval_seconds)
+            .unwrap_or(300);
+
+        // -------- Insights --------
+        let insights_windows = env("INSIGHTS_WINDOWS")
+            .map(|raw| split_list(&raw))
+            .or(insights.windows)
+            .unwrap_or_default();
+        for invalid in insights_windows.iter().filter(|w| parse_duration(w).is_none()) {
+            r.errors.push(format!("Invalid INSIGHTS_WINDOWS entry: {}", invalid));
+        }
+        let insights_baseline_window = cli
+            .baseline_window
+            .clone()
+            .or_else(|| env("INSIGHTS_BASELINE_WINDOW"))
+            .or(insights.baseline_window);
+        let insights_baseline_mode =
+            r.json(cli.baseline_mode.clone(), "INSIGHTS_BASELINE_MODE", insights.baseline_mode);
+        let insights_threshold_multiplier =
+            r.layered(None, "INSIGHTS_THRESHOLD_MULTIPLIER", insights.threshold_multiplier);
+        let insights_minimum_spike_duration =
+            r.duration(None, "INSIGHTS_MIN_SPIKE_DURATION", insights.minimum_spike_duration);
+        let insights_congestion_window = r.duration(
+            cli.congestion_window.clone(),
+            "INSIGHTS_CONGESTION_WINDOW",
+            insights.congestion_window,
+        );
+        let insights_strategy = r.json(None, "INSIGHTS_STRATEGY", insights.strategy);
+        let insights_severity_cutoffs = r.json(
+            cli.severity_cutoffs.clone(),
+            "INSIGHTS_SEVERITY_CUTOFFS",
+            insights.severity_cutoffs,
+        );
+
+        // -------- Extremes --------
+        let extremes_tracking_period =
+            r.duration(None, "EXTREMES_TRACKING_PERIOD", extremes.tracking_period);
+        let extremes_periods_to_keep =
+            r.layered(None, "EXTREMES_PERIODS_TO_KEEP", extremes.periods_to_keep);
+        let extremes_top_n = r.layered(None, "EXTREMES_TOP_N", extremes.top_n);
+
+        // -------- Alerts --------
+        let alerts_enabled = r.layered(None, "ALERTS_ENABLED", alerts.enabled).unwrap_or(true);
+        let alerts_min_severity = match cli
+            .alerts_min_severity
+            .clone()
+            .or_else(|| env("ALERTS_MIN_SEVERITY"))
+            .or(alerts.min_severity)
+        {
+            Some(raw) => {
+                let parsed = SpikeSeverity::from_name(raw.trim());
+                if parsed.is_none() {
+                    r.errors.push(format!(
+                        "Invalid ALERTS_MIN_SEVERITY: {}. Must be one of: Minor, Moderate, Major, Critical",
+                        raw
+                    ));
+                }
+                parsed
+            }
+            None => None,
+        };
 
-        Ok(Self {
+        // -------- Admin API --------
+        let admin_api_key = r.secret("ADMIN_API_KEY", api.admin_api_key, api.admin_api_key_file);
+
+        let mut errors = r.errors;
+        let (Some(stellar_network), Some(poll_interval_seconds)) = (stellar_network, poll_interval_seconds) else {
+            return Err(format_errors(&errors));
+        };
+        let config = Self {
+            horizon_url: horizon_url
+                .unwrap_or_else(|| stellar_network.default_horizon_url().to_string()),
             stellar_network,
-            horizon_url,
+            horizon_timeout_seconds,
             poll_interval_seconds,
             cache_ttl_seconds,
             api_port,
             allowed_origins,
+            rate_limits,
+            rate_limit_trust_forwarded_for,
             retry_attempts,
             base_retry_delay_ms,
             database_url,
             storage_retention_days,
+            rollup_retention_days,
+            snapshot_interval_seconds,
+            insights_windows,
+            insights_baseline_window,
+            insights_baseline_mode,
+            insights_threshold_multiplier,
+            insights_minimum_spike_duration,
+            insights_congestion_window,
+            insights_strategy,
+            insights_severity_cutoffs,
+            extremes_tracking_period,
+            extremes_periods_to_keep,
+            extremes_top_n,
+            alerts_enabled,
+            alerts_min_severity,
+            admin_api_key,
+            config_file: cli.config.clone(),
+        };
+
+        // Only check the combined insights settings once each one parsed
+        if errors.is_empty() {
+            if let Err(err) = config.insights_config() {
+                errors.push(format!("Invalid insights settings: {}", err));
+            }
+        }
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(format_errors(&errors))
+        }
+    }
+
+    /// Insights settings from the file, env or CLI, to apply on top of the
+    /// defaults or, on reload, the running engine's configuration.
+    pub fn insights_overrides(&self) -> ConfigOverrides {
+        ConfigOverrides {
+            threshold_multiplier: self.insights_threshold_multiplier,
+            minimum_spike_duration: self.insights_minimum_spike_duration,
+            windows: self.insights_windows.clone(),
+            baseline_window: self.insights_baseline_window.clone(),
+            baseline_mode: self.insights_baseline_mode.clone(),
+            strategy: self.insights_strategy.clone(),
+            severity_cutoffs: self.insights_severity_cutoffs,
+            congestion_window: self.insights_congestion_window,
+            polling_interval: Some(chrono::Duration::seconds(self.poll_interval_seconds as i64)),
+            extremes_tracking_period: self.extremes_tracking_period,
+            extremes_periods_to_keep: self.extremes_periods_to_keep,
+            extremes_top_n: self.extremes_top_n,
+        }
+    }
+
+    /// Retention for raw points and every rollup tier.
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            raw: chrono::Duration::days(self.storage_retention_days as i64),
+            rollup_days: self.rollup_retention_days.clone(),
+        }
+    }
+
+    /// Insights engine configuration: the defaults with the configured
+    /// windows, spike and extremes settings applied.
+    pub fn insights_config(&self) -> Result<InsightsConfig, String> {
+        self.insights_overrides()
+            .apply(&InsightsConfig::default())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Parse `1m=90,1h=0` into days per rollup resolution.
+fn parse_rollup_retention(raw: &str) -> Result<BTreeMap<String, u64>, String> {
+    split_list(raw)
+        .into_iter()
+        .map(|entry| {
+            entry
+                .split_once('=')
+                .and_then(|(resolution, days)| {
+                    Some((resolution.trim().to_string(), days.trim().parse().ok()?))
+                })
+                .ok_or_else(|| format!("Invalid ROLLUP_RETENTION_DAYS entry: {}", entry))
+        })
+        .collect()
+}
+
+/// Parse `fees=120/1m,live=off` into the limit given for each route group.
+fn parse_rate_limits(raw: &str) -> Result<BTreeMap<String, String>, String> {
+    split_list(raw)
+        .into_iter()
+        .map(|entry| {
+            entry
+                .split_once('=')
+                .map(|(group, limit)| (group.trim().to_string(), limit.trim().to_string()))
+                .ok_or_else(|| format!("Invalid RATE_LIMITS entry: {}", entry))
         })
+        .collect()
+}
+
+fn format_errors(errors: &[String]) -> String {
+    match errors {
+        [single] => single.clone(),
+        _ => format!("{} configuration errors:\n  - {}", errors.len(), errors.join("\n  - ")),
     }
 }
 
@@ -150,6 +560,7 @@ mod tests {
             network: Some(network.to_string()),
             horizon_url: horizon_url.map(str::to_string),
             poll_interval: Some(30),
+            ..Cli::default()
         }
     }
 
@@ -184,6 +595,41 @@ mod tests {
         assert_eq!(config.horizon_url, "https://horizon-testnet.stellar.org");
     }
 
+    #[test]
+    fn rollup_retention_overrides_merge_over_defaults() {
+        let cli = make_cli("testnet", None);
+        let env = HashMap::from([("ROLLUP_RETENTION_DAYS", "1m=30, 1d=0")]);
+        let config = Config::from_sources_with_overrides(&cli, &env).unwrap();
+        let policy = config.retention_policy();
+        assert_eq!(policy.rollup_days["1m"], 30);
+        assert_eq!(policy.rollup_days["5m"], 90);
+        assert_eq!(policy.rollup_days["1d"], 0);
+
+        for invalid in ["2m=30", "1m=3", "1m", "1m=x"] {
+            let env = HashMap::from([("ROLLUP_RETENTION_DAYS", invalid)]);
+            assert!(Config::from_sources_with_overrides(&cli, &env).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn rate_limit_overrides_merge_over_defaults() {
+        let cli = make_cli("testnet", None);
+        let env = HashMap::from([("RATE_LIMITS", "fees=10/1s, live=off")]);
+        let config = Config::from_sources_with_overrides(&cli, &env).unwrap();
+        assert_eq!(
+            config.rate_limits[&RouteGroup::Fees],
+            Some(RateLimit { requests: 10, per: std::time::Duration::from_secs(1) })
+        );
+        assert_eq!(config.rate_limits[&RouteGroup::Live], None);
+        assert!(config.rate_limits[&RouteGroup::Admin].is_some());
+        assert!(!config.rate_limit_trust_forwarded_for);
+
+        for invalid in ["feeds=10/1s", "fees=10", "fees", "fees=0/1m"] {
+            let env = HashMap::from([("RATE_LIMITS", invalid)]);
+            assert!(Config::from_sources_with_overrides(&cli, &env).is_err(), "{}", invalid);
+        }
+    }
+
     #[test]
     fn mainnet_without_horizon_url_uses_default() {
         let cli = make_cli("mainnet", None);
@@ -261,4 +707,159 @@ mod tests {
             vec!["http://localhost:3000", "https://app.example.com"]
         );
     }
+
+    #[test]
+    fn insights_windows_default_to_empty() {
+        let cli = make_cli("testnet", None);
+        let config = Config::from_sources_with_overrides(&cli, &no_env()).unwrap();
+        assert!(config.insights_windows.is_empty());
+    }
+
+    #[test]
+    fn insights_windows_parses_duration_list() {
+        let cli = make_cli("testnet", None);
+        let env = HashMap::from([("INSIGHTS_WINDOWS", "5m, 1h,7d")]);
+        let config = Config::from_sources_with_overrides(&cli, &env).unwrap();
+        assert_eq!(config.insights_windows, vec!["5m", "1h", "7d"]);
+    }
+
+    #[test]
+    fn insights_windows_rejects_invalid_duration() {
+        let cli = make_cli("testnet", None);
+        let env = HashMap::from([("INSIGHTS_WINDOWS", "5m,soon")]);
+        let err = Config::from_sources_with_overrides(&cli, &env).unwrap_err();
+        assert!(err.contains("soon"));
+    }
+
+    #[test]
+    fn admin_api_key_is_redacted_in_debug_output() {
+        let cli = make_cli("testnet", None);
+        let env = HashMap::from([("ADMIN_API_KEY", "s3cret")]);
+        let config = Config::from_sources_with_overrides(&cli, &env).unwrap();
+        assert_eq!(config.admin_api_key.as_ref().map(Secret::expose), Some("s3cret"));
+        assert!(!format!("{:?}", config).contains("s3cret"));
+    }
+
+    // ---- Config file layering ----
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("sft-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn file_values_are_overridden_by_env_then_cli() {
+        let path = temp_file(
+            "layers.toml",
+            r#"
+            [horizon]
+            network = "mainnet"
+            [poller]
+            interval_seconds = 60
+            [api]
+            port = 9000
+            cache_ttl_seconds = 30
+            [insights]
+            windows = ["5m", "1h", "24h"]
+            baseline_window = "24h"
+            baseline_mode = { HourOfWeek = { weeks = 2, min_samples = 3 } }
+            threshold_multiplier = 3.0
+            congestion_window = "2h"
+            severity_cutoffs = { moderate = 4.0, major = 6.0, critical = 10.0 }
+            [extremes]
+            tracking_period = "12h"
+            top_n = 3
+            [alerts]
+            enabled = false
+            min_severity = "Major"
+            "#,
+        );
+        let cli = Cli {
+            config: Some(path.clone()),
+            poll_interval: Some(15),
+            baseline_window: Some("5m".to_string()),
+            congestion_window: Some("30m".to_string()),
+            ..Cli::default()
+        };
+        let env = HashMap::from([
+            ("POLL_INTERVAL_SECONDS", "45"),
+            ("API_PORT", "9100"),
+            ("EXTREMES_TOP_N", "8"),
+            ("INSIGHTS_BASELINE_MODE", r#"{"HourOfWeek":{"weeks":4,"min_samples":3}}"#),
+            ("INSIGHTS_CONGESTION_WINDOW", "1h"),
+            ("ALERTS_MIN_SEVERITY", "Critical"),
+        ]);
+        let config = Config::from_sources_with_overrides(&cli, &env).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(config.stellar_network, StellarNetwork::Mainnet);
+        assert_eq!(config.poll_interval_seconds, 15);
+        assert_eq!(config.api_port, 9100);
+        assert_eq!(config.cache_ttl_seconds, 30);
+        let insights = config.insights_config().unwrap();
+        assert_eq!(insights.baseline_window, "5m");
+        let spike = &insights.spike_detection;
+        assert_eq!(spike.threshold_multiplier, 3.0);
+        assert_eq!(spike.baseline_mode, BaselineMode::HourOfWeek { weeks: 4, min_samples: 3 });
+        assert_eq!(spike.congestion_window, chrono::Duration::minutes(30));
+        assert_eq!(
+            spike.severity_cutoffs,
+            Some(SeverityCutoffs { moderate: 4.0, major: 6.0, critical: 10.0 })
+        );
+        assert_eq!(insights.extremes.tracking_period, chrono::Duration::hours(12));
+        assert_eq!(insights.extremes.top_n, 8);
+        assert!(!config.alerts_enabled);
+        assert_eq!(config.alerts_min_severity, Some(SpikeSeverity::Critical));
+    }
+
+    #[test]
+    fn every_invalid_setting_is_reported_at_once() {
+        let cli = make_cli("testnet", None);
+        let env = HashMap::from([
+            ("API_PORT", "http"),
+            ("STORAGE_RETENTION_DAYS", "0"),
+            ("INSIGHTS_WINDOWS", "5m,soon"),
+            ("INSIGHTS_STRATEGY", "{\"type\":\"magic\"}"),
+            ("INSIGHTS_BASELINE_MODE", "hourly"),
+            ("INSIGHTS_CONGESTION_WINDOW", "a while"),
+            ("ALERTS_MIN_SEVERITY", "Severe"),
+        ]);
+        let err = Config::from_sources_with_overrides(&cli, &env).unwrap_err();
+        assert!(err.starts_with("7 configuration errors"));
+        assert!(err.contains("API_PORT"));
+        assert!(err.contains("STORAGE_RETENTION_DAYS"));
+        assert!(err.contains("soon"));
+        assert!(err.contains("INSIGHTS_STRATEGY"));
+        assert!(err.contains("INSIGHTS_BASELINE_MODE"));
+        assert!(err.contains("INSIGHTS_CONGESTION_WINDOW"));
+        assert!(err.contains("ALERTS_MIN_SEVERITY"));
+    }
+
+    #[test]
+    fn insights_values_are_validated_together() {
+        let cli = make_cli("testnet", None);
+        let env = HashMap::from([("INSIGHTS_THRESHOLD_MULTIPLIER", "-1")]);
+        let err = Config::from_sources_with_overrides(&cli, &env).unwrap_err();
+        assert!(err.contains("threshold_multiplier must be positive"));
+
+        let env = HashMap::from([("INSIGHTS_WINDOWS", "5m,1h"), ("INSIGHTS_BASELINE_WINDOW", "24h")]);
+        let err = Config::from_sources_with_overrides(&cli, &env).unwrap_err();
+        assert!(err.contains("Baseline window '24h'"));
+    }
+
+    #[test]
+    fn secrets_load_from_file_paths() {
+        let path = temp_file("admin-key", "from-file\n");
+        let cli = make_cli("testnet", None);
+        let path_str = path.display().to_string();
+        let env = HashMap::from([("ADMIN_API_KEY_FILE", path_str.as_str())]);
+        let config = Config::from_sources_with_overrides(&cli, &env).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(config.admin_api_key.unwrap().expose(), "from-file");
+
+        let env = HashMap::from([("ADMIN_API_KEY_FILE", path_str.as_str())]);
+        let err = Config::from_sources_with_overrides(&cli, &env).unwrap_err();
+        assert!(err.contains("ADMIN_API_KEY_FILE"));
+    }
 }
@@ -0,0 +1,222 @@
+//! TOML configuration file given by `--config`.
+//!
+//! Every setting is optional; values left out fall through to the
+//! built-in defaults, and environment variables and CLI flags override
+//! whatever the file sets. Unknown keys are rejected so typos surface at
+//! startup instead of being silently ignored.
+//!
+//! ```toml
+//! [horizon]
+//! network = "mainnet"
+//! timeout_seconds = 10
+//!
+//! [poller]
+//! interval_seconds = 30
+//!
+//! [api]
+//! port = 8080
+//! allowed_origins = ["https://app.example.com"]
+//! admin_api_key_file = "/run/secrets/admin_api_key"
+//! rate_limits = { fees = "120/1m", admin = "10/1m", live = "off" }
+//! trust_forwarded_for = true
+//!
+//! [database]
+//! url = "sqlite://stellar_fees.db"
+//! retention_days = 7
+//! rollup_retention_days = { "1m" = 90, "1h" = 0 }
+//!
+//! [insights]
+//! windows = ["5m", "1h", "24h"]
+//! baseline_window = "1h"
+//! baseline_mode = { HourOfWeek = { weeks = 4, min_samples = 3 } }
+//! threshold_multiplier = 2.5
+//! minimum_spike_duration = "2m"
+//! congestion_window = "30m"
+//! strategy = { type = "z_score", window = 30, threshold = 3.0 }
+//! severity_cutoffs = { moderate = 4.0, major = 6.0, critical = 10.0 }
+//!
+//! [extremes]
+//! tracking_period = "24h"
+//! periods_to_keep = 30
+//! top_n = 5
+//!
+//! [alerts]
+//! enabled = true
+//! min_severity = "Major"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::insights::config::{BaselineMode, SpikeStrategy};
+use crate::insights::strategy::SeverityCutoffs;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub horizon: HorizonSection,
+    pub poller: PollerSection,
+    pub api: ApiSection,
+    pub database: DatabaseSection,
+    pub insights: InsightsSection,
+    pub extremes: ExtremesSection,
+    pub alerts: AlertsSection,
+}
+
+/// `[horizon]` — network selection and client options
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HorizonSection {
+    pub network: Option<String>,
+    pub url: Option<String>,
+    pub timeout_seconds: Option<u64>,
+    pub retry_attempts: Option<u32>,
+    pub base_retry_delay_ms: Option<u64>,
+}
+
+/// `[poller]` — polling and engine snapshot cadence
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PollerSection {
+    pub interval_seconds: Option<u64>,
+    pub snapshot_interval_seconds: Option<u64>,
+}
+
+/// `[api]` — HTTP server
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiSection {
+    pub port: Option<u16>,
+    pub cache_ttl_seconds: Option<u64>,
+    pub allowed_origins: Option<Vec<String>>,
+    pub admin_api_key: Option<String>,
+    pub admin_api_key_file: Option<PathBuf>,
+    /// Limit per route group, e.g. `{ fees = "120/1m", live = "off" }`
+    pub rate_limits: Option<BTreeMap<String, String>>,
+    pub trust_forwarded_for: Option<bool>,
+}
+
+/// `[database]` — storage and retention
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseSection {
+    pub url: Option<String>,
+    pub retention_days: Option<u64>,
+    /// Days per rollup resolution, e.g. `{ "1m" = 90, "1h" = 0 }`; 0 keeps forever
+    pub rollup_retention_days: Option<BTreeMap<String, u64>>,
+}
+
+/// `[insights]` — rolling windows and spike detection
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InsightsSection {
+    pub windows: Option<Vec<String>>,
+    /// Window whose average is the spike baseline, named after its spec
+    pub baseline_window: Option<String>,
+    pub baseline_mode: Option<BaselineMode>,
+    pub threshold_multiplier: Option<f64>,
+    pub minimum_spike_duration: Option<String>,
+    pub congestion_window: Option<String>,
+    pub strategy: Option<SpikeStrategy>,
+    pub severity_cutoffs: Option<SeverityCutoffs>,
+}
+
+/// `[extremes]` — lowest and highest fee tracking
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExtremesSection {
+    pub tracking_period: Option<String>,
+    pub periods_to_keep: Option<usize>,
+    pub top_n: Option<usize>,
+}
+
+/// `[alerts]` — alert dispatch
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsSection {
+    pub enabled: Option<bool>,
+    /// Lowest severity any rule fires at, e.g. `"Major"`
+    pub min_severity: Option<String>,
+}
+
+impl FileConfig {
+    /// Read and parse the file at `path`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read config file {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_section() {
+        let file = FileConfig::parse(
+            r#"
+            [horizon]
+            network = "mainnet"
+            timeout_seconds = 5
+
+            [poller]
+            interval_seconds = 15
+
+            [api]
+            allowed_origins = ["https://a.example.com"]
+
+            [database]
+            retention_days = 30
+
+            [insights]
+            windows = ["10m", "2h"]
+            baseline_mode = { HourOfWeek = { weeks = 4, min_samples = 3 } }
+            strategy = { type = "z_score", window = 30, threshold = 3.0 }
+            severity_cutoffs = { moderate = 4.0, major = 6.0, critical = 10.0 }
+
+            [extremes]
+            tracking_period = "12h"
+            top_n = 3
+
+            [alerts]
+            enabled = false
+            min_severity = "Major"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(file.horizon.network.as_deref(), Some("mainnet"));
+        assert_eq!(file.poller.interval_seconds, Some(15));
+        assert_eq!(file.api.allowed_origins.unwrap(), vec!["https://a.example.com"]);
+        assert_eq!(file.database.retention_days, Some(30));
+        assert_eq!(
+            file.insights.strategy,
+            Some(SpikeStrategy::ZScore { window: 30, threshold: 3.0 })
+        );
+        assert_eq!(
+            file.insights.baseline_mode,
+            Some(BaselineMode::HourOfWeek { weeks: 4, min_samples: 3 })
+        );
+        assert_eq!(
+            file.insights.severity_cutoffs,
+            Some(SeverityCutoffs { moderate: 4.0, major: 6.0, critical: 10.0 })
+        );
+        assert_eq!(file.extremes.tracking_period.as_deref(), Some("12h"));
+        assert_eq!(file.extremes.top_n, Some(3));
+        assert_eq!(file.alerts.enabled, Some(false));
+        assert_eq!(file.alerts.min_severity.as_deref(), Some("Major"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let err = FileConfig::parse("[poller]\ninterval_secs = 15\n").unwrap_err();
+        assert!(err.contains("interval_secs"));
+    }
+}
@@ -1,10 +1,20 @@
 //! Database connection pool and migrations.
 //!
-//! Call [`create_pool`] at startup. It connects to the SQLite database
-//! and runs all pending migrations automatically via `sqlx::migrate!`.
+//! `serve` calls [`create_pool`] at startup. It connects to the SQLite
+//! database and runs all pending migrations automatically via
+//! `sqlx::migrate!`. Other commands use [`connect_migrated`], which leaves
+//! the schema alone and fails while migrations are pending.
 
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::migrate::Migrator;
 use sqlx::SqlitePool;
 
+use crate::repository::{FeeRepository, ROLLUP_RESOLUTIONS};
+
+/// Migrations embedded from `./migrations` at build time.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
 /// Create a SQLite connection pool and run all pending migrations.
 ///
 /// `database_url` must be a valid SQLite connection string, e.g.:
@@ -14,11 +24,91 @@ use sqlx::SqlitePool;
 /// Returns an error if the connection cannot be established or any
 /// migration fails.
 pub async fn create_pool(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
-    let pool = SqlitePool::connect(database_url).await?;
-    sqlx::migrate!("./migrations").run(&pool).await?;
+    let pool = connect(database_url).await?;
+    run_migrations(&pool).await?;
+    Ok(pool)
+}
+
+/// Connect without touching the schema, e.g. to report migration status.
+pub async fn connect(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
+    SqlitePool::connect(database_url).await
+}
+
+/// Connect to a database whose schema is already current. Fails instead
+/// of migrating when any migration is pending.
+pub async fn connect_migrated(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
+    let pool = connect(database_url).await?;
+    let pending = migration_status(&pool)
+        .await?
+        .iter()
+        .filter(|migration| migration.applied_at.is_none())
+        .count();
+    if pending > 0 {
+        return Err(sqlx::Error::Configuration(
+            format!("{} migrations pending; run `migrate` first", pending).into(),
+        ));
+    }
     Ok(pool)
 }
 
+/// Apply every pending migration, then build any rollup tier that has no
+/// buckets yet from the stored raw points. SQL can't build the quantile
+/// sketches, so tiers introduced by a migration are filled in here once.
+pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    MIGRATOR.run(pool).await?;
+    let backfilled = FeeRepository::new(pool.clone())
+        .backfill_empty_rollups(ROLLUP_RESOLUTIONS)
+        .await?;
+    if backfilled > 0 {
+        tracing::info!("Backfilled {} fee rollup buckets from stored points", backfilled);
+    }
+    Ok(())
+}
+
+/// Whether one embedded migration has been applied to the database.
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+/// Every embedded migration, oldest first, with the time it was applied.
+pub async fn migration_status(pool: &SqlitePool) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+    use sqlx::Row;
+
+    // sqlx creates its bookkeeping table on the first run
+    let tracked: Option<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'",
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let mut applied = std::collections::HashMap::new();
+    if tracked.is_some() {
+        let rows = sqlx::query("SELECT version, installed_on FROM _sqlx_migrations WHERE success = 1")
+            .fetch_all(pool)
+            .await?;
+        for row in rows {
+            let version: i64 = row.try_get("version")?;
+            // Stored by SQLite's CURRENT_TIMESTAMP, i.e. "YYYY-MM-DD HH:MM:SS" in UTC
+            let installed_on: String = row.try_get("installed_on")?;
+            let installed_on = NaiveDateTime::parse_from_str(&installed_on, "%Y-%m-%d %H:%M:%S")
+                .map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+            applied.insert(version, installed_on.and_utc());
+        }
+    }
+
+    Ok(MIGRATOR
+        .iter()
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            description: migration.description.to_string(),
+            applied_at: applied.get(&migration.version).copied(),
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -36,7 +126,7 @@ mod tests {
         let pool = create_pool("sqlite::memory:").await.unwrap();
 
         // Run migrations a second time explicitly
-        let result = sqlx::migrate!("./migrations").run(&pool).await;
+        let result = run_migrations(&pool).await;
         assert!(result.is_ok(), "Second migration run failed: {:?}", result.err());
     }
 
@@ -69,4 +159,31 @@ mod tests {
 
         assert!(result.is_ok(), "Insert failed: {:?}", result.err());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn migration_status_reports_pending_then_applied() {
+        let pool = connect("sqlite::memory:").await.unwrap();
+        let pending = migration_status(&pool).await.unwrap();
+        assert!(!pending.is_empty());
+        assert!(pending.iter().all(|m| m.applied_at.is_none()));
+
+        run_migrations(&pool).await.unwrap();
+        let applied = migration_status(&pool).await.unwrap();
+        assert_eq!(applied.len(), pending.len());
+        assert!(applied.iter().all(|m| m.applied_at.is_some()));
+        assert_eq!(applied[0].version, 1);
+    }
+
+    #[tokio::test]
+    async fn connect_migrated_refuses_pending_migrations() {
+        let path = std::env::temp_dir().join(format!("sft-db-{}.db", std::process::id()));
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+
+        let err = connect_migrated(&url).await.unwrap_err();
+        assert!(err.to_string().contains("run `migrate` first"));
+
+        create_pool(&url).await.unwrap().close().await;
+        assert!(connect_migrated(&url).await.is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,703 @@
+//! Bulk export of stored data.
+//!
+//! Fee data points, Horizon snapshots, rollups and alert events can each be
+//! written as CSV (a header row, then one row per record), NDJSON (one JSON
+//! object per line, with the same fields as the API returns) or Parquet
+//! (one row group per batch). Rows are read and encoded a batch at a time,
+//! so memory stays bounded however large the range: [`Exporter`] hands back
+//! the encoded bytes of each batch, gzipped if asked, for the caller to
+//! write to a file or stream to a client.
+
+use std::io::{self, Write};
+use std::mem;
+use std::sync::{Arc, Mutex as StdMutex};
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::Deserialize;
+
+use crate::insights::FeeDataPoint;
+use crate::repository::FeeRepository;
+
+/// How far before `to` an export starts when no `from` is given.
+pub const DEFAULT_EXPORT_HOURS: i64 = 24;
+
+/// Rows read, encoded and handed back per batch.
+const EXPORT_BATCH_SIZE: i64 = 5_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// A table that can be exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportDataset {
+    /// Raw fee data points
+    #[default]
+    Points,
+    /// Horizon fee_stats captures
+    Snapshots,
+    /// Fee rollups, every resolution unless one is selected
+    Rollups,
+    /// Alert webhook deliveries
+    #[value(name = "alert_events")]
+    AlertEvents,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Text,
+    Integer,
+    Real,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportColumn {
+    pub name: &'static str,
+    pub kind: ColumnKind,
+}
+
+const fn text(name: &'static str) -> ExportColumn {
+    ExportColumn { name, kind: ColumnKind::Text }
+}
+
+const fn integer(name: &'static str) -> ExportColumn {
+    ExportColumn { name, kind: ColumnKind::Integer }
+}
+
+const fn real(name: &'static str) -> ExportColumn {
+    ExportColumn { name, kind: ColumnKind::Real }
+}
+
+// The first column of each table is the time exports are ranged and ordered by
+const POINT_COLUMNS: &[ExportColumn] = &[
+    text("timestamp"),
+    integer("fee_amount"),
+    text("transaction_hash"),
+    integer("ledger_sequence"),
+];
+
+const SNAPSHOT_COLUMNS: &[ExportColumn] = &[
+    text("captured_at"),
+    text("base_fee"),
+    text("min_fee"),
+    text("max_fee"),
+    text("avg_fee"),
+];
+
+const ROLLUP_COLUMNS: &[ExportColumn] = &[
+    text("bucket_start"),
+    text("resolution"),
+    integer("sample_count"),
+    integer("fee_sum"),
+    integer("min_fee"),
+    integer("max_fee"),
+    integer("open_fee"),
+    text("open_at"),
+    integer("close_fee"),
+    text("close_at"),
+    text("sketch"),
+];
+
+const ALERT_EVENT_COLUMNS: &[ExportColumn] = &[
+    text("triggered_at"),
+    integer("id"),
+    integer("config_id"),
+    text("severity"),
+    integer("peak_fee"),
+    real("baseline_fee"),
+    real("spike_ratio"),
+    // webhook_url is left out: exports are public and the URLs are secrets
+    integer("delivered"),
+];
+
+impl ExportDataset {
+    pub fn table(self) -> &'static str {
+        match self {
+            ExportDataset::Points => "fee_data_points",
+            ExportDataset::Snapshots => "fee_snapshots",
+            ExportDataset::Rollups => "fee_rollups",
+            ExportDataset::AlertEvents => "alert_events",
+        }
+    }
+
+    pub fn columns(self) -> &'static [ExportColumn] {
+        match self {
+            ExportDataset::Points => POINT_COLUMNS,
+            ExportDataset::Snapshots => SNAPSHOT_COLUMNS,
+            ExportDataset::Rollups => ROLLUP_COLUMNS,
+            ExportDataset::AlertEvents => ALERT_EVENT_COLUMNS,
+        }
+    }
+
+    /// The column the export range applies to and rows are ordered by.
+    pub fn time_column(self) -> &'static str {
+        self.columns()[0].name
+    }
+
+    /// The columns named in `names`, in that order; every column when
+    /// `names` is empty.
+    pub fn select_columns(self, names: &[String]) -> Result<Vec<ExportColumn>, String> {
+        if names.is_empty() {
+            return Ok(self.columns().to_vec());
+        }
+        names
+            .iter()
+            .map(|name| {
+                self.columns()
+                    .iter()
+                    .find(|column| column.name == name.trim())
+                    .copied()
+                    .ok_or_else(|| {
+                        format!(
+                            "Unknown {} column '{}'. Must be one of: {}",
+                            self.table(),
+                            name.trim(),
+                            self.columns().iter().map(|c| c.name).collect::<Vec<_>>().join(", ")
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+/// One exported field; `None` is a SQL NULL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportValue {
+    Text(Option<String>),
+    Integer(Option<i64>),
+    Real(Option<f64>),
+}
+
+/// A row as read for export, with the `(time, rowid)` key the next batch
+/// continues after.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportRow {
+    pub time: String,
+    pub rowid: i64,
+    pub values: Vec<ExportValue>,
+}
+
+/// What to export.
+#[derive(Debug, Clone)]
+pub struct ExportRequest {
+    pub dataset: ExportDataset,
+    pub format: ExportFormat,
+    /// Rows with `from <= time < to`
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub columns: Vec<ExportColumn>,
+    pub gzip: bool,
+    /// Only rollups at this resolution; ignored for other datasets
+    pub resolution: Option<String>,
+}
+
+/// Reads a request's rows batch by batch and encodes them.
+pub struct Exporter {
+    request: ExportRequest,
+    encoder: Option<Encoder<Sink<ChunkBuffer>>>,
+    buffer: ChunkBuffer,
+    after: Option<(String, i64)>,
+    rows: u64,
+}
+
+impl Exporter {
+    pub fn new(request: ExportRequest) -> io::Result<Self> {
+        let buffer = ChunkBuffer::default();
+        let sink = if request.gzip {
+            Sink::Gzip(GzEncoder::new(buffer.clone(), Compression::default()))
+        } else {
+            Sink::Plain(buffer.clone())
+        };
+        let encoder = Encoder::new(request.format, request.dataset.table(), &request.columns, sink)?;
+        Ok(Self {
+            request,
+            encoder: Some(encoder),
+            buffer,
+            after: None,
+            rows: 0,
+        })
+    }
+
+    /// Read and encode the next batch and return the bytes produced, which
+    /// may be none while a compressor is buffering. After the last batch
+    /// the format's trailer is included, and `None` is returned from then on.
+    pub async fn next_chunk(&mut self, repository: &FeeRepository) -> io::Result<Option<Vec<u8>>> {
+        let Some(encoder) = self.encoder.as_mut() else {
+            return Ok(None);
+        };
+
+        let after = self.after.as_ref().map(|(time, rowid)| (time.as_str(), *rowid));
+        let batch = repository
+            .fetch_export_batch(&self.request, after, EXPORT_BATCH_SIZE)
+            .await
+            .map_err(io::Error::other)?;
+
+        if !batch.is_empty() {
+            encoder.write_rows(&self.request.columns, &batch)?;
+            self.rows += batch.len() as u64;
+        }
+        match batch.last() {
+            Some(last) if batch.len() as i64 == EXPORT_BATCH_SIZE => {
+                self.after = Some((last.time.clone(), last.rowid));
+            }
+            _ => {
+                if let Some(encoder) = self.encoder.take() {
+                    encoder.finish()?.finish()?;
+                }
+            }
+        }
+        Ok(Some(self.buffer.take()))
+    }
+
+    /// Rows encoded so far.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+}
+
+/// Write fee data points already in memory to `out` in `format`. Returns
+/// the number of points written.
+pub fn write_points<W: Write + Send>(
+    out: W,
+    format: ExportFormat,
+    points: &[FeeDataPoint],
+) -> io::Result<usize> {
+    let rows: Vec<ExportRow> = points
+        .iter()
+        .map(|point| ExportRow {
+            time: point.timestamp.to_rfc3339(),
+            rowid: 0,
+            values: vec![
+                ExportValue::Text(Some(point.timestamp.to_rfc3339())),
+                ExportValue::Integer(Some(point.fee_amount as i64)),
+                ExportValue::Text(Some(point.transaction_hash.clone())),
+                ExportValue::Integer(Some(point.ledger_sequence as i64)),
+            ],
+        })
+        .collect();
+
+    let mut encoder = Encoder::new(format, ExportDataset::Points.table(), POINT_COLUMNS, out)?;
+    encoder.write_rows(POINT_COLUMNS, &rows)?;
+    encoder.finish()?.flush()?;
+    Ok(points.len())
+}
+
+/// Encodes rows in one format onto a writer.
+enum Encoder<W: Write + Send> {
+    Csv(W),
+    Ndjson(W),
+    Parquet(SerializedFileWriter<W>),
+}
+
+impl<W: Write + Send> Encoder<W> {
+    fn new(format: ExportFormat, table: &str, columns: &[ExportColumn], mut out: W) -> io::Result<Self> {
+        match format {
+            ExportFormat::Csv => {
+                let header: Vec<&str> = columns.iter().map(|c| c.name).collect();
+                writeln!(out, "{}", header.join(","))?;
+                Ok(Encoder::Csv(out))
+            }
+            ExportFormat::Ndjson => Ok(Encoder::Ndjson(out)),
+            ExportFormat::Parquet => {
+                let schema = parse_message_type(&parquet_schema(table, columns))
+                    .map_err(io::Error::other)?;
+                let properties = WriterProperties::builder().build();
+                SerializedFileWriter::new(out, Arc::new(schema), Arc::new(properties))
+                    .map(Encoder::Parquet)
+                    .map_err(io::Error::other)
+            }
+        }
+    }
+
+    fn write_rows(&mut self, columns: &[ExportColumn], rows: &[ExportRow]) -> io::Result<()> {
+        match self {
+            Encoder::Csv(out) => {
+                for row in rows {
+                    let fields: Vec<String> = row.values.iter().map(csv_value).collect();
+                    writeln!(out, "{}", fields.join(","))?;
+                }
+            }
+            Encoder::Ndjson(out) => {
+                for row in rows {
+                    let object: serde_json::Map<String, serde_json::Value> = columns
+                        .iter()
+                        .zip(&row.values)
+                        .map(|(column, value)| (column.name.to_string(), json_value(value)))
+                        .collect();
+                    serde_json::to_writer(&mut *out, &object)?;
+                    out.write_all(b"\n")?;
+                }
+            }
+            Encoder::Parquet(writer) => {
+                write_row_group(writer, columns, rows).map_err(io::Error::other)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write any trailer and return the writer.
+    fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Csv(out) | Encoder::Ndjson(out) => Ok(out),
+            Encoder::Parquet(writer) => writer.into_inner().map_err(io::Error::other),
+        }
+    }
+}
+
+/// Parquet schema with one optional column per export column.
+fn parquet_schema(table: &str, columns: &[ExportColumn]) -> String {
+    let fields: Vec<String> = columns
+        .iter()
+        .map(|column| match column.kind {
+            ColumnKind::Text => format!("OPTIONAL BYTE_ARRAY {} (UTF8);", column.name),
+            ColumnKind::Integer => format!("OPTIONAL INT64 {};", column.name),
+            ColumnKind::Real => format!("OPTIONAL DOUBLE {};", column.name),
+        })
+        .collect();
+    format!("message {} {{ {} }}", table, fields.join(" "))
+}
+
+fn write_row_group<W: Write + Send>(
+    writer: &mut SerializedFileWriter<W>,
+    columns: &[ExportColumn],
+    rows: &[ExportRow],
+) -> parquet::errors::Result<()> {
+    let mut row_group = writer.next_row_group()?;
+    for (index, export_column) in columns.iter().enumerate() {
+        let Some(mut column) = row_group.next_column()? else {
+            break;
+        };
+        // Null values are left out and marked by a definition level of 0
+        let levels: Vec<i16> = rows
+            .iter()
+            .map(|row| match &row.values[index] {
+                ExportValue::Text(None) | ExportValue::Integer(None) | ExportValue::Real(None) => 0,
+                _ => 1,
+            })
+            .collect();
+        let values = rows.iter().map(|row| &row.values[index]);
+        match export_column.kind {
+            ColumnKind::Integer => {
+                let values: Vec<i64> = values
+                    .filter_map(|v| match v {
+                        ExportValue::Integer(value) => *value,
+                        _ => None,
+                    })
+                    .collect();
+                column.typed::<Int64Type>().write_batch(&values, Some(&levels), None)?;
+            }
+            ColumnKind::Real => {
+                let values: Vec<f64> = values
+                    .filter_map(|v| match v {
+                        ExportValue::Real(value) => *value,
+                        _ => None,
+                    })
+                    .collect();
+                column.typed::<DoubleType>().write_batch(&values, Some(&levels), None)?;
+            }
+            ColumnKind::Text => {
+                let values: Vec<ByteArray> = values
+                    .filter_map(|v| match v {
+                        ExportValue::Text(Some(value)) => Some(ByteArray::from(value.as_str())),
+                        _ => None,
+                    })
+                    .collect();
+                column.typed::<ByteArrayType>().write_batch(&values, Some(&levels), None)?;
+            }
+        }
+        column.close()?;
+    }
+    row_group.close()?;
+    Ok(())
+}
+
+fn csv_value(value: &ExportValue) -> String {
+    match value {
+        ExportValue::Text(Some(text)) => csv_field(text),
+        ExportValue::Integer(Some(number)) => number.to_string(),
+        ExportValue::Real(Some(number)) => number.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn json_value(value: &ExportValue) -> serde_json::Value {
+    match value {
+        ExportValue::Text(text) => text.clone().into(),
+        ExportValue::Integer(number) => (*number).into(),
+        ExportValue::Real(number) => (*number).into(),
+    }
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// The encoder's output, optionally gzipped.
+enum Sink<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+}
+
+impl<W: Write> Sink<W> {
+    fn finish(self) -> io::Result<W> {
+        match self {
+            Sink::Plain(out) => Ok(out),
+            Sink::Gzip(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Sink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Plain(out) => out.write(buf),
+            Sink::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Plain(out) => out.flush(),
+            Sink::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Bytes written since the last `take`, shared with the writer chain that
+/// owns the other handle.
+#[derive(Clone, Default)]
+struct ChunkBuffer(Arc<StdMutex<Vec<u8>>>);
+
+impl ChunkBuffer {
+    fn take(&self) -> Vec<u8> {
+        mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl Write for ChunkBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    use chrono::{Duration, TimeZone, Utc};
+    use flate2::read::GzDecoder;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    use crate::db::create_pool;
+    use crate::repository::{AlertEvent, ROLLUP_RESOLUTIONS};
+
+    fn make_point(hash: &str) -> FeeDataPoint {
+        FeeDataPoint {
+            fee_amount: 250,
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+            transaction_hash: hash.to_string(),
+            ledger_sequence: 42,
+        }
+    }
+
+    async fn export_all(repository: &FeeRepository, request: ExportRequest) -> Vec<u8> {
+        let mut exporter = Exporter::new(request).unwrap();
+        let mut out = Vec::new();
+        while let Some(chunk) = exporter.next_chunk(repository).await.unwrap() {
+            out.extend(chunk);
+        }
+        out
+    }
+
+    #[test]
+    fn writes_csv_with_header_and_quoting() {
+        let mut out = Vec::new();
+        let written = write_points(&mut out, ExportFormat::Csv, &[make_point("abc"), make_point("a,\"b")]).unwrap();
+
+        assert_eq!(written, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "timestamp,fee_amount,transaction_hash,ledger_sequence\n\
+             2024-05-01T12:00:00+00:00,250,abc,42\n\
+             2024-05-01T12:00:00+00:00,250,\"a,\"\"b\",42\n"
+        );
+    }
+
+    #[test]
+    fn writes_one_json_object_per_line() {
+        let mut out = Vec::new();
+        write_points(&mut out, ExportFormat::Ndjson, &[make_point("a"), make_point("b")]).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<serde_json::Value> =
+            text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["transaction_hash"], "b");
+        assert_eq!(lines[0]["fee_amount"], 250);
+    }
+
+    #[test]
+    fn rejects_unknown_columns() {
+        let columns = ExportDataset::AlertEvents
+            .select_columns(&["peak_fee".to_string(), "triggered_at".to_string()])
+            .unwrap();
+        assert_eq!(columns, vec![integer("peak_fee"), text("triggered_at")]);
+
+        let err = ExportDataset::Points.select_columns(&["fee".to_string()]).unwrap_err();
+        assert!(err.contains("fee_amount"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn alert_event_exports_leave_out_webhook_urls() {
+        let repository = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let triggered_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        repository
+            .log_alert_event(&AlertEvent {
+                id: None,
+                config_id: None,
+                severity: "Major".to_string(),
+                peak_fee: 5_000,
+                baseline_fee: 100.0,
+                spike_ratio: 50.0,
+                webhook_url: "https://hooks.example.com/secret-token".to_string(),
+                delivered: true,
+                triggered_at: triggered_at.to_rfc3339(),
+            })
+            .await
+            .unwrap();
+
+        let out = export_all(
+            &repository,
+            ExportRequest {
+                dataset: ExportDataset::AlertEvents,
+                format: ExportFormat::Ndjson,
+                from: triggered_at - Duration::hours(1),
+                to: triggered_at + Duration::hours(1),
+                columns: ExportDataset::AlertEvents.select_columns(&[]).unwrap(),
+                gzip: false,
+                resolution: None,
+            },
+        )
+        .await;
+
+        let text = String::from_utf8(out).unwrap();
+        let row: serde_json::Value = serde_json::from_str(text.trim()).unwrap();
+        assert_eq!(row["severity"], "Major");
+        assert!(row.get("webhook_url").is_none());
+        assert!(!text.contains("secret-token"));
+        assert!(ExportDataset::AlertEvents
+            .select_columns(&["webhook_url".to_string()])
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn exports_selected_rollup_columns_gzipped_across_batches() {
+        let repository = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        let points: Vec<FeeDataPoint> = (0..EXPORT_BATCH_SIZE + 10)
+            .map(|i| FeeDataPoint {
+                fee_amount: 100 + i as u64,
+                timestamp: start + Duration::minutes(i),
+                transaction_hash: format!("tx-{}", i),
+                ledger_sequence: i as u64,
+            })
+            .collect();
+        for resolution in ROLLUP_RESOLUTIONS {
+            repository.merge_rollups(resolution, &points).await.unwrap();
+        }
+
+        let out = export_all(
+            &repository,
+            ExportRequest {
+                dataset: ExportDataset::Rollups,
+                format: ExportFormat::Csv,
+                from: start,
+                to: start + Duration::days(30),
+                columns: ExportDataset::Rollups
+                    .select_columns(&["bucket_start".to_string(), "sample_count".to_string()])
+                    .unwrap(),
+                gzip: true,
+                resolution: Some("1m".to_string()),
+            },
+        )
+        .await;
+
+        let mut csv = String::new();
+        GzDecoder::new(out.as_slice()).read_to_string(&mut csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "bucket_start,sample_count");
+        assert_eq!(lines.len() as i64, EXPORT_BATCH_SIZE + 11);
+        assert_eq!(lines[1], "2024-05-01T00:00:00+00:00,1");
+    }
+
+    #[tokio::test]
+    async fn exports_points_as_parquet() {
+        let repository = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        repository
+            .insert_fee_points(&[make_point("a"), make_point("b"), make_point("c")])
+            .await
+            .unwrap();
+        let at = make_point("a").timestamp;
+
+        let out = export_all(
+            &repository,
+            ExportRequest {
+                dataset: ExportDataset::Points,
+                format: ExportFormat::Parquet,
+                from: at,
+                to: at + Duration::seconds(1),
+                columns: ExportDataset::Points.columns().to_vec(),
+                gzip: false,
+                resolution: None,
+            },
+        )
+        .await;
+
+        let reader = SerializedFileReader::new(axum::body::Bytes::from(out)).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 3);
+        let names: Vec<&str> = metadata.schema_descr().columns().iter().map(|c| c.name()).collect();
+        assert_eq!(names, ["timestamp", "fee_amount", "transaction_hash", "ledger_sequence"]);
+    }
+}
@@ -0,0 +1,420 @@
+//! Bulk import of fee data points.
+//!
+//! Reads the CSV and NDJSON that `export` writes for fee data points: CSV
+//! needs a header row naming at least `timestamp`, `fee_amount` and
+//! `transaction_hash` (`ledger_sequence` defaults to 0, other columns are
+//! ignored), NDJSON one object per line with the same fields. Each row is
+//! checked with the rules the insights engine applies to polled data.
+//!
+//! Rows are stored a batch at a time. Points whose transaction hash is
+//! already stored, or appeared earlier in the input, are skipped, and the
+//! rollups of every resolution are updated with the points inserted.
+
+use std::io::{self, BufRead};
+use std::mem;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::insights::{validate_fee_point, FeeDataPoint};
+use crate::repository::{FeeRepository, ROLLUP_RESOLUTIONS};
+
+/// Rejected rows listed individually in a report; the rest are only counted.
+pub const MAX_REPORTED_REJECTIONS: usize = 100;
+
+/// Points validated, deduplicated and inserted together.
+const IMPORT_BATCH_SIZE: usize = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Csv,
+    Ndjson,
+}
+
+impl ImportFormat {
+    /// NDJSON for `.ndjson` and `.jsonl` files, optionally gzipped; CSV
+    /// for anything else.
+    pub fn from_path(path: &Path) -> Self {
+        let name = path.to_string_lossy().to_ascii_lowercase();
+        let name = name.strip_suffix(".gz").unwrap_or(&name);
+        if name.ends_with(".ndjson") || name.ends_with(".jsonl") {
+            ImportFormat::Ndjson
+        } else {
+            ImportFormat::Csv
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("Failed to read input: {0}")]
+    Read(#[from] io::Error),
+    #[error("Failed to store fee data: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// A row that was not imported, by its line in the input.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RejectedRow {
+    pub line: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct ImportReport {
+    pub rows_read: usize,
+    pub imported: usize,
+    /// Rows whose transaction hash was already stored or earlier in the input
+    pub duplicates: usize,
+    pub rejected: usize,
+    /// The first rejected rows, up to [`MAX_REPORTED_REJECTIONS`]
+    pub rejections: Vec<RejectedRow>,
+    /// Rollup buckets created or updated, across all resolutions
+    pub rollup_buckets: usize,
+    /// Range of the imported points
+    pub first_timestamp: Option<DateTime<Utc>>,
+    pub last_timestamp: Option<DateTime<Utc>>,
+}
+
+impl ImportReport {
+    fn reject(&mut self, line: usize, reason: String) {
+        self.rejected += 1;
+        if self.rejections.len() < MAX_REPORTED_REJECTIONS {
+            self.rejections.push(RejectedRow { line, reason });
+        }
+    }
+}
+
+/// One input row, before validation.
+#[derive(Debug, Deserialize)]
+struct ImportedPoint {
+    timestamp: DateTime<Utc>,
+    fee_amount: u64,
+    transaction_hash: String,
+    #[serde(default)]
+    ledger_sequence: u64,
+}
+
+impl From<ImportedPoint> for FeeDataPoint {
+    fn from(point: ImportedPoint) -> Self {
+        Self {
+            fee_amount: point.fee_amount,
+            timestamp: point.timestamp,
+            transaction_hash: point.transaction_hash,
+            ledger_sequence: point.ledger_sequence,
+        }
+    }
+}
+
+/// Import every valid, new point in `input`. Rows are validated as of
+/// `now`. Fails only when the input cannot be read or the database write
+/// fails; batches stored before that stay imported.
+pub async fn import_points<R: BufRead>(
+    repository: &FeeRepository,
+    input: R,
+    format: ImportFormat,
+    now: DateTime<Utc>,
+) -> Result<ImportReport, ImportError> {
+    let mut rows = RowReader::new(input, format)?;
+    let mut report = ImportReport::default();
+    let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+
+    loop {
+        let row = rows.next_row()?;
+        let finished = row.is_none();
+        if let Some((line, parsed)) = row {
+            report.rows_read += 1;
+            let point = parsed.and_then(|point| {
+                let point = FeeDataPoint::from(point);
+                validate_fee_point(&point, now).map(|()| point)
+            });
+            match point {
+                Ok(point) => batch.push(point),
+                Err(reason) => report.reject(line, reason),
+            }
+        }
+        if batch.len() >= IMPORT_BATCH_SIZE || (finished && !batch.is_empty()) {
+            store_batch(repository, mem::take(&mut batch), &mut report).await?;
+        }
+        if finished {
+            return Ok(report);
+        }
+    }
+}
+
+async fn store_batch(
+    repository: &FeeRepository,
+    batch: Vec<FeeDataPoint>,
+    report: &mut ImportReport,
+) -> Result<(), ImportError> {
+    let hashes: Vec<&str> = batch.iter().map(|p| p.transaction_hash.as_str()).collect();
+    let mut seen = repository.existing_transaction_hashes(&hashes).await?;
+    let total = batch.len();
+    let fresh: Vec<FeeDataPoint> = batch
+        .into_iter()
+        .filter(|point| seen.insert(point.transaction_hash.clone()))
+        .collect();
+    report.duplicates += total - fresh.len();
+    if fresh.is_empty() {
+        return Ok(());
+    }
+
+    repository.insert_fee_points(&fresh).await?;
+    for resolution in ROLLUP_RESOLUTIONS {
+        report.rollup_buckets += repository.merge_rollups(resolution, &fresh).await?;
+    }
+
+    report.imported += fresh.len();
+    for point in &fresh {
+        if report.first_timestamp.is_none_or(|first| point.timestamp < first) {
+            report.first_timestamp = Some(point.timestamp);
+        }
+        if report.last_timestamp.is_none_or(|last| point.timestamp > last) {
+            report.last_timestamp = Some(point.timestamp);
+        }
+    }
+    Ok(())
+}
+
+/// Positions of the fields a CSV row is read from.
+struct CsvColumns {
+    timestamp: usize,
+    fee_amount: usize,
+    transaction_hash: usize,
+    ledger_sequence: Option<usize>,
+}
+
+impl CsvColumns {
+    fn from_header(header: &[String]) -> io::Result<Self> {
+        let find = |name: &str| header.iter().position(|column| column.trim() == name);
+        let require = |name: &str| {
+            find(name).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("CSV header has no {} column", name),
+                )
+            })
+        };
+        Ok(Self {
+            timestamp: require("timestamp")?,
+            fee_amount: require("fee_amount")?,
+            transaction_hash: require("transaction_hash")?,
+            ledger_sequence: find("ledger_sequence"),
+        })
+    }
+
+    fn parse(&self, fields: &[String]) -> Result<ImportedPoint, String> {
+        let field = |index: usize, name: &str| {
+            fields
+                .get(index)
+                .map(|value| value.trim())
+                .ok_or_else(|| format!("Missing {}", name))
+        };
+        let timestamp = field(self.timestamp, "timestamp")?;
+        let fee_amount = field(self.fee_amount, "fee_amount")?;
+        let ledger_sequence = match self.ledger_sequence {
+            Some(index) => field(index, "ledger_sequence")?,
+            None => "",
+        };
+
+        Ok(ImportedPoint {
+            timestamp: DateTime::parse_from_rfc3339(timestamp)
+                .map_err(|_| format!("Invalid timestamp '{}'", timestamp))?
+                .with_timezone(&Utc),
+            fee_amount: fee_amount
+                .parse()
+                .map_err(|_| format!("Invalid fee_amount '{}'", fee_amount))?,
+            transaction_hash: field(self.transaction_hash, "transaction_hash")?.to_string(),
+            ledger_sequence: match ledger_sequence {
+                "" => 0,
+                value => value
+                    .parse()
+                    .map_err(|_| format!("Invalid ledger_sequence '{}'", value))?,
+            },
+        })
+    }
+}
+
+/// Reads input rows with the line each starts on.
+struct RowReader<R> {
+    input: R,
+    line: usize,
+    csv: Option<CsvColumns>,
+}
+
+impl<R: BufRead> RowReader<R> {
+    fn new(input: R, format: ImportFormat) -> io::Result<Self> {
+        let mut reader = Self { input, line: 0, csv: None };
+        if format == ImportFormat::Csv {
+            let header = reader.next_record()?.map(|(_, fields)| fields).unwrap_or_default();
+            reader.csv = Some(CsvColumns::from_header(&header)?);
+        }
+        Ok(reader)
+    }
+
+    fn next_row(&mut self) -> io::Result<Option<(usize, Result<ImportedPoint, String>)>> {
+        match &self.csv {
+            Some(_) => {
+                let Some((line, fields)) = self.next_record()? else {
+                    return Ok(None);
+                };
+                let columns = self.csv.as_ref().expect("CSV columns are read with the header");
+                Ok(Some((line, columns.parse(&fields))))
+            }
+            None => {
+                let Some((line, text)) = self.next_line()? else {
+                    return Ok(None);
+                };
+                Ok(Some((line, serde_json::from_str(&text).map_err(|e| e.to_string()))))
+            }
+        }
+    }
+
+    /// The next non-blank line and its number.
+    fn next_line(&mut self) -> io::Result<Option<(usize, String)>> {
+        loop {
+            let mut text = String::new();
+            if self.input.read_line(&mut text)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            if !text.trim().is_empty() {
+                return Ok(Some((self.line, text)));
+            }
+        }
+    }
+
+    /// The next CSV record, which continues onto further lines while a
+    /// quoted field is open.
+    fn next_record(&mut self) -> io::Result<Option<(usize, Vec<String>)>> {
+        let Some((line, mut text)) = self.next_line()? else {
+            return Ok(None);
+        };
+        while text.matches('"').count() % 2 == 1 {
+            let mut more = String::new();
+            if self.input.read_line(&mut more)? == 0 {
+                break;
+            }
+            self.line += 1;
+            text.push_str(&more);
+        }
+        Ok(Some((line, split_csv_record(text.trim_end_matches(['\r', '\n'])))))
+    }
+}
+
+/// Split a CSV record into fields, unquoting quoted ones.
+fn split_csv_record(record: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = record.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    use crate::db::create_pool;
+
+    #[test]
+    fn splits_quoted_csv_fields() {
+        assert_eq!(split_csv_record("a,\"b,\"\"c\"\"\",,d"), ["a", "b,\"c\"", "", "d"]);
+    }
+
+    #[tokio::test]
+    async fn imports_valid_new_points_and_reports_the_rest() {
+        let repository = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let now = Utc::now();
+        let stored = FeeDataPoint {
+            fee_amount: 100,
+            timestamp: now - Duration::days(1),
+            transaction_hash: "stored".to_string(),
+            ledger_sequence: 1,
+        };
+        repository.insert_fee_points(&[stored]).await.unwrap();
+
+        let day = (now - Duration::days(2)).to_rfc3339();
+        let future = (now + Duration::days(1)).to_rfc3339();
+        let csv = format!(
+            "ledger_sequence,transaction_hash,fee_amount,timestamp,extra\n\
+             7,a,100,{day},x\n\
+             8,b,200,{day},x\n\
+             \n\
+             9,a,300,{day},x\n\
+             10,stored,100,{day},x\n\
+             11,c,0,{day},x\n\
+             12,d,100,{future},x\n\
+             13,e,lots,{day},x\n"
+        );
+
+        let report = import_points(&repository, csv.as_bytes(), ImportFormat::Csv, now)
+            .await
+            .unwrap();
+
+        assert_eq!(report.rows_read, 7);
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.duplicates, 2);
+        assert_eq!(report.rejected, 3);
+        let lines: Vec<usize> = report.rejections.iter().map(|r| r.line).collect();
+        assert_eq!(lines, [7, 8, 9]);
+        assert!(report.rejections[0].reason.contains("Zero fee amount"));
+        assert!(report.rejections[2].reason.contains("fee_amount"));
+        assert!(report.rollup_buckets >= ROLLUP_RESOLUTIONS.len());
+
+        let imported = repository
+            .fetch_range(now - Duration::days(3), now)
+            .await
+            .unwrap();
+        assert_eq!(imported.len(), 3);
+        let hourly = repository
+            .fetch_rollups("1h", now - Duration::days(3), now - Duration::days(1) - Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(hourly.iter().map(|r| r.sample_count).sum::<u64>(), 2);
+    }
+
+    #[tokio::test]
+    async fn imports_ndjson_and_rejects_unparseable_lines() {
+        let repository = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let now = Utc::now();
+        let ndjson = format!(
+            "{{\"timestamp\":\"{}\",\"fee_amount\":150,\"transaction_hash\":\"x\"}}\nnot json\n",
+            now.to_rfc3339()
+        );
+
+        let report = import_points(&repository, ndjson.as_bytes(), ImportFormat::Ndjson, now)
+            .await
+            .unwrap();
+
+        assert_eq!((report.imported, report.rejected), (1, 1));
+        assert_eq!(report.rejections[0].line, 2);
+        assert_eq!(report.first_timestamp, Some(now));
+    }
+
+    #[tokio::test]
+    async fn csv_without_required_columns_fails() {
+        let repository = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let err = import_points(&repository, "timestamp,fee\n".as_bytes(), ImportFormat::Csv, Utc::now())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("fee_amount"), "{}", err);
+    }
+}
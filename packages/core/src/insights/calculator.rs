@@ -1,122 +1,240 @@
 //! Rolling Average Calculator
+//!
+//! Windows keep aggregates rather than raw points: each is split into
+//! buckets aligned to one of the rollup resolutions, holding a quantile
+//! sketch (and so the count and sum) of the fees that fell in them, next to
+//! running totals and a sketch over the whole window. Old data leaves a
+//! window a bucket at a time, once the bucket ends before the window starts,
+//! so a window covers its duration plus at most one bucket.
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 
+use crate::clock::{system_clock, SharedClock};
 use crate::insights::{
     types::*,
     error::InsightsError,
     config::AverageConfig,
+    sketch::QuantileSketch,
 };
 
-/// Circular buffer for efficient storage of fee data points
-#[derive(Debug, Clone)]
-struct CircularBuffer<T> {
-    data: VecDeque<T>,
-    max_size: usize,
+/// Running count and sum of the fees in a window, kept in step with its
+/// buckets so the average never has to re-sum them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+struct WindowTotals {
+    sample_count: usize,
+    total_fee: u64,
 }
 
-impl<T> CircularBuffer<T> {
-    fn new(max_size: usize) -> Self {
+impl WindowTotals {
+    fn add(&mut self, sketch: &QuantileSketch) {
+        self.sample_count += sketch.count() as usize;
+        self.total_fee = self.total_fee.saturating_add(sketch.sum());
+    }
+    
+    fn remove(&mut self, sketch: &QuantileSketch) {
+        self.sample_count = self.sample_count.saturating_sub(sketch.count() as usize);
+        self.total_fee = self.total_fee.saturating_sub(sketch.sum());
+    }
+    
+    fn average(&self) -> Option<f64> {
+        (self.sample_count > 0).then(|| self.total_fee as f64 / self.sample_count as f64)
+    }
+}
+
+/// Fees that fell into one bucket of a window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowBucket {
+    pub start: DateTime<Utc>,
+    pub sketch: QuantileSketch,
+}
+
+/// Aggregated contents of one rolling window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    /// Width of every bucket
+    bucket_width: Duration,
+    /// Non-empty buckets, oldest first
+    buckets: VecDeque<WindowBucket>,
+    totals: WindowTotals,
+    /// Sketch over every bucket
+    sketch: QuantileSketch,
+}
+
+impl WindowState {
+    fn new(bucket_width: Duration) -> Self {
         Self {
-            data: VecDeque::with_capacity(max_size),
-            max_size,
+            bucket_width,
+            buckets: VecDeque::new(),
+            totals: WindowTotals::default(),
+            sketch: QuantileSketch::default(),
         }
     }
     
-    fn push(&mut self, item: T) {
-        if self.data.len() >= self.max_size {
-            self.data.pop_front();
-        }
-        self.data.push_back(item);
+    /// Width of the buckets this state was built with
+    pub fn bucket_width(&self) -> Duration {
+        self.bucket_width
     }
     
-    fn iter(&self) -> impl Iterator<Item = &T> {
-        self.data.iter()
+    /// Number of fees in the window
+    pub fn sample_count(&self) -> usize {
+        self.totals.sample_count
     }
     
-    fn len(&self) -> usize {
-        self.data.len()
+    /// Add `sketch` to the bucket containing `at`
+    fn merge_at(&mut self, at: DateTime<Utc>, sketch: &QuantileSketch) -> Result<(), InsightsError> {
+        let start = at.duration_trunc(self.bucket_width)
+            .map_err(|e| InsightsError::calculation_error(e.to_string()))?;
+        
+        self.sketch.merge(sketch)?;
+        match self.buckets.binary_search_by_key(&start, |bucket| bucket.start) {
+            Ok(index) => self.buckets[index].sketch.merge(sketch)?,
+            Err(index) => self.buckets.insert(index, WindowBucket { start, sketch: sketch.clone() }),
+        }
+        self.totals.add(sketch);
+        Ok(())
     }
     
-    fn is_empty(&self) -> bool {
-        self.data.is_empty()
+    /// Drop the buckets that end at or before `window_start`
+    fn expire(&mut self, window_start: DateTime<Utc>) {
+        while let Some(front) = self.buckets.front() {
+            if front.start + self.bucket_width > window_start {
+                break;
+            }
+            if let Some(bucket) = self.buckets.pop_front() {
+                // Every bucket was merged into the window sketch, so this
+                // only fails if the accuracy differs, which it cannot
+                let _ = self.sketch.subtract(&bucket.sketch);
+                self.totals.remove(&bucket.sketch);
+            }
+        }
     }
 }
 
 /// Calculator for rolling averages across multiple time windows
 pub struct RollingAverageCalculator {
     config: AverageConfig,
-    windows: HashMap<TimeWindow, CircularBuffer<FeeDataPoint>>,
+    windows: HashMap<TimeWindow, WindowState>,
     time_windows: Vec<TimeWindow>,
+    clock: SharedClock,
 }
 
 impl RollingAverageCalculator {
     /// Create a new rolling average calculator
     pub fn new(config: AverageConfig, time_windows: Vec<TimeWindow>) -> Self {
-        let mut windows = HashMap::new();
-        
-        // Initialize circular buffers for each time window
-        for window in &time_windows {
-            windows.insert(window.clone(), CircularBuffer::new(config.max_buffer_size));
-        }
+        Self::with_clock(config, time_windows, system_clock())
+    }
+    
+    /// Create a calculator whose windows are measured back from `clock`
+    /// rather than the system clock
+    pub fn with_clock(config: AverageConfig, time_windows: Vec<TimeWindow>, clock: SharedClock) -> Self {
+        // Each window is bucketed finely enough to expire smoothly, but into
+        // no more than the configured number of buckets
+        let windows = time_windows
+            .iter()
+            .map(|window| (window.clone(), WindowState::new(config.bucket_width(window.duration))))
+            .collect();
         
         Self {
             config,
             windows,
             time_windows,
+            clock,
         }
     }
     
     /// Add a new data point to all relevant time windows
     pub fn add_data_point(&mut self, point: FeeDataPoint) {
-        let now = Utc::now();
+        let now = self.clock.now();
         
-        // Add to each time window if the point is within the window duration
         for window in &self.time_windows {
-            if let Some(buffer) = self.windows.get_mut(window) {
-                // Check if the data point is within the time window
-                let window_start = now - window.duration;
-                if point.timestamp >= window_start {
-                    buffer.push(point.clone());
-                }
+            if let Some(state) = self.windows.get_mut(window) {
+                add_to_window(window, state, &point, now);
             }
         }
         
-        // Clean old data points from all buffers
+        // Drop buckets that have left their windows
         self.clean_old_data(now);
     }
     
-    /// Clean old data points that are outside their respective time windows
-    fn clean_old_data(&mut self, current_time: DateTime<Utc>) {
-        for (window, buffer) in &mut self.windows {
-            let window_start = current_time - window.duration;
-            
-            // Remove data points that are too old
-            while let Some(front) = buffer.data.front() {
-                if front.timestamp < window_start {
-                    buffer.data.pop_front();
-                } else {
-                    break;
-                }
+    /// Add a data point to `window` only, if it falls within it
+    pub fn add_data_point_to(&mut self, window: &TimeWindow, point: &FeeDataPoint) {
+        let now = self.clock.now();
+        if let Some(state) = self.windows.get_mut(window) {
+            add_to_window(window, state, point, now);
+            state.expire(now - window.duration);
+        }
+    }
+    
+    /// Aggregated state of every window, e.g. to snapshot the calculator
+    pub fn window_states(&self) -> Vec<(TimeWindow, WindowState)> {
+        self.time_windows
+            .iter()
+            .filter_map(|window| Some((window.clone(), self.windows.get(window)?.clone())))
+            .collect()
+    }
+    
+    /// Replace the contents of `window` by `state`, which may come from a
+    /// longer window bucketed at the same width. Returns `false` and leaves
+    /// the window alone when it is not configured or `state` was bucketed
+    /// at another width.
+    pub fn restore_window(&mut self, window: &TimeWindow, mut state: WindowState) -> bool {
+        if !self.windows.contains_key(window) || state.bucket_width != self.config.bucket_width(window.duration) {
+            return false;
+        }
+        
+        state.expire(self.clock.now() - window.duration);
+        self.windows.insert(window.clone(), state);
+        true
+    }
+    
+    /// Rebuild `window` from persisted history: `buckets` (e.g. rollups at
+    /// the window's bucket resolution) and the raw `points` after them.
+    /// Returns `false` when the window is not configured.
+    pub fn rebuild_window(&mut self, window: &TimeWindow, buckets: Vec<WindowBucket>, points: &[FeeDataPoint]) -> bool {
+        if !self.windows.contains_key(window) {
+            return false;
+        }
+        
+        let now = self.clock.now();
+        let mut state = WindowState::new(self.config.bucket_width(window.duration));
+        for bucket in &buckets {
+            if let Err(err) = state.merge_at(bucket.start, &bucket.sketch) {
+                tracing::warn!("Bucket at {} not added to window '{}': {}", bucket.start, window.name, err);
             }
         }
+        for point in points {
+            add_to_window(window, &mut state, point, now);
+        }
+        state.expire(now - window.duration);
+        self.windows.insert(window.clone(), state);
+        true
+    }
+    
+    /// Drop buckets that are entirely outside their respective time windows
+    fn clean_old_data(&mut self, current_time: DateTime<Utc>) {
+        for (window, state) in &mut self.windows {
+            state.expire(current_time - window.duration);
+        }
     }
     
-    /// Calculate averages for all time windows
+    /// Calculate averages for all configured time windows
     pub fn calculate_averages(&self) -> Result<RollingAverages, InsightsError> {
-        let now = Utc::now();
+        let now = self.clock.now();
+        let mut averages = RollingAverages::default();
         
-        // Calculate average for each predefined window type
-        let short_term = self.calculate_average_for_window("short_term", now)?;
-        let medium_term = self.calculate_average_for_window("medium_term", now)?;
-        let long_term = self.calculate_average_for_window("long_term", now)?;
+        for window in &self.time_windows {
+            let result = self.calculate_average_for_window(&window.name, now)?;
+            averages.windows.insert(window.name.clone(), result);
+        }
         
-        Ok(RollingAverages {
-            short_term,
-            medium_term,
-            long_term,
-        })
+        Ok(averages)
+    }
+    
+    /// Get the configured time windows
+    pub fn time_windows(&self) -> &[TimeWindow] {
+        &self.time_windows
     }
     
     /// Calculate average for a specific time window by name
@@ -125,24 +243,21 @@ impl RollingAverageCalculator {
         let time_window = self.time_windows.iter()
             .find(|w| w.name == window_name)
             .ok_or_else(|| InsightsError::config_error(format!("Time window '{}' not found", window_name)))?;
-            
-        let buffer = self.windows.get(time_window)
-            .ok_or_else(|| InsightsError::config_error(format!("Buffer for window '{}' not found", window_name)))?;
-            
-        if buffer.is_empty() {
+        
+        let state = self.windows.get(time_window)
+            .ok_or_else(|| InsightsError::config_error(format!("Buckets for window '{}' not found", window_name)))?;
+        
+        let Some(average) = state.totals.average() else {
             return Ok(AverageResult {
                 value: 0.0,
                 sample_count: 0,
                 is_partial: true,
                 calculated_at,
                 time_window: time_window.clone(),
+                percentiles: None,
             });
-        }
-        
-        // Calculate the average fee
-        let total_fee: u64 = buffer.iter().map(|point| point.fee_amount).sum();
-        let sample_count = buffer.len();
-        let average = total_fee as f64 / sample_count as f64;
+        };
+        let sample_count = state.totals.sample_count;
         
         // Determine if this is a partial result (insufficient samples)
         let is_partial = sample_count < time_window.min_samples;
@@ -153,34 +268,40 @@ impl RollingAverageCalculator {
             is_partial,
             calculated_at,
             time_window: time_window.clone(),
+            percentiles: state.sketch.percentiles(),
         })
     }
     
     /// Get average for a specific time window
     pub fn get_average_for_window(&self, window: &TimeWindow) -> Option<AverageResult> {
-        let buffer = self.windows.get(window)?;
-        
-        if buffer.is_empty() {
-            return None;
-        }
-        
-        let total_fee: u64 = buffer.iter().map(|point| point.fee_amount).sum();
-        let sample_count = buffer.len();
-        let average = total_fee as f64 / sample_count as f64;
+        let state = self.windows.get(window)?;
+        let average = state.totals.average()?;
+        let sample_count = state.totals.sample_count;
         let is_partial = sample_count < window.min_samples;
         
         Some(AverageResult {
             value: average,
             sample_count,
             is_partial,
-            calculated_at: Utc::now(),
+            calculated_at: self.clock.now(),
             time_window: window.clone(),
+            percentiles: state.sketch.percentiles(),
         })
     }
     
+    /// Get the percentile sketch for a specific time window
+    pub fn get_sketch_for_window(&self, window: &TimeWindow) -> Option<&QuantileSketch> {
+        self.windows.get(window).map(|state| &state.sketch)
+    }
+    
+    /// Get the calculator configuration
+    pub fn config(&self) -> &AverageConfig {
+        &self.config
+    }
+    
     /// Get the number of data points in a specific time window
     pub fn get_sample_count(&self, window: &TimeWindow) -> usize {
-        self.windows.get(window).map(|buffer| buffer.len()).unwrap_or(0)
+        self.windows.get(window).map(WindowState::sample_count).unwrap_or(0)
     }
     
     /// Check if a time window has sufficient data for reliable calculations
@@ -188,4 +309,15 @@ impl RollingAverageCalculator {
         let sample_count = self.get_sample_count(window);
         sample_count >= window.min_samples
     }
-}
\ No newline at end of file
+}
+
+/// Add `point` to `window`'s buckets if it falls within the window
+fn add_to_window(window: &TimeWindow, state: &mut WindowState, point: &FeeDataPoint, now: DateTime<Utc>) {
+    if point.timestamp < now - window.duration {
+        return;
+    }
+    let sketch = QuantileSketch::from_values([point.fee_amount]);
+    if let Err(err) = state.merge_at(point.timestamp, &sketch) {
+        tracing::warn!("Fee point not added to window '{}': {}", window.name, err);
+    }
+}
@@ -2,37 +2,341 @@
 
 use chrono::Duration;
 use serde::{Deserialize, Serialize};
-use crate::insights::types::TimeWindow;
+use std::collections::HashSet;
+use utoipa::ToSchema;
+use crate::insights::{
+    error::InsightsError,
+    strategy::SeverityCutoffs,
+    types::{parse_duration, DurationSchema, TimeWindow},
+};
+
+/// Widths a rolling window's buckets can take, finest first. They match
+/// the stored rollup resolutions, so a window can be rebuilt from rollups.
+pub const WINDOW_BUCKET_RESOLUTIONS: &[&str] = &["1m", "5m", "15m", "1h", "1d"];
+
+/// Default most buckets a rolling window is split into
+pub const MAX_WINDOW_BUCKETS: usize = 720;
 
 /// Configuration for the fee insights engine
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct InsightsConfig {
+    #[schema(value_type = DurationSchema)]
     pub polling_interval: Duration,
     pub time_windows: Vec<TimeWindow>,
+    /// Name of the time window whose average is used as the spike baseline
+    pub baseline_window: String,
     pub spike_detection: SpikeConfig,
+    #[schema(value_type = DurationSchema)]
     pub storage_retention: Duration,
+    /// Periods over which the lowest and highest fees are tracked
+    #[serde(default)]
+    pub extremes: ExtremesConfig,
+}
+
+impl InsightsConfig {
+    /// Build a configuration whose windows come from duration strings such
+    /// as `["5m", "1h", "24h", "7d"]`. Windows are named after their spec and
+    /// the middle window by duration becomes the spike baseline.
+    pub fn with_window_specs(specs: &[&str]) -> Result<Self, InsightsError> {
+        let mut time_windows = specs
+            .iter()
+            .map(|spec| {
+                TimeWindow::from_spec(spec, AverageConfig::default().min_samples_for_calculation)
+                    .ok_or_else(|| InsightsError::config_error(format!("Invalid time window '{}'", spec)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        time_windows.sort_by_key(|window| window.duration);
+        
+        let baseline_window = time_windows
+            .get(time_windows.len() / 2)
+            .map(|window| window.name.clone())
+            .unwrap_or_default();
+        
+        let config = Self {
+            time_windows,
+            baseline_window,
+            ..Self::default()
+        };
+        config.validate()?;
+        Ok(config)
+    }
+    
+    /// Check that the intervals and time windows are usable and the baseline
+    /// window exists
+    pub fn validate(&self) -> Result<(), InsightsError> {
+        if self.time_windows.is_empty() {
+            return Err(InsightsError::config_error("At least one time window is required"));
+        }
+        if self.polling_interval < Duration::seconds(1) {
+            return Err(InsightsError::config_error("polling_interval must be at least one second"));
+        }
+        if self.storage_retention <= Duration::zero() {
+            return Err(InsightsError::config_error("storage_retention must be positive"));
+        }
+        self.extremes.validate()?;
+        
+        let mut names = HashSet::new();
+        for window in &self.time_windows {
+            if window.duration <= Duration::zero() {
+                return Err(InsightsError::config_error(
+                    format!("Time window '{}' must have a positive duration", window.name)
+                ));
+            }
+            if !names.insert(window.name.as_str()) {
+                return Err(InsightsError::config_error(
+                    format!("Duplicate time window '{}'", window.name)
+                ));
+            }
+        }
+        
+        if !names.contains(self.baseline_window.as_str()) {
+            return Err(InsightsError::config_error(
+                format!("Baseline window '{}' is not a configured time window", self.baseline_window)
+            ));
+        }
+        
+        self.spike_detection.validate()
+    }
+}
+
+/// Changes applied on top of a base configuration, e.g. from the config
+/// file or a backtest's candidate settings. Unset fields keep the base value.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    pub threshold_multiplier: Option<f64>,
+    pub minimum_spike_duration: Option<Duration>,
+    pub windows: Vec<String>,
+    /// Name of the window used as the spike baseline
+    pub baseline_window: Option<String>,
+    pub baseline_mode: Option<BaselineMode>,
+    pub strategy: Option<SpikeStrategy>,
+    pub severity_cutoffs: Option<SeverityCutoffs>,
+    pub congestion_window: Option<Duration>,
+    /// How often the poller delivers points
+    pub polling_interval: Option<Duration>,
+    pub extremes_tracking_period: Option<Duration>,
+    pub extremes_periods_to_keep: Option<usize>,
+    pub extremes_top_n: Option<usize>,
+}
+
+impl ConfigOverrides {
+    /// Apply the overrides on top of `base` and validate the result.
+    pub fn apply(&self, base: &InsightsConfig) -> Result<InsightsConfig, InsightsError> {
+        let mut config = base.clone();
+        if !self.windows.is_empty() {
+            let specs: Vec<&str> = self.windows.iter().map(String::as_str).collect();
+            let windowed = InsightsConfig::with_window_specs(&specs)?;
+            // The baseline stays put if it is still one of the windows
+            if !windowed.time_windows.iter().any(|window| window.name == base.baseline_window) {
+                config.baseline_window = windowed.baseline_window;
+            }
+            config.time_windows = windowed.time_windows;
+        }
+        if let Some(name) = &self.baseline_window {
+            config.baseline_window = name.clone();
+        }
+
+        let spike = &mut config.spike_detection;
+        if let Some(multiplier) = self.threshold_multiplier {
+            spike.threshold_multiplier = multiplier;
+        }
+        if let Some(duration) = self.minimum_spike_duration {
+            spike.minimum_spike_duration = duration;
+        }
+        if let Some(mode) = &self.baseline_mode {
+            spike.baseline_mode = mode.clone();
+        }
+        if let Some(strategy) = &self.strategy {
+            spike.strategy = strategy.clone();
+        }
+        if let Some(cutoffs) = self.severity_cutoffs {
+            spike.severity_cutoffs = Some(cutoffs);
+        }
+        if let Some(window) = self.congestion_window {
+            spike.congestion_window = window;
+        }
+        if let Some(interval) = self.polling_interval {
+            config.polling_interval = interval;
+        }
+        let extremes = &mut config.extremes;
+        if let Some(period) = self.extremes_tracking_period {
+            extremes.tracking_period = period;
+        }
+        if let Some(periods) = self.extremes_periods_to_keep {
+            extremes.historical_periods_to_keep = periods;
+        }
+        if let Some(top_n) = self.extremes_top_n {
+            extremes.top_n = top_n;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
 }
 
 /// Configuration for spike detection
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SpikeConfig {
     pub threshold_multiplier: f64,
+    #[schema(value_type = DurationSchema)]
     pub minimum_spike_duration: Duration,
+    #[schema(value_type = DurationSchema)]
     pub congestion_window: Duration,
+    pub baseline_mode: BaselineMode,
+    /// Which detection strategy decides that a fee is a spike
+    pub strategy: SpikeStrategy,
+    /// Overrides the strategy's default score cutoffs for spike severity;
+    /// given in the units of the configured strategy's score
+    pub severity_cutoffs: Option<SeverityCutoffs>,
+}
+
+/// Built-in spike detection strategies
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpikeStrategy {
+    /// Fee at or above `baseline * threshold_multiplier`; scored by ratio
+    Threshold,
+    /// Standard score against the previous `window` fees
+    ZScore { window: usize, threshold: f64 },
+    /// Median absolute deviation score against the previous `window` fees
+    Mad { window: usize, threshold: f64 },
+    /// Cumulative sum of relative excess over the baseline, less `drift`
+    Cusum { drift: f64, threshold: f64 },
+}
+
+impl SpikeStrategy {
+    /// Severity cutoffs in the units of this strategy's score: a ratio to
+    /// baseline for the threshold strategy, deviations for z-score and MAD,
+    /// and for CUSUM multiples of its firing threshold, since the sum is
+    /// accumulated relative excess and grows with the threshold chosen
+    pub fn default_severity_cutoffs(&self) -> SeverityCutoffs {
+        let (moderate, major, critical) = match self {
+            SpikeStrategy::Threshold => (3.0, 5.0, 10.0),
+            SpikeStrategy::ZScore { .. } => (4.0, 6.0, 10.0),
+            SpikeStrategy::Mad { .. } => (5.0, 8.0, 12.0),
+            SpikeStrategy::Cusum { threshold, .. } => (2.0 * threshold, 4.0 * threshold, 8.0 * threshold),
+        };
+        SeverityCutoffs { moderate, major, critical }
+    }
+}
+
+impl SpikeConfig {
+    /// Severity cutoffs in effect, honouring any override
+    pub fn severity_cutoffs(&self) -> SeverityCutoffs {
+        self.severity_cutoffs
+            .unwrap_or_else(|| self.strategy.default_severity_cutoffs())
+    }
+    
+    /// Check that the strategy parameters are usable
+    pub fn validate(&self) -> Result<(), InsightsError> {
+        if self.threshold_multiplier <= 0.0 {
+            return Err(InsightsError::config_error("threshold_multiplier must be positive"));
+        }
+        if self.congestion_window <= Duration::zero() {
+            return Err(InsightsError::config_error("congestion_window must be positive"));
+        }
+        
+        match &self.strategy {
+            SpikeStrategy::Threshold => {}
+            SpikeStrategy::ZScore { window, threshold } | SpikeStrategy::Mad { window, threshold } => {
+                if *window < 2 {
+                    return Err(InsightsError::config_error("Spike strategy window must hold at least 2 fees"));
+                }
+                if *threshold <= 0.0 {
+                    return Err(InsightsError::config_error("Spike strategy threshold must be positive"));
+                }
+            }
+            SpikeStrategy::Cusum { drift, threshold } => {
+                if *drift < 0.0 || *threshold <= 0.0 {
+                    return Err(InsightsError::config_error(
+                        "CUSUM drift must be non-negative and threshold positive"
+                    ));
+                }
+            }
+        }
+        
+        let cutoffs = self.severity_cutoffs();
+        if !(cutoffs.moderate <= cutoffs.major && cutoffs.major <= cutoffs.critical) {
+            return Err(InsightsError::config_error("Severity cutoffs must be ascending"));
+        }
+        
+        Ok(())
+    }
+}
+
+/// What each fee is compared against when looking for spikes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub enum BaselineMode {
+    /// The medium-term rolling average
+    RollingAverage,
+    /// The median fee seen in the same hour-of-week (UTC) over the last
+    /// `weeks` weeks. Slots with fewer than `min_samples` historical points
+    /// fall back to the rolling average.
+    HourOfWeek { weeks: u32, min_samples: usize },
 }
 
 /// Configuration for rolling averages
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AverageConfig {
-    pub max_buffer_size: usize,
+    /// How often new points arrive
+    pub polling_interval: Duration,
+    /// Most buckets a window is split into; windows keep aggregates per
+    /// bucket rather than raw points and drop them a bucket at a time
+    pub max_buckets: usize,
     pub min_samples_for_calculation: usize,
 }
 
+impl AverageConfig {
+    /// Defaults for points arriving every `polling_interval`.
+    pub fn for_polling_interval(polling_interval: Duration) -> Self {
+        Self {
+            polling_interval,
+            ..Self::default()
+        }
+    }
+
+    /// Rollup resolution a window of length `window` is bucketed by: the
+    /// finest that splits it into at most `max_buckets` buckets, else the
+    /// coarsest.
+    pub fn bucket_resolution(&self, window: Duration) -> &'static str {
+        let max_buckets = self.max_buckets.max(1) as i64;
+        WINDOW_BUCKET_RESOLUTIONS
+            .iter()
+            .copied()
+            .find(|resolution| {
+                let width = parse_duration(resolution).unwrap_or(Duration::days(1));
+                window.num_seconds() <= width.num_seconds() * max_buckets
+            })
+            .unwrap_or(WINDOW_BUCKET_RESOLUTIONS[WINDOW_BUCKET_RESOLUTIONS.len() - 1])
+    }
+
+    /// Width of the buckets a window of length `window` is split into
+    pub fn bucket_width(&self, window: Duration) -> Duration {
+        parse_duration(self.bucket_resolution(window)).unwrap_or(Duration::days(1))
+    }
+}
+
+impl ExtremesConfig {
+    /// Check that periods have a length and keep at least one fee
+    pub fn validate(&self) -> Result<(), InsightsError> {
+        if self.tracking_period <= Duration::zero() {
+            return Err(InsightsError::config_error("extremes tracking_period must be positive"));
+        }
+        if self.top_n == 0 {
+            return Err(InsightsError::config_error("extremes top_n must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
 /// Configuration for extremes tracking
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct ExtremesConfig {
+    #[schema(value_type = DurationSchema)]
     pub tracking_period: Duration,
     pub historical_periods_to_keep: usize,
+    /// Number of lowest and highest fees kept per period
+    pub top_n: usize,
 }
 
 impl Default for InsightsConfig {
@@ -56,8 +360,10 @@ impl Default for InsightsConfig {
                     min_samples: 100,
                 },
             ],
+            baseline_window: "medium_term".to_string(),
             spike_detection: SpikeConfig::default(),
             storage_retention: Duration::days(7),
+            extremes: ExtremesConfig::default(),
         }
     }
 }
@@ -68,6 +374,9 @@ impl Default for SpikeConfig {
             threshold_multiplier: 2.0,
             minimum_spike_duration: Duration::minutes(5),
             congestion_window: Duration::hours(1),
+            baseline_mode: BaselineMode::RollingAverage,
+            strategy: SpikeStrategy::Threshold,
+            severity_cutoffs: None,
         }
     }
 }
@@ -75,7 +384,8 @@ impl Default for SpikeConfig {
 impl Default for AverageConfig {
     fn default() -> Self {
         Self {
-            max_buffer_size: 10000,
+            polling_interval: Duration::minutes(1),
+            max_buckets: MAX_WINDOW_BUCKETS,
             min_samples_for_calculation: 5,
         }
     }
@@ -86,6 +396,7 @@ impl Default for ExtremesConfig {
         Self {
             tracking_period: Duration::hours(24),
             historical_periods_to_keep: 30,
+            top_n: 5,
         }
     }
 }
\ No newline at end of file
@@ -1,12 +1,15 @@
 //! Congestion Detection System
 
-use chrono::{Utc, Duration};
+use chrono::{DateTime, Utc, Duration};
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
+use crate::clock::{system_clock, SharedClock};
 use crate::insights::{
     types::*,
     error::InsightsError,
     config::SpikeConfig,
+    strategy::{build_detector, SpikeDetector},
 };
 
 /// Analyzer for trend patterns
@@ -24,13 +27,14 @@ impl TrendAnalyzer {
         }
     }
     
-    fn add_spike(&mut self, spike: FeeSpike) {
-        self.recent_spikes.push_back(spike);
-        self.clean_old_spikes();
+    /// Add a spike, or replace the earlier state of a spike with the same ID
+    fn add_spike(&mut self, spike: FeeSpike, now: DateTime<Utc>) {
+        upsert_spike(&mut self.recent_spikes, spike);
+        self.clean_old_spikes(now);
     }
     
-    fn clean_old_spikes(&mut self) {
-        let cutoff_time = Utc::now() - self.congestion_window;
+    fn clean_old_spikes(&mut self, now: DateTime<Utc>) {
+        let cutoff_time = now - self.congestion_window;
         
         while let Some(front_spike) = self.recent_spikes.front() {
             if front_spike.start_time < cutoff_time {
@@ -125,32 +129,81 @@ impl TrendAnalyzer {
     }
 }
 
+/// Replace the spike with the same ID, searching from the newest end, or
+/// append it when it is new
+fn upsert_spike(spikes: &mut VecDeque<FeeSpike>, spike: FeeSpike) {
+    match spikes.iter_mut().rev().find(|existing| existing.id == spike.id) {
+        Some(existing) => *existing = spike,
+        None => spikes.push_back(spike),
+    }
+}
+
+/// Serializable state of a [`CongestionDetector`], used by engine snapshots
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectorSnapshot {
+    /// Spikes inside the congestion window, oldest first
+    pub recent_spikes: Vec<FeeSpike>,
+    pub historical_spikes: Vec<FeeSpike>,
+    pub open_spike: Option<FeeSpike>,
+    pub announced_spike_id: Option<String>,
+    /// Name of the strategy that produced `strategy_state`
+    pub strategy: String,
+    pub strategy_state: Vec<f64>,
+}
+
 /// Detector for network congestion through fee spike analysis
 pub struct CongestionDetector {
     config: SpikeConfig,
+    strategy: Box<dyn SpikeDetector>,
     trend_analyzer: TrendAnalyzer,
     historical_spikes: VecDeque<FeeSpike>,
+    /// Spike still in progress at the end of the last batch
+    open_spike: Option<FeeSpike>,
+    /// ID of the open spike that has already been announced
+    announced_spike_id: Option<String>,
+    pending_events: Vec<SpikeEvent>,
+    clock: SharedClock,
 }
 
 impl CongestionDetector {
     /// Create a new congestion detector
     pub fn new(config: SpikeConfig) -> Self {
+        Self::with_clock(config, system_clock())
+    }
+    
+    /// Create a detector that ages out spikes against `clock` rather than
+    /// the system clock
+    pub fn with_clock(config: SpikeConfig, clock: SharedClock) -> Self {
         Self {
             trend_analyzer: TrendAnalyzer::new(config.congestion_window),
+            strategy: build_detector(&config.strategy, config.threshold_multiplier),
             config,
             historical_spikes: VecDeque::new(),
+            open_spike: None,
+            announced_spike_id: None,
+            pending_events: Vec::new(),
+            clock,
         }
     }
     
     /// Analyze congestion patterns
     pub fn analyze_congestion(&mut self, current_fees: &[FeeDataPoint], baseline: f64) -> Result<CongestionTrends, InsightsError> {
+        self.analyze_congestion_against(current_fees, |_| baseline)
+    }
+    
+    /// Analyze congestion patterns, resolving a separate baseline for each fee point
+    pub fn analyze_congestion_against<F>(&mut self, current_fees: &[FeeDataPoint], baseline_for: F) -> Result<CongestionTrends, InsightsError>
+    where
+        F: Fn(&FeeDataPoint) -> f64,
+    {
         // Detect new spikes in the current fee data
-        let new_spikes = self.detect_spikes(current_fees, baseline)?;
+        let new_spikes = self.detect_spikes_against(current_fees, baseline_for)?;
         
         // Add new spikes to the trend analyzer
+        let now = self.clock.now();
         for spike in &new_spikes {
-            self.trend_analyzer.add_spike(spike.clone());
-            self.historical_spikes.push_back(spike.clone());
+            self.trend_analyzer.add_spike(spike.clone(), now);
+            upsert_spike(&mut self.historical_spikes, spike.clone());
         }
         
         // Maintain historical spike buffer (keep last 1000 spikes)
@@ -159,7 +212,7 @@ impl CongestionDetector {
         }
         
         // Clean old spikes from trend analyzer
-        self.trend_analyzer.clean_old_spikes();
+        self.trend_analyzer.clean_old_spikes(now);
         
         // Calculate current trend indicators
         let current_trend = self.trend_analyzer.determine_trend_indicator();
@@ -178,7 +231,7 @@ impl CongestionDetector {
     }
     
     /// Detect fee spikes in the given fee data
-    pub fn detect_spikes(&self, fees: &[FeeDataPoint], baseline: f64) -> Result<Vec<FeeSpike>, InsightsError> {
+    pub fn detect_spikes(&mut self, fees: &[FeeDataPoint], baseline: f64) -> Result<Vec<FeeSpike>, InsightsError> {
         if fees.is_empty() {
             return Ok(Vec::new());
         }
@@ -187,38 +240,68 @@ impl CongestionDetector {
             return Err(InsightsError::invalid_data("Baseline must be positive"));
         }
         
+        self.detect_spikes_against(fees, |_| baseline)
+    }
+    
+    /// Detect fee spikes, comparing each fee point against its own baseline
+    ///
+    /// Used by seasonality-aware detection, where the expected fee depends on
+    /// the hour-of-week the point falls in.
+    pub fn detect_spikes_against<F>(&mut self, fees: &[FeeDataPoint], baseline_for: F) -> Result<Vec<FeeSpike>, InsightsError>
+    where
+        F: Fn(&FeeDataPoint) -> f64,
+    {
+        if fees.is_empty() {
+            return Ok(Vec::new());
+        }
+        
         let mut spikes = Vec::new();
-        let threshold = baseline * self.config.threshold_multiplier;
         
         // Sort fees by timestamp to process in chronological order
         let mut sorted_fees = fees.to_vec();
-        sorted_fees.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        sorted_fees.sort_by_key(|point| point.timestamp);
         
-        let mut current_spike: Option<FeeSpike> = None;
+        // Resume a spike left open by the previous batch
+        let mut current_spike: Option<FeeSpike> = self.open_spike.take();
         
         for fee_point in &sorted_fees {
             let fee_amount = fee_point.fee_amount as f64;
+            let baseline = baseline_for(fee_point);
             
-            if fee_amount >= threshold {
+            if baseline <= 0.0 {
+                return Err(InsightsError::invalid_data("Baseline must be positive"));
+            }
+            
+            let evaluation = self.strategy.evaluate(fee_amount, baseline);
+            
+            if evaluation.is_spike {
                 // This is a spike
                 match &mut current_spike {
                     None => {
                         // Start a new spike
                         current_spike = Some(FeeSpike {
+                            id: FeeSpike::id_for(fee_point.timestamp),
                             peak_fee: fee_point.fee_amount,
                             baseline_fee: baseline,
                             spike_ratio: fee_amount / baseline,
                             start_time: fee_point.timestamp,
                             duration: Duration::zero(),
-                            severity: self.classify_spike_severity(fee_amount / baseline),
+                            severity: self.classify_spike_severity(evaluation.score),
+                            strategy: self.strategy.name().to_string(),
+                            score: evaluation.score,
+                            end_time: None,
                         });
                     }
                     Some(spike) => {
                         // Continue existing spike, update peak if necessary
                         if fee_point.fee_amount > spike.peak_fee {
                             spike.peak_fee = fee_point.fee_amount;
+                            spike.baseline_fee = baseline;
                             spike.spike_ratio = fee_amount / baseline;
-                            spike.severity = self.classify_spike_severity(fee_amount / baseline);
+                        }
+                        if evaluation.score > spike.score {
+                            spike.score = evaluation.score;
+                            spike.severity = self.classify_spike_severity(evaluation.score);
                         }
                         spike.duration = fee_point.timestamp - spike.start_time;
                     }
@@ -227,37 +310,68 @@ impl CongestionDetector {
                 // Not a spike, end current spike if it exists
                 if let Some(mut spike) = current_spike.take() {
                     spike.duration = fee_point.timestamp - spike.start_time;
+                    spike.end_time = Some(fee_point.timestamp);
                     
                     // Only include spikes that meet minimum duration
                     if spike.duration >= self.config.minimum_spike_duration {
+                        self.record_event(&spike);
                         spikes.push(spike);
+                    } else if self.announced_spike_id.as_deref() == Some(spike.id.as_str()) {
+                        self.announced_spike_id = None;
                     }
                 }
             }
         }
         
-        // Handle case where spike continues to the end of data
+        // A spike that continues to the end of the data stays open for the next batch
         if let Some(mut spike) = current_spike {
             if let Some(last_fee) = sorted_fees.last() {
                 spike.duration = last_fee.timestamp - spike.start_time;
                 
                 if spike.duration >= self.config.minimum_spike_duration {
-                    spikes.push(spike);
+                    self.record_event(&spike);
+                    spikes.push(spike.clone());
                 }
             }
+            self.open_spike = Some(spike);
         }
         
         Ok(spikes)
     }
     
-    /// Classify the severity of a spike based on its ratio to baseline
-    pub fn classify_spike_severity(&self, spike_ratio: f64) -> SpikeSeverity {
-        match spike_ratio {
-            r if r >= 10.0 => SpikeSeverity::Critical,
-            r if r >= 5.0 => SpikeSeverity::Major,
-            r if r >= 3.0 => SpikeSeverity::Moderate,
-            _ => SpikeSeverity::Minor,
+    /// Queue the lifecycle event(s) for a reported spike
+    fn record_event(&mut self, spike: &FeeSpike) {
+        let announced = self.announced_spike_id.as_deref() == Some(spike.id.as_str());
+        
+        if !announced {
+            self.pending_events.push(SpikeEvent { kind: SpikeEventKind::Opened, spike: spike.clone() });
         }
+        
+        if spike.is_open() {
+            if announced {
+                self.pending_events.push(SpikeEvent { kind: SpikeEventKind::Updated, spike: spike.clone() });
+            }
+            self.announced_spike_id = Some(spike.id.clone());
+        } else {
+            self.pending_events.push(SpikeEvent { kind: SpikeEventKind::Closed, spike: spike.clone() });
+            self.announced_spike_id = None;
+        }
+    }
+    
+    /// Drain the spike lifecycle events produced since the last call
+    pub fn take_spike_events(&mut self) -> Vec<SpikeEvent> {
+        std::mem::take(&mut self.pending_events)
+    }
+    
+    /// Get the spike currently in progress, if any
+    pub fn get_open_spike(&self) -> Option<&FeeSpike> {
+        self.open_spike.as_ref()
+    }
+    
+    /// Classify the severity of a spike from its strategy score; for the
+    /// default threshold strategy the score is the ratio to baseline
+    pub fn classify_spike_severity(&self, score: f64) -> SpikeSeverity {
+        self.config.severity_cutoffs().classify(score)
     }
     
     /// Calculate trend strength based on recent spike activity
@@ -275,9 +389,43 @@ impl CongestionDetector {
         self.historical_spikes.iter().cloned().collect()
     }
     
+    /// Capture spike history, the open spike and strategy state
+    pub fn snapshot(&self) -> DetectorSnapshot {
+        DetectorSnapshot {
+            recent_spikes: self.get_recent_spikes(),
+            historical_spikes: self.get_historical_spikes(),
+            open_spike: self.open_spike.clone(),
+            announced_spike_id: self.announced_spike_id.clone(),
+            strategy: self.strategy.name().to_string(),
+            strategy_state: self.strategy.state(),
+        }
+    }
+    
+    /// Replace spike history with a snapshot. Strategy state is only
+    /// restored when the snapshot was taken with the same strategy.
+    pub fn restore_snapshot(&mut self, snapshot: DetectorSnapshot) {
+        self.clear_history();
+        
+        let now = self.clock.now();
+        for spike in snapshot.recent_spikes {
+            self.trend_analyzer.add_spike(spike, now);
+        }
+        self.historical_spikes = snapshot.historical_spikes.into();
+        self.open_spike = snapshot.open_spike;
+        self.announced_spike_id = snapshot.announced_spike_id;
+        
+        if snapshot.strategy == self.strategy.name() {
+            self.strategy.restore_state(&snapshot.strategy_state);
+        }
+    }
+    
     /// Clear all spike history (useful for testing or reset scenarios)
     pub fn clear_history(&mut self) {
         self.trend_analyzer.recent_spikes.clear();
         self.historical_spikes.clear();
+        self.strategy.reset();
+        self.open_spike = None;
+        self.announced_spike_id = None;
+        self.pending_events.clear();
     }
 }
\ No newline at end of file
@@ -3,15 +3,22 @@
 use chrono::{DateTime, Utc};
 use std::time::Instant;
 
+use crate::clock::{system_clock, SharedClock};
 use crate::insights::{
     types::*,
     error::InsightsError,
-    config::{InsightsConfig, AverageConfig, ExtremesConfig},
-    calculator::RollingAverageCalculator,
+    config::{InsightsConfig, AverageConfig, BaselineMode, ExtremesConfig},
+    calculator::{RollingAverageCalculator, WindowBucket, WindowState},
     tracker::ExtremesTracker,
     detector::CongestionDetector,
+    seasonality::SeasonalityProfile,
+    sketch::QuantileSketch,
+    snapshot::{EngineSnapshot, WindowSnapshot, SNAPSHOT_VERSION},
 };
 
+/// How often the hour-of-week baseline is rebuilt from persisted history
+pub const SEASONAL_REFRESH_INTERVAL: chrono::Duration = chrono::Duration::hours(1);
+
 /// Central fee insights engine that orchestrates all analysis operations
 pub struct FeeInsightsEngine {
     config: InsightsConfig,
@@ -19,23 +26,63 @@ pub struct FeeInsightsEngine {
     tracker: ExtremesTracker,
     detector: CongestionDetector,
     last_update: Option<DateTime<Utc>>,
+    /// When the configuration was last replaced
+    reconfigured_at: Option<DateTime<Utc>>,
+    /// Newest fee timestamp processed so far
+    last_point_at: Option<DateTime<Utc>>,
     last_insights: Option<CurrentInsights>,
+    seasonal_profile: Option<SeasonalityProfile>,
+    clock: SharedClock,
+}
+
+/// Check one fee data point against the rules every processed point must
+/// meet, as of `now`. Returns why the point is invalid.
+pub fn validate_fee_point(fee_point: &FeeDataPoint, now: DateTime<Utc>) -> Result<(), String> {
+    // Check for reasonable fee amounts (not zero, not excessively large)
+    if fee_point.fee_amount == 0 {
+        return Err("Zero fee amount".to_string());
+    }
+
+    // Check for reasonable fee amounts (Stellar fees are typically in stroops)
+    if fee_point.fee_amount > 1_000_000_000 { // 1000 XLM in stroops
+        return Err(format!("Unreasonably large fee amount {}", fee_point.fee_amount));
+    }
+
+    // Check for valid transaction hash
+    if fee_point.transaction_hash.is_empty() {
+        return Err("Empty transaction hash".to_string());
+    }
+
+    // Check for reasonable timestamp (not too far in the future)
+    if fee_point.timestamp > now + chrono::Duration::hours(1) {
+        return Err(format!("Future timestamp {}", fee_point.timestamp));
+    }
+
+    Ok(())
 }
 
 impl FeeInsightsEngine {
     /// Create a new fee insights engine with the given configuration
     pub fn new(config: InsightsConfig) -> Self {
+        Self::with_clock(config, system_clock())
+    }
+    
+    /// Create an engine that reads the time from `clock`. Pass a
+    /// [`ManualClock`](crate::clock::ManualClock) to process historical
+    /// data as of its own timestamps.
+    pub fn with_clock(config: InsightsConfig, clock: SharedClock) -> Self {
         // Create component configurations
-        let average_config = AverageConfig::default();
-        let extremes_config = ExtremesConfig::default();
+        let average_config = AverageConfig::for_polling_interval(config.polling_interval);
+        let extremes_config = config.extremes.clone();
         
         // Initialize components
-        let calculator = RollingAverageCalculator::new(
+        let calculator = RollingAverageCalculator::with_clock(
             average_config,
-            config.time_windows.clone()
+            config.time_windows.clone(),
+            clock.clone(),
         );
-        let tracker = ExtremesTracker::new(extremes_config);
-        let detector = CongestionDetector::new(config.spike_detection.clone());
+        let tracker = ExtremesTracker::with_clock(extremes_config, clock.clone());
+        let detector = CongestionDetector::with_clock(config.spike_detection.clone(), clock.clone());
         
         Self {
             config,
@@ -43,14 +90,18 @@ impl FeeInsightsEngine {
             tracker,
             detector,
             last_update: None,
+            reconfigured_at: None,
+            last_point_at: None,
             last_insights: None,
+            seasonal_profile: None,
+            clock,
         }
     }
     
     /// Process new fee data and update insights
     pub async fn process_fee_data(&mut self, data: &[FeeDataPoint]) -> Result<InsightsUpdate, InsightsError> {
         let start_time = Instant::now();
-        let processing_start = Utc::now();
+        let processing_start = self.clock.now();
         
         if data.is_empty() {
             return Err(InsightsError::invalid_data("No fee data provided"));
@@ -69,10 +120,18 @@ impl FeeInsightsEngine {
         
         // Calculate rolling averages to get baseline for congestion detection
         let rolling_averages = self.calculator.calculate_averages()?;
-        let baseline = rolling_averages.medium_term.value; // Use medium-term as baseline
+        let baseline = self.baseline_from(&rolling_averages);
         
-        // Update congestion detection
-        let congestion_trends = self.detector.analyze_congestion(data, baseline)?;
+        // Update congestion detection, comparing against the same hour-of-week
+        // when a seasonal baseline is configured and has enough history
+        let congestion_trends = match (&self.config.spike_detection.baseline_mode, &self.seasonal_profile) {
+            (BaselineMode::HourOfWeek { min_samples, .. }, Some(profile)) => {
+                self.detector.analyze_congestion_against(data, |point| {
+                    profile.baseline_at(point.timestamp, *min_samples).unwrap_or(baseline)
+                })?
+            }
+            _ => self.detector.analyze_congestion(data, baseline)?,
+        };
         
         // Get current extremes
         let extremes = self.tracker.get_current_extremes()
@@ -92,6 +151,10 @@ impl FeeInsightsEngine {
         
         // Update last update time
         self.last_update = Some(processing_start);
+        self.last_point_at = data.iter()
+            .map(|point| point.timestamp)
+            .chain(self.last_point_at)
+            .max();
         self.last_insights = Some(insights.clone());
         
         // Calculate processing time
@@ -107,35 +170,11 @@ impl FeeInsightsEngine {
     
     /// Validate fee data for basic correctness
     pub fn validate_fee_data(&self, data: &[FeeDataPoint]) -> Result<(), InsightsError> {
+        let now = self.clock.now();
         for (i, fee_point) in data.iter().enumerate() {
-            // Check for reasonable fee amounts (not zero, not excessively large)
-            if fee_point.fee_amount == 0 {
-                return Err(InsightsError::invalid_data(
-                    format!("Zero fee amount at index {}", i)
-                ));
-            }
-            
-            // Check for reasonable fee amounts (Stellar fees are typically in stroops)
-            if fee_point.fee_amount > 1_000_000_000 { // 1000 XLM in stroops
-                return Err(InsightsError::invalid_data(
-                    format!("Unreasonably large fee amount {} at index {}", fee_point.fee_amount, i)
-                ));
-            }
-            
-            // Check for valid transaction hash
-            if fee_point.transaction_hash.is_empty() {
-                return Err(InsightsError::invalid_data(
-                    format!("Empty transaction hash at index {}", i)
-                ));
-            }
-            
-            // Check for reasonable timestamp (not too far in the future)
-            let now = Utc::now();
-            if fee_point.timestamp > now + chrono::Duration::hours(1) {
-                return Err(InsightsError::invalid_data(
-                    format!("Future timestamp at index {}: {}", i, fee_point.timestamp)
-                ));
-            }
+            validate_fee_point(fee_point, now).map_err(|reason| {
+                InsightsError::invalid_data(format!("{} at index {}", reason, i))
+            })?;
         }
         
         Ok(())
@@ -183,7 +222,7 @@ impl FeeInsightsEngine {
         // In reality, this would depend on network activity
         match self.last_update {
             Some(last) => {
-                let time_diff = Utc::now() - last;
+                let time_diff = self.clock.now() - last;
                 let intervals = time_diff.num_seconds() / self.config.polling_interval.num_seconds();
                 intervals.max(1) as usize
             }
@@ -193,7 +232,7 @@ impl FeeInsightsEngine {
     
     /// Create default extremes when no data is available
     fn create_default_extremes(&self) -> FeeExtremes {
-        let now = Utc::now();
+        let now = self.clock.now();
         let default_extreme = ExtremeValue {
             value: 100, // Default Stellar base fee in stroops
             timestamp: now,
@@ -205,6 +244,8 @@ impl FeeInsightsEngine {
             current_max: default_extreme,
             period_start: now,
             period_end: now,
+            lowest: Vec::new(),
+            highest: Vec::new(),
         }
     }
     
@@ -238,31 +279,38 @@ impl FeeInsightsEngine {
             rolling_averages,
             extremes,
             congestion_trends,
-            last_updated: self.last_update.unwrap_or_else(Utc::now),
+            last_updated: self.last_update.unwrap_or_else(|| self.clock.now()),
             data_quality,
         }
     }
     
     /// Create default rolling averages when no data is available
     fn create_default_rolling_averages(&self) -> RollingAverages {
-        let now = Utc::now();
-        let default_result = AverageResult {
-            value: 100.0, // Default Stellar base fee
-            sample_count: 0,
-            is_partial: true,
-            calculated_at: now,
-            time_window: TimeWindow {
-                name: "default".to_string(),
-                duration: chrono::Duration::hours(1),
-                min_samples: 1,
-            },
-        };
+        let now = self.clock.now();
+        let mut averages = RollingAverages::default();
         
-        RollingAverages {
-            short_term: default_result.clone(),
-            medium_term: default_result.clone(),
-            long_term: default_result,
+        for window in &self.config.time_windows {
+            averages.windows.insert(window.name.clone(), AverageResult {
+                value: 100.0, // Default Stellar base fee
+                sample_count: 0,
+                is_partial: true,
+                calculated_at: now,
+                time_window: window.clone(),
+                percentiles: None,
+            });
         }
+        
+        averages
+    }
+    
+    /// Average of the configured baseline window, falling back to the
+    /// shortest window if the baseline window is missing
+    fn baseline_from(&self, averages: &RollingAverages) -> f64 {
+        averages
+            .get(&self.config.baseline_window)
+            .or_else(|| averages.shortest())
+            .map(|result| result.value)
+            .unwrap_or(0.0)
     }
     
     /// Get rolling averages
@@ -277,6 +325,21 @@ impl FeeInsightsEngine {
             .unwrap_or_else(|_| self.create_default_extremes())
     }
     
+    /// Get extremes for up to `periods` closed periods, most recent first
+    pub fn get_extremes_history(&self, periods: usize) -> Vec<FeeExtremes> {
+        self.tracker.get_historical_extremes(periods)
+    }
+    
+    /// Drain extreme periods closed since the last call, for persistence
+    pub fn take_closed_extreme_periods(&mut self) -> Vec<FeeExtremes> {
+        self.tracker.take_closed_periods()
+    }
+    
+    /// Reload persisted extreme periods into the tracker's history
+    pub fn restore_extremes_history(&mut self, periods: Vec<FeeExtremes>) {
+        self.tracker.restore_historical_periods(periods);
+    }
+    
     /// Get congestion trends
     pub fn get_congestion_trends(&self) -> CongestionTrends {
         CongestionTrends {
@@ -287,36 +350,205 @@ impl FeeInsightsEngine {
         }
     }
     
+    /// Drain spike lifecycle events (opened, updated, closed) produced by
+    /// processing since the last call
+    pub fn take_spike_events(&mut self) -> Vec<SpikeEvent> {
+        self.detector.take_spike_events()
+    }
+    
+    /// Number of weeks of history to load when the seasonal baseline is due
+    /// for a refresh, or `None` when no refresh is needed
+    pub fn seasonal_refresh_due(&self, now: DateTime<Utc>) -> Option<u32> {
+        match self.config.spike_detection.baseline_mode {
+            BaselineMode::HourOfWeek { weeks, .. } => {
+                let is_stale = self.seasonal_profile
+                    .as_ref()
+                    .map(|profile| now - profile.to >= SEASONAL_REFRESH_INTERVAL)
+                    .unwrap_or(true);
+                is_stale.then_some(weeks)
+            }
+            BaselineMode::RollingAverage => None,
+        }
+    }
+    
+    /// Replace the hour-of-week profile used for seasonal spike baselines
+    pub fn set_seasonal_profile(&mut self, profile: SeasonalityProfile) {
+        self.seasonal_profile = Some(profile);
+    }
+    
+    /// Get the hour-of-week profile used for seasonal spike baselines
+    pub fn get_seasonal_profile(&self) -> Option<&SeasonalityProfile> {
+        self.seasonal_profile.as_ref()
+    }
+    
+    /// The maintained quantile sketch of the configured window lasting
+    /// `duration`, if there is one
+    pub fn window_sketch(&self, duration: chrono::Duration) -> Option<&QuantileSketch> {
+        let window = self.calculator.time_windows().iter().find(|w| w.duration == duration)?;
+        self.calculator.get_sketch_for_window(window)
+    }
+    
     /// Get engine configuration
     pub fn get_config(&self) -> &InsightsConfig {
         &self.config
     }
     
+    /// Get the clock the engine reads the time from
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+    
     /// Get last update time
     pub fn get_last_update(&self) -> Option<DateTime<Utc>> {
         self.last_update
     }
+
+    /// When the insights last changed: new data was processed or the
+    /// configuration was replaced.
+    pub fn get_last_change(&self) -> Option<DateTime<Utc>> {
+        self.last_update.max(self.reconfigured_at)
+    }
+    
+    /// Get the newest fee timestamp processed so far
+    pub fn get_last_point_at(&self) -> Option<DateTime<Utc>> {
+        self.last_point_at
+    }
+    
+    /// Capture the engine state in a versioned snapshot
+    pub fn snapshot(&self) -> EngineSnapshot {
+        EngineSnapshot {
+            version: SNAPSHOT_VERSION,
+            taken_at: self.clock.now(),
+            last_update: self.last_update,
+            last_point_at: self.last_point_at,
+            windows: self.calculator
+                .window_states()
+                .into_iter()
+                .map(|(window, state)| WindowSnapshot { window, state })
+                .collect(),
+            extremes: self.tracker.snapshot(),
+            detector: self.detector.snapshot(),
+            last_insights: self.last_insights.clone(),
+        }
+    }
+    
+    /// Replace the engine state with a snapshot. The seasonal profile is
+    /// not part of a snapshot and is rebuilt on the next refresh. Returns
+    /// the rolling windows no saved window covers, which start empty and
+    /// should be rebuilt with [`rebuild_window`](Self::rebuild_window).
+    pub fn restore_snapshot(&mut self, snapshot: EngineSnapshot) -> Result<Vec<TimeWindow>, InsightsError> {
+        snapshot.check_version()?;
+        
+        self.calculator = RollingAverageCalculator::with_clock(
+            AverageConfig::for_polling_interval(self.config.polling_interval),
+            self.config.time_windows.clone(),
+            self.clock.clone(),
+        );
+        let saved: Vec<(TimeWindow, WindowState)> = snapshot.windows
+            .into_iter()
+            .map(|saved| (saved.window, saved.state))
+            .collect();
+        let uncovered = carry_over_windows(&mut self.calculator, &saved);
+        self.tracker.restore_snapshot(snapshot.extremes);
+        self.detector.restore_snapshot(snapshot.detector);
+        self.last_update = snapshot.last_update;
+        self.last_point_at = snapshot.last_point_at;
+        self.last_insights = snapshot.last_insights;
+        
+        Ok(uncovered)
+    }
+    
+    /// Swap in a new configuration without losing state.
+    ///
+    /// The configuration is validated first, so on error the engine is left
+    /// untouched. Rolling windows take over the aggregates of the current
+    /// window of the same length, or of a longer one bucketed the same way;
+    /// the others start empty and are returned, to be rebuilt from persisted history
+    /// with [`rebuild_window`](Self::rebuild_window). Spike history carries
+    /// over to a detector built from the new spike settings. Extremes
+    /// tracking does not depend on the config and is kept as is.
+    pub fn reconfigure(&mut self, config: InsightsConfig) -> Result<Vec<TimeWindow>, InsightsError> {
+        config.validate()?;
+        
+        let mut calculator = RollingAverageCalculator::with_clock(
+            AverageConfig::for_polling_interval(config.polling_interval),
+            config.time_windows.clone(),
+            self.clock.clone(),
+        );
+        let uncovered = carry_over_windows(&mut calculator, &self.calculator.window_states());
+        
+        let mut detector = CongestionDetector::with_clock(config.spike_detection.clone(), self.clock.clone());
+        detector.restore_snapshot(self.detector.snapshot());
+        
+        self.calculator = calculator;
+        self.detector = detector;
+        self.tracker.reconfigure(config.extremes.clone());
+        self.config = config;
+        self.reconfigured_at = Some(self.clock.now());
+        
+        Ok(uncovered)
+    }
+    
+    /// Rollup resolution `window`'s buckets are aligned to
+    pub fn window_bucket_resolution(&self, window: &TimeWindow) -> &'static str {
+        self.calculator.config().bucket_resolution(window.duration)
+    }
+    
+    /// Replace `window`'s contents with `buckets` at its
+    /// [bucket resolution](Self::window_bucket_resolution) and the raw
+    /// `points` after them. Returns `false` when the window is not
+    /// configured.
+    pub fn rebuild_window(&mut self, window: &TimeWindow, buckets: Vec<WindowBucket>, points: &[FeeDataPoint]) -> bool {
+        self.calculator.rebuild_window(window, buckets, points)
+    }
     
     /// Reset all components (useful for testing or maintenance)
     pub fn reset(&mut self) -> Result<(), InsightsError> {
         // Reset calculator by creating a new one
-        let average_config = AverageConfig::default();
-        self.calculator = RollingAverageCalculator::new(
+        let average_config = AverageConfig::for_polling_interval(self.config.polling_interval);
+        self.calculator = RollingAverageCalculator::with_clock(
             average_config,
-            self.config.time_windows.clone()
+            self.config.time_windows.clone(),
+            self.clock.clone(),
         );
         
         // Reset tracker
-        let extremes_config = ExtremesConfig::default();
-        self.tracker = ExtremesTracker::new(extremes_config);
+        let extremes_config = self.config.extremes.clone();
+        self.tracker = ExtremesTracker::with_clock(extremes_config, self.clock.clone());
         
         // Reset detector
         self.detector.clear_history();
         
         // Reset update time
         self.last_update = None;
+        self.last_point_at = None;
         self.last_insights = None;
+        self.seasonal_profile = None;
         
         Ok(())
     }
 }
+
+/// Fill each of `calculator`'s windows from the saved window of the same
+/// length, else from the shortest longer one bucketed at the same width.
+/// Returns the windows no saved window covers, which are left empty.
+fn carry_over_windows(
+    calculator: &mut RollingAverageCalculator,
+    saved: &[(TimeWindow, WindowState)],
+) -> Vec<TimeWindow> {
+    let windows = calculator.time_windows().to_vec();
+    windows
+        .into_iter()
+        .filter(|window| {
+            let width = calculator.config().bucket_width(window.duration);
+            let source = saved
+                .iter()
+                .filter(|(saved, state)| saved.duration >= window.duration && state.bucket_width() == width)
+                .min_by_key(|(saved, _)| saved.duration);
+            match source {
+                Some((_, state)) => !calculator.restore_window(window, state.clone()),
+                None => true,
+            }
+        })
+        .collect()
+}
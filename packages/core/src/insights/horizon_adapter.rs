@@ -14,6 +14,9 @@ use crate::insights::{
 };
 use crate::services::horizon::HorizonClient;
 
+/// Transactions fetched per poll, and so the most fee points one poll yields
+pub const TRANSACTIONS_PER_POLL: usize = 100;
+
 /// Adapter that implements FeeDataProvider for HorizonClient
 pub struct HorizonFeeDataProvider {
     client: HorizonClient,
@@ -117,8 +120,7 @@ impl HorizonFeeDataProvider {
 #[async_trait]
 impl FeeDataProvider for HorizonFeeDataProvider {
     async fn fetch_latest_fees(&self) -> ProviderResult<Vec<FeeDataPoint>> {
-        // Fetch recent transactions (last 100 by default)
-        let transactions = self.fetch_recent_transactions(100).await?;
+        let transactions = self.fetch_recent_transactions(TRANSACTIONS_PER_POLL as u32).await?;
         
         // Convert to fee data points, filtering out failed conversions
         let mut fee_data_points = Vec::new();
@@ -16,13 +16,21 @@ pub mod error;
 pub mod config;
 pub mod provider;
 pub mod horizon_adapter;
+pub mod seasonality;
+pub mod sketch;
+pub mod replay;
+pub mod snapshot;
+pub mod strategy;
 
 #[cfg(test)]
 mod tests;
 
-pub use engine::FeeInsightsEngine;
+pub use engine::{validate_fee_point, FeeInsightsEngine};
 pub use types::*;
 pub use error::InsightsError;
 pub use config::InsightsConfig;
+pub use sketch::QuantileSketch;
+pub use snapshot::EngineSnapshot;
+pub use strategy::SpikeDetector;
 pub use provider::{FeeDataProvider, ProviderMetadata};
 pub use horizon_adapter::HorizonFeeDataProvider;
\ No newline at end of file
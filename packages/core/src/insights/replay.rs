@@ -0,0 +1,79 @@
+//! Replaying historical fee data through an insights engine.
+//!
+//! Points are fed oldest first in batches spanning one poll interval, with
+//! a [`ManualClock`] pinned to the last point of each batch, so every
+//! window, extremes period and trend sees the data as it did live.
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::clock::ManualClock;
+use crate::insights::config::InsightsConfig;
+use crate::insights::engine::FeeInsightsEngine;
+use crate::insights::types::FeeDataPoint;
+
+/// A fresh engine driven by a manual clock.
+pub struct Replayer {
+    clock: ManualClock,
+    engine: FeeInsightsEngine,
+    batch: Duration,
+    failed_batches: usize,
+}
+
+impl Replayer {
+    /// Create a replayer whose clock starts at `start`.
+    pub fn new(config: InsightsConfig, start: DateTime<Utc>, batch: Duration) -> Self {
+        let clock = ManualClock::new(start);
+        let engine = FeeInsightsEngine::with_clock(config, clock.shared());
+        Self {
+            clock,
+            engine,
+            batch,
+            failed_batches: 0,
+        }
+    }
+
+    /// Feed `points`, oldest first, calling `after_batch` once each batch
+    /// has been processed.
+    pub async fn replay<F>(&mut self, points: &[FeeDataPoint], mut after_batch: F)
+    where
+        F: FnMut(&mut FeeInsightsEngine),
+    {
+        for chunk in batches(points, self.batch) {
+            if let Some(last) = chunk.last() {
+                self.clock.set(last.timestamp);
+            }
+            if let Err(err) = self.engine.process_fee_data(chunk).await {
+                tracing::debug!("Replayed batch rejected: {}", err);
+                self.failed_batches += 1;
+            }
+            after_batch(&mut self.engine);
+        }
+    }
+
+    /// Move the clock to `at` without feeding any data.
+    pub fn advance_to(&self, at: DateTime<Utc>) {
+        self.clock.set(at);
+    }
+
+    pub fn engine(&self) -> &FeeInsightsEngine {
+        &self.engine
+    }
+
+    /// Batches the engine rejected, e.g. for lack of a baseline
+    pub fn failed_batches(&self) -> usize {
+        self.failed_batches
+    }
+}
+
+/// Split points into consecutive runs spanning at most `batch` each.
+pub fn batches(points: &[FeeDataPoint], batch: Duration) -> Vec<&[FeeDataPoint]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for i in 1..=points.len() {
+        if i == points.len() || points[i].timestamp - points[start].timestamp >= batch {
+            chunks.push(&points[start..i]);
+            start = i;
+        }
+    }
+    chunks
+}
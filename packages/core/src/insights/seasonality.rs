@@ -0,0 +1,510 @@
+//! Seasonality Analysis
+//!
+//! Buckets historical fee data by hour-of-week (Monday 00:00 through
+//! Sunday 23:00 in a caller-chosen UTC offset) so recurring daily and
+//! weekly fee patterns can be charted and used to pick cheap time slots.
+//!
+//! Only fixed offsets are supported, not named zones: where daylight
+//! saving time applies, local peaks shift by an hour against the profile
+//! for part of the year.
+//!
+//! Profiles over persisted history are built from fee rollups rather than
+//! raw points, which are only kept for days: hourly rollups for whole-hour
+//! offsets, finer ones for offsets such as `+05:30`.
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::insights::{
+    types::FeeDataPoint,
+    error::InsightsError,
+    sketch::QuantileSketch,
+};
+
+/// Number of hour-of-week slots (7 days x 24 hours)
+pub const HOURS_PER_WEEK: usize = 168;
+
+const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Fee statistics for a single hour-of-week slot
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HourOfWeekStats {
+    pub day_of_week: u8,  // 0 = Monday
+    pub day_name: String,
+    pub hour: u8,
+    pub median_fee: Option<u64>,
+    pub p90_fee: Option<u64>,
+    pub transaction_count: usize,
+}
+
+/// Hour-of-week fee profile built from historical data
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SeasonalityProfile {
+    pub timezone: String,
+    pub weeks: u32,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub total_transactions: usize,
+    /// Always `HOURS_PER_WEEK` entries, ordered Monday 00:00 first
+    pub buckets: Vec<HourOfWeekStats>,
+}
+
+/// The cheapest upcoming window according to the historical pattern
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BestWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub expected_median_fee: f64,
+    pub expected_p90_fee: f64,
+    pub sample_count: usize,
+}
+
+impl SeasonalityProfile {
+    /// Build a profile from raw fee data points
+    pub fn build(
+        points: &[FeeDataPoint],
+        offset: FixedOffset,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        weeks: u32,
+    ) -> Self {
+        let mut fees_by_slot: Vec<Vec<u64>> = vec![Vec::new(); HOURS_PER_WEEK];
+
+        for point in points {
+            if point.timestamp >= from && point.timestamp < to {
+                fees_by_slot[hour_of_week(point.timestamp, offset)].push(point.fee_amount);
+            }
+        }
+
+        let buckets = fees_by_slot
+            .into_iter()
+            .enumerate()
+            .map(|(slot, mut fees)| {
+                fees.sort_unstable();
+                HourOfWeekStats {
+                    day_of_week: (slot / 24) as u8,
+                    day_name: DAY_NAMES[slot / 24].to_string(),
+                    hour: (slot % 24) as u8,
+                    median_fee: percentile_nearest_rank(&fees, 50),
+                    p90_fee: percentile_nearest_rank(&fees, 90),
+                    transaction_count: fees.len(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            timezone: format_utc_offset(offset),
+            weeks,
+            from,
+            to,
+            total_transactions: buckets.iter().map(|b| b.transaction_count).sum(),
+            buckets,
+        }
+    }
+
+    /// Build a profile from rollup buckets, given as their start and the
+    /// sketch of their fees. Each bucket must lie within one local hour, see
+    /// [`rollup_resolution`]; percentiles are then estimates within the
+    /// sketch accuracy.
+    pub fn from_rollups<'a>(
+        rollups: impl IntoIterator<Item = (DateTime<Utc>, &'a QuantileSketch)>,
+        offset: FixedOffset,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        weeks: u32,
+    ) -> Self {
+        let mut sketches: Vec<QuantileSketch> = vec![QuantileSketch::default(); HOURS_PER_WEEK];
+
+        for (bucket_start, sketch) in rollups {
+            if bucket_start >= from && bucket_start < to {
+                // Sketches from one store share the default accuracy
+                let _ = sketches[hour_of_week(bucket_start, offset)].merge(sketch);
+            }
+        }
+
+        let buckets = sketches
+            .iter()
+            .enumerate()
+            .map(|(slot, sketch)| HourOfWeekStats {
+                day_of_week: (slot / 24) as u8,
+                day_name: DAY_NAMES[slot / 24].to_string(),
+                hour: (slot % 24) as u8,
+                median_fee: sketch.quantile(50.0),
+                p90_fee: sketch.quantile(90.0),
+                transaction_count: sketch.count() as usize,
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            timezone: format_utc_offset(offset),
+            weeks,
+            from,
+            to,
+            total_transactions: buckets.iter().map(|b| b.transaction_count).sum(),
+            buckets,
+        }
+    }
+
+    /// Get the bucket covering the given instant
+    pub fn bucket_at(&self, at: DateTime<Utc>) -> &HourOfWeekStats {
+        &self.buckets[hour_of_week(at, self.offset())]
+    }
+
+    /// Historical median fee for the hour-of-week slot covering `at`, or
+    /// `None` when the slot has fewer than `min_samples` data points
+    pub fn baseline_at(&self, at: DateTime<Utc>, min_samples: usize) -> Option<f64> {
+        let bucket = self.bucket_at(at);
+        if bucket.transaction_count < min_samples.max(1) {
+            return None;
+        }
+        bucket.median_fee.map(|fee| fee as f64)
+    }
+
+    /// Find the cheapest contiguous window of `window_hours` hours starting
+    /// within the next 24 hours, ranked by historical median fee.
+    ///
+    /// Candidate windows start on local hour boundaries. Windows touching a
+    /// slot without any historical samples are skipped; returns `None` when
+    /// no candidate has full coverage.
+    pub fn best_window(&self, now: DateTime<Utc>, window_hours: usize) -> Option<BestWindow> {
+        if window_hours == 0 || window_hours > 24 {
+            return None;
+        }
+
+        let offset = self.offset();
+        let local_now = now.with_timezone(&offset);
+        let hour_start = offset
+            .from_local_datetime(&local_now.date_naive().and_hms_opt(local_now.hour(), 0, 0)?)
+            .single()?
+            .with_timezone(&Utc);
+        let first_start = if hour_start == now {
+            hour_start
+        } else {
+            hour_start + Duration::hours(1)
+        };
+
+        let mut best: Option<BestWindow> = None;
+
+        for candidate in 0..=(24 - window_hours) {
+            let start = first_start + Duration::hours(candidate as i64);
+            let slots: Vec<&HourOfWeekStats> = (0..window_hours)
+                .map(|h| self.bucket_at(start + Duration::hours(h as i64)))
+                .collect();
+
+            if slots.iter().any(|s| s.transaction_count == 0) {
+                continue;
+            }
+
+            let expected_median_fee = slots.iter()
+                .filter_map(|s| s.median_fee)
+                .sum::<u64>() as f64 / window_hours as f64;
+            let expected_p90_fee = slots.iter()
+                .filter_map(|s| s.p90_fee)
+                .sum::<u64>() as f64 / window_hours as f64;
+
+            let is_better = best.as_ref()
+                .map(|b| expected_median_fee < b.expected_median_fee)
+                .unwrap_or(true);
+
+            if is_better {
+                best = Some(BestWindow {
+                    start,
+                    end: start + Duration::hours(window_hours as i64),
+                    expected_median_fee,
+                    expected_p90_fee,
+                    sample_count: slots.iter().map(|s| s.transaction_count).sum(),
+                });
+            }
+        }
+
+        best
+    }
+
+    fn offset(&self) -> FixedOffset {
+        parse_utc_offset(&self.timezone).unwrap_or_else(|_| utc_offset())
+    }
+}
+
+/// Hour-of-week slot index (0..168) for a timestamp in the given offset
+pub fn hour_of_week(timestamp: DateTime<Utc>, offset: FixedOffset) -> usize {
+    let local = timestamp.with_timezone(&offset);
+    local.weekday().num_days_from_monday() as usize * 24 + local.hour() as usize
+}
+
+/// Start of the hour-of-week slot containing `timestamp`
+pub fn slot_start(timestamp: DateTime<Utc>, offset: FixedOffset) -> DateTime<Utc> {
+    let local = timestamp.with_timezone(&offset);
+    let into_slot = Duration::minutes(local.minute() as i64)
+        + Duration::seconds(local.second() as i64)
+        + Duration::nanoseconds(local.nanosecond() as i64);
+    timestamp - into_slot
+}
+
+/// Parse a timezone given as `UTC`, `Z` or a fixed offset such as `+02:00`,
+/// `-0530` or `+9`.
+pub fn parse_utc_offset(value: &str) -> Result<FixedOffset, InsightsError> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() || trimmed.eq_ignore_ascii_case("utc") || trimmed == "Z" {
+        return Ok(utc_offset());
+    }
+
+    let invalid = || InsightsError::config_error(format!(
+        "Invalid timezone '{}': expected UTC or a fixed offset like +02:00 (named zones are not supported)",
+        value
+    ));
+
+    let sign = match trimmed.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return Err(invalid()),
+    };
+    let digits: String = trimmed[1..].chars().filter(|c| *c != ':').collect();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.parse::<i32>().map_err(|_| invalid())?, 0),
+        3 | 4 => {
+            let split = digits.len() - 2;
+            (
+                digits[..split].parse::<i32>().map_err(|_| invalid())?,
+                digits[split..].parse::<i32>().map_err(|_| invalid())?,
+            )
+        }
+        _ => return Err(invalid()),
+    };
+
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+/// Coarsest rollup resolution whose buckets each lie within one local hour
+/// at `offset`
+pub fn rollup_resolution(offset: FixedOffset) -> &'static str {
+    match offset.local_minus_utc() % 3600 {
+        0 => "1h",
+        seconds if seconds % 900 == 0 => "15m",
+        _ => "1m",
+    }
+}
+
+/// Render an offset the way `parse_utc_offset` accepts it back
+pub fn format_utc_offset(offset: FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    if seconds == 0 {
+        return "UTC".to_string();
+    }
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// The UTC offset, used when no timezone is requested
+pub fn utc_offset() -> FixedOffset {
+    FixedOffset::east_opt(0).expect("zero offset is always valid")
+}
+
+fn percentile_nearest_rank(sorted: &[u64], percentile: usize) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((percentile * sorted.len()).saturating_add(99) / 100).max(1);
+    Some(sorted[rank - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_at(timestamp: DateTime<Utc>, fee_amount: u64) -> FeeDataPoint {
+        FeeDataPoint {
+            fee_amount,
+            timestamp,
+            transaction_hash: format!("hash_{}_{}", fee_amount, timestamp.timestamp()),
+            ledger_sequence: 1,
+        }
+    }
+
+    // 2024-01-01 is a Monday
+    fn monday() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn hour_of_week_starts_on_monday_midnight() {
+        assert_eq!(hour_of_week(monday(), utc_offset()), 0);
+        assert_eq!(hour_of_week(monday() + Duration::hours(30), utc_offset()), 30);
+        assert_eq!(hour_of_week(monday() - Duration::hours(1), utc_offset()), 167);
+    }
+
+    #[test]
+    fn hour_of_week_respects_offset() {
+        let plus_two = parse_utc_offset("+02:00").unwrap();
+        // Monday 00:00 UTC is Monday 02:00 at +02:00
+        assert_eq!(hour_of_week(monday(), plus_two), 2);
+    }
+
+    #[test]
+    fn slot_start_truncates_to_the_local_hour() {
+        let at = monday() + Duration::minutes(135) + Duration::seconds(7);
+        assert_eq!(slot_start(at, utc_offset()), monday() + Duration::hours(2));
+        let india = parse_utc_offset("+05:30").unwrap();
+        assert_eq!(slot_start(at, india), monday() + Duration::minutes(90));
+    }
+
+    #[test]
+    fn parse_utc_offset_accepts_common_forms() {
+        assert_eq!(parse_utc_offset("UTC").unwrap().local_minus_utc(), 0);
+        assert_eq!(parse_utc_offset("+02:00").unwrap().local_minus_utc(), 7200);
+        assert_eq!(parse_utc_offset("-0530").unwrap().local_minus_utc(), -19800);
+        assert_eq!(parse_utc_offset("+9").unwrap().local_minus_utc(), 32400);
+        assert!(parse_utc_offset("Europe/Berlin").is_err());
+        assert!(parse_utc_offset("+25:00").is_err());
+    }
+
+    #[test]
+    fn rollup_profile_matches_raw_profile_within_accuracy() {
+        let points: Vec<FeeDataPoint> = (0..48u64)
+            .map(|i| point_at(monday() + Duration::minutes(i as i64 * 5), 100 + i * 10))
+            .collect();
+        let from = monday() - Duration::weeks(1);
+        let to = monday() + Duration::weeks(1);
+
+        // Hourly rollups of the same points
+        let rollups: Vec<(DateTime<Utc>, QuantileSketch)> = points
+            .chunks(12)
+            .map(|hour| (hour[0].timestamp, QuantileSketch::from_values(hour.iter().map(|p| p.fee_amount))))
+            .collect();
+        let from_rollups = SeasonalityProfile::from_rollups(
+            rollups.iter().map(|(start, sketch)| (*start, sketch)),
+            utc_offset(),
+            from,
+            to,
+            2,
+        );
+        let raw = SeasonalityProfile::build(&points, utc_offset(), from, to, 2);
+
+        assert_eq!(from_rollups.total_transactions, 48);
+        for (rolled, exact) in from_rollups.buckets.iter().zip(&raw.buckets) {
+            assert_eq!(rolled.transaction_count, exact.transaction_count);
+            if let (Some(estimate), Some(exact)) = (rolled.median_fee, exact.median_fee) {
+                assert!(estimate.abs_diff(exact) <= exact / 50, "{} vs {}", estimate, exact);
+            }
+        }
+    }
+
+    #[test]
+    fn rollup_resolution_keeps_buckets_within_a_local_hour() {
+        assert_eq!(rollup_resolution(utc_offset()), "1h");
+        assert_eq!(rollup_resolution(parse_utc_offset("-05:00").unwrap()), "1h");
+        assert_eq!(rollup_resolution(parse_utc_offset("+05:30").unwrap()), "15m");
+        assert_eq!(rollup_resolution(parse_utc_offset("+05:45").unwrap()), "15m");
+        assert_eq!(rollup_resolution(parse_utc_offset("+05:07").unwrap()), "1m");
+    }
+
+    #[test]
+    fn format_utc_offset_roundtrips() {
+        for value in ["UTC", "+02:00", "-05:30"] {
+            let offset = parse_utc_offset(value).unwrap();
+            assert_eq!(format_utc_offset(offset), value);
+        }
+    }
+
+    #[test]
+    fn profile_computes_median_p90_and_count_per_slot() {
+        let points: Vec<FeeDataPoint> = (1..=10u64)
+            .map(|i| point_at(monday() + Duration::minutes(i as i64), i * 100))
+            .collect();
+
+        let profile = SeasonalityProfile::build(
+            &points,
+            utc_offset(),
+            monday() - Duration::weeks(1),
+            monday() + Duration::weeks(1),
+            2,
+        );
+
+        assert_eq!(profile.buckets.len(), HOURS_PER_WEEK);
+        assert_eq!(profile.total_transactions, 10);
+        let slot = &profile.buckets[0];
+        assert_eq!(slot.day_name, "Mon");
+        assert_eq!(slot.transaction_count, 10);
+        assert_eq!(slot.median_fee, Some(500));
+        assert_eq!(slot.p90_fee, Some(900));
+        assert_eq!(profile.buckets[1].transaction_count, 0);
+        assert_eq!(profile.buckets[1].median_fee, None);
+    }
+
+    #[test]
+    fn profile_ignores_points_outside_range() {
+        let points = vec![
+            point_at(monday() - Duration::weeks(3), 100),
+            point_at(monday(), 200),
+        ];
+        let profile = SeasonalityProfile::build(
+            &points,
+            utc_offset(),
+            monday() - Duration::weeks(1),
+            monday() + Duration::hours(1),
+            1,
+        );
+        assert_eq!(profile.total_transactions, 1);
+    }
+
+    #[test]
+    fn baseline_at_requires_min_samples() {
+        let points: Vec<FeeDataPoint> = (1..=3u64)
+            .map(|i| point_at(monday() + Duration::minutes(i as i64), i * 100))
+            .collect();
+        let profile = SeasonalityProfile::build(
+            &points,
+            utc_offset(),
+            monday(),
+            monday() + Duration::weeks(1),
+            1,
+        );
+
+        let next_week = monday() + Duration::weeks(1) + Duration::minutes(10);
+        assert_eq!(profile.baseline_at(next_week, 3), Some(200.0));
+        assert_eq!(profile.baseline_at(next_week, 4), None);
+        assert_eq!(profile.baseline_at(next_week + Duration::hours(1), 1), None);
+    }
+
+    #[test]
+    fn best_window_picks_cheapest_covered_slot() {
+        // Every hour of Monday has data; 03:00 is the cheapest
+        let points: Vec<FeeDataPoint> = (0..24)
+            .map(|h| point_at(monday() + Duration::hours(h), if h == 3 { 100 } else { 500 }))
+            .collect();
+        let profile = SeasonalityProfile::build(
+            &points,
+            utc_offset(),
+            monday(),
+            monday() + Duration::weeks(1),
+            1,
+        );
+
+        let now = monday() - Duration::weeks(1) + Duration::minutes(30);
+        let best = profile.best_window(now, 1).unwrap();
+
+        assert_eq!(best.start, monday() - Duration::weeks(1) + Duration::hours(3));
+        assert_eq!(best.expected_median_fee, 100.0);
+    }
+
+    #[test]
+    fn best_window_returns_none_without_history() {
+        let profile = SeasonalityProfile::build(
+            &[],
+            utc_offset(),
+            monday(),
+            monday() + Duration::weeks(1),
+            1,
+        );
+        assert!(profile.best_window(monday(), 1).is_none());
+    }
+}
@@ -0,0 +1,299 @@
+//! Quantile Sketch
+//!
+//! A DDSketch-style streaming quantile estimator. Values are counted in
+//! logarithmically sized bins so any reported percentile is within a fixed
+//! relative error of the true value. Sketches with the same accuracy can be
+//! merged, and because bins are plain counts a value can also be removed
+//! again, which lets rolling windows evict old points without a rebuild.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::insights::{
+    types::FeePercentiles,
+    error::InsightsError,
+};
+
+/// Default relative accuracy of reported percentiles (1%)
+pub const DEFAULT_RELATIVE_ACCURACY: f64 = 0.01;
+
+/// Mergeable quantile sketch over fee amounts in stroops
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantileSketch {
+    relative_accuracy: f64,
+    bins: BTreeMap<i32, u64>,
+    zero_count: u64,
+    count: u64,
+    sum: u64,
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        Self::new(DEFAULT_RELATIVE_ACCURACY)
+    }
+}
+
+impl QuantileSketch {
+    /// Create an empty sketch; `relative_accuracy` must be in (0, 1)
+    pub fn new(relative_accuracy: f64) -> Self {
+        Self {
+            relative_accuracy: relative_accuracy.clamp(1e-4, 0.5),
+            bins: BTreeMap::new(),
+            zero_count: 0,
+            count: 0,
+            sum: 0,
+        }
+    }
+
+    /// Build a sketch from a sequence of fee amounts
+    pub fn from_values(values: impl IntoIterator<Item = u64>) -> Self {
+        let mut sketch = Self::default();
+        for value in values {
+            sketch.insert(value);
+        }
+        sketch
+    }
+
+    /// Record a fee amount
+    pub fn insert(&mut self, value: u64) {
+        match self.key(value) {
+            Some(key) => *self.bins.entry(key).or_insert(0) += 1,
+            None => self.zero_count += 1,
+        }
+        self.count += 1;
+        self.sum = self.sum.saturating_add(value);
+    }
+
+    /// Remove a previously inserted fee amount. Returns `false` and leaves
+    /// the sketch untouched when no matching value was recorded.
+    pub fn remove(&mut self, value: u64) -> bool {
+        let removed = match self.key(value) {
+            Some(key) => match self.bins.get_mut(&key) {
+                Some(bin) => {
+                    *bin -= 1;
+                    if *bin == 0 {
+                        self.bins.remove(&key);
+                    }
+                    true
+                }
+                None => false,
+            },
+            None if self.zero_count > 0 => {
+                self.zero_count -= 1;
+                true
+            }
+            None => false,
+        };
+
+        if removed {
+            self.count -= 1;
+            self.sum = self.sum.saturating_sub(value);
+        }
+        removed
+    }
+
+    /// Merge another sketch into this one
+    pub fn merge(&mut self, other: &QuantileSketch) -> Result<(), InsightsError> {
+        if (self.relative_accuracy - other.relative_accuracy).abs() > f64::EPSILON {
+            return Err(InsightsError::calculation_error(format!(
+                "Cannot merge sketches with relative accuracy {} and {}",
+                self.relative_accuracy, other.relative_accuracy
+            )));
+        }
+
+        for (key, count) in &other.bins {
+            *self.bins.entry(*key).or_insert(0) += count;
+        }
+        self.zero_count += other.zero_count;
+        self.count += other.count;
+        self.sum = self.sum.saturating_add(other.sum);
+        Ok(())
+    }
+
+    /// Remove every value recorded in `other`, which must have been merged
+    /// into or inserted into this sketch before
+    pub fn subtract(&mut self, other: &QuantileSketch) -> Result<(), InsightsError> {
+        if (self.relative_accuracy - other.relative_accuracy).abs() > f64::EPSILON {
+            return Err(InsightsError::calculation_error(format!(
+                "Cannot subtract sketches with relative accuracy {} and {}",
+                self.relative_accuracy, other.relative_accuracy
+            )));
+        }
+
+        for (key, count) in &other.bins {
+            if let Some(bin) = self.bins.get_mut(key) {
+                *bin = bin.saturating_sub(*count);
+                if *bin == 0 {
+                    self.bins.remove(key);
+                }
+            }
+        }
+        self.zero_count = self.zero_count.saturating_sub(other.zero_count);
+        self.count = self.count.saturating_sub(other.count);
+        self.sum = self.sum.saturating_sub(other.sum);
+        Ok(())
+    }
+
+    /// Number of recorded values
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Whether no values have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Exact sum of the recorded values
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    /// Exact mean of the recorded values
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+
+    /// Estimate the value at `percentile` (0-100) using nearest rank
+    pub fn quantile(&self, percentile: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let percentile = percentile.clamp(0.0, 100.0);
+        let rank = ((percentile / 100.0 * self.count as f64).ceil() as u64).max(1);
+
+        let mut seen = self.zero_count;
+        if seen >= rank {
+            return Some(0);
+        }
+        for (key, count) in &self.bins {
+            seen += count;
+            if seen >= rank {
+                return Some(self.value_of(*key));
+            }
+        }
+
+        // Unreachable while counts are consistent; fall back to the top bin
+        self.bins.keys().next_back().map(|key| self.value_of(*key))
+    }
+
+    /// Standard p10 through p99 summary, or `None` when empty
+    pub fn percentiles(&self) -> Option<FeePercentiles> {
+        Some(FeePercentiles {
+            p10: self.quantile(10.0)?,
+            p25: self.quantile(25.0)?,
+            p50: self.quantile(50.0)?,
+            p75: self.quantile(75.0)?,
+            p90: self.quantile(90.0)?,
+            p95: self.quantile(95.0)?,
+            p99: self.quantile(99.0)?,
+        })
+    }
+
+    fn gamma(&self) -> f64 {
+        (1.0 + self.relative_accuracy) / (1.0 - self.relative_accuracy)
+    }
+
+    /// Bin index for a value; zero is tracked separately
+    fn key(&self, value: u64) -> Option<i32> {
+        if value == 0 {
+            return None;
+        }
+        Some(((value as f64).ln() / self.gamma().ln()).ceil() as i32)
+    }
+
+    /// Representative value of a bin, within the relative accuracy of
+    /// every value counted in it
+    fn value_of(&self, key: i32) -> u64 {
+        let gamma = self.gamma();
+        (2.0 * gamma.powi(key) / (gamma + 1.0)).round() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exact_percentile(sorted: &[u64], percentile: f64) -> u64 {
+        let rank = ((percentile / 100.0 * sorted.len() as f64).ceil() as usize).max(1);
+        sorted[rank - 1]
+    }
+
+    fn assert_within_accuracy(estimate: u64, exact: u64) {
+        let error = (estimate as f64 - exact as f64).abs() / exact as f64;
+        assert!(
+            error <= DEFAULT_RELATIVE_ACCURACY + 1e-9 || estimate.abs_diff(exact) <= 1,
+            "estimate {} not within accuracy of {}",
+            estimate,
+            exact
+        );
+    }
+
+    #[test]
+    fn percentiles_are_within_relative_accuracy() {
+        let values: Vec<u64> = (1..=5_000u64).map(|i| 100 + (i * 37) % 20_000).collect();
+        let sketch = QuantileSketch::from_values(values.iter().copied());
+        let mut sorted = values.clone();
+        sorted.sort_unstable();
+
+        for percentile in [10.0, 25.0, 50.0, 75.0, 90.0, 95.0, 99.0] {
+            assert_within_accuracy(
+                sketch.quantile(percentile).unwrap(),
+                exact_percentile(&sorted, percentile),
+            );
+        }
+        assert_eq!(sketch.count(), 5_000);
+    }
+
+    #[test]
+    fn remove_reverses_insert() {
+        let mut sketch = QuantileSketch::from_values([100, 200, 300]);
+        let before = sketch.clone();
+
+        sketch.insert(5_000);
+        assert!(sketch.remove(5_000));
+        assert_eq!(sketch, before);
+
+        assert!(!sketch.remove(9_999_999));
+        assert_eq!(sketch.count(), 3);
+    }
+
+    #[test]
+    fn merged_sketch_matches_sketch_of_all_values() {
+        let mut left = QuantileSketch::from_values(100..600);
+        let right = QuantileSketch::from_values(600..2_000);
+        left.merge(&right).unwrap();
+
+        assert_eq!(left, QuantileSketch::from_values(100..2_000));
+        assert!(left.merge(&QuantileSketch::new(0.05)).is_err());
+    }
+
+    #[test]
+    fn subtract_reverses_merge() {
+        let mut sketch = QuantileSketch::from_values(100..600);
+        let before = sketch.clone();
+        let other = QuantileSketch::from_values([0, 150, 20_000]);
+
+        sketch.merge(&other).unwrap();
+        sketch.subtract(&other).unwrap();
+        assert_eq!(sketch, before);
+        assert!(sketch.subtract(&QuantileSketch::new(0.05)).is_err());
+    }
+
+    #[test]
+    fn empty_sketch_has_no_percentiles() {
+        let sketch = QuantileSketch::default();
+        assert!(sketch.percentiles().is_none());
+        assert!(sketch.mean().is_none());
+    }
+
+    #[test]
+    fn sketch_round_trips_through_json() {
+        let sketch = QuantileSketch::from_values([0, 100, 150, 10_000]);
+        let json = serde_json::to_string(&sketch).unwrap();
+        let restored: QuantileSketch = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, sketch);
+        assert_eq!(restored.quantile(10.0), Some(0));
+    }
+}
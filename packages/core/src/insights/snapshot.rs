@@ -0,0 +1,58 @@
+//! Engine Snapshots
+//!
+//! A versioned, serializable copy of everything the insights engine has
+//! accumulated: rolling window aggregates, extremes periods, spike history
+//! and the last computed insights. Restoring a snapshot at startup and
+//! replaying only newer points avoids re-running a full day of history
+//! through the engine, and keeps windows longer than that intact.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::insights::{
+    types::{CurrentInsights, TimeWindow},
+    calculator::WindowState,
+    error::InsightsError,
+    tracker::ExtremesSnapshot,
+    detector::DetectorSnapshot,
+};
+
+/// Current snapshot format. Bump whenever the layout changes incompatibly;
+/// snapshots with another version are rejected and the engine starts cold.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Serialized state of a [`FeeInsightsEngine`](crate::insights::FeeInsightsEngine)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineSnapshot {
+    pub version: u32,
+    pub taken_at: DateTime<Utc>,
+    pub last_update: Option<DateTime<Utc>>,
+    /// Newest fee timestamp the engine has processed; points after this
+    /// still need to be replayed after a restore
+    pub last_point_at: Option<DateTime<Utc>>,
+    /// Bucketed aggregates of each rolling window
+    pub windows: Vec<WindowSnapshot>,
+    pub extremes: ExtremesSnapshot,
+    pub detector: DetectorSnapshot,
+    pub last_insights: Option<CurrentInsights>,
+}
+
+/// Aggregated contents of one rolling window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowSnapshot {
+    pub window: TimeWindow,
+    pub state: WindowState,
+}
+
+impl EngineSnapshot {
+    /// Fail unless this snapshot was written in the current format
+    pub fn check_version(&self) -> Result<(), InsightsError> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(InsightsError::invalid_data(format!(
+                "Unsupported engine snapshot version {} (expected {})",
+                self.version, SNAPSHOT_VERSION
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+//! Spike Detection Strategies
+//!
+//! Each strategy decides, point by point, whether a fee is anomalous and
+//! how strongly. [`CongestionDetector`](crate::insights::detector::CongestionDetector)
+//! groups consecutive anomalous points into [`FeeSpike`](crate::insights::types::FeeSpike)s.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use utoipa::ToSchema;
+
+use crate::insights::{
+    config::SpikeStrategy,
+    types::SpikeSeverity,
+};
+
+/// Outcome of scoring a single fee against a strategy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpikeScore {
+    /// Strategy-specific anomaly score (ratio, z-score, CUSUM sum, ...)
+    pub score: f64,
+    /// Whether the score crossed the strategy's spike threshold
+    pub is_spike: bool,
+}
+
+/// Score cutoffs that map a strategy score to a spike severity.
+/// Scores below `moderate` are classed as minor.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SeverityCutoffs {
+    pub moderate: f64,
+    pub major: f64,
+    pub critical: f64,
+}
+
+impl SeverityCutoffs {
+    /// Classify a strategy score
+    pub fn classify(&self, score: f64) -> SpikeSeverity {
+        match score {
+            s if s >= self.critical => SpikeSeverity::Critical,
+            s if s >= self.major => SpikeSeverity::Major,
+            s if s >= self.moderate => SpikeSeverity::Moderate,
+            _ => SpikeSeverity::Minor,
+        }
+    }
+}
+
+/// A pluggable spike detection strategy
+pub trait SpikeDetector: Send + Sync {
+    /// Short identifier recorded on every spike this strategy fires
+    fn name(&self) -> &'static str;
+
+    /// Score `fee` against the expected `baseline` and update any internal
+    /// state, such as a rolling history of recent fees
+    fn evaluate(&mut self, fee: f64, baseline: f64) -> SpikeScore;
+
+    /// Forget accumulated state
+    fn reset(&mut self);
+
+    /// Accumulated state as plain numbers, for engine snapshots
+    fn state(&self) -> Vec<f64> {
+        Vec::new()
+    }
+
+    /// Reload state previously returned by [`SpikeDetector::state`]
+    fn restore_state(&mut self, _state: &[f64]) {}
+}
+
+/// Build the detector described by a strategy configuration
+pub fn build_detector(strategy: &SpikeStrategy, threshold_multiplier: f64) -> Box<dyn SpikeDetector> {
+    match strategy {
+        SpikeStrategy::Threshold => Box::new(ThresholdDetector { multiplier: threshold_multiplier }),
+        SpikeStrategy::ZScore { window, threshold } => {
+            Box::new(ZScoreDetector::new(*window, *threshold))
+        }
+        SpikeStrategy::Mad { window, threshold } => Box::new(MadDetector::new(*window, *threshold)),
+        SpikeStrategy::Cusum { drift, threshold } => Box::new(CusumDetector {
+            drift: *drift,
+            threshold: *threshold,
+            sum: 0.0,
+        }),
+    }
+}
+
+/// Fires when `fee >= baseline * multiplier`; the score is `fee / baseline`
+#[derive(Debug, Clone)]
+pub struct ThresholdDetector {
+    multiplier: f64,
+}
+
+impl SpikeDetector for ThresholdDetector {
+    fn name(&self) -> &'static str {
+        "threshold"
+    }
+
+    fn evaluate(&mut self, fee: f64, baseline: f64) -> SpikeScore {
+        SpikeScore {
+            score: fee / baseline,
+            is_spike: fee >= baseline * self.multiplier,
+        }
+    }
+
+    fn reset(&mut self) {}
+}
+
+/// Bounded history of recent fees shared by the rolling strategies
+#[derive(Debug, Clone)]
+struct RecentFees {
+    values: VecDeque<f64>,
+    capacity: usize,
+}
+
+impl RecentFees {
+    fn new(capacity: usize) -> Self {
+        Self {
+            values: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(2),
+        }
+    }
+
+    fn push(&mut self, fee: f64) {
+        if self.values.len() >= self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(fee);
+    }
+
+    fn has_history(&self) -> bool {
+        self.values.len() >= 2
+    }
+
+    fn restore(&mut self, values: &[f64]) {
+        self.values.clear();
+        for fee in values {
+            self.push(*fee);
+        }
+    }
+}
+
+/// Fires when a fee is `threshold` standard deviations above the mean of
+/// the previous `window` fees
+#[derive(Debug, Clone)]
+pub struct ZScoreDetector {
+    history: RecentFees,
+    threshold: f64,
+}
+
+impl ZScoreDetector {
+    pub fn new(window: usize, threshold: f64) -> Self {
+        Self {
+            history: RecentFees::new(window),
+            threshold,
+        }
+    }
+}
+
+impl SpikeDetector for ZScoreDetector {
+    fn name(&self) -> &'static str {
+        "z_score"
+    }
+
+    fn evaluate(&mut self, fee: f64, _baseline: f64) -> SpikeScore {
+        let score = if self.history.has_history() {
+            let values = &self.history.values;
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+            // A flat history would divide by zero; treat one stroop as the noise floor
+            (fee - mean) / variance.sqrt().max(1.0)
+        } else {
+            0.0
+        };
+
+        self.history.push(fee);
+        SpikeScore {
+            score,
+            is_spike: score >= self.threshold,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.history.values.clear();
+    }
+
+    fn state(&self) -> Vec<f64> {
+        self.history.values.iter().copied().collect()
+    }
+
+    fn restore_state(&mut self, state: &[f64]) {
+        self.history.restore(state);
+    }
+}
+
+/// Fires when a fee's robust z-score, based on the median absolute
+/// deviation of the previous `window` fees, reaches `threshold`
+#[derive(Debug, Clone)]
+pub struct MadDetector {
+    history: RecentFees,
+    threshold: f64,
+}
+
+impl MadDetector {
+    /// Scales MAD so the score is comparable to a z-score for normal data
+    const CONSISTENCY: f64 = 0.6745;
+
+    pub fn new(window: usize, threshold: f64) -> Self {
+        Self {
+            history: RecentFees::new(window),
+            threshold,
+        }
+    }
+
+    fn median(values: &mut [f64]) -> f64 {
+        values.sort_by(|a, b| a.total_cmp(b));
+        let mid = values.len() / 2;
+        if values.len().is_multiple_of(2) {
+            (values[mid - 1] + values[mid]) / 2.0
+        } else {
+            values[mid]
+        }
+    }
+}
+
+impl SpikeDetector for MadDetector {
+    fn name(&self) -> &'static str {
+        "mad"
+    }
+
+    fn evaluate(&mut self, fee: f64, _baseline: f64) -> SpikeScore {
+        let score = if self.history.has_history() {
+            let mut values: Vec<f64> = self.history.values.iter().copied().collect();
+            let median = Self::median(&mut values);
+            let mut deviations: Vec<f64> = values.iter().map(|v| (v - median).abs()).collect();
+            let mad = Self::median(&mut deviations).max(1.0);
+            Self::CONSISTENCY * (fee - median) / mad
+        } else {
+            0.0
+        };
+
+        self.history.push(fee);
+        SpikeScore {
+            score,
+            is_spike: score >= self.threshold,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.history.values.clear();
+    }
+
+    fn state(&self) -> Vec<f64> {
+        self.history.values.iter().copied().collect()
+    }
+
+    fn restore_state(&mut self, state: &[f64]) {
+        self.history.restore(state);
+    }
+}
+
+/// One-sided CUSUM change-point detector on the relative excess over the
+/// baseline. Small excesses below `drift` decay the sum; a sustained rise
+/// accumulates until it crosses `threshold`.
+#[derive(Debug, Clone)]
+pub struct CusumDetector {
+    drift: f64,
+    threshold: f64,
+    sum: f64,
+}
+
+impl SpikeDetector for CusumDetector {
+    fn name(&self) -> &'static str {
+        "cusum"
+    }
+
+    fn evaluate(&mut self, fee: f64, baseline: f64) -> SpikeScore {
+        let excess = fee / baseline - 1.0;
+        self.sum = (self.sum + excess - self.drift).max(0.0);
+        SpikeScore {
+            score: self.sum,
+            is_spike: self.sum >= self.threshold,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.sum = 0.0;
+    }
+
+    fn state(&self) -> Vec<f64> {
+        vec![self.sum]
+    }
+
+    fn restore_state(&mut self, state: &[f64]) {
+        self.sum = state.first().copied().unwrap_or(0.0).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_scores_ratio_to_baseline() {
+        let mut detector = build_detector(&SpikeStrategy::Threshold, 2.0);
+        assert_eq!(detector.evaluate(150.0, 100.0), SpikeScore { score: 1.5, is_spike: false });
+        assert_eq!(detector.evaluate(300.0, 100.0), SpikeScore { score: 3.0, is_spike: true });
+        assert_eq!(detector.name(), "threshold");
+    }
+
+    #[test]
+    fn z_score_needs_history_then_flags_outliers() {
+        let mut detector = build_detector(&SpikeStrategy::ZScore { window: 10, threshold: 3.0 }, 2.0);
+        assert!(!detector.evaluate(100.0, 100.0).is_spike);
+        for fee in [110.0, 90.0, 105.0, 95.0, 100.0] {
+            assert!(!detector.evaluate(fee, 100.0).is_spike);
+        }
+        let outlier = detector.evaluate(200.0, 100.0);
+        assert!(outlier.is_spike, "score {}", outlier.score);
+    }
+
+    #[test]
+    fn mad_ignores_a_single_earlier_outlier() {
+        let mut detector = build_detector(&SpikeStrategy::Mad { window: 10, threshold: 3.5 }, 2.0);
+        for fee in [100.0, 102.0, 98.0, 5_000.0, 101.0, 99.0] {
+            detector.evaluate(fee, 100.0);
+        }
+        // The earlier 5000 outlier barely moves the median, so 130 still stands out
+        assert!(detector.evaluate(130.0, 100.0).is_spike);
+        assert!(!detector.evaluate(101.0, 100.0).is_spike);
+    }
+
+    #[test]
+    fn cusum_fires_on_sustained_rise_and_decays() {
+        let mut detector = build_detector(&SpikeStrategy::Cusum { drift: 0.2, threshold: 1.0 }, 2.0);
+        assert!(!detector.evaluate(150.0, 100.0).is_spike); // sum 0.3
+        assert!(!detector.evaluate(150.0, 100.0).is_spike); // sum 0.6
+        assert!(!detector.evaluate(150.0, 100.0).is_spike); // sum 0.9
+        assert!(detector.evaluate(150.0, 100.0).is_spike); // sum 1.2
+        assert!(detector.evaluate(50.0, 100.0).score < 0.6);
+
+        detector.reset();
+        assert_eq!(detector.evaluate(100.0, 100.0).score, 0.0);
+    }
+
+    #[test]
+    fn rolling_state_survives_restore() {
+        let strategy = SpikeStrategy::ZScore { window: 10, threshold: 3.0 };
+        let mut original = build_detector(&strategy, 2.0);
+        for fee in [110.0, 90.0, 105.0, 95.0, 100.0] {
+            original.evaluate(fee, 100.0);
+        }
+
+        let mut restored = build_detector(&strategy, 2.0);
+        restored.restore_state(&original.state());
+        assert_eq!(restored.evaluate(200.0, 100.0), original.evaluate(200.0, 100.0));
+
+        let mut cusum = build_detector(&SpikeStrategy::Cusum { drift: 0.2, threshold: 1.0 }, 2.0);
+        cusum.restore_state(&[0.9]);
+        assert!(cusum.evaluate(150.0, 100.0).is_spike);
+    }
+
+    #[test]
+    fn severity_cutoffs_classify_scores() {
+        let cutoffs = SeverityCutoffs { moderate: 3.0, major: 5.0, critical: 10.0 };
+        assert_eq!(cutoffs.classify(1.0), SpikeSeverity::Minor);
+        assert_eq!(cutoffs.classify(3.0), SpikeSeverity::Moderate);
+        assert_eq!(cutoffs.classify(9.9), SpikeSeverity::Major);
+        assert_eq!(cutoffs.classify(10.0), SpikeSeverity::Critical);
+    }
+}
@@ -119,9 +119,9 @@ mod tests {
         let averages = calculator.calculate_averages().unwrap();
         
         // Should calculate correct average: (100 + 200) / 2 = 150
-        assert_eq!(averages.short_term.value, 150.0);
-        assert_eq!(averages.short_term.sample_count, 2);
-        assert!(!averages.short_term.is_partial);
+        assert_eq!(averages.get("short_term").unwrap().value, 150.0);
+        assert_eq!(averages.get("short_term").unwrap().sample_count, 2);
+        assert!(!averages.get("short_term").unwrap().is_partial);
     }
 
     #[test]
@@ -165,8 +165,8 @@ mod tests {
         let averages = calculator.calculate_averages().unwrap();
         
         // Should be marked as partial
-        assert!(averages.short_term.is_partial);
-        assert_eq!(averages.short_term.sample_count, 2);
+        assert!(averages.get("short_term").unwrap().is_partial);
+        assert_eq!(averages.get("short_term").unwrap().sample_count, 2);
     }
 
     #[test]
@@ -213,16 +213,93 @@ mod tests {
         let averages = calculator.calculate_averages().unwrap();
         
         // Should only include the recent data point
-        assert_eq!(averages.short_term.value, 200.0);
-        assert_eq!(averages.short_term.sample_count, 1);
+        assert_eq!(averages.get("short_term").unwrap().value, 200.0);
+        assert_eq!(averages.get("short_term").unwrap().sample_count, 1);
     }
 
     #[test]
-    fn test_circular_buffer_capacity_limit() {
+    fn test_windows_expire_whole_buckets() {
+        use crate::clock::ManualClock;
+        use chrono::TimeZone;
+        
+        // At most 4 buckets: the 1h window is split into 15 minute buckets
+        let config = AverageConfig {
+            max_buckets: 4,
+            ..AverageConfig::default()
+        };
+        let window = TimeWindow::from_spec("1h", 1).unwrap();
+        assert_eq!(config.bucket_width(window.duration), Duration::minutes(15));
+        
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let clock = ManualClock::new(start);
+        let mut calculator = RollingAverageCalculator::with_clock(config, vec![window.clone()], clock.shared());
+        let point = |minutes: i64, fee_amount: u64| FeeDataPoint {
+            fee_amount,
+            timestamp: start + Duration::minutes(minutes),
+            transaction_hash: format!("tx-{}", minutes),
+            ledger_sequence: 1,
+        };
+        for (minutes, fee) in [(-50, 100), (-40, 200), (-5, 300)] {
+            calculator.add_data_point(point(minutes, fee));
+        }
+        assert_eq!(calculator.get_sample_count(&window), 3);
+        
+        // The 11:00 bucket ends as the window starts at 11:15
+        clock.advance(Duration::minutes(15));
+        calculator.add_data_point(point(15, 400));
+        let average = calculator.get_average_for_window(&window).unwrap();
+        assert_eq!(average.sample_count, 3);
+        assert_eq!(average.value, 300.0);
+        
+        // The 11:20 fee is older than the window now, but its bucket only
+        // ends at 11:30
+        clock.advance(Duration::minutes(10));
+        calculator.add_data_point(point(25, 500));
+        assert_eq!(calculator.get_sample_count(&window), 4);
+        
+        clock.advance(Duration::minutes(5));
+        calculator.add_data_point(point(30, 600));
+        let average = calculator.get_average_for_window(&window).unwrap();
+        assert_eq!(average.sample_count, 4);
+        assert_eq!(average.value, 450.0);
+    }
+
+    #[test]
+    fn test_windows_bucket_by_the_finest_rollup_resolution_that_fits() {
         let config = AverageConfig {
-            max_buffer_size: 3, // Small buffer for testing
-            min_samples_for_calculation: 1,
+            polling_interval: Duration::minutes(1),
+            ..AverageConfig::default()
         };
+        assert_eq!(config.bucket_resolution(Duration::minutes(5)), "1m");
+        assert_eq!(config.bucket_resolution(Duration::hours(1)), "1m");
+        assert_eq!(config.bucket_resolution(Duration::hours(24)), "5m");
+        assert_eq!(config.bucket_resolution(Duration::days(7)), "15m");
+        assert_eq!(config.bucket_resolution(Duration::days(30)), "1h");
+        assert_eq!(config.bucket_resolution(Duration::days(365)), "1d");
+        
+        // A week of one point a minute is counted in full from 672 buckets
+        let mut calculator = RollingAverageCalculator::new(
+            config,
+            vec![TimeWindow::from_spec("7d", 1).unwrap()],
+        );
+        let now = Utc::now();
+        let count = 7 * 24 * 60;
+        for i in 0..count {
+            calculator.add_data_point(FeeDataPoint {
+                fee_amount: 100,
+                timestamp: now - Duration::minutes(count - 1 - i),
+                transaction_hash: format!("tx-{}", i),
+                ledger_sequence: i as u64,
+            });
+        }
+        let averages = calculator.calculate_averages().unwrap();
+        assert_eq!(averages.get("7d").unwrap().sample_count, count as usize);
+        assert_eq!(averages.get("7d").unwrap().value, 100.0);
+    }
+
+    #[test]
+    fn test_rolling_average_empty_dataset() {
+        let config = AverageConfig::default();
         let time_windows = vec![
             TimeWindow {
                 name: "short_term".to_string(),
@@ -241,58 +318,200 @@ mod tests {
             },
         ];
         
-        let mut calculator = RollingAverageCalculator::new(config, time_windows);
+        let calculator = RollingAverageCalculator::new(config, time_windows);
+        let averages = calculator.calculate_averages().unwrap();
+        
+        // Should return zero values with appropriate metadata
+        assert_eq!(averages.get("short_term").unwrap().value, 0.0);
+        assert_eq!(averages.get("short_term").unwrap().sample_count, 0);
+        assert!(averages.get("short_term").unwrap().is_partial);
+    }
+
+    #[test]
+    fn test_rolling_average_custom_windows_keyed_by_name() {
+        let config = AverageConfig::default();
+        let time_windows = vec![
+            TimeWindow::from_spec("5m", 1).unwrap(),
+            TimeWindow::from_spec("7d", 1).unwrap(),
+        ];
         
+        let mut calculator = RollingAverageCalculator::new(config, time_windows);
         let now = Utc::now();
+        for (minutes_ago, fee) in [(2, 100), (30, 300), (3 * 24 * 60, 500)] {
+            calculator.add_data_point(FeeDataPoint {
+                fee_amount: fee,
+                timestamp: now - Duration::minutes(minutes_ago),
+                transaction_hash: format!("tx-{}", minutes_ago),
+                ledger_sequence: 1,
+            });
+        }
+        
+        let averages = calculator.calculate_averages().unwrap();
         
-        // Add 5 data points (more than buffer capacity of 3)
-        for i in 0..5 {
+        assert_eq!(averages.windows.len(), 2);
+        assert_eq!(averages.get("5m").unwrap().value, 100.0);
+        assert_eq!(averages.get("7d").unwrap().value, 300.0);
+        assert_eq!(averages.shortest().unwrap().time_window.name, "5m");
+        assert!(averages.get("short_term").is_none());
+    }
+
+    #[test]
+    fn test_rolling_percentiles_follow_bucket_expiry() {
+        use crate::clock::ManualClock;
+        use chrono::TimeZone;
+        
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let clock = ManualClock::new(start);
+        let mut calculator = RollingAverageCalculator::with_clock(
+            AverageConfig::default(),
+            vec![TimeWindow::from_spec("1h", 1).unwrap()],
+            clock.shared(),
+        );
+        
+        let fees = [(-59, 10_000u64), (-10, 100), (-5, 200)];
+        for (i, (minutes, fee)) in fees.into_iter().enumerate() {
             calculator.add_data_point(FeeDataPoint {
-                fee_amount: (i + 1) * 100,
-                timestamp: now - Duration::minutes(i as i64 * 5),
-                transaction_hash: format!("hash{}", i),
-                ledger_sequence: i + 1,
+                fee_amount: fee,
+                timestamp: start + Duration::minutes(minutes),
+                transaction_hash: format!("tx-{}", i),
+                ledger_sequence: 1,
             });
         }
         
+        clock.advance(Duration::minutes(2));
+        calculator.add_data_point(FeeDataPoint {
+            fee_amount: 300,
+            timestamp: start + Duration::minutes(2),
+            transaction_hash: "tx-3".to_string(),
+            ledger_sequence: 2,
+        });
+        
+        // The 10_000 stroop fee's bucket expired, so it must not show up as p99
         let averages = calculator.calculate_averages().unwrap();
+        let percentiles = averages.get("1h").unwrap().percentiles.unwrap();
+        assert!((297..=303).contains(&percentiles.p99));
+        assert!((99..=101).contains(&percentiles.p10));
+    }
+
+    #[test]
+    fn test_window_totals_follow_time_expiry() {
+        use crate::clock::ManualClock;
+        
+        let start = Utc::now();
+        let clock = ManualClock::new(start);
+        let mut calculator = RollingAverageCalculator::with_clock(
+            AverageConfig::default(),
+            vec![TimeWindow::from_spec("1h", 1).unwrap()],
+            clock.shared(),
+        );
+        let window = calculator.time_windows()[0].clone();
+        
+        for (i, fee) in [1_000u64, 100, 200].into_iter().enumerate() {
+            calculator.add_data_point(FeeDataPoint {
+                fee_amount: fee,
+                timestamp: start - Duration::minutes(50 - 20 * i as i64),
+                transaction_hash: format!("tx-{}", i),
+                ledger_sequence: 1,
+            });
+        }
+        assert_eq!(calculator.get_average_for_window(&window).unwrap().value, 1_300.0 / 3.0);
         
-        // Should only have 3 data points (buffer capacity)
-        assert_eq!(averages.short_term.sample_count, 3);
+        // Twenty minutes on, the 1000 stroop fee has left the window
+        clock.advance(Duration::minutes(20));
+        calculator.add_data_point(FeeDataPoint {
+            fee_amount: 300,
+            timestamp: start + Duration::minutes(20),
+            transaction_hash: "tx-3".to_string(),
+            ledger_sequence: 2,
+        });
+        let average = calculator.get_average_for_window(&window).unwrap();
+        assert_eq!(average.sample_count, 3);
+        assert_eq!(average.value, 200.0);
+        assert_eq!(calculator.get_sample_count(&window), 3);
+    }
+
+    #[test]
+    fn test_parse_and_format_duration() {
+        assert_eq!(parse_duration("5m"), Some(Duration::minutes(5)));
+        assert_eq!(parse_duration("15m"), Some(Duration::minutes(15)));
+        assert_eq!(parse_duration("30d"), Some(Duration::days(30)));
+        assert_eq!(parse_duration("2w"), Some(Duration::days(14)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        
+        for invalid in ["", "0h", "10", "h", "5x", "-5m"] {
+            assert_eq!(parse_duration(invalid), None, "{:?} should be rejected", invalid);
+        }
         
-        // Should contain the most recent 3 points: 500, 400, 300
-        // Average should be (500 + 400 + 300) / 3 = 400
-        assert_eq!(averages.short_term.value, 400.0);
+        assert_eq!(format_duration(Duration::hours(24)), "24h");
+        assert_eq!(format_duration(Duration::days(7)), "7d");
+        assert_eq!(format_duration(Duration::minutes(90)), "90m");
+        assert_eq!(format_duration(Duration::seconds(45)), "45s");
     }
 
     #[test]
-    fn test_rolling_average_empty_dataset() {
-        let config = AverageConfig::default();
-        let time_windows = vec![
-            TimeWindow {
-                name: "short_term".to_string(),
-                duration: Duration::hours(1),
-                min_samples: 1,
-            },
-            TimeWindow {
-                name: "medium_term".to_string(),
-                duration: Duration::hours(6),
-                min_samples: 1,
-            },
-            TimeWindow {
-                name: "long_term".to_string(),
-                duration: Duration::hours(24),
-                min_samples: 1,
-            },
-        ];
+    fn test_insights_config_validation() {
+        assert!(InsightsConfig::default().validate().is_ok());
         
-        let calculator = RollingAverageCalculator::new(config, time_windows);
-        let averages = calculator.calculate_averages().unwrap();
+        let config = InsightsConfig::with_window_specs(&["7d", "5m", "1h"]).unwrap();
+        let names: Vec<&str> = config.time_windows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, vec!["5m", "1h", "7d"]);
+        assert_eq!(config.baseline_window, "1h");
         
-        // Should return zero values with appropriate metadata
-        assert_eq!(averages.short_term.value, 0.0);
-        assert_eq!(averages.short_term.sample_count, 0);
-        assert!(averages.short_term.is_partial);
+        assert!(InsightsConfig::with_window_specs(&[]).is_err());
+        assert!(InsightsConfig::with_window_specs(&["1h", "1h"]).is_err());
+        assert!(InsightsConfig::with_window_specs(&["soon"]).is_err());
+        
+        let missing_baseline = InsightsConfig {
+            baseline_window: "30d".to_string(),
+            ..InsightsConfig::default()
+        };
+        assert!(missing_baseline.validate().is_err());
+    }
+    
+    #[test]
+    fn test_window_overrides_keep_the_rest_of_the_base_config() {
+        use crate::insights::config::{BaselineMode, ConfigOverrides};
+        
+        let mut base = InsightsConfig::with_window_specs(&["5m", "1h", "24h"]).unwrap();
+        base.polling_interval = Duration::seconds(30);
+        base.storage_retention = Duration::days(30);
+        base.spike_detection.congestion_window = Duration::minutes(20);
+        base.spike_detection.baseline_mode = BaselineMode::HourOfWeek { weeks: 4, min_samples: 3 };
+        
+        let overrides = ConfigOverrides {
+            windows: vec!["1h".to_string(), "7d".to_string()],
+            ..ConfigOverrides::default()
+        };
+        let config = overrides.apply(&base).unwrap();
+        let names: Vec<&str> = config.time_windows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, vec!["1h", "7d"]);
+        assert_eq!(config.baseline_window, "1h");
+        assert_eq!(config.polling_interval, Duration::seconds(30));
+        assert_eq!(config.storage_retention, Duration::days(30));
+        assert_eq!(config.spike_detection.congestion_window, Duration::minutes(20));
+        assert_eq!(config.spike_detection.baseline_mode, base.spike_detection.baseline_mode);
+        
+        // A baseline that is no longer a window moves to the middle one
+        let overrides = ConfigOverrides {
+            windows: vec!["10m".to_string(), "2h".to_string(), "2d".to_string()],
+            ..ConfigOverrides::default()
+        };
+        assert_eq!(overrides.apply(&base).unwrap().baseline_window, "2h");
+        
+        let overrides = ConfigOverrides {
+            baseline_window: Some("24h".to_string()),
+            congestion_window: Some(Duration::hours(2)),
+            ..ConfigOverrides::default()
+        };
+        let config = overrides.apply(&base).unwrap();
+        assert_eq!(config.baseline_window, "24h");
+        assert_eq!(config.spike_detection.congestion_window, Duration::hours(2));
+        
+        let overrides = ConfigOverrides {
+            congestion_window: Some(Duration::zero()),
+            ..ConfigOverrides::default()
+        };
+        assert!(overrides.apply(&base).is_err());
     }
 
     // =============================================================================
@@ -399,6 +618,115 @@ mod tests {
         assert_eq!(extremes.current_max.timestamp, now);
     }
 
+    #[test]
+    fn test_extremes_top_n_ordering() {
+        let config = ExtremesConfig { top_n: 3, ..ExtremesConfig::default() };
+        let mut tracker = ExtremesTracker::new(config);
+
+        let now = Utc::now();
+        let fee_data: Vec<FeeDataPoint> = [500u64, 100, 900, 300, 700, 200]
+            .iter()
+            .enumerate()
+            .map(|(i, fee)| FeeDataPoint {
+                fee_amount: *fee,
+                timestamp: now,
+                transaction_hash: format!("hash{}", i),
+                ledger_sequence: i as u64,
+            })
+            .collect();
+
+        tracker.update_with_fees(&fee_data).unwrap();
+        let extremes = tracker.get_current_extremes().unwrap();
+
+        let lowest: Vec<u64> = extremes.lowest.iter().map(|v| v.value).collect();
+        let highest: Vec<u64> = extremes.highest.iter().map(|v| v.value).collect();
+        assert_eq!(lowest, vec![100, 200, 300]);
+        assert_eq!(highest, vec![900, 700, 500]);
+        assert_eq!(extremes.current_min.value, 100);
+        assert_eq!(extremes.current_max.value, 900);
+    }
+
+    #[test]
+    fn test_extremes_closed_periods_are_drained_once() {
+        let mut tracker = ExtremesTracker::new(ExtremesConfig::default());
+        tracker.update_with_fees(&[FeeDataPoint {
+            fee_amount: 250,
+            timestamp: Utc::now(),
+            transaction_hash: "hash1".to_string(),
+            ledger_sequence: 1,
+        }]).unwrap();
+
+        tracker.reset_current_period().unwrap();
+
+        let closed = tracker.take_closed_periods();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].current_max.value, 250);
+        assert!(tracker.take_closed_periods().is_empty());
+        assert_eq!(tracker.historical_period_count(), 1);
+    }
+
+    #[test]
+    fn test_extremes_restore_keeps_newest_periods() {
+        let config = ExtremesConfig { historical_periods_to_keep: 2, ..ExtremesConfig::default() };
+        let mut tracker = ExtremesTracker::new(config);
+
+        let start = Utc::now() - Duration::days(10);
+        let period = |day: i64, fee: u64| {
+            let value = ExtremeValue {
+                value: fee,
+                timestamp: start + Duration::days(day),
+                transaction_hash: format!("hash{}", day),
+            };
+            FeeExtremes {
+                current_min: value.clone(),
+                current_max: value.clone(),
+                period_start: start + Duration::days(day),
+                period_end: start + Duration::days(day + 1),
+                lowest: vec![value.clone()],
+                highest: vec![value],
+            }
+        };
+
+        // Out of order, with a duplicate start
+        tracker.restore_historical_periods(vec![period(2, 300), period(0, 100), period(1, 200), period(2, 300)]);
+
+        assert_eq!(tracker.historical_period_count(), 2);
+        let history = tracker.get_historical_extremes(5);
+        assert_eq!(history[0].current_max.value, 300);
+        assert_eq!(history[1].current_max.value, 200);
+        // Restored periods are already persisted and must not be re-queued
+        assert!(tracker.take_closed_periods().is_empty());
+    }
+
+    #[test]
+    fn test_extremes_reconfigure_trims_history() {
+        let mut tracker = ExtremesTracker::new(ExtremesConfig::default());
+        let start = Utc::now() - Duration::days(10);
+        let periods = (0..4)
+            .map(|day| {
+                let value = ExtremeValue {
+                    value: 100 + day as u64,
+                    timestamp: start + Duration::days(day),
+                    transaction_hash: format!("hash{}", day),
+                };
+                FeeExtremes {
+                    current_min: value.clone(),
+                    current_max: value.clone(),
+                    period_start: start + Duration::days(day),
+                    period_end: start + Duration::days(day + 1),
+                    lowest: vec![value.clone()],
+                    highest: vec![value],
+                }
+            })
+            .collect();
+        tracker.restore_historical_periods(periods);
+
+        tracker.reconfigure(ExtremesConfig { historical_periods_to_keep: 2, ..ExtremesConfig::default() });
+
+        assert_eq!(tracker.historical_period_count(), 2);
+        assert_eq!(tracker.get_historical_extremes(5)[0].current_max.value, 103);
+    }
+
     // =============================================================================
     // UNIT TESTS - Congestion Detector
     // =============================================================================
@@ -418,8 +746,9 @@ mod tests {
             threshold_multiplier: 2.0,
             minimum_spike_duration: Duration::minutes(1),
             congestion_window: Duration::hours(1),
+            ..SpikeConfig::default()
         };
-        let detector = CongestionDetector::new(config);
+        let mut detector = CongestionDetector::new(config);
         
         let now = Utc::now();
         let baseline = 100.0;
@@ -478,8 +807,9 @@ mod tests {
             threshold_multiplier: 2.0,
             minimum_spike_duration: Duration::seconds(1), // Very short duration
             congestion_window: Duration::hours(1),
+            ..SpikeConfig::default()
         };
-        let detector = CongestionDetector::new(config);
+        let mut detector = CongestionDetector::new(config);
         
         let now = Utc::now();
         let baseline = 200.0;
@@ -506,6 +836,362 @@ mod tests {
         assert_eq!(spikes[0].baseline_fee, baseline);
     }
 
+    #[test]
+    fn test_spike_detection_with_per_point_baseline() {
+        let config = SpikeConfig {
+            minimum_spike_duration: Duration::seconds(1),
+            ..SpikeConfig::default()
+        };
+        let mut detector = CongestionDetector::new(config);
+        
+        let now = Utc::now();
+        let fee_data = vec![
+            FeeDataPoint {
+                fee_amount: 500, // Normal for a busy hour with baseline 400
+                timestamp: now - Duration::minutes(30),
+                transaction_hash: "hash1".to_string(),
+                ledger_sequence: 1,
+            },
+            FeeDataPoint {
+                fee_amount: 300, // Spike for a quiet hour with baseline 100
+                timestamp: now - Duration::minutes(20),
+                transaction_hash: "hash2".to_string(),
+                ledger_sequence: 2,
+            },
+            FeeDataPoint {
+                fee_amount: 100,
+                timestamp: now - Duration::minutes(10),
+                transaction_hash: "hash3".to_string(),
+                ledger_sequence: 3,
+            },
+        ];
+        
+        let spikes = detector
+            .detect_spikes_against(&fee_data, |point| if point.ledger_sequence == 1 { 400.0 } else { 100.0 })
+            .unwrap();
+        
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(spikes[0].peak_fee, 300);
+        assert_eq!(spikes[0].baseline_fee, 100.0);
+        assert_eq!(spikes[0].spike_ratio, 3.0);
+    }
+
+    fn fee_series(fees: &[u64]) -> Vec<FeeDataPoint> {
+        let now = Utc::now();
+        fees.iter().enumerate().map(|(i, fee)| FeeDataPoint {
+            fee_amount: *fee,
+            timestamp: now - Duration::minutes((fees.len() - i) as i64),
+            transaction_hash: format!("hash{}", i),
+            ledger_sequence: i as u64,
+        }).collect()
+    }
+
+    #[test]
+    fn test_spikes_record_strategy_and_score() {
+        let config = SpikeConfig {
+            minimum_spike_duration: Duration::zero(),
+            ..SpikeConfig::default()
+        };
+        let mut detector = CongestionDetector::new(config);
+        
+        let spikes = detector.detect_spikes(&fee_series(&[100, 400, 600, 100]), 100.0).unwrap();
+        
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(spikes[0].strategy, "threshold");
+        assert_eq!(spikes[0].score, 6.0);
+        assert_eq!(spikes[0].severity, SpikeSeverity::Major);
+    }
+
+    #[test]
+    fn test_spike_lifecycle_spans_batches() {
+        let config = SpikeConfig {
+            minimum_spike_duration: Duration::zero(),
+            ..SpikeConfig::default()
+        };
+        let mut detector = CongestionDetector::new(config);
+        let now = Utc::now();
+        let point = |fee: u64, minutes_ago: i64| FeeDataPoint {
+            fee_amount: fee,
+            timestamp: now - Duration::minutes(minutes_ago),
+            transaction_hash: format!("hash{}", minutes_ago),
+            ledger_sequence: 1,
+        };
+        
+        detector.analyze_congestion(&[point(100, 30), point(400, 20)], 100.0).unwrap();
+        let opened = detector.take_spike_events();
+        assert_eq!(opened.len(), 1);
+        assert_eq!(opened[0].kind, SpikeEventKind::Opened);
+        let id = opened[0].spike.id.clone();
+        
+        detector.analyze_congestion(&[point(700, 10)], 100.0).unwrap();
+        let updated = detector.take_spike_events();
+        assert_eq!(updated.len(), 1);
+        assert_eq!(updated[0].kind, SpikeEventKind::Updated);
+        assert_eq!(updated[0].spike.id, id);
+        assert_eq!(updated[0].spike.peak_fee, 700);
+        
+        detector.analyze_congestion(&[point(100, 5)], 100.0).unwrap();
+        let closed = detector.take_spike_events();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].kind, SpikeEventKind::Closed);
+        assert_eq!(closed[0].spike.id, id);
+        assert_eq!(closed[0].spike.end_time, Some(now - Duration::minutes(5)));
+        
+        // The same spike is tracked once, in its final state
+        let history = detector.get_historical_spikes();
+        assert_eq!(history.len(), 1);
+        assert!(!history[0].is_open());
+        assert!(detector.get_open_spike().is_none());
+    }
+
+    #[test]
+    fn test_z_score_strategy_flags_outlier_missed_by_threshold() {
+        use crate::insights::config::SpikeStrategy;
+        
+        // 180 is under 2x the baseline but far outside the recent spread
+        let fees = fee_series(&[100, 101, 99, 100, 102, 98, 100, 180, 100]);
+        
+        let mut threshold = CongestionDetector::new(SpikeConfig {
+            minimum_spike_duration: Duration::zero(),
+            ..SpikeConfig::default()
+        });
+        assert!(threshold.detect_spikes(&fees, 100.0).unwrap().is_empty());
+        
+        let mut z_score = CongestionDetector::new(SpikeConfig {
+            minimum_spike_duration: Duration::zero(),
+            strategy: SpikeStrategy::ZScore { window: 20, threshold: 3.0 },
+            ..SpikeConfig::default()
+        });
+        let spikes = z_score.detect_spikes(&fees, 100.0).unwrap();
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(spikes[0].strategy, "z_score");
+        assert_eq!(spikes[0].peak_fee, 180);
+        assert!(spikes[0].score >= 3.0);
+    }
+
+    #[test]
+    fn test_severity_cutoffs_override_and_validation() {
+        use crate::insights::{config::SpikeStrategy, strategy::SeverityCutoffs};
+        
+        let config = SpikeConfig {
+            severity_cutoffs: Some(SeverityCutoffs { moderate: 1.5, major: 2.0, critical: 2.5 }),
+            ..SpikeConfig::default()
+        };
+        let detector = CongestionDetector::new(config.clone());
+        assert_eq!(detector.classify_spike_severity(2.2), SpikeSeverity::Major);
+        assert!(config.validate().is_ok());
+        
+        let bad_window = SpikeConfig {
+            strategy: SpikeStrategy::Mad { window: 1, threshold: 3.5 },
+            ..SpikeConfig::default()
+        };
+        assert!(bad_window.validate().is_err());
+        
+        let descending = SpikeConfig {
+            severity_cutoffs: Some(SeverityCutoffs { moderate: 5.0, major: 3.0, critical: 10.0 }),
+            ..SpikeConfig::default()
+        };
+        assert!(descending.validate().is_err());
+    }
+
+    #[test]
+    fn test_cusum_spikes_are_graded_against_its_threshold() {
+        use crate::insights::config::SpikeStrategy;
+        
+        let config = SpikeConfig {
+            minimum_spike_duration: Duration::zero(),
+            strategy: SpikeStrategy::Cusum { drift: 0.2, threshold: 1.0 },
+            ..SpikeConfig::default()
+        };
+        
+        // Each fee at 3x the baseline adds 1.8 to the sum
+        let grade = |fees: &[u64]| {
+            let mut detector = CongestionDetector::new(config.clone());
+            let spikes = detector.detect_spikes(&fee_series(fees), 100.0).unwrap();
+            assert_eq!(spikes.len(), 1);
+            assert_eq!(spikes[0].strategy, "cusum");
+            spikes[0].severity.clone()
+        };
+        assert_eq!(grade(&[300]), SpikeSeverity::Minor);
+        assert_eq!(grade(&[300, 300]), SpikeSeverity::Moderate);
+        assert_eq!(grade(&[300, 300, 300]), SpikeSeverity::Major);
+        assert_eq!(grade(&[300, 300, 300, 300, 300]), SpikeSeverity::Critical);
+        
+        // The cutoffs follow the threshold the sum is compared with
+        let cutoffs = SpikeStrategy::Cusum { drift: 0.2, threshold: 5.0 }.default_severity_cutoffs();
+        assert_eq!((cutoffs.moderate, cutoffs.major, cutoffs.critical), (10.0, 20.0, 40.0));
+    }
+
+    #[test]
+    fn test_engine_uses_hour_of_week_baseline_when_available() {
+        use crate::insights::config::BaselineMode;
+        use crate::insights::seasonality::{utc_offset, SeasonalityProfile};
+        
+        let mut config = InsightsConfig::default();
+        config.spike_detection.minimum_spike_duration = Duration::seconds(1);
+        config.spike_detection.baseline_mode = BaselineMode::HourOfWeek { weeks: 1, min_samples: 1 };
+        let mut engine = FeeInsightsEngine::new(config);
+        
+        // The same hour last week regularly saw fees of 1000
+        let now = Utc::now();
+        let last_week: Vec<FeeDataPoint> = (0..6)
+            .map(|i| FeeDataPoint {
+                fee_amount: 1000,
+                timestamp: now - Duration::weeks(1) - Duration::seconds(5 - i),
+                transaction_hash: format!("old{}", i),
+                ledger_sequence: i as u64,
+            })
+            .collect();
+        engine.set_seasonal_profile(SeasonalityProfile::build(
+            &last_week,
+            utc_offset(),
+            now - Duration::weeks(1) - Duration::hours(1),
+            now,
+            1,
+        ));
+        
+        // A regular peak at 1000 is a spike against the rolling average of 400,
+        // but matches the hour-of-week median
+        let fee_data: Vec<FeeDataPoint> = [100, 100, 100, 100, 1000, 1000]
+            .iter()
+            .enumerate()
+            .map(|(i, fee)| FeeDataPoint {
+                fee_amount: *fee,
+                timestamp: now - Duration::seconds(5 - i as i64),
+                transaction_hash: format!("hash{}", i),
+                ledger_sequence: i as u64,
+            })
+            .collect();
+        
+        let mut rolling_config = InsightsConfig::default();
+        rolling_config.spike_detection.minimum_spike_duration = Duration::seconds(1);
+        let mut rolling_engine = FeeInsightsEngine::new(rolling_config);
+        let rolling = tokio_test::block_on(rolling_engine.process_fee_data(&fee_data)).unwrap();
+        assert_eq!(rolling.insights.congestion_trends.recent_spikes.len(), 1);
+        
+        let seasonal = tokio_test::block_on(engine.process_fee_data(&fee_data)).unwrap();
+        assert!(seasonal.insights.congestion_trends.recent_spikes.is_empty());
+    }
+
+    #[test]
+    fn test_seasonal_refresh_due_only_in_hour_of_week_mode() {
+        use crate::insights::config::BaselineMode;
+        
+        let engine = FeeInsightsEngine::new(InsightsConfig::default());
+        assert_eq!(engine.seasonal_refresh_due(Utc::now()), None);
+        
+        let mut config = InsightsConfig::default();
+        config.spike_detection.baseline_mode = BaselineMode::HourOfWeek { weeks: 4, min_samples: 3 };
+        let engine = FeeInsightsEngine::new(config);
+        assert_eq!(engine.seasonal_refresh_due(Utc::now()), Some(4));
+    }
+
+    // =============================================================================
+    // UNIT TESTS - Engine Snapshots
+    // =============================================================================
+
+    fn spiking_engine() -> FeeInsightsEngine {
+        let mut config = InsightsConfig::default();
+        config.spike_detection.minimum_spike_duration = Duration::zero();
+        FeeInsightsEngine::new(config)
+    }
+
+    fn points_from(fees: &[u64], start: chrono::DateTime<Utc>) -> Vec<FeeDataPoint> {
+        fees.iter()
+            .enumerate()
+            .map(|(i, fee)| FeeDataPoint {
+                fee_amount: *fee,
+                timestamp: start + Duration::seconds(i as i64),
+                transaction_hash: format!("snap_{}_{}", start.timestamp(), i),
+                ledger_sequence: i as u64,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restore_continues_open_spike() {
+        // The tracker only counts fees from its own creation onwards
+        let start = Utc::now();
+        let mut original = spiking_engine();
+        original.process_fee_data(&points_from(&[100, 100, 100, 100, 1000, 1000], start)).await.unwrap();
+        let opened = original.take_spike_events();
+        assert_eq!(opened.len(), 1);
+
+        let json = serde_json::to_string(&original.snapshot()).unwrap();
+        let mut restored = spiking_engine();
+        restored.restore_snapshot(serde_json::from_str(&json).unwrap()).unwrap();
+
+        let before = original.get_rolling_averages();
+        let after = restored.get_rolling_averages();
+        for (name, result) in &before.windows {
+            assert_eq!(after.get(name).unwrap().sample_count, result.sample_count);
+            assert_eq!(after.get(name).unwrap().value, result.value);
+        }
+        assert_eq!(restored.get_extremes().current_max.value, 1000);
+        assert_eq!(restored.get_last_update(), original.get_last_update());
+
+        // Recovery closes the spike opened before the snapshot, not a new one
+        let recovery = points_from(&[100], start + Duration::minutes(1));
+        restored.process_fee_data(&recovery).await.unwrap();
+        let events = restored.take_spike_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, SpikeEventKind::Closed);
+        assert_eq!(events[0].spike.id, opened[0].spike.id);
+    }
+
+    #[test]
+    fn test_snapshot_with_other_version_is_rejected() {
+        let mut snapshot = FeeInsightsEngine::new(InsightsConfig::default()).snapshot();
+        snapshot.version += 1;
+
+        let mut engine = FeeInsightsEngine::new(InsightsConfig::default());
+        assert!(engine.restore_snapshot(snapshot).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restores_into_different_windows() {
+        let mut original = FeeInsightsEngine::new(InsightsConfig::default());
+        original.process_fee_data(&points_from(&[100, 200, 300], Utc::now() - Duration::minutes(30))).await.unwrap();
+
+        let config = InsightsConfig::with_window_specs(&["10m", "1h", "24h", "2d"]).unwrap();
+        let mut restored = FeeInsightsEngine::new(config);
+        let uncovered = restored.restore_snapshot(original.snapshot()).unwrap();
+        
+        // No saved window is as long as two days, so that one is left to
+        // be rebuilt from rollups
+        assert_eq!(uncovered.len(), 1);
+        assert_eq!(uncovered[0].name, "2d");
+        assert_eq!(restored.get_rolling_averages().get("2d").unwrap().sample_count, 0);
+
+        let averages = restored.get_rolling_averages();
+        assert_eq!(averages.get("10m").unwrap().sample_count, 0);
+        assert_eq!(averages.get("1h").unwrap().sample_count, 3);
+        assert_eq!(averages.get("24h").unwrap().value, 200.0);
+    }
+
+    #[tokio::test]
+    async fn test_manual_clock_processes_history_as_of_its_timestamps() {
+        use crate::clock::ManualClock;
+
+        let start = Utc::now() - Duration::days(3);
+        let points = points_from(&[100, 200, 300], start);
+
+        // Against the system clock three-day-old points fall outside every
+        // window, leaving no baseline to detect spikes against
+        let mut live = FeeInsightsEngine::new(InsightsConfig::default());
+        assert!(live.process_fee_data(&points).await.is_err());
+        assert_eq!(live.get_rolling_averages().shortest().unwrap().sample_count, 0);
+
+        let clock = ManualClock::new(start + Duration::seconds(2)).shared();
+        let mut replay = FeeInsightsEngine::with_clock(InsightsConfig::default(), clock);
+        let update = replay.process_fee_data(&points).await.unwrap();
+
+        let shortest = update.insights.rolling_averages.shortest().unwrap();
+        assert_eq!(shortest.sample_count, 3);
+        assert_eq!(shortest.value, 200.0);
+        assert_eq!(update.insights.last_updated, start + Duration::seconds(2));
+    }
+
     // =============================================================================
     // UNIT TESTS - Data Validation
     // =============================================================================
@@ -676,7 +1362,7 @@ mod tests {
         
         // Should maintain accuracy with large numbers
         let expected_average = (999_999_999.0 + 999_999_998.0) / 2.0;
-        assert_eq!(averages.short_term.value, expected_average);
+        assert_eq!(averages.get("short_term").unwrap().value, expected_average);
     }
 
     #[test]
@@ -700,7 +1386,7 @@ mod tests {
     #[test]
     fn test_division_by_zero_handling() {
         let config = SpikeConfig::default();
-        let detector = CongestionDetector::new(config);
+        let mut detector = CongestionDetector::new(config);
         
         let now = Utc::now();
         let fee_data = vec![
@@ -726,7 +1412,7 @@ mod tests {
     #[test]
     fn test_decimal_precision_maintenance() {
         let config = SpikeConfig::default();
-        let detector = CongestionDetector::new(config);
+        let mut detector = CongestionDetector::new(config);
         
         let now = Utc::now();
         let baseline = 100.0;
@@ -801,8 +1487,28 @@ mod tests {
             let expected_average = total as f64 / adjusted_fee_points.len() as f64;
             
             // Should match calculated average
-            prop_assert_eq!(averages.short_term.value, expected_average);
-            prop_assert_eq!(averages.short_term.sample_count, adjusted_fee_points.len());
+            prop_assert_eq!(averages.get("short_term").unwrap().value, expected_average);
+            prop_assert_eq!(averages.get("short_term").unwrap().sample_count, adjusted_fee_points.len());
+        }
+
+        /// Every configured time window is reported in the rolling averages
+        #[test]
+        fn prop_rolling_averages_cover_configured_windows(
+            windows in prop::collection::vec(time_window_strategy(), 1..6)
+        ) {
+            // Suffix names with their index so generated names stay unique
+            let windows: Vec<TimeWindow> = windows.into_iter().enumerate().map(|(i, mut window)| {
+                window.name = format!("{}_{}", window.name, i);
+                window
+            }).collect();
+            
+            let calculator = RollingAverageCalculator::new(AverageConfig::default(), windows.clone());
+            let averages = calculator.calculate_averages().unwrap();
+            
+            prop_assert_eq!(averages.windows.len(), windows.len());
+            for window in &windows {
+                prop_assert_eq!(&averages.get(&window.name).unwrap().time_window, window);
+            }
         }
 
         /// **Feature: fee-metrics-core-calculations, Property 5: Extremes identification accuracy**
@@ -836,8 +1542,9 @@ mod tests {
                 threshold_multiplier: 1.5, // Lower threshold to catch more spikes
                 minimum_spike_duration: Duration::seconds(1),
                 congestion_window: Duration::hours(1),
+                ..SpikeConfig::default()
             };
-            let detector = CongestionDetector::new(config);
+            let mut detector = CongestionDetector::new(config);
             
             let spikes = detector.detect_spikes(&fee_points, baseline).unwrap();
             
@@ -929,7 +1636,7 @@ mod tests {
         let update = result.unwrap();
         
         // Verify insights were calculated
-        assert!(update.insights.rolling_averages.short_term.value > 0.0);
+        assert!(update.insights.rolling_averages.get("short_term").unwrap().value > 0.0);
         assert!(update.insights.extremes.current_min.value > 0);
         assert!(update.insights.extremes.current_max.value > 0);
         assert_eq!(update.data_points_processed, 4);
@@ -960,6 +1667,6 @@ mod tests {
         // Verify reset worked
         assert!(engine.get_last_update().is_none());
         let insights = engine.get_current_insights();
-        assert_eq!(insights.rolling_averages.short_term.sample_count, 0);
+        assert_eq!(insights.rolling_averages.get("short_term").unwrap().sample_count, 0);
     }
 }
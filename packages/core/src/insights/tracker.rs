@@ -1,8 +1,10 @@
 //! Extremes Tracker for min/max fee values
 
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
+use crate::clock::{system_clock, SharedClock};
 use crate::insights::{
     types::*,
     error::InsightsError,
@@ -12,22 +14,49 @@ use crate::insights::{
 /// Represents a tracking period for extremes
 #[derive(Debug, Clone)]
 struct ExtremePeriod {
-    min_value: Option<ExtremeValue>,
-    max_value: Option<ExtremeValue>,
+    /// Lowest fees seen, lowest first, at most `top_n` entries
+    lowest: Vec<ExtremeValue>,
+    /// Highest fees seen, highest first, at most `top_n` entries
+    highest: Vec<ExtremeValue>,
+    top_n: usize,
     period_start: DateTime<Utc>,
     period_end: DateTime<Utc>,
 }
 
 impl ExtremePeriod {
-    fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+    fn new(start: DateTime<Utc>, end: DateTime<Utc>, top_n: usize) -> Self {
         Self {
-            min_value: None,
-            max_value: None,
+            lowest: Vec::new(),
+            highest: Vec::new(),
+            top_n: top_n.max(1),
             period_start: start,
             period_end: end,
         }
     }
     
+    fn from_fee_extremes(extremes: FeeExtremes, top_n: usize) -> Self {
+        let mut lowest = extremes.lowest;
+        let mut highest = extremes.highest;
+        if lowest.is_empty() {
+            lowest.push(extremes.current_min);
+        }
+        if highest.is_empty() {
+            highest.push(extremes.current_max);
+        }
+        
+        Self {
+            lowest,
+            highest,
+            top_n: top_n.max(1),
+            period_start: extremes.period_start,
+            period_end: extremes.period_end,
+        }
+    }
+    
+    fn has_data(&self) -> bool {
+        !self.lowest.is_empty()
+    }
+    
     fn update_with_fee(&mut self, fee_point: &FeeDataPoint) {
         let extreme_value = ExtremeValue {
             value: fee_point.fee_amount,
@@ -35,64 +64,91 @@ impl ExtremePeriod {
             transaction_hash: fee_point.transaction_hash.clone(),
         };
         
-        // Update minimum
-        match &self.min_value {
-            None => self.min_value = Some(extreme_value.clone()),
-            Some(current_min) => {
-                if fee_point.fee_amount < current_min.value {
-                    self.min_value = Some(extreme_value.clone());
-                }
-            }
+        // The most recent fee wins ties, so a new value goes before existing equal values
+        let low_index = self.lowest.partition_point(|v| v.value < extreme_value.value);
+        if low_index < self.top_n {
+            self.lowest.insert(low_index, extreme_value.clone());
+            self.lowest.truncate(self.top_n);
         }
         
-        // Update maximum
-        match &self.max_value {
-            None => self.max_value = Some(extreme_value),
-            Some(current_max) => {
-                if fee_point.fee_amount > current_max.value {
-                    self.max_value = Some(extreme_value);
-                }
-            }
+        let high_index = self.highest.partition_point(|v| v.value > extreme_value.value);
+        if high_index < self.top_n {
+            self.highest.insert(high_index, extreme_value);
+            self.highest.truncate(self.top_n);
         }
     }
     
     fn to_fee_extremes(&self) -> Option<FeeExtremes> {
-        match (&self.min_value, &self.max_value) {
+        match (self.lowest.first(), self.highest.first()) {
             (Some(min), Some(max)) => Some(FeeExtremes {
                 current_min: min.clone(),
                 current_max: max.clone(),
                 period_start: self.period_start,
                 period_end: self.period_end,
+                lowest: self.lowest.clone(),
+                highest: self.highest.clone(),
             }),
             _ => None,
         }
     }
 }
 
+/// Serializable state of an [`ExtremesTracker`], used by engine snapshots
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtremesSnapshot {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    /// Lowest values of the current period, lowest first
+    pub lowest: Vec<ExtremeValue>,
+    /// Highest values of the current period, highest first
+    pub highest: Vec<ExtremeValue>,
+    /// Closed periods, oldest first
+    pub history: Vec<FeeExtremes>,
+}
+
 /// Tracker for minimum and maximum fee values
 pub struct ExtremesTracker {
     config: ExtremesConfig,
     current_period: ExtremePeriod,
     historical_periods: VecDeque<ExtremePeriod>,
+    /// Periods closed since the last `take_closed_periods` call
+    closed_periods: Vec<FeeExtremes>,
+    clock: SharedClock,
 }
 
 impl ExtremesTracker {
     /// Create a new extremes tracker
     pub fn new(config: ExtremesConfig) -> Self {
-        let now = Utc::now();
+        Self::with_clock(config, system_clock())
+    }
+    
+    /// Create a tracker whose periods follow `clock` rather than the system clock
+    pub fn with_clock(config: ExtremesConfig, clock: SharedClock) -> Self {
+        let now = clock.now();
         let period_start = now;
         let period_end = now + config.tracking_period;
         
         Self {
+            current_period: ExtremePeriod::new(period_start, period_end, config.top_n),
             config,
-            current_period: ExtremePeriod::new(period_start, period_end),
             historical_periods: VecDeque::new(),
+            closed_periods: Vec::new(),
+            clock,
         }
     }
     
+    /// Switch to `config`. The current period keeps its length and size;
+    /// the new ones apply from the next period.
+    pub fn reconfigure(&mut self, config: ExtremesConfig) {
+        self.config = config;
+        while self.historical_periods.len() > self.config.historical_periods_to_keep {
+            self.historical_periods.pop_front();
+        }
+    }
+
     /// Update with new fee data
     pub fn update_with_fees(&mut self, fees: &[FeeDataPoint]) -> Result<(), InsightsError> {
-        let now = Utc::now();
+        let now = self.clock.now();
         
         // Check if we need to rotate to a new period
         if now >= self.current_period.period_end {
@@ -113,20 +169,48 @@ impl ExtremesTracker {
     
     /// Rotate to a new tracking period, preserving the current period as historical
     fn rotate_period(&mut self, current_time: DateTime<Utc>) -> Result<(), InsightsError> {
-        // Move current period to historical periods
-        let completed_period = std::mem::replace(
-            &mut self.current_period,
-            ExtremePeriod::new(current_time, current_time + self.config.tracking_period)
+        let next_period = ExtremePeriod::new(
+            current_time,
+            current_time + self.config.tracking_period,
+            self.config.top_n,
         );
+        let completed_period = std::mem::replace(&mut self.current_period, next_period);
+        self.archive_period(completed_period);
+        Ok(())
+    }
+    
+    /// Move a completed period into history, queueing it for persistence
+    fn archive_period(&mut self, period: ExtremePeriod) {
+        if let Some(extremes) = period.to_fee_extremes() {
+            self.closed_periods.push(extremes);
+        }
         
-        self.historical_periods.push_back(completed_period);
+        self.historical_periods.push_back(period);
         
         // Maintain the configured number of historical periods
         while self.historical_periods.len() > self.config.historical_periods_to_keep {
             self.historical_periods.pop_front();
         }
+    }
+    
+    /// Drain periods closed since the last call, oldest first, so they can be persisted
+    pub fn take_closed_periods(&mut self) -> Vec<FeeExtremes> {
+        std::mem::take(&mut self.closed_periods)
+    }
+    
+    /// Load previously persisted periods into history, e.g. at startup.
+    /// Periods may be given in any order; only the newest are kept.
+    pub fn restore_historical_periods(&mut self, periods: Vec<FeeExtremes>) {
+        let mut restored: Vec<ExtremePeriod> = periods
+            .into_iter()
+            .map(|extremes| ExtremePeriod::from_fee_extremes(extremes, self.config.top_n))
+            .chain(self.historical_periods.drain(..))
+            .collect();
+        restored.sort_by_key(|period| period.period_start);
+        restored.dedup_by_key(|period| period.period_start);
         
-        Ok(())
+        let excess = restored.len().saturating_sub(self.config.historical_periods_to_keep);
+        self.historical_periods = restored.into_iter().skip(excess).collect();
     }
     
     /// Get current extremes
@@ -155,24 +239,16 @@ impl ExtremesTracker {
     
     /// Reset the current tracking period while preserving historical data
     pub fn reset_current_period(&mut self) -> Result<(), InsightsError> {
-        let now = Utc::now();
+        let now = self.clock.now();
+        let next_period = ExtremePeriod::new(now, now + self.config.tracking_period, self.config.top_n);
         
         // If the current period has data, preserve it as historical
-        if self.current_period.min_value.is_some() || self.current_period.max_value.is_some() {
-            let completed_period = std::mem::replace(
-                &mut self.current_period,
-                ExtremePeriod::new(now, now + self.config.tracking_period)
-            );
-            
-            self.historical_periods.push_back(completed_period);
-            
-            // Maintain the configured number of historical periods
-            while self.historical_periods.len() > self.config.historical_periods_to_keep {
-                self.historical_periods.pop_front();
-            }
+        if self.current_period.has_data() {
+            let completed_period = std::mem::replace(&mut self.current_period, next_period);
+            self.archive_period(completed_period);
         } else {
             // Just reset the current period if it has no data
-            self.current_period = ExtremePeriod::new(now, now + self.config.tracking_period);
+            self.current_period = next_period;
         }
         
         Ok(())
@@ -180,7 +256,7 @@ impl ExtremesTracker {
     
     /// Check if the current period has any data
     pub fn has_current_data(&self) -> bool {
-        self.current_period.min_value.is_some() || self.current_period.max_value.is_some()
+        self.current_period.has_data()
     }
     
     /// Get the number of historical periods stored
@@ -188,6 +264,29 @@ impl ExtremesTracker {
         self.historical_periods.len()
     }
     
+    /// Capture the current period and history
+    pub fn snapshot(&self) -> ExtremesSnapshot {
+        ExtremesSnapshot {
+            period_start: self.current_period.period_start,
+            period_end: self.current_period.period_end,
+            lowest: self.current_period.lowest.clone(),
+            highest: self.current_period.highest.clone(),
+            history: self.get_all_historical_extremes(),
+        }
+    }
+    
+    /// Replace the current period and history with a snapshot
+    pub fn restore_snapshot(&mut self, snapshot: ExtremesSnapshot) {
+        let top_n = self.config.top_n;
+        let mut current = ExtremePeriod::new(snapshot.period_start, snapshot.period_end, top_n);
+        current.lowest = snapshot.lowest.into_iter().take(top_n).collect();
+        current.highest = snapshot.highest.into_iter().take(top_n).collect();
+        self.current_period = current;
+        
+        self.historical_periods.clear();
+        self.restore_historical_periods(snapshot.history);
+    }
+    
     /// Get the current tracking period information
     pub fn get_current_period_info(&self) -> (DateTime<Utc>, DateTime<Utc>) {
         (self.current_period.period_start, self.current_period.period_end)
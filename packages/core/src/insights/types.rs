@@ -2,9 +2,14 @@
 
 use chrono::{DateTime, Utc, Duration};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use utoipa::openapi::schema::{ArrayBuilder, KnownFormat, ObjectBuilder, Schema, SchemaFormat, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{PartialSchema, ToSchema};
 
 /// A single fee data point from the blockchain
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FeeDataPoint {
     pub fee_amount: u64,
     pub timestamp: DateTime<Utc>,
@@ -13,8 +18,9 @@ pub struct FeeDataPoint {
 }
 
 /// Complete insights data structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CurrentInsights {
+    #[schema(value_type = BTreeMap<String, AverageResult>)]
     pub rolling_averages: RollingAverages,
     pub extremes: FeeExtremes,
     pub congestion_trends: CongestionTrends,
@@ -22,43 +28,185 @@ pub struct CurrentInsights {
     pub data_quality: DataQuality,
 }
 
-/// Rolling averages across different time windows
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Rolling averages keyed by configured time window name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct RollingAverages {
-    pub short_term: AverageResult,    // 1 hour
-    pub medium_term: AverageResult,   // 6 hours
-    pub long_term: AverageResult,     // 24 hours
+    pub windows: BTreeMap<String, AverageResult>,
+}
+
+impl RollingAverages {
+    /// Get the result for a window by name
+    pub fn get(&self, window_name: &str) -> Option<&AverageResult> {
+        self.windows.get(window_name)
+    }
+    
+    /// Get the result for the window with the shortest duration
+    pub fn shortest(&self) -> Option<&AverageResult> {
+        self.windows.values().min_by_key(|result| result.time_window.duration)
+    }
+    
+    /// Iterate over results ordered by window duration, shortest first
+    pub fn by_duration(&self) -> Vec<&AverageResult> {
+        let mut results: Vec<&AverageResult> = self.windows.values().collect();
+        results.sort_by_key(|result| result.time_window.duration);
+        results
+    }
+    
+    /// Value of the shortest window, or 0.0 when no windows are configured
+    pub fn current_value(&self) -> f64 {
+        self.shortest().map(|result| result.value).unwrap_or(0.0)
+    }
 }
 
 /// Result of a rolling average calculation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AverageResult {
     pub value: f64,
     pub sample_count: usize,
     pub is_partial: bool,
     pub calculated_at: DateTime<Utc>,
     pub time_window: TimeWindow,
+    /// Streaming percentiles for the window, absent when it has no samples
+    #[serde(default)]
+    pub percentiles: Option<FeePercentiles>,
+}
+
+/// Fee percentiles in stroops, estimated from a quantile sketch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct FeePercentiles {
+    pub p10: u64,
+    pub p25: u64,
+    pub p50: u64,
+    pub p75: u64,
+    pub p90: u64,
+    pub p95: u64,
+    pub p99: u64,
 }
 
 /// Time window configuration
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
 pub struct TimeWindow {
     pub name: String,
+    #[schema(value_type = DurationSchema)]
     pub duration: Duration,
     pub min_samples: usize,
 }
 
+impl TimeWindow {
+    /// Build a window from a duration string such as `5m`, `6h` or `30d`,
+    /// using the string itself as the window name
+    pub fn from_spec(spec: &str, min_samples: usize) -> Option<Self> {
+        Some(Self {
+            name: spec.trim().to_string(),
+            duration: parse_duration(spec)?,
+            min_samples,
+        })
+    }
+    
+    /// Short label for the window duration, e.g. `1h` or `7d`
+    pub fn label(&self) -> String {
+        format_duration(self.duration)
+    }
+}
+
+/// OpenAPI schema for `chrono::Duration`, which serializes as
+/// `[seconds, nanoseconds]`
+pub struct DurationSchema;
+
+impl PartialSchema for DurationSchema {
+    fn schema() -> RefOr<Schema> {
+        ArrayBuilder::new()
+            .items(
+                ObjectBuilder::new()
+                    .schema_type(Type::Integer)
+                    .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int64))),
+            )
+            .min_items(Some(2))
+            .max_items(Some(2))
+            .description(Some("Duration as `[seconds, nanoseconds]`"))
+            .into()
+    }
+}
+
+impl ToSchema for DurationSchema {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("Duration")
+    }
+}
+
+/// Parse a duration string made of `<number><unit>` parts, where unit is one
+/// of `s`, `m`, `h`, `d` or `w` (e.g. `15m`, `24h`, `1h30m`, `30d`).
+/// Returns `None` for malformed or non-positive durations.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+    
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        
+        let amount: i64 = digits.parse().ok()?;
+        digits.clear();
+        let part = match c {
+            's' => Duration::try_seconds(amount)?,
+            'm' => Duration::try_minutes(amount)?,
+            'h' => Duration::try_hours(amount)?,
+            'd' => Duration::try_days(amount)?,
+            'w' => Duration::try_weeks(amount)?,
+            _ => return None,
+        };
+        total = total.checked_add(&part)?;
+    }
+    
+    // Trailing digits without a unit are not allowed
+    if !digits.is_empty() || total <= Duration::zero() {
+        return None;
+    }
+    
+    Some(total)
+}
+
+/// Format a duration using the largest whole unit, e.g. `90m` or `7d`.
+/// A single day stays `24h`, matching the labels of the default windows.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds();
+    
+    if seconds > 86_400 && seconds % 86_400 == 0 {
+        return format!("{}d", seconds / 86_400);
+    }
+    for (size, unit) in [(3_600, "h"), (60, "m")] {
+        if seconds != 0 && seconds % size == 0 {
+            return format!("{}{}", seconds / size, unit);
+        }
+    }
+    format!("{}s", seconds)
+}
+
 /// Fee extremes (min/max) tracking
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FeeExtremes {
     pub current_min: ExtremeValue,
     pub current_max: ExtremeValue,
     pub period_start: DateTime<Utc>,
     pub period_end: DateTime<Utc>,
+    /// Lowest fees in the period, lowest first
+    #[serde(default)]
+    pub lowest: Vec<ExtremeValue>,
+    /// Highest fees in the period, highest first
+    #[serde(default)]
+    pub highest: Vec<ExtremeValue>,
 }
 
 /// An extreme fee value with metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ExtremeValue {
     pub value: u64,
     pub timestamp: DateTime<Utc>,
@@ -66,27 +214,69 @@ pub struct ExtremeValue {
 }
 
 /// Congestion trend analysis results
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CongestionTrends {
     pub current_trend: TrendIndicator,
     pub recent_spikes: Vec<FeeSpike>,
     pub trend_strength: TrendStrength,
+    #[schema(value_type = Option<DurationSchema>)]
     pub predicted_duration: Option<Duration>,
 }
 
 /// A detected fee spike
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FeeSpike {
+    /// Stable identifier shared by every lifecycle event of this spike
+    #[serde(default)]
+    pub id: String,
     pub peak_fee: u64,
     pub baseline_fee: f64,
     pub spike_ratio: f64,
     pub start_time: DateTime<Utc>,
+    #[schema(value_type = DurationSchema)]
     pub duration: Duration,
     pub severity: SpikeSeverity,
+    /// Name of the detection strategy that fired this spike
+    #[serde(default)]
+    pub strategy: String,
+    /// Highest strategy score seen during the spike
+    #[serde(default)]
+    pub score: f64,
+    /// When fees recovered; `None` while the spike is still open
+    #[serde(default)]
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+impl FeeSpike {
+    /// Identifier for a spike starting at `start_time`
+    pub fn id_for(start_time: DateTime<Utc>) -> String {
+        format!("spk_{}", start_time.timestamp_millis())
+    }
+    
+    /// Whether fees have not yet recovered from this spike
+    pub fn is_open(&self) -> bool {
+        self.end_time.is_none()
+    }
+}
+
+/// Stage in a spike's lifecycle
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SpikeEventKind {
+    Opened,
+    Updated,
+    Closed,
+}
+
+/// A change to a spike, carrying the spike's state after the change
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SpikeEvent {
+    pub kind: SpikeEventKind,
+    pub spike: FeeSpike,
 }
 
 /// Trend indicator for congestion
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum TrendIndicator {
     Normal,
     Rising,
@@ -95,7 +285,7 @@ pub enum TrendIndicator {
 }
 
 /// Strength of a congestion trend
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum TrendStrength {
     Weak,
     Moderate,
@@ -103,7 +293,7 @@ pub enum TrendStrength {
 }
 
 /// Severity classification for fee spikes
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 pub enum SpikeSeverity {
     Minor,
     Moderate,
@@ -111,10 +301,34 @@ pub enum SpikeSeverity {
     Critical,
 }
 
+impl SpikeSeverity {
+    /// Name used in storage and query parameters, e.g. `"Major"`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpikeSeverity::Minor => "Minor",
+            SpikeSeverity::Moderate => "Moderate",
+            SpikeSeverity::Major => "Major",
+            SpikeSeverity::Critical => "Critical",
+        }
+    }
+    
+    /// Parse a severity name as produced by [`SpikeSeverity::as_str`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Minor" => Some(SpikeSeverity::Minor),
+            "Moderate" => Some(SpikeSeverity::Moderate),
+            "Major" => Some(SpikeSeverity::Major),
+            "Critical" => Some(SpikeSeverity::Critical),
+            _ => None,
+        }
+    }
+}
+
 /// Data quality indicators
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DataQuality {
     pub completeness: f64,  // 0.0 to 1.0
+    #[schema(value_type = DurationSchema)]
     pub freshness: Duration,
     pub has_gaps: bool,
     pub last_gap: Option<DateTime<Utc>>,
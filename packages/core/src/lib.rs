@@ -3,19 +3,30 @@
 
 pub mod alerts;
 pub mod api;
+pub mod backtest;
 pub mod cache;
+pub mod clock;
 pub mod db;
 pub mod error;
+pub mod export;
+pub mod import;
 pub mod insights;
+pub mod live;
 pub mod metrics;
+pub mod point_in_time;
+pub mod reload;
 pub mod repository;
+pub mod retention;
 pub mod scheduler;
 pub mod services;
+pub mod shutdown;
 pub mod store;
 
 // These modules are only needed by the binary.
 // Declared pub so integration tests can reach them if needed, but they
 // contain no logic of interest to tests.
 pub mod cli;
+pub mod commands;
 pub mod config;
+pub mod config_file;
 pub mod logging;
@@ -0,0 +1,364 @@
+//! Live feed of fee, insights, spike and alert updates.
+//!
+//! The scheduler publishes to a [`LiveFeed`] after every tick and on every
+//! spike lifecycle change, and alert delivery publishes each alert event.
+//! Subscribers (the `/ws` endpoint) receive each message through a bounded
+//! broadcast channel. The feed also keeps the latest state of every topic
+//! so that a new subscriber can start from a snapshot instead of waiting
+//! for the next tick.
+//!
+//! Messages are numbered in publish order, and the most recent ones are
+//! kept so a reconnecting client can be sent what it missed.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex as StdMutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{broadcast, Mutex};
+
+use crate::api::fees::{cached_current_fees, CurrentFeeResponse, FeeStatsProvider};
+use crate::cache::ResponseCache;
+use crate::insights::{CurrentInsights, FeeDataPoint, FeeSpike, SpikeEvent, SpikeEventKind};
+use crate::repository::AlertEvent;
+
+/// Messages buffered per subscriber before it is reported as lagging,
+/// and kept for clients resuming after a reconnect.
+pub const LIVE_FEED_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Topic {
+    /// Horizon fee stats, as served by `/fees/current`
+    #[serde(rename = "fees.current")]
+    FeesCurrent,
+    /// The fee data points fetched by a tick
+    #[serde(rename = "fees.points")]
+    FeesPoints,
+    /// Current insights, as served by `/insights`
+    #[serde(rename = "insights")]
+    Insights,
+    /// Spike lifecycle events; the snapshot is the list of open spikes
+    #[serde(rename = "spikes")]
+    Spikes,
+    /// Alert events as they are logged
+    #[serde(rename = "alerts")]
+    Alerts,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 5] = [
+        Topic::FeesCurrent,
+        Topic::FeesPoints,
+        Topic::Insights,
+        Topic::Spikes,
+        Topic::Alerts,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Topic::FeesCurrent => "fees.current",
+            Topic::FeesPoints => "fees.points",
+            Topic::Insights => "insights",
+            Topic::Spikes => "spikes",
+            Topic::Alerts => "alerts",
+        }
+    }
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Topic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Topic::ALL
+            .into_iter()
+            .find(|topic| topic.as_str() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Topic::ALL.iter().map(Topic::as_str).collect();
+                format!("Unknown topic '{}'. Must be one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+/// One update on a topic.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveMessage {
+    /// Position in publish order, starting at 1 for each process
+    pub id: u64,
+    pub topic: Topic,
+    pub network: String,
+    pub at: DateTime<Utc>,
+    pub data: Value,
+}
+
+/// Spikes opened and not yet closed, by id.
+#[derive(Default)]
+struct OpenSpikes {
+    spikes: BTreeMap<String, FeeSpike>,
+    /// Id and time of the last spike event
+    changed: Option<(u64, DateTime<Utc>)>,
+}
+
+/// Where `fees.current` updates come from; shares `/fees/current`'s cache.
+struct CurrentFeesSource {
+    provider: Arc<dyn FeeStatsProvider + Send + Sync>,
+    cache: Arc<Mutex<ResponseCache<CurrentFeeResponse>>>,
+}
+
+pub struct LiveFeed {
+    network: String,
+    /// When the feed was created; tells ids from an earlier process apart
+    started_at: DateTime<Utc>,
+    sender: broadcast::Sender<Arc<LiveMessage>>,
+    /// The last [`LIVE_FEED_CAPACITY`] messages, oldest first
+    recent: StdMutex<VecDeque<Arc<LiveMessage>>>,
+    /// Latest message per topic, except spikes
+    latest: StdMutex<HashMap<Topic, Arc<LiveMessage>>>,
+    open_spikes: StdMutex<OpenSpikes>,
+    current_fees: Option<CurrentFeesSource>,
+}
+
+impl LiveFeed {
+    pub fn new(network: impl Into<String>) -> Self {
+        let (sender, _) = broadcast::channel(LIVE_FEED_CAPACITY);
+        Self {
+            network: network.into(),
+            started_at: Utc::now(),
+            sender,
+            recent: StdMutex::new(VecDeque::with_capacity(LIVE_FEED_CAPACITY)),
+            latest: StdMutex::new(HashMap::new()),
+            open_spikes: StdMutex::new(OpenSpikes::default()),
+            current_fees: None,
+        }
+    }
+
+    /// Publish `fees.current` after each tick, fetched through `cache`.
+    pub fn with_current_fees(
+        mut self,
+        provider: Arc<dyn FeeStatsProvider + Send + Sync>,
+        cache: Arc<Mutex<ResponseCache<CurrentFeeResponse>>>,
+    ) -> Self {
+        self.current_fees = Some(CurrentFeesSource { provider, cache });
+        self
+    }
+
+    /// The Stellar network every message is about.
+    pub fn network(&self) -> &str {
+        &self.network
+    }
+
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<LiveMessage>> {
+        self.sender.subscribe()
+    }
+
+    /// Parse topic names, rejecting any `network` this feed does not carry.
+    pub fn parse_topics<S: AsRef<str>>(&self, names: &[S], network: Option<&str>) -> Result<Vec<Topic>, String> {
+        if let Some(network) = network {
+            if network != self.network {
+                return Err(format!(
+                    "Unsupported network '{}'. This server tracks {}",
+                    network, self.network
+                ));
+            }
+        }
+        names.iter().map(|name| name.as_ref().trim().parse()).collect()
+    }
+
+    /// Messages published after `id`, or `None` when some of them are no
+    /// longer kept or `id` was never published.
+    pub fn replay_after(&self, id: u64) -> Option<Vec<Arc<LiveMessage>>> {
+        let recent = self.recent.lock().unwrap();
+        let last = recent.back().map_or(0, |message| message.id);
+        let first = recent.front().map_or(last + 1, |message| message.id);
+        if id > last || id + 1 < first {
+            return None;
+        }
+        Some(recent.iter().filter(|message| message.id > id).cloned().collect())
+    }
+
+    /// The current state of `topic`, or `None` before anything was published.
+    pub fn snapshot(&self, topic: Topic) -> Option<LiveMessage> {
+        if topic == Topic::Spikes {
+            let open_spikes = self.open_spikes.lock().unwrap();
+            let (id, at) = open_spikes.changed?;
+            let spikes: Vec<&FeeSpike> = open_spikes.spikes.values().collect();
+            return Some(LiveMessage {
+                id,
+                topic,
+                network: self.network.clone(),
+                at,
+                data: serde_json::to_value(spikes).ok()?,
+            });
+        }
+        self.latest.lock().unwrap().get(&topic).map(|message| (**message).clone())
+    }
+
+    /// Send `data` to every subscriber of `topic`; returns the message id.
+    pub fn publish(&self, topic: Topic, data: Value, at: DateTime<Utc>) -> u64 {
+        // Held while sending so subscribers see ids in order
+        let mut recent = self.recent.lock().unwrap();
+        let id = recent.back().map_or(1, |message| message.id + 1);
+        let message = Arc::new(LiveMessage {
+            id,
+            topic,
+            network: self.network.clone(),
+            at,
+            data,
+        });
+        if recent.len() == LIVE_FEED_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(message.clone());
+        if topic != Topic::Spikes {
+            self.latest.lock().unwrap().insert(topic, message.clone());
+        }
+        // Without subscribers the message is only kept for snapshots and replay
+        let _ = self.sender.send(message);
+        id
+    }
+
+    /// Publish what one scheduler tick produced: its points, the insights
+    /// they led to, and fresh fee stats when a source is configured.
+    pub async fn publish_tick(
+        &self,
+        points: &[FeeDataPoint],
+        insights: Option<&CurrentInsights>,
+        at: DateTime<Utc>,
+    ) {
+        if let Ok(data) = serde_json::to_value(points) {
+            self.publish(Topic::FeesPoints, data, at);
+        }
+        if let Some(data) = insights.and_then(|insights| serde_json::to_value(insights).ok()) {
+            self.publish(Topic::Insights, data, at);
+        }
+        if let Some(source) = &self.current_fees {
+            match cached_current_fees(source.provider.as_ref(), &source.cache).await {
+                Ok(fees) => {
+                    if let Ok(data) = serde_json::to_value(fees) {
+                        self.publish(Topic::FeesCurrent, data, at);
+                    }
+                }
+                Err(err) => tracing::warn!("Failed to fetch current fees for the live feed: {}", err),
+            }
+        }
+    }
+
+    /// Publish spike lifecycle events, keeping track of open spikes.
+    pub fn publish_spike_events(&self, events: &[SpikeEvent], at: DateTime<Utc>) {
+        for event in events {
+            let Ok(data) = serde_json::to_value(event) else {
+                continue;
+            };
+            let mut open_spikes = self.open_spikes.lock().unwrap();
+            match event.kind {
+                SpikeEventKind::Closed => {
+                    open_spikes.spikes.remove(&event.spike.id);
+                }
+                SpikeEventKind::Opened | SpikeEventKind::Updated => {
+                    open_spikes.spikes.insert(event.spike.id.clone(), event.spike.clone());
+                }
+            }
+            let id = self.publish(Topic::Spikes, data, at);
+            open_spikes.changed = Some((id, at));
+        }
+    }
+
+    /// Publish a fired alert. The webhook target is left out, since any
+    /// client may subscribe.
+    pub fn publish_alert(&self, event: &AlertEvent, at: DateTime<Utc>) {
+        if let Ok(mut data) = serde_json::to_value(event) {
+            if let Some(fields) = data.as_object_mut() {
+                fields.remove("webhook_url");
+            }
+            self.publish(Topic::Alerts, data, at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::insights::SpikeSeverity;
+
+    fn spike_event(id: &str, kind: SpikeEventKind) -> SpikeEvent {
+        SpikeEvent {
+            kind,
+            spike: FeeSpike {
+                id: id.to_string(),
+                peak_fee: 5_000,
+                baseline_fee: 100.0,
+                spike_ratio: 50.0,
+                start_time: Utc::now(),
+                duration: chrono::Duration::seconds(30),
+                severity: SpikeSeverity::Major,
+                strategy: "threshold".to_string(),
+                score: 50.0,
+                end_time: None,
+            },
+        }
+    }
+
+    #[test]
+    fn parses_topic_names() {
+        assert_eq!("fees.current".parse::<Topic>(), Ok(Topic::FeesCurrent));
+        assert_eq!("alerts".parse::<Topic>(), Ok(Topic::Alerts));
+        assert!("fees".parse::<Topic>().unwrap_err().contains("fees.points"));
+    }
+
+    #[tokio::test]
+    async fn publishes_to_subscribers_and_keeps_snapshots() {
+        let feed = LiveFeed::new("testnet");
+        assert!(feed.snapshot(Topic::FeesPoints).is_none());
+
+        let mut rx = feed.subscribe();
+        let point = FeeDataPoint {
+            fee_amount: 100,
+            timestamp: Utc::now(),
+            transaction_hash: "live_1".to_string(),
+            ledger_sequence: 1,
+        };
+        feed.publish_tick(&[point], None, Utc::now()).await;
+
+        let message = rx.recv().await.unwrap();
+        assert_eq!(message.topic, Topic::FeesPoints);
+        assert_eq!(message.network, "testnet");
+        assert_eq!(message.data[0]["transaction_hash"], "live_1");
+        assert_eq!(feed.snapshot(Topic::FeesPoints).unwrap().data, message.data);
+        // No insights or fee stats source, so nothing else was published
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn spike_snapshot_lists_open_spikes() {
+        let feed = LiveFeed::new("testnet");
+        assert!(feed.snapshot(Topic::Spikes).is_none());
+
+        feed.publish_spike_events(
+            &[
+                spike_event("spk_1", SpikeEventKind::Opened),
+                spike_event("spk_2", SpikeEventKind::Opened),
+                spike_event("spk_1", SpikeEventKind::Closed),
+            ],
+            Utc::now(),
+        );
+
+        let snapshot = feed.snapshot(Topic::Spikes).unwrap();
+        let ids: Vec<&str> = snapshot.data.as_array().unwrap().iter().map(|s| s["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["spk_2"]);
+
+        feed.publish_spike_events(&[spike_event("spk_2", SpikeEventKind::Closed)], Utc::now());
+        assert_eq!(feed.snapshot(Topic::Spikes).unwrap().data, serde_json::json!([]));
+    }
+}
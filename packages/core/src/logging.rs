@@ -3,7 +3,8 @@ use tracing_subscriber::{fmt, EnvFilter};
 
 /// Initialize structured logging for the application.
 ///
-/// This must be called once at startup (in main.rs).
+/// This must be called once at startup (in main.rs). Logs go to stderr so
+/// command output on stdout can be piped.
 pub fn init_logging() {
     let filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new("info"));
@@ -11,6 +12,7 @@ pub fn init_logging() {
     fmt()
         .with_env_filter(filter)
         .with_target(false)
+        .with_writer(std::io::stderr)
         .compact()
         .init();
 
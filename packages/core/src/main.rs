@@ -4,19 +4,31 @@
 
 mod alerts;
 mod api;
+mod backtest;
 mod cache;
+mod clock;
 mod metrics;
 mod cli;
+mod commands;
 mod config;
+mod config_file;
 mod db;
 mod error;
+mod export;
+mod import;
 mod insights;
+mod live;
 mod logging;
+mod point_in_time;
+mod reload;
 mod repository;
+mod retention;
 mod services;
 mod scheduler;
+mod shutdown;
 mod store;
 
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::{routing::get, Router};
@@ -25,17 +37,23 @@ use clap::Parser;
 use dotenvy::dotenv;
 use std::time::Duration;
 use tokio::sync::{Mutex, RwLock};
-use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::cors::CorsLayer;
 
+use crate::api::rate_limit::{RateLimitState, RateLimiter, RouteGroup};
 use crate::cache::ResponseCache;
-use crate::cli::Cli;
+use crate::clock::{system_clock, SharedClock};
+use crate::cli::{Cli, Command, ConfigCommand, DbCommand, InsightsCommand, MigrateAction};
 use crate::config::Config;
 use crate::error::AppError;
-use crate::insights::{FeeInsightsEngine, InsightsConfig, HorizonFeeDataProvider};
+use crate::insights::HorizonFeeDataProvider;
+use crate::live::LiveFeed;
 use crate::logging::init_logging;
 use crate::metrics::AppMetrics;
+use crate::point_in_time::PointInTimeInsights;
 use crate::repository::FeeRepository;
-use crate::scheduler::run_fee_polling_with_retry;
+use crate::reload::{run_sighup_reload, Reloader};
+use crate::scheduler::{run_engine_snapshots, run_fee_polling_with_settings, save_engine_snapshot};
+use crate::shutdown::Shutdown;
 use crate::services::horizon::HorizonClient;
 use crate::store::{FeeHistoryStore, DEFAULT_CAPACITY};
 
@@ -60,15 +78,66 @@ async fn main() {
 
     tracing::info!("Configuration loaded: {:?}", config);
 
+    let command = cli.command.clone().unwrap_or(Command::Serve);
+
+    // ---- Commands that manage the database themselves, or need none ----
+    let early = match &command {
+        Command::Config(ConfigCommand::Check) => Some(commands::config_check(&config)),
+        Command::Migrate { action } => {
+            Some(commands::migrate(&config, action.unwrap_or(MigrateAction::Run)).await)
+        }
+        _ => None,
+    };
+    if let Some(result) = early {
+        exit_on_error(result);
+        return;
+    }
+
     // ---- Database ----
-    let db_pool = db::create_pool(&config.database_url)
-        .await
-        .unwrap_or_else(|err| {
-            tracing::error!("Failed to initialise database: {}", err);
-            std::process::exit(1);
-        });
-    tracing::info!("Database initialised: {}", config.database_url);
+    // Only serve and migrate change the schema
+    let db_pool = match &command {
+        Command::Serve => db::create_pool(&config.database_url).await,
+        _ => db::connect_migrated(&config.database_url).await,
+    };
+    let db_pool = db_pool.unwrap_or_else(|err| {
+        tracing::error!("Failed to open database: {}", err);
+        std::process::exit(1);
+    });
+    tracing::info!("Database opened: {}", config.database_url);
+
+    let repository = Arc::new(FeeRepository::new(db_pool));
+    let clock = system_clock();
+
+    let result = match &command {
+        Command::Serve => {
+            serve(cli, config, repository, clock).await;
+            Ok(())
+        }
+        Command::PollOnce(args) => commands::poll_once(&config, repository, clock, args).await,
+        Command::Db(DbCommand::Prune(args)) => {
+            commands::db_prune(&config, &repository, clock, args).await
+        }
+        Command::Export(args) => commands::export(&repository, clock, args).await,
+        Command::Import(args) => commands::import(&repository, clock, args).await,
+        Command::Insights(InsightsCommand::Show(args)) => {
+            commands::insights_show(&config, repository, clock, args).await
+        }
+        Command::Backtest(args) => commands::backtest(&config, &repository, args).await,
+        Command::Config(_) | Command::Migrate { .. } => Ok(()),
+    };
+    exit_on_error(result);
+}
 
+fn exit_on_error(result: Result<(), String>) {
+    if let Err(err) = result {
+        tracing::error!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Run the API server, poller, snapshotter and config reloader until Ctrl+C
+/// or SIGTERM.
+async fn serve(cli: Cli, config: Config, repository: Arc<FeeRepository>, clock: SharedClock) {
     // ---- Metrics ----
     let app_metrics = Arc::new(
         AppMetrics::new().unwrap_or_else(|err| {
@@ -77,61 +146,64 @@ async fn main() {
         }),
     );
 
-    let repository = Arc::new(FeeRepository::new(db_pool));
-
     // ---- Shared state ----
-    let horizon_client = Arc::new(HorizonClient::new(config.horizon_url.clone()));
+    let horizon_client = Arc::new(HorizonClient::with_timeout(
+        config.horizon_url.clone(),
+        Duration::from_secs(config.horizon_timeout_seconds),
+    ));
     tracing::info!("Horizon client initialized: {}", horizon_client.base_url());
 
     let fee_store = Arc::new(RwLock::new(FeeHistoryStore::new(DEFAULT_CAPACITY)));
 
-    let insights_engine = Arc::new(RwLock::new(
-        FeeInsightsEngine::new(InsightsConfig::default()),
-    ));
+    let insights_engine = commands::restore_insights_engine(&config, &repository, clock.clone())
+        .await
+        .unwrap_or_else(|err| {
+            tracing::error!("{}", err);
+            std::process::exit(1);
+        });
     let current_fees_cache = Arc::new(Mutex::new(ResponseCache::new(Duration::from_secs(
         config.cache_ttl_seconds,
     ))));
 
     // ---- Startup rehydration ----
-    let rehydration_window = chrono::Utc::now() - chrono::Duration::hours(24);
+    let rehydration_window = clock.now() - chrono::Duration::hours(commands::REHYDRATION_HOURS);
     match repository.fetch_since(rehydration_window).await {
         Ok(points) if !points.is_empty() => {
-            let count = points.len();
-            {
-                let mut store = fee_store.write().await;
-                for point in &points {
-                    store.push(point.clone());
-                }
-            }
-            {
-                let mut engine = insights_engine.write().await;
-                if let Err(err) = engine.process_fee_data(&points).await {
-                    tracing::warn!("Insights engine error during rehydration: {}", err);
-                }
+            let mut store = fee_store.write().await;
+            for point in &points {
+                store.push(point.clone());
             }
-            tracing::info!("Restored {} fee data points from database", count);
+            tracing::info!("Restored {} fee data points from database", points.len());
         }
         Ok(_) => tracing::info!("No historical fee data found — starting cold"),
         Err(err) => tracing::warn!("Failed to rehydrate store from database: {}", err),
     }
+
     let horizon_provider = Arc::new(HorizonFeeDataProvider::new(
         (*horizon_client).clone(),
     ));
     let fee_stats_provider: Arc<dyn api::fees::FeeStatsProvider + Send + Sync> =
         horizon_client.clone();
 
+    // ---- Live feed ----
+    // Published to by the poller; /fees/current and the feed share one cache
+    let live_feed = Arc::new(
+        LiveFeed::new(config.stellar_network.as_str())
+            .with_current_fees(fee_stats_provider.clone(), current_fees_cache.clone()),
+    );
+
     // ---- CORS policy ----
-    let origins: Vec<axum::http::HeaderValue> = config
-        .allowed_origins
-        .iter()
-        .map(|o| o.parse().expect("Invalid origin in ALLOWED_ORIGINS"))
-        .collect();
+    // Origins are shared with the SIGHUP reloader so they can change live
+    let origins = reload::shared_origins(&config.allowed_origins);
+    // Retention tiers too, so history and candle queries follow a reload
+    let retention = config.retention_policy().shared();
 
     let cors = CorsLayer::new()
-        .allow_origin(AllowOrigin::list(origins))
+        .allow_origin(reload::allow_shared_origins(origins.clone()))
         .allow_methods([
             Method::GET,
             Method::POST,
+            Method::PUT,
             Method::PATCH,
             Method::DELETE,
             Method::OPTIONS,
@@ -139,6 +211,7 @@ async fn main() {
         .allow_headers([
             HeaderName::from_static("content-type"),
             HeaderName::from_static("x-api-key"),
+            HeaderName::from_static("x-admin-actor"),
         ])
         .expose_headers([
             HeaderName::from_static("etag"),
@@ -148,56 +221,79 @@ async fn main() {
             HeaderName::from_static("x-ratelimit-remaining"),
             HeaderName::from_static("x-ratelimit-reset"),
             HeaderName::from_static("retry-after"),
+            HeaderName::from_static("deprecation"),
+            HeaderName::from_static("sunset"),
+            HeaderName::from_static("link"),
         ])
         .max_age(Duration::from_secs(3600));
 
+    // SIGHUP reloads the config; poll settings reach the poller and the
+    // Cache-Control max-age through a watch channel
+    let (poll_settings_tx, poll_settings_rx) =
+        tokio::sync::watch::channel(Reloader::poll_settings_of(&config));
+
     // ---- Axum router ----
     // fees routes get shared state (Horizon client, store, insights engine)
     // insights routes get Arc<RwLock<FeeInsightsEngine>> as their own state
     // Both sub-routers are Router<()> after with_state, so merge works fine
+    let point_in_time = Arc::new(
+        PointInTimeInsights::new(repository.clone())
+            .with_snapshot_interval(config.snapshot_interval_seconds),
+    );
+    let fees_state = Arc::new(api::fees::FeesApiState {
+        fee_stats_provider: Some(fee_stats_provider),
+        fee_cache: current_fees_cache.clone(),
+        fee_store: fee_store.clone(),
+        insights_engine: Some(insights_engine.clone()),
+        point_in_time: Some(point_in_time.clone()),
+        repository: Some(repository.clone()),
+        retention: retention.clone(),
+        clock: clock.clone(),
+    });
+    // ETag / Last-Modified / Cache-Control on /fees/* and /insights/*
+    let conditional_get = axum::middleware::from_fn_with_state(
+        api::conditional::ConditionalState {
+            fee_cache: current_fees_cache,
+            fee_store: fee_store.clone(),
+            insights_engine: insights_engine.clone(),
+            poll_settings: poll_settings_rx.clone(),
+        },
+        api::conditional::conditional_get,
+    );
+    // Token buckets per client IP and API key, shared by /v1 and the
+    // unversioned routes
+    let rate_limiter = Arc::new(
+        RateLimiter::new(config.rate_limits.clone(), config.rate_limit_trust_forwarded_for)
+            .with_metrics(app_metrics.clone()),
+    );
+    let rate_limit = |group| {
+        axum::middleware::from_fn_with_state(
+            RateLimitState { limiter: rate_limiter.clone(), group },
+            api::rate_limit::rate_limit,
+        )
+    };
     let fees_router = Router::new()
         .route("/fees/current", get(api::fees::current_fees))
         .route("/fees/history", get(api::fees::fee_history))
         .route("/fees/trend", get(api::fees::fee_trend))
-        .with_state(Arc::new(api::fees::FeesApiState {
-            fee_stats_provider: Some(fee_stats_provider),
-            fee_cache: current_fees_cache,
-            fee_store: fee_store.clone(),
-            insights_engine: Some(insights_engine.clone()),
-        }));
+        .route("/fees/candles", get(api::fees::fee_candles))
+        .route("/fees/export", get(api::export::export_fees))
+        .with_state(fees_state.clone())
+        .layer(conditional_get.clone())
+        .layer(rate_limit(RouteGroup::Fees));
 
-    // Clone for metrics endpoint closure
-    let metrics_for_handler = app_metrics.clone();
-
-    let app = Router::new()
-        .route("/health", get(api::health::health))
-        .route(
-            "/metrics",
-            get(move || {
-                let m = metrics_for_handler.clone();
-                async move {
-                    match m.render() {
-                        Ok(body) => axum::response::Response::builder()
-                            .status(200)
-                            .header(
-                                axum::http::header::CONTENT_TYPE,
-                                "text/plain; version=0.0.4",
-                            )
-                            .body(axum::body::Body::from(body))
-                            .unwrap(),
-                        Err(err) => {
-                            tracing::error!("Failed to render metrics: {}", err);
-                            axum::response::Response::builder()
-                                .status(500)
-                                .body(axum::body::Body::from("metrics error"))
-                                .unwrap()
-                        }
-                    }
-                }
-            }),
+    // Routes served the same with and without the /v1 prefix
+    let shared_router = Router::new()
+        .merge(
+            api::insights::create_insights_router(
+                insights_engine.clone(),
+                Some(repository.clone()),
+                Some(point_in_time),
+                clock.clone(),
+            )
+            .layer(conditional_get.clone())
+            .layer(rate_limit(RouteGroup::Insights)),
         )
-        .merge(fees_router)
-        .merge(api::insights::create_insights_router(insights_engine.clone()))
         .merge(
             Router::new()
                 .route("/alerts/config", axum::routing::post(api::alerts::create_alert))
@@ -205,7 +301,46 @@ async fn main() {
                 .route("/alerts/config/:id", axum::routing::patch(api::alerts::update_alert))
                 .route("/alerts/config/:id", axum::routing::delete(api::alerts::delete_alert))
                 .route("/alerts/history", axum::routing::get(api::alerts::get_alert_history))
-                .with_state(repository.clone()),
+                .with_state(repository.clone())
+                .layer(rate_limit(RouteGroup::Alerts)),
+        )
+        .merge(
+            Router::new()
+                .route("/ws", get(api::ws::live_feed))
+                .route("/fees/stream", get(api::stream::fee_stream))
+                .with_state(live_feed.clone())
+                .layer(rate_limit(RouteGroup::Live)),
+        )
+        .merge(
+            api::admin::create_admin_router(api::admin::AdminApiState {
+                insights_engine: insights_engine.clone(),
+                repository: repository.clone(),
+                api_key: config.admin_api_key.clone(),
+                clock: clock.clone(),
+            })
+            .layer(rate_limit(RouteGroup::Admin)),
+        );
+
+    let app = Router::new()
+        .route("/health", get(api::health::health))
+        .route("/openapi.json", get(api::openapi::openapi_json))
+        .route("/docs", get(api::openapi::docs))
+        .route("/metrics", get(api::metrics::metrics).with_state(app_metrics.clone()))
+        .nest(
+            api::v1::API_PREFIX,
+            api::v1::create_fees_router(fees_state)
+                .layer(conditional_get)
+                .layer(rate_limit(RouteGroup::Fees))
+                .merge(
+                    shared_router
+                        .clone()
+                        .layer(axum::middleware::from_fn(api::v1::reject_unit)),
+                ),
+        )
+        .merge(
+            fees_router
+                .merge(shared_router)
+                .layer(axum::middleware::from_fn(api::v1::deprecate_legacy)),
         )
         .layer(cors);
 
@@ -221,24 +356,51 @@ async fn main() {
     tracing::info!("API server listening on {}", addr);
 
     // ---- Run server + scheduler concurrently ----
+    let shutdown = Shutdown::on_signal();
     tokio::join!(
         async {
-            axum::serve(listener, app)
+            // Peer addresses identify clients to the rate limiter
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown({
+                    let mut shutdown = shutdown.clone();
+                    async move { shutdown.requested().await }
+                })
                 .await
                 .unwrap_or_else(|err| tracing::error!("Server error: {}", err));
         },
-        run_fee_polling_with_retry(
+        run_fee_polling_with_settings(
             horizon_provider,
             fee_store,
-            insights_engine,
-            config.poll_interval_seconds,
+            insights_engine.clone(),
+            poll_settings_rx,
             config.retry_attempts,
             config.base_retry_delay_ms,
-            Some(repository),
-            config.storage_retention_days,
+            Some(repository.clone()),
             Some(app_metrics),
+            Some(live_feed),
+            shutdown.clone(),
+        ),
+        run_engine_snapshots(
+            insights_engine.clone(),
+            repository.clone(),
+            config.snapshot_interval_seconds,
+            shutdown.clone(),
+        ),
+        run_sighup_reload(
+            cli.clone(),
+            Reloader {
+                current: config.clone(),
+                poll_settings: poll_settings_tx,
+                origins,
+                retention,
+                insights_engine: insights_engine.clone(),
+            },
+            shutdown.clone(),
         ),
     );
 
+    // Polling has stopped, so this snapshot covers every processed point
+    save_engine_snapshot(&insights_engine, &repository).await;
+
     tracing::info!("Application shut down cleanly");
 }
@@ -28,6 +28,9 @@ pub struct AppMetrics {
     pub http_requests_total: CounterVec,
     /// HTTP request latency histogram in seconds.
     pub http_request_duration: Histogram,
+    /// Requests refused by the rate limiter, labelled by route group and
+    /// whether the client's IP or API key bucket was empty.
+    pub rate_limited_total: CounterVec,
     /// The registry that owns all of the above metrics.
     pub registry: Registry,
 }
@@ -79,6 +82,14 @@ impl AppMetrics {
             .buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]),
         )?;
 
+        let rate_limited_total = CounterVec::new(
+            Opts::new(
+                "stellar_fee_tracker_rate_limited_requests_total",
+                "Requests rejected by the rate limiter, by route group and client bucket",
+            ),
+            &["group", "client"],
+        )?;
+
         registry.register(Box::new(polls_total.clone()))?;
         registry.register(Box::new(poll_errors_total.clone()))?;
         registry.register(Box::new(fee_points_stored.clone()))?;
@@ -86,6 +97,7 @@ impl AppMetrics {
         registry.register(Box::new(spikes_detected_total.clone()))?;
         registry.register(Box::new(http_requests_total.clone()))?;
         registry.register(Box::new(http_request_duration.clone()))?;
+        registry.register(Box::new(rate_limited_total.clone()))?;
 
         Ok(Self {
             polls_total,
@@ -95,6 +107,7 @@ impl AppMetrics {
             spikes_detected_total,
             http_requests_total,
             http_request_duration,
+            rate_limited_total,
             registry,
         })
     }
@@ -239,6 +252,10 @@ mod integration_tests {
             .with_label_values(&["GET", "/fees/current", "200"])
             .inc();
         metrics.http_request_duration.observe(0.042);
+        metrics
+            .rate_limited_total
+            .with_label_values(&["fees", "ip"])
+            .inc();
 
         let req = Request::builder()
             .method(Method::GET)
@@ -247,7 +264,7 @@ mod integration_tests {
             .unwrap();
         let resp = app.oneshot(req).await.unwrap();
         let bytes = resp.into_body().collect().await.unwrap().to_bytes();
-        let body = String::from_utf8(bytes).unwrap();
+        let body = String::from_utf8(bytes.to_vec()).unwrap();
 
         assert!(body.contains("stellar_fee_tracker_polls_total"));
         assert!(body.contains("stellar_fee_tracker_poll_errors_total"));
@@ -256,6 +273,7 @@ mod integration_tests {
         assert!(body.contains("stellar_fee_tracker_spikes_detected_total"));
         assert!(body.contains("stellar_fee_tracker_http_requests_total"));
         assert!(body.contains("stellar_fee_tracker_http_request_duration_seconds"));
+        assert!(body.contains("stellar_fee_tracker_rate_limited_requests_total"));
     }
 
     #[tokio::test]
@@ -270,7 +288,7 @@ mod integration_tests {
             .unwrap();
         let resp = app.oneshot(req).await.unwrap();
         let bytes = resp.into_body().collect().await.unwrap().to_bytes();
-        let body = String::from_utf8(bytes).unwrap();
+        let body = String::from_utf8(bytes.to_vec()).unwrap();
 
         // Prometheus text format: metric_name value\n
         assert!(body.contains("stellar_fee_tracker_polls_total 5"));
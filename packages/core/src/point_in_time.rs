@@ -0,0 +1,222 @@
+//! Point-in-time insights.
+//!
+//! Answers "what did `/insights` look like at time T?" for the `?at=`
+//! query parameter. Insights recorded alongside engine snapshots are used
+//! when one lies within a snapshot interval before T; otherwise the persisted fee
+//! points leading up to T are replayed through a fresh engine built from
+//! the live configuration. Results are cached per second and configuration,
+//! so a configuration change through the admin API is picked up at once.
+
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::Mutex;
+
+use crate::cache::KeyedCache;
+use crate::insights::replay::Replayer;
+use crate::insights::{CurrentInsights, InsightsConfig};
+use crate::repository::FeeRepository;
+
+/// Number of distinct instants kept in the cache
+const CACHE_CAPACITY: usize = 256;
+/// How long a cached result is served before being rebuilt
+const CACHE_TTL: StdDuration = StdDuration::from_secs(600);
+/// Snapshot interval assumed unless told otherwise, matching the
+/// `SNAPSHOT_INTERVAL_SECONDS` default
+const DEFAULT_SNAPSHOT_INTERVAL_SECONDS: u64 = 300;
+
+/// Builds and caches insights as of past instants.
+pub struct PointInTimeInsights {
+    repository: Arc<FeeRepository>,
+    /// How long a stored snapshot stands in for the instants after it
+    snapshot_interval: Duration,
+    cache: Mutex<KeyedCache<(DateTime<Utc>, String), CurrentInsights>>,
+}
+
+impl PointInTimeInsights {
+    pub fn new(repository: Arc<FeeRepository>) -> Self {
+        Self {
+            repository,
+            snapshot_interval: Duration::seconds(DEFAULT_SNAPSHOT_INTERVAL_SECONDS as i64),
+            cache: Mutex::new(KeyedCache::new(CACHE_CAPACITY, CACHE_TTL)),
+        }
+    }
+
+    /// Accept stored snapshots up to `seconds` old, the interval at which
+    /// the server records them.
+    pub fn with_snapshot_interval(mut self, seconds: u64) -> Self {
+        self.snapshot_interval = Duration::seconds(seconds.max(1) as i64);
+        self
+    }
+
+    /// Insights as of `at`, truncated to the second.
+    pub async fn insights_at(
+        &self,
+        config: &InsightsConfig,
+        at: DateTime<Utc>,
+    ) -> Result<CurrentInsights, sqlx::Error> {
+        let at = DateTime::from_timestamp(at.timestamp(), 0).unwrap_or(at);
+        let key = (at, serde_json::to_string(config).unwrap_or_default());
+        if let Some(cached) = self.cache.lock().await.get(&key) {
+            return Ok(cached);
+        }
+
+        // Never stricter than one poll, which is how often insights change
+        let max_age = self.snapshot_interval.max(config.polling_interval);
+        let insights = match self
+            .repository
+            .fetch_insights_snapshot_at(at, max_age)
+            .await?
+        {
+            Some(stored) => stored,
+            None => self.rebuild(config, at).await?,
+        };
+
+        self.cache.lock().await.set(key, insights.clone());
+        Ok(insights)
+    }
+
+    /// Replay the points needed to fill every window, extremes period and
+    /// congestion window ending at `at`.
+    async fn rebuild(
+        &self,
+        config: &InsightsConfig,
+        at: DateTime<Utc>,
+    ) -> Result<CurrentInsights, sqlx::Error> {
+        let from = at - lookback(config);
+        let points = self
+            .repository
+            .fetch_range(from, at + Duration::seconds(1))
+            .await?;
+
+        let mut replayer = Replayer::new(config.clone(), from, config.polling_interval);
+        replayer.replay(&points, |engine| {
+            engine.take_spike_events();
+        })
+        .await;
+        replayer.advance_to(at);
+
+        Ok(replayer.engine().get_current_insights())
+    }
+}
+
+/// How far before `at` data can still affect the insights.
+fn lookback(config: &InsightsConfig) -> Duration {
+    config
+        .time_windows
+        .iter()
+        .map(|window| window.duration)
+        .chain([
+            config.spike_detection.congestion_window,
+            config.extremes.tracking_period,
+        ])
+        .max()
+        .unwrap_or_else(|| Duration::hours(24))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_pool;
+    use crate::insights::FeeDataPoint;
+
+    async fn make_service() -> (PointInTimeInsights, Arc<FeeRepository>) {
+        let repository = Arc::new(FeeRepository::new(create_pool("sqlite::memory:").await.unwrap()));
+        (PointInTimeInsights::new(repository.clone()), repository)
+    }
+
+    fn minute_points(fees: &[u64], start: DateTime<Utc>) -> Vec<FeeDataPoint> {
+        fees.iter()
+            .enumerate()
+            .map(|(i, &fee_amount)| FeeDataPoint {
+                fee_amount,
+                timestamp: start + Duration::minutes(i as i64),
+                transaction_hash: format!("pit_{}", i),
+                ledger_sequence: i as u64,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn rebuilds_from_points_before_the_instant() {
+        let (service, repository) = make_service().await;
+        let start = Utc::now() - Duration::days(3);
+        let mut fees = vec![100; 30];
+        fees.extend(vec![900; 30]);
+        repository.insert_fee_points(&minute_points(&fees, start)).await.unwrap();
+
+        let config = InsightsConfig::default();
+        let before = service
+            .insights_at(&config, start + Duration::minutes(20))
+            .await
+            .unwrap();
+        let after = service
+            .insights_at(&config, start + Duration::minutes(59))
+            .await
+            .unwrap();
+
+        assert_eq!(before.extremes.current_max.value, 100);
+        assert_eq!(after.extremes.current_max.value, 900);
+        assert!(after.rolling_averages.current_value() > before.rolling_averages.current_value());
+        assert!(before.last_updated <= start + Duration::minutes(20));
+    }
+
+    #[tokio::test]
+    async fn prefers_stored_snapshot_and_caches_result() {
+        let (service, repository) = make_service().await;
+        let at = DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let recorded = CurrentInsights {
+            last_updated: at - Duration::seconds(30),
+            ..Replayer::new(InsightsConfig::default(), at, Duration::minutes(1))
+                .engine()
+                .get_current_insights()
+        };
+        repository.insert_insights_snapshot(&recorded).await.unwrap();
+
+        let config = InsightsConfig::default();
+        let insights = service.insights_at(&config, at).await.unwrap();
+        assert_eq!(insights.last_updated, recorded.last_updated);
+
+        // Too far from any snapshot: rebuilt from (no) points instead
+        let older = service
+            .insights_at(&config, at - Duration::minutes(10))
+            .await
+            .unwrap();
+        assert_eq!(older.last_updated, at - Duration::minutes(10));
+
+        assert_eq!(service.cache.lock().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn snapshots_cover_the_snapshot_interval() {
+        let (service, repository) = make_service().await;
+        let taken_at = DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let recorded = CurrentInsights {
+            last_updated: taken_at,
+            ..Replayer::new(InsightsConfig::default(), taken_at, Duration::minutes(1))
+                .engine()
+                .get_current_insights()
+        };
+        repository.insert_insights_snapshot(&recorded).await.unwrap();
+        let config = InsightsConfig::default();
+
+        // Several polls after the snapshot but before the next one is due
+        let insights = service
+            .insights_at(&config, taken_at + Duration::minutes(4))
+            .await
+            .unwrap();
+        assert_eq!(insights.last_updated, taken_at);
+
+        let service = PointInTimeInsights::new(repository).with_snapshot_interval(120);
+        let insights = service
+            .insights_at(&config, taken_at + Duration::minutes(4))
+            .await
+            .unwrap();
+        assert_eq!(insights.last_updated, taken_at + Duration::minutes(4));
+    }
+}
@@ -0,0 +1,263 @@
+//! Configuration reload on SIGHUP.
+//!
+//! The configuration is rebuilt from the same sources as at startup and
+//! the settings that are safe to change in place are applied: poll
+//! interval, retention, alert dispatch, CORS origins, the insights spike
+//! detection settings and extremes tracking.
+//! Anything else that changed is logged as needing a restart. An invalid
+//! configuration is rejected as a whole and the running one is kept.
+
+use std::sync::{Arc, RwLock as StdRwLock};
+
+use axum::http::HeaderValue;
+use tokio::sync::{watch, RwLock};
+use tower_http::cors::AllowOrigin;
+
+use crate::cli::Cli;
+use crate::config::Config;
+use crate::insights::config::ConfigOverrides;
+use crate::insights::FeeInsightsEngine;
+use crate::retention::SharedRetention;
+use crate::scheduler::PollSettings;
+use crate::shutdown::Shutdown;
+
+/// CORS origins shared with the CORS layer so they can be swapped live.
+pub type SharedOrigins = Arc<StdRwLock<Vec<HeaderValue>>>;
+
+/// Build the shared origin list from configured strings. Invalid entries
+/// are rejected by config validation, so they are skipped here.
+pub fn shared_origins(origins: &[String]) -> SharedOrigins {
+    Arc::new(StdRwLock::new(parse_origins(origins)))
+}
+
+/// CORS origin policy that consults `origins` on every request.
+pub fn allow_shared_origins(origins: SharedOrigins) -> AllowOrigin {
+    AllowOrigin::predicate(move |origin, _| {
+        origins
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .any(|allowed| allowed == origin)
+    })
+}
+
+fn parse_origins(origins: &[String]) -> Vec<HeaderValue> {
+    origins.iter().filter_map(|o| o.parse().ok()).collect()
+}
+
+/// Everything a reload can change, plus the configuration now in effect.
+pub struct Reloader {
+    pub current: Config,
+    pub poll_settings: watch::Sender<PollSettings>,
+    pub origins: SharedOrigins,
+    /// Retention tiers, read by the API to pick the tier a query uses
+    pub retention: SharedRetention,
+    pub insights_engine: Arc<RwLock<FeeInsightsEngine>>,
+}
+
+impl Reloader {
+    pub fn poll_settings_of(config: &Config) -> PollSettings {
+        PollSettings {
+            poll_interval_seconds: config.poll_interval_seconds,
+            retention: config.retention_policy(),
+            alerts_enabled: config.alerts_enabled,
+            alerts_min_severity: config.alerts_min_severity.clone(),
+        }
+    }
+
+    /// Apply the safe changes between the current configuration and `new`.
+    /// Returns a description of each change that was applied.
+    pub async fn apply(&mut self, new: Config) -> Vec<String> {
+        let mut applied = Vec::new();
+        let old = &self.current;
+
+        let poll = Self::poll_settings_of(&new);
+        if poll != Self::poll_settings_of(old) {
+            applied.push(format!(
+                "poll interval {}s, retention {}d, alerts {}",
+                poll.poll_interval_seconds,
+                poll.retention.raw.num_days(),
+                if poll.alerts_enabled { "enabled" } else { "disabled" },
+            ));
+            *self.retention.write().unwrap_or_else(|e| e.into_inner()) = poll.retention.clone();
+            self.poll_settings.send_replace(poll);
+        }
+
+        if new.allowed_origins != old.allowed_origins {
+            *self.origins.write().unwrap_or_else(|e| e.into_inner()) =
+                parse_origins(&new.allowed_origins);
+            applied.push(format!("allowed origins {}", new.allowed_origins.join(", ")));
+        }
+
+        if new.insights_threshold_multiplier != old.insights_threshold_multiplier
+            || new.insights_minimum_spike_duration != old.insights_minimum_spike_duration
+            || new.insights_strategy != old.insights_strategy
+            || new.insights_baseline_window != old.insights_baseline_window
+            || new.insights_baseline_mode != old.insights_baseline_mode
+            || new.insights_severity_cutoffs != old.insights_severity_cutoffs
+            || new.insights_congestion_window != old.insights_congestion_window
+            || new.poll_interval_seconds != old.poll_interval_seconds
+            || new.extremes_tracking_period != old.extremes_tracking_period
+            || new.extremes_periods_to_keep != old.extremes_periods_to_keep
+            || new.extremes_top_n != old.extremes_top_n
+        {
+            // Only the spike, polling and extremes settings are applied, on
+            // top of whatever the engine runs now, so admin API changes to
+            // the windows stay
+            let thresholds = ConfigOverrides {
+                windows: Vec::new(),
+                ..new.insights_overrides()
+            };
+            let mut engine = self.insights_engine.write().await;
+            let result = thresholds
+                .apply(engine.get_config())
+                // The windows are unchanged, so none need rebuilding
+                .and_then(|config| engine.reconfigure(config))
+                .map(|_| ());
+            match result {
+                Ok(()) => applied.push("insights thresholds, polling interval and extremes".to_string()),
+                Err(err) => tracing::warn!("Insights thresholds not reloaded: {}", err),
+            }
+        }
+
+        for setting in restart_required(old, &new) {
+            tracing::warn!("Changed setting {} takes effect after a restart", setting);
+        }
+
+        self.current = new;
+        applied
+    }
+}
+
+/// Settings that differ between `old` and `new` but are only read at startup.
+fn restart_required(old: &Config, new: &Config) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    let mut check = |name, differs: bool| {
+        if differs {
+            changed.push(name);
+        }
+    };
+    check("stellar_network", old.stellar_network != new.stellar_network);
+    check("horizon_url", old.horizon_url != new.horizon_url);
+    check("horizon_timeout_seconds", old.horizon_timeout_seconds != new.horizon_timeout_seconds);
+    check("api_port", old.api_port != new.api_port);
+    check("cache_ttl_seconds", old.cache_ttl_seconds != new.cache_ttl_seconds);
+    check("retry_attempts", old.retry_attempts != new.retry_attempts);
+    check("base_retry_delay_ms", old.base_retry_delay_ms != new.base_retry_delay_ms);
+    check("database_url", old.database_url != new.database_url);
+    check("snapshot_interval_seconds", old.snapshot_interval_seconds != new.snapshot_interval_seconds);
+    check("insights_windows", old.insights_windows != new.insights_windows);
+    check("admin_api_key", old.admin_api_key != new.admin_api_key);
+    check("rate_limits", old.rate_limits != new.rate_limits);
+    check("rate_limit_trust_forwarded_for", old.rate_limit_trust_forwarded_for != new.rate_limit_trust_forwarded_for);
+    changed
+}
+
+/// Reload the configuration every time SIGHUP is received, until `shutdown`
+/// is requested.
+pub async fn run_sighup_reload(cli: Cli, mut reloader: Reloader, mut shutdown: Shutdown) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal as unix_signal, SignalKind};
+
+        let mut hangup = match unix_signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(err) => {
+                tracing::warn!("SIGHUP reload unavailable: {}", err);
+                return;
+            }
+        };
+
+        loop {
+            tokio::select! {
+                _ = hangup.recv() => {
+                    match Config::from_sources(&cli) {
+                        Ok(config) => {
+                            let applied = reloader.apply(config).await;
+                            if applied.is_empty() {
+                                tracing::info!("Configuration reloaded; nothing to apply");
+                            } else {
+                                tracing::info!("Configuration reloaded: {}", applied.join("; "));
+                            }
+                        }
+                        Err(err) => tracing::error!("Configuration reload rejected: {}", err),
+                    }
+                }
+
+                _ = shutdown.requested() => break,
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (cli, &mut reloader);
+        shutdown.requested().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::insights::types::SpikeSeverity;
+    use crate::insights::InsightsConfig;
+
+    fn make_config(env: &[(&str, &str)]) -> Config {
+        let cli = Cli {
+            network: Some("testnet".to_string()),
+            ..Cli::default()
+        };
+        let mut env: HashMap<&str, &str> = env.iter().copied().collect();
+        env.entry("POLL_INTERVAL_SECONDS").or_insert("30");
+        Config::from_sources_with_overrides(&cli, &env).unwrap()
+    }
+
+    #[tokio::test]
+    async fn applies_safe_settings_in_place() {
+        let config = make_config(&[]);
+        let (poll_settings, poll_rx) = watch::channel(Reloader::poll_settings_of(&config));
+        let origins = shared_origins(&config.allowed_origins);
+        let retention = config.retention_policy().shared();
+        let engine = Arc::new(RwLock::new(FeeInsightsEngine::new(InsightsConfig::default())));
+        let mut reloader = Reloader {
+            current: config,
+            poll_settings,
+            origins: origins.clone(),
+            retention: retention.clone(),
+            insights_engine: engine.clone(),
+        };
+
+        let applied = reloader
+            .apply(make_config(&[
+                ("POLL_INTERVAL_SECONDS", "10"),
+                ("ROLLUP_RETENTION_DAYS", "1m=30"),
+                ("ALLOWED_ORIGINS", "https://app.example.com"),
+                ("INSIGHTS_THRESHOLD_MULTIPLIER", "4.5"),
+                ("INSIGHTS_CONGESTION_WINDOW", "30m"),
+                ("ALERTS_MIN_SEVERITY", "Major"),
+                ("API_PORT", "9090"),
+            ]))
+            .await;
+
+        assert_eq!(applied.len(), 3);
+        assert_eq!(poll_rx.borrow().poll_interval_seconds, 10);
+        assert_eq!(poll_rx.borrow().alerts_min_severity, Some(SpikeSeverity::Major));
+        assert_eq!(retention.read().unwrap().rollup_days["1m"], 30);
+        assert_eq!(origins.read().unwrap()[0], "https://app.example.com");
+        let spike = engine.read().await.get_config().spike_detection.clone();
+        assert_eq!(spike.threshold_multiplier, 4.5);
+        assert_eq!(spike.congestion_window, chrono::Duration::minutes(30));
+        assert_eq!(reloader.current.api_port, 9090);
+        assert!(reloader.apply(make_config(&[
+            ("POLL_INTERVAL_SECONDS", "10"),
+            ("ROLLUP_RETENTION_DAYS", "1m=30"),
+            ("ALLOWED_ORIGINS", "https://app.example.com"),
+            ("INSIGHTS_THRESHOLD_MULTIPLIER", "4.5"),
+            ("INSIGHTS_CONGESTION_WINDOW", "30m"),
+            ("ALERTS_MIN_SEVERITY", "Major"),
+            ("API_PORT", "9090"),
+        ])).await.is_empty());
+    }
+}
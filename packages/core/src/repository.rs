@@ -7,18 +7,31 @@
 //! On startup, [`FeeRepository::fetch_since`] rehydrates the in-memory
 //! [`FeeHistoryStore`] from the last 24 hours of persisted data.
 
+use std::collections::HashSet;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use utoipa::ToSchema;
 
-use crate::insights::types::FeeDataPoint;
+use crate::export::{ColumnKind, ExportDataset, ExportRequest, ExportRow, ExportValue};
+use crate::insights::config::InsightsConfig;
+use crate::insights::sketch::QuantileSketch;
+use crate::insights::snapshot::EngineSnapshot;
+use crate::insights::types::{parse_duration, CurrentInsights, ExtremeValue, FeeDataPoint, FeeExtremes, FeeSpike, SpikeSeverity};
 use crate::services::horizon::HorizonFeeStats;
 
+/// Name under which the insights engine snapshot is stored.
+const INSIGHTS_SNAPSHOT_NAME: &str = "insights";
+
+/// Name under which the runtime insights configuration is stored.
+const ACTIVE_INSIGHTS_CONFIG: &str = "active";
+
 /// Valid threshold values for alert configurations.
 pub const VALID_THRESHOLDS: &[&str] = &["Minor", "Major", "Critical"];
 
 /// A single alert webhook configuration row.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AlertConfig {
     pub id: i64,
     pub webhook_url: String,
@@ -29,7 +42,7 @@ pub struct AlertConfig {
 
 
 /// A single fired-alert log entry.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AlertEvent {
     pub id: Option<i64>,
     pub config_id: Option<i64>,
@@ -42,6 +55,86 @@ pub struct AlertEvent {
     pub triggered_at: String,
 }
 
+/// One change to the runtime insights configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct InsightsConfigChange {
+    pub id: i64,
+    pub changed_at: DateTime<Utc>,
+    /// Who made the change, when the caller said
+    pub actor: Option<String>,
+    pub previous_config: InsightsConfig,
+    pub new_config: InsightsConfig,
+}
+
+/// Finest resolution of the fee rollups maintained after every poll tick.
+pub const ROLLUP_RESOLUTION_1M: &str = "1m";
+
+/// Every resolution the scheduler maintains rollups at, finest first.
+pub const ROLLUP_RESOLUTIONS: &[&str] = &[ROLLUP_RESOLUTION_1M, "5m", "15m", "1h", "1d"];
+
+/// Aggregated fee statistics for one time bucket, including a quantile
+/// sketch so percentiles over many buckets can be merged cheaply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeRollup {
+    pub resolution: String,
+    pub bucket_start: DateTime<Utc>,
+    pub sample_count: u64,
+    pub fee_sum: u64,
+    pub min_fee: u64,
+    pub max_fee: u64,
+    pub sketch: QuantileSketch,
+    /// Fee of the earliest point in the bucket, and its time. The time is
+    /// `None` for buckets older than open/close tracking.
+    pub open_fee: u64,
+    pub open_at: Option<DateTime<Utc>>,
+    /// Fee of the latest point in the bucket, and its time
+    pub close_fee: u64,
+    pub close_at: Option<DateTime<Utc>>,
+}
+
+impl FeeRollup {
+    fn empty(resolution: &str, bucket_start: DateTime<Utc>) -> Self {
+        Self {
+            resolution: resolution.to_string(),
+            bucket_start,
+            sample_count: 0,
+            fee_sum: 0,
+            min_fee: u64::MAX,
+            max_fee: 0,
+            sketch: QuantileSketch::default(),
+            open_fee: 0,
+            open_at: None,
+            close_fee: 0,
+            close_at: None,
+        }
+    }
+
+    fn add(&mut self, fee_amount: u64, timestamp: DateTime<Utc>) {
+        self.sample_count += 1;
+        self.fee_sum = self.fee_sum.saturating_add(fee_amount);
+        self.min_fee = self.min_fee.min(fee_amount);
+        self.max_fee = self.max_fee.max(fee_amount);
+        self.sketch.insert(fee_amount);
+        if self.open_at.is_none_or(|at| timestamp < at) {
+            self.open_fee = fee_amount;
+            self.open_at = Some(timestamp);
+        }
+        if self.close_at.is_none_or(|at| timestamp >= at) {
+            self.close_fee = fee_amount;
+            self.close_at = Some(timestamp);
+        }
+    }
+}
+
+/// Count, minimum, maximum and average of the fees in a range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeAmountStats {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub avg: f64,
+}
+
 /// Repository for reading and writing fee data to SQLite.
 pub struct FeeRepository {
     pool: SqlitePool,
@@ -83,6 +176,26 @@ impl FeeRepository {
         Ok(())
     }
 
+    /// The subset of `hashes` already stored as fee data points.
+    pub async fn existing_transaction_hashes(
+        &self,
+        hashes: &[&str],
+    ) -> Result<HashSet<String>, sqlx::Error> {
+        if hashes.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let placeholders = vec!["?"; hashes.len()].join(", ");
+        let sql = format!(
+            "SELECT DISTINCT transaction_hash FROM fee_data_points WHERE transaction_hash IN ({})",
+            placeholders
+        );
+        let mut query = sqlx::query_scalar::<_, String>(&sql);
+        for hash in hashes {
+            query = query.bind(*hash);
+        }
+        Ok(query.fetch_all(&self.pool).await?.into_iter().collect())
+    }
+
     /// Fetch all fee data points with timestamp >= `since`, ordered ascending.
     pub async fn fetch_since(
         &self,
@@ -100,29 +213,148 @@ impl FeeRepository {
         .fetch_all(&self.pool)
         .await?;
 
-        let points = rows
-            .into_iter()
-            .filter_map(|row| {
-                use sqlx::Row;
-                let fee_amount: i64 = row.try_get("fee_amount").ok()?;
-                let timestamp_str: String = row.try_get("timestamp").ok()?;
-                let transaction_hash: String = row.try_get("transaction_hash").ok()?;
-                let ledger_sequence: i64 = row.try_get("ledger_sequence").ok()?;
-
-                let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                    .ok()?
-                    .with_timezone(&Utc);
-
-                Some(FeeDataPoint {
-                    fee_amount: fee_amount as u64,
-                    timestamp,
-                    transaction_hash,
-                    ledger_sequence: ledger_sequence as u64,
-                })
-            })
-            .collect();
+        Ok(rows.into_iter().filter_map(row_to_fee_point).collect())
+    }
 
-        Ok(points)
+    /// Fetch all fee data points with `from <= timestamp < to`, ordered ascending.
+    pub async fn fetch_range(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<FeeDataPoint>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT fee_amount, timestamp, transaction_hash, ledger_sequence
+             FROM fee_data_points
+             WHERE timestamp >= ? AND timestamp < ?
+             ORDER BY timestamp ASC",
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().filter_map(row_to_fee_point).collect())
+    }
+
+    /// Fetch up to `limit` fee data points with `from <= timestamp <= to`,
+    /// ordered by timestamp then transaction hash, starting after the
+    /// `(timestamp, transaction_hash)` key `after` when given.
+    pub async fn fetch_page(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        after: Option<(DateTime<Utc>, &str)>,
+        limit: i64,
+    ) -> Result<Vec<FeeDataPoint>, sqlx::Error> {
+        let (after_timestamp, after_hash) = match after {
+            Some((timestamp, hash)) => (Some(timestamp.to_rfc3339()), hash),
+            None => (None, ""),
+        };
+
+        let rows = sqlx::query(
+            "SELECT fee_amount, timestamp, transaction_hash, ledger_sequence
+             FROM fee_data_points
+             WHERE timestamp >= ? AND timestamp <= ?
+               AND (?3 IS NULL OR timestamp > ?3 OR (timestamp = ?3 AND transaction_hash > ?4))
+             ORDER BY timestamp ASC, transaction_hash ASC
+             LIMIT ?5",
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .bind(after_timestamp)
+        .bind(after_hash)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().filter_map(row_to_fee_point).collect())
+    }
+
+    /// Count, min, max and average fee of the points with
+    /// `from <= timestamp <= to`, aggregated in SQL.
+    pub async fn fee_amount_stats(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<FeeAmountStats, sqlx::Error> {
+        use sqlx::Row;
+
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS count, MIN(fee_amount) AS min, MAX(fee_amount) AS max,
+                    AVG(fee_amount) AS avg
+             FROM fee_data_points
+             WHERE timestamp >= ? AND timestamp <= ?",
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(FeeAmountStats {
+            count: row.try_get::<i64, _>("count")? as u64,
+            min: row.try_get::<Option<i64>, _>("min")?.unwrap_or(0) as u64,
+            max: row.try_get::<Option<i64>, _>("max")?.unwrap_or(0) as u64,
+            avg: row.try_get::<Option<f64>, _>("avg")?.unwrap_or(0.0),
+        })
+    }
+
+    /// Fees at each 1-based rank in `ranks` (ascending) of the points with
+    /// `from <= timestamp <= to` ordered by fee, read in one ordered pass.
+    /// Ranks past the last point are left out.
+    pub async fn fee_amounts_at_ranks(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        ranks: &[u64],
+    ) -> Result<Vec<u64>, sqlx::Error> {
+        use tokio_stream::StreamExt;
+
+        let mut fees = sqlx::query_scalar::<_, i64>(
+            "SELECT fee_amount FROM fee_data_points
+             WHERE timestamp >= ? AND timestamp <= ?
+             ORDER BY fee_amount ASC",
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch(&self.pool);
+
+        let mut values = Vec::with_capacity(ranks.len());
+        let mut rank = 0;
+        while values.len() < ranks.len() {
+            let Some(fee) = fees.next().await.transpose()? else {
+                break;
+            };
+            rank += 1;
+            while ranks.get(values.len()) == Some(&rank) {
+                values.push(fee as u64);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Fees of the points with `from <= timestamp <= to` that fall outside
+    /// `[inner_from, inner_to)`: the ragged ends of a range around the
+    /// whole buckets inside it.
+    pub async fn fetch_fee_amounts_outside(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        inner_from: DateTime<Utc>,
+        inner_to: DateTime<Utc>,
+    ) -> Result<Vec<u64>, sqlx::Error> {
+        let fees: Vec<i64> = sqlx::query_scalar(
+            "SELECT fee_amount FROM fee_data_points
+             WHERE timestamp >= ? AND timestamp <= ?
+               AND (timestamp < ? OR timestamp >= ?)",
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .bind(inner_from.to_rfc3339())
+        .bind(inner_to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(fees.into_iter().map(|fee| fee as u64).collect())
     }
 
     /// Insert a fee snapshot (point-in-time Horizon fee_stats capture).
@@ -158,7 +390,593 @@ impl FeeRepository {
 
         Ok(result.rows_affected())
     }
-}
+
+    /// Number of fee data points older than `cutoff`, i.e. what
+    /// [`prune_older_than`](Self::prune_older_than) would delete.
+    pub async fn count_older_than(&self, cutoff: DateTime<Utc>) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM fee_data_points WHERE timestamp < ?")
+            .bind(cutoff.to_rfc3339())
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    // ---- Fee rollups ----
+
+    /// Fold `points` into the rollups at `resolution` (e.g. `"1m"`), merging
+    /// with any existing buckets. Returns the number of buckets touched.
+    pub async fn merge_rollups(
+        &self,
+        resolution: &str,
+        points: &[FeeDataPoint],
+    ) -> Result<usize, sqlx::Error> {
+        self.fold_rollups(resolution, points, false).await
+    }
+
+    /// Build rollups at each of `resolutions` for buckets holding raw points
+    /// older than `cutoff` that have none yet, e.g. for imported points or
+    /// ones stored before a resolution was maintained. Buckets are built
+    /// whole: one straddling `cutoff` also takes the raw points after it, so
+    /// it is complete once those before `cutoff` are pruned. Existing
+    /// buckets are left alone. Returns the number of buckets created.
+    ///
+    /// Raw points are pruned up to the cutoff after every call, so only the
+    /// buckets of points that crossed it since are looked at, and only the
+    /// points of those without a rollup are read.
+    pub async fn downsample_before(
+        &self,
+        cutoff: DateTime<Utc>,
+        resolutions: &[&str],
+    ) -> Result<usize, sqlx::Error> {
+        let mut created = 0;
+        for resolution in resolutions {
+            let width = rollup_width(resolution)?;
+            let rows = sqlx::query(
+                "WITH expiring AS (
+                     SELECT DISTINCT CAST(strftime('%s', timestamp) AS INTEGER) / ?1 * ?1 AS start
+                     FROM fee_data_points
+                     WHERE timestamp < ?2
+                 ),
+                 missing AS (
+                     SELECT strftime('%Y-%m-%dT%H:%M:%S+00:00', start, 'unixepoch') AS bucket_start,
+                            strftime('%Y-%m-%dT%H:%M:%S+00:00', start + ?1, 'unixepoch') AS bucket_end
+                     FROM expiring
+                 )
+                 SELECT p.fee_amount, p.timestamp, p.transaction_hash, p.ledger_sequence
+                 FROM missing m
+                 JOIN fee_data_points p
+                   ON p.timestamp >= m.bucket_start AND p.timestamp < m.bucket_end
+                 WHERE NOT EXISTS (
+                     SELECT 1 FROM fee_rollups r
+                     WHERE r.resolution = ?3 AND r.bucket_start = m.bucket_start
+                 )
+                 ORDER BY p.timestamp ASC",
+            )
+            .bind(width)
+            .bind(cutoff.to_rfc3339())
+            .bind(resolution)
+            .fetch_all(&self.pool)
+            .await?;
+            let points: Vec<FeeDataPoint> = rows.into_iter().filter_map(row_to_fee_point).collect();
+            if !points.is_empty() {
+                created += self.fold_rollups(resolution, &points, true).await?;
+            }
+        }
+        Ok(created)
+    }
+
+    /// Build every tier in `resolutions` that has no buckets at all from the
+    /// stored raw points, e.g. tiers added after those points were written.
+    /// Tiers holding any bucket are already maintained and left alone.
+    /// Points are read a day at a time, so every bucket is built whole from
+    /// one batch. Returns the number of buckets created.
+    pub async fn backfill_empty_rollups(&self, resolutions: &[&str]) -> Result<usize, sqlx::Error> {
+        let mut empty = Vec::new();
+        for resolution in resolutions {
+            let buckets: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM fee_rollups WHERE resolution = ?")
+                .bind(resolution)
+                .fetch_one(&self.pool)
+                .await?;
+            if buckets == 0 {
+                empty.push(*resolution);
+            }
+        }
+        if empty.is_empty() {
+            return Ok(0);
+        }
+
+        let span: (Option<String>, Option<String>) =
+            sqlx::query_as("SELECT MIN(timestamp), MAX(timestamp) FROM fee_data_points")
+                .fetch_one(&self.pool)
+                .await?;
+        let parse = |value: Option<String>| {
+            value.and_then(|v| DateTime::parse_from_rfc3339(&v).ok()).map(|at| at.with_timezone(&Utc))
+        };
+        let (Some(oldest), Some(newest)) = (parse(span.0), parse(span.1)) else {
+            return Ok(0);
+        };
+
+        let day = chrono::Duration::days(1);
+        let mut batch_start = DateTime::from_timestamp(oldest.timestamp().div_euclid(86_400) * 86_400, 0)
+            .unwrap_or(oldest);
+        let mut created = 0;
+        while batch_start <= newest {
+            let points = self.fetch_range(batch_start, batch_start + day).await?;
+            for resolution in &empty {
+                created += self.fold_rollups(resolution, &points, true).await?;
+            }
+            batch_start += day;
+        }
+        Ok(created)
+    }
+
+    /// Write `points` into rollups at `resolution`. With `missing_only`,
+    /// buckets that already exist are skipped instead of merged into.
+    async fn fold_rollups(
+        &self,
+        resolution: &str,
+        points: &[FeeDataPoint],
+        missing_only: bool,
+    ) -> Result<usize, sqlx::Error> {
+        let width = rollup_width(resolution)?;
+
+        let mut buckets: std::collections::BTreeMap<i64, Vec<&FeeDataPoint>> =
+            std::collections::BTreeMap::new();
+        for point in points {
+            let start = point.timestamp.timestamp().div_euclid(width) * width;
+            buckets.entry(start).or_default().push(point);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let mut written = 0;
+
+        for (start, fees) in &buckets {
+            let Some(bucket_start) = DateTime::from_timestamp(*start, 0) else {
+                continue;
+            };
+            let bucket_str = bucket_start.to_rfc3339();
+
+            let existing = sqlx::query(
+                "SELECT resolution, bucket_start, sample_count, fee_sum, min_fee, max_fee, sketch,
+                        open_fee, open_at, close_fee, close_at
+                 FROM fee_rollups
+                 WHERE resolution = ? AND bucket_start = ?",
+            )
+            .bind(resolution)
+            .bind(&bucket_str)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if missing_only && existing.is_some() {
+                continue;
+            }
+            let mut rollup = existing
+                .and_then(row_to_rollup)
+                .unwrap_or_else(|| FeeRollup::empty(resolution, bucket_start));
+            for point in fees {
+                rollup.add(point.fee_amount, point.timestamp);
+            }
+
+            let sketch_json = serde_json::to_string(&rollup.sketch)
+                .map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+
+            sqlx::query(
+                "INSERT OR REPLACE INTO fee_rollups
+                 (resolution, bucket_start, sample_count, fee_sum, min_fee, max_fee, sketch,
+                  open_fee, open_at, close_fee, close_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(resolution)
+            .bind(&bucket_str)
+            .bind(rollup.sample_count as i64)
+            .bind(rollup.fee_sum as i64)
+            .bind(rollup.min_fee as i64)
+            .bind(rollup.max_fee as i64)
+            .bind(&sketch_json)
+            .bind(rollup.open_fee as i64)
+            .bind(rollup.open_at.map(|at| at.to_rfc3339()))
+            .bind(rollup.close_fee as i64)
+            .bind(rollup.close_at.map(|at| at.to_rfc3339()))
+            .execute(&mut *tx)
+            .await?;
+            written += 1;
+        }
+
+        tx.commit().await?;
+        Ok(written)
+    }
+
+    /// Fetch rollups at `resolution` with `from <= bucket_start < to`, ordered ascending.
+    pub async fn fetch_rollups(
+        &self,
+        resolution: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<FeeRollup>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT resolution, bucket_start, sample_count, fee_sum, min_fee, max_fee, sketch,
+                    open_fee, open_at, close_fee, close_at
+             FROM fee_rollups
+             WHERE resolution = ? AND bucket_start >= ? AND bucket_start < ?
+             ORDER BY bucket_start ASC",
+        )
+        .bind(resolution)
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().filter_map(row_to_rollup).collect())
+    }
+
+    /// Delete rollups at `resolution` whose bucket starts before `cutoff`.
+    pub async fn prune_rollups_older_than(
+        &self,
+        resolution: &str,
+        cutoff: DateTime<Utc>,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM fee_rollups WHERE resolution = ? AND bucket_start < ?")
+            .bind(resolution)
+            .bind(cutoff.to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn count_rollups_older_than(
+        &self,
+        resolution: &str,
+        cutoff: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM fee_rollups WHERE resolution = ? AND bucket_start < ?")
+            .bind(resolution)
+            .bind(cutoff.to_rfc3339())
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    // ---- Fee spikes ----
+
+    /// Insert a spike or overwrite the stored state of the spike with the
+    /// same ID, as of `updated_at`.
+    pub async fn upsert_spike(&self, spike: &FeeSpike, updated_at: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO fee_spikes
+             (id, severity, strategy, score, peak_fee, baseline_fee, spike_ratio,
+              start_time, end_time, duration_secs, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&spike.id)
+        .bind(spike.severity.as_str())
+        .bind(&spike.strategy)
+        .bind(spike.score)
+        .bind(spike.peak_fee as i64)
+        .bind(spike.baseline_fee)
+        .bind(spike.spike_ratio)
+        .bind(spike.start_time.to_rfc3339())
+        .bind(spike.end_time.map(|t| t.to_rfc3339()))
+        .bind(spike.duration.num_seconds())
+        .bind(updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Query spikes overlapping `[from, to)`, newest first. Open spikes
+    /// overlap any range that ends after they started.
+    pub async fn query_spikes(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        severity_filter: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<FeeSpike>, sqlx::Error> {
+        let limit = limit.clamp(1, 500);
+
+        let mut conditions = vec!["1=1"];
+        if from.is_some() {
+            conditions.push("(end_time IS NULL OR end_time >= ?)");
+        }
+        if to.is_some() {
+            conditions.push("start_time < ?");
+        }
+        if severity_filter.is_some() {
+            conditions.push("severity = ?");
+        }
+
+        let sql = format!(
+            "SELECT id, severity, strategy, score, peak_fee, baseline_fee, spike_ratio,
+                    start_time, end_time, duration_secs
+             FROM fee_spikes
+             WHERE {}
+             ORDER BY start_time DESC
+             LIMIT ?",
+            conditions.join(" AND ")
+        );
+
+        let mut q = sqlx::query(&sql);
+        if let Some(from) = from {
+            q = q.bind(from.to_rfc3339());
+        }
+        if let Some(to) = to {
+            q = q.bind(to.to_rfc3339());
+        }
+        if let Some(severity) = severity_filter {
+            q = q.bind(severity);
+        }
+        let rows = q.bind(limit).fetch_all(&self.pool).await?;
+
+        Ok(rows.into_iter().filter_map(row_to_spike).collect())
+    }
+
+    /// Fetch a single spike by ID.
+    pub async fn get_spike(&self, id: &str) -> Result<Option<FeeSpike>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT id, severity, strategy, score, peak_fee, baseline_fee, spike_ratio,
+                    start_time, end_time, duration_secs
+             FROM fee_spikes
+             WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.and_then(row_to_spike))
+    }
+
+    // ---- Extreme periods ----
+
+    /// Store a closed extremes period, replacing any row for the same start.
+    pub async fn insert_extreme_period(&self, extremes: &FeeExtremes) -> Result<(), sqlx::Error> {
+        let to_json = |values: &[ExtremeValue]| {
+            serde_json::to_string(values).map_err(|e| sqlx::Error::Protocol(e.to_string()))
+        };
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO fee_extreme_periods
+             (period_start, period_end, min_fee, max_fee, lowest, highest)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(extremes.period_start.to_rfc3339())
+        .bind(extremes.period_end.to_rfc3339())
+        .bind(extremes.current_min.value as i64)
+        .bind(extremes.current_max.value as i64)
+        .bind(to_json(&extremes.lowest)?)
+        .bind(to_json(&extremes.highest)?)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetch the `limit` most recent extremes periods, newest first.
+    pub async fn fetch_extreme_periods(&self, limit: i64) -> Result<Vec<FeeExtremes>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT period_start, period_end, lowest, highest
+             FROM fee_extreme_periods
+             ORDER BY period_start DESC
+             LIMIT ?",
+        )
+        .bind(limit.max(1))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().filter_map(row_to_extremes).collect())
+    }
+
+    // ---- Engine snapshots ----
+
+    /// Save the insights engine snapshot, replacing any previous one.
+    pub async fn save_engine_snapshot(&self, snapshot: &EngineSnapshot) -> Result<(), sqlx::Error> {
+        let payload =
+            serde_json::to_string(snapshot).map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO engine_snapshots (name, version, taken_at, payload)
+             VALUES (?, ?, ?, ?)",
+        )
+        .bind(INSIGHTS_SNAPSHOT_NAME)
+        .bind(snapshot.version as i64)
+        .bind(snapshot.taken_at.to_rfc3339())
+        .bind(payload)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load the saved insights engine snapshot, if there is one.
+    pub async fn load_engine_snapshot(&self) -> Result<Option<EngineSnapshot>, sqlx::Error> {
+        let row = sqlx::query("SELECT version, payload FROM engine_snapshots WHERE name = ?")
+            .bind(INSIGHTS_SNAPSHOT_NAME)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        use sqlx::Row;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let version: i64 = row.try_get("version")?;
+        let payload: String = row.try_get("payload")?;
+
+        serde_json::from_str(&payload).map(Some).map_err(|e| {
+            sqlx::Error::Protocol(format!("Unreadable engine snapshot (version {}): {}", version, e))
+        })
+    }
+
+    // ---- Insights snapshots ----
+
+    /// Record computed insights, keyed by their `last_updated` time.
+    pub async fn insert_insights_snapshot(&self, insights: &CurrentInsights) -> Result<(), sqlx::Error> {
+        let payload =
+            serde_json::to_string(insights).map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+
+        sqlx::query("INSERT OR REPLACE INTO insights_snapshots (taken_at, payload) VALUES (?, ?)")
+            .bind(insights.last_updated.to_rfc3339())
+            .bind(payload)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The most recent insights recorded at or before `at`, provided they
+    /// are no older than `max_age`.
+    pub async fn fetch_insights_snapshot_at(
+        &self,
+        at: DateTime<Utc>,
+        max_age: chrono::Duration,
+    ) -> Result<Option<CurrentInsights>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT payload FROM insights_snapshots
+             WHERE taken_at <= ? AND taken_at >= ?
+             ORDER BY taken_at DESC
+             LIMIT 1",
+        )
+        .bind(at.to_rfc3339())
+        .bind((at - max_age).to_rfc3339())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        use sqlx::Row;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let payload: String = row.try_get("payload")?;
+
+        serde_json::from_str(&payload)
+            .map(Some)
+            .map_err(|e| sqlx::Error::Protocol(e.to_string()))
+    }
+
+    /// Delete insights snapshots recorded before `cutoff`.
+    /// Returns the number of rows deleted.
+    pub async fn prune_insights_snapshots_older_than(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM insights_snapshots WHERE taken_at < ?")
+            .bind(cutoff.to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn count_insights_snapshots_older_than(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM insights_snapshots WHERE taken_at < ?")
+            .bind(cutoff.to_rfc3339())
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    // ---- Runtime insights config ----
+
+    /// Store `new` as the active insights configuration and append the
+    /// change to the audit trail, in one transaction.
+    pub async fn save_insights_config(
+        &self,
+        previous: &InsightsConfig,
+        new: &InsightsConfig,
+        actor: Option<&str>,
+        changed_at: DateTime<Utc>,
+    ) -> Result<InsightsConfigChange, sqlx::Error> {
+        let encode = |config: &InsightsConfig| {
+            serde_json::to_string(config).map_err(|e| sqlx::Error::Protocol(e.to_string()))
+        };
+        let previous_payload = encode(previous)?;
+        let new_payload = encode(new)?;
+        let changed_at_str = changed_at.to_rfc3339();
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO insights_config (name, updated_at, payload) VALUES (?, ?, ?)",
+        )
+        .bind(ACTIVE_INSIGHTS_CONFIG)
+        .bind(&changed_at_str)
+        .bind(&new_payload)
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query(
+            "INSERT INTO insights_config_changes (changed_at, actor, previous_config, new_config)
+             VALUES (?, ?, ?, ?)",
+        )
+        .bind(&changed_at_str)
+        .bind(actor)
+        .bind(&previous_payload)
+        .bind(&new_payload)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(InsightsConfigChange {
+            id: result.last_insert_rowid(),
+            changed_at,
+            actor: actor.map(str::to_string),
+            previous_config: previous.clone(),
+            new_config: new.clone(),
+        })
+    }
+
+    /// Load the insights configuration last applied at runtime, if any.
+    pub async fn load_insights_config(&self) -> Result<Option<InsightsConfig>, sqlx::Error> {
+        let row = sqlx::query("SELECT payload FROM insights_config WHERE name = ?")
+            .bind(ACTIVE_INSIGHTS_CONFIG)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        use sqlx::Row;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let payload: String = row.try_get("payload")?;
+
+        serde_json::from_str(&payload)
+            .map(Some)
+            .map_err(|e| sqlx::Error::Protocol(format!("Unreadable insights config: {}", e)))
+    }
+
+    /// Most recent insights configuration changes, newest first.
+    pub async fn list_insights_config_changes(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<InsightsConfigChange>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, changed_at, actor, previous_config, new_config
+             FROM insights_config_changes
+             ORDER BY id DESC
+             LIMIT ?",
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        use sqlx::Row;
+        let decode = |payload: String| -> Result<InsightsConfig, sqlx::Error> {
+            serde_json::from_str(&payload).map_err(|e| sqlx::Error::Protocol(e.to_string()))
+        };
+        rows.into_iter()
+            .map(|row| {
+                let changed_at: String = row.try_get("changed_at")?;
+                Ok(InsightsConfigChange {
+                    id: row.try_get("id")?,
+                    changed_at: DateTime::parse_from_rfc3339(&changed_at)
+                        .map_err(|e| sqlx::Error::Protocol(e.to_string()))?
+                        .with_timezone(&Utc),
+                    actor: row.try_get("actor")?,
+                    previous_config: decode(row.try_get("previous_config")?)?,
+                    new_config: decode(row.try_get("new_config")?)?,
+                })
+            })
+            .collect()
+    }
 
     // ---- Alert config CRUD ----
 
@@ -384,6 +1202,196 @@ impl FeeRepository {
         Ok(count)
     }
 
+    // ---- Bulk export ----
+
+    /// Fetch up to `limit` rows of an export, ordered by the dataset's time
+    /// column then rowid, starting after the `(time, rowid)` key `after`.
+    pub async fn fetch_export_batch(
+        &self,
+        request: &ExportRequest,
+        after: Option<(&str, i64)>,
+        limit: i64,
+    ) -> Result<Vec<ExportRow>, sqlx::Error> {
+        use sqlx::Row;
+
+        let time = request.dataset.time_column();
+        let columns: Vec<&str> = request.columns.iter().map(|c| c.name).collect();
+        let resolution = match request.dataset {
+            ExportDataset::Rollups => request.resolution.as_deref(),
+            _ => None,
+        };
+        // Identifiers come from the dataset's fixed column list
+        let sql = format!(
+            "SELECT {time} AS export_time, rowid AS export_rowid, {columns}
+             FROM {table}
+             WHERE {time} >= ?1 AND {time} < ?2
+               AND (?3 IS NULL OR {time} > ?3 OR ({time} = ?3 AND rowid > ?4))
+               {resolution_filter}
+             ORDER BY {time} ASC, rowid ASC
+             LIMIT ?5",
+            time = time,
+            columns = columns.join(", "),
+            table = request.dataset.table(),
+            resolution_filter = if resolution.is_some() { "AND resolution = ?6" } else { "" },
+        );
+
+        let (after_time, after_rowid) = match after {
+            Some((time, rowid)) => (Some(time), rowid),
+            None => (None, 0),
+        };
+        let mut query = sqlx::query(&sql)
+            .bind(request.from.to_rfc3339())
+            .bind(request.to.to_rfc3339())
+            .bind(after_time)
+            .bind(after_rowid)
+            .bind(limit);
+        if let Some(resolution) = resolution {
+            query = query.bind(resolution);
+        }
+        let rows = query.fetch_all(&self.pool).await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let values = request
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .map(|(index, column)| {
+                        let index = index + 2;
+                        Ok(match column.kind {
+                            ColumnKind::Text => ExportValue::Text(row.try_get(index)?),
+                            ColumnKind::Integer => ExportValue::Integer(row.try_get(index)?),
+                            ColumnKind::Real => ExportValue::Real(row.try_get(index)?),
+                        })
+                    })
+                    .collect::<Result<_, sqlx::Error>>()?;
+                Ok(ExportRow {
+                    time: row.try_get("export_time")?,
+                    rowid: row.try_get("export_rowid")?,
+                    values,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Map a `fee_data_points` row to a [`FeeDataPoint`], skipping malformed rows.
+/// Bucket width of a rollup resolution in seconds.
+fn rollup_width(resolution: &str) -> Result<i64, sqlx::Error> {
+    parse_duration(resolution)
+        .map(|d| d.num_seconds())
+        .filter(|secs| *secs > 0)
+        .ok_or_else(|| sqlx::Error::Protocol(format!("invalid rollup resolution: {}", resolution)))
+}
+
+fn row_to_fee_point(row: sqlx::sqlite::SqliteRow) -> Option<FeeDataPoint> {
+    use sqlx::Row;
+    let fee_amount: i64 = row.try_get("fee_amount").ok()?;
+    let timestamp_str: String = row.try_get("timestamp").ok()?;
+    let transaction_hash: String = row.try_get("transaction_hash").ok()?;
+    let ledger_sequence: i64 = row.try_get("ledger_sequence").ok()?;
+
+    let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
+        .ok()?
+        .with_timezone(&Utc);
+
+    Some(FeeDataPoint {
+        fee_amount: fee_amount as u64,
+        timestamp,
+        transaction_hash,
+        ledger_sequence: ledger_sequence as u64,
+    })
+}
+
+/// Map a `fee_spikes` row to a [`FeeSpike`], skipping malformed rows.
+fn row_to_spike(row: sqlx::sqlite::SqliteRow) -> Option<FeeSpike> {
+    use sqlx::Row;
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    };
+
+    let severity: String = row.try_get("severity").ok()?;
+    let peak_fee: i64 = row.try_get("peak_fee").ok()?;
+    let start_time: String = row.try_get("start_time").ok()?;
+    let end_time: Option<String> = row.try_get("end_time").ok()?;
+    let duration_secs: i64 = row.try_get("duration_secs").ok()?;
+
+    Some(FeeSpike {
+        id: row.try_get("id").ok()?,
+        peak_fee: peak_fee as u64,
+        baseline_fee: row.try_get("baseline_fee").ok()?,
+        spike_ratio: row.try_get("spike_ratio").ok()?,
+        start_time: parse_time(&start_time)?,
+        duration: chrono::Duration::seconds(duration_secs),
+        severity: SpikeSeverity::from_name(&severity)?,
+        strategy: row.try_get("strategy").ok()?,
+        score: row.try_get("score").ok()?,
+        end_time: match end_time {
+            Some(value) => Some(parse_time(&value)?),
+            None => None,
+        },
+    })
+}
+
+/// Map a `fee_extreme_periods` row to [`FeeExtremes`], skipping malformed rows.
+fn row_to_extremes(row: sqlx::sqlite::SqliteRow) -> Option<FeeExtremes> {
+    use sqlx::Row;
+    let period_start: String = row.try_get("period_start").ok()?;
+    let period_end: String = row.try_get("period_end").ok()?;
+    let lowest: String = row.try_get("lowest").ok()?;
+    let highest: String = row.try_get("highest").ok()?;
+
+    let lowest: Vec<ExtremeValue> = serde_json::from_str(&lowest).ok()?;
+    let highest: Vec<ExtremeValue> = serde_json::from_str(&highest).ok()?;
+
+    Some(FeeExtremes {
+        current_min: lowest.first()?.clone(),
+        current_max: highest.first()?.clone(),
+        period_start: DateTime::parse_from_rfc3339(&period_start).ok()?.with_timezone(&Utc),
+        period_end: DateTime::parse_from_rfc3339(&period_end).ok()?.with_timezone(&Utc),
+        lowest,
+        highest,
+    })
+}
+
+/// Map a `fee_rollups` row to a [`FeeRollup`], skipping malformed rows.
+fn row_to_rollup(row: sqlx::sqlite::SqliteRow) -> Option<FeeRollup> {
+    use sqlx::Row;
+    let resolution: String = row.try_get("resolution").ok()?;
+    let bucket_start_str: String = row.try_get("bucket_start").ok()?;
+    let sample_count: i64 = row.try_get("sample_count").ok()?;
+    let fee_sum: i64 = row.try_get("fee_sum").ok()?;
+    let min_fee: i64 = row.try_get("min_fee").ok()?;
+    let max_fee: i64 = row.try_get("max_fee").ok()?;
+    let sketch_json: String = row.try_get("sketch").ok()?;
+    let open_fee: Option<i64> = row.try_get("open_fee").ok()?;
+    let open_at: Option<String> = row.try_get("open_at").ok()?;
+    let close_fee: Option<i64> = row.try_get("close_fee").ok()?;
+    let close_at: Option<String> = row.try_get("close_at").ok()?;
+
+    let bucket_start = DateTime::parse_from_rfc3339(&bucket_start_str)
+        .ok()?
+        .with_timezone(&Utc);
+    let parse_at = |at: Option<String>| {
+        at.and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+            .map(|at| at.with_timezone(&Utc))
+    };
+
+    Some(FeeRollup {
+        resolution,
+        bucket_start,
+        sample_count: sample_count as u64,
+        fee_sum: fee_sum as u64,
+        min_fee: min_fee as u64,
+        max_fee: max_fee as u64,
+        sketch: serde_json::from_str(&sketch_json).ok()?,
+        open_fee: open_fee.unwrap_or_default() as u64,
+        open_at: parse_at(open_at),
+        close_fee: close_fee.unwrap_or_default() as u64,
+        close_at: parse_at(close_at),
+    })
 }
 
 #[cfg(test)]
@@ -466,9 +1474,11 @@ mod tests {
         repo.insert_fee_points(&points).await.unwrap();
 
         let cutoff = Utc::now() - Duration::hours(1);
+        assert_eq!(repo.count_older_than(cutoff).await.unwrap(), 1);
         let deleted = repo.prune_older_than(cutoff).await.unwrap();
 
         assert_eq!(deleted, 1);
+        assert_eq!(repo.count_older_than(cutoff).await.unwrap(), 0);
 
         let remaining = repo.fetch_since(Utc::now() - Duration::days(1)).await.unwrap();
         assert_eq!(remaining.len(), 2);
@@ -509,6 +1519,305 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn fetch_range_excludes_points_at_or_after_upper_bound() {
+        let repo = make_repo().await;
+        let points = vec![
+            make_point(100, 7200), // 2 hours ago — before range
+            make_point(200, 1800), // 30 min ago — inside range
+            make_point(300, 60),   // 1 min ago — after range
+        ];
+
+        repo.insert_fee_points(&points).await.unwrap();
+
+        let fetched = repo
+            .fetch_range(Utc::now() - Duration::hours(1), Utc::now() - Duration::minutes(5))
+            .await
+            .unwrap();
+
+        assert_eq!(fetched.len(), 1);
+        assert_eq!(fetched[0].fee_amount, 200);
+    }
+
+    #[tokio::test]
+    async fn fetch_page_continues_after_key_within_shared_timestamp() {
+        let repo = make_repo().await;
+        let at = Utc::now() - Duration::minutes(10);
+        let point = |fee_amount: u64, timestamp| FeeDataPoint {
+            fee_amount,
+            timestamp,
+            transaction_hash: format!("hash_{}", fee_amount),
+            ledger_sequence: 1,
+        };
+        // Three points share a ledger close time
+        repo.insert_fee_points(&[
+            point(300, at),
+            point(100, at),
+            point(200, at),
+            point(400, at + Duration::seconds(5)),
+        ])
+        .await
+        .unwrap();
+        let (from, to) = (at - Duration::minutes(1), Utc::now());
+
+        let first = repo.fetch_page(from, to, None, 2).await.unwrap();
+        let fees: Vec<u64> = first.iter().map(|p| p.fee_amount).collect();
+        assert_eq!(fees, vec![100, 200]);
+
+        let last = &first[1];
+        let second = repo
+            .fetch_page(from, to, Some((last.timestamp, &last.transaction_hash)), 2)
+            .await
+            .unwrap();
+        let fees: Vec<u64> = second.iter().map(|p| p.fee_amount).collect();
+        assert_eq!(fees, vec![300, 400]);
+
+        let stats = repo.fee_amount_stats(from, to).await.unwrap();
+        assert_eq!(stats, FeeAmountStats { count: 4, min: 100, max: 400, avg: 250.0 });
+        // Repeated ranks are fine; ranks past the last point are left out
+        let ranked = repo.fee_amounts_at_ranks(from, to, &[2, 2, 4, 5]).await.unwrap();
+        assert_eq!(ranked, vec![200, 200, 400]);
+        let ends = repo
+            .fetch_fee_amounts_outside(from, to, at, at + Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(ends, vec![400]);
+
+        let empty = repo.fee_amount_stats(to, to + Duration::minutes(1)).await.unwrap();
+        assert_eq!(empty.count, 0);
+    }
+
+    #[tokio::test]
+    async fn merge_rollups_accumulates_into_existing_buckets() {
+        let repo = make_repo().await;
+        let bucket = DateTime::from_timestamp(1_700_000_040, 0).unwrap();
+        let point = |fee_amount: u64, offset_secs: i64| FeeDataPoint {
+            fee_amount,
+            timestamp: bucket + Duration::seconds(offset_secs),
+            transaction_hash: format!("hash_{}", fee_amount),
+            ledger_sequence: 1,
+        };
+
+        let touched = repo
+            .merge_rollups(ROLLUP_RESOLUTION_1M, &[point(100, 5), point(300, 30), point(900, 75)])
+            .await
+            .unwrap();
+        assert_eq!(touched, 2);
+
+        // A later tick lands in the first bucket again
+        repo.merge_rollups(ROLLUP_RESOLUTION_1M, &[point(200, 50)]).await.unwrap();
+
+        let rollups = repo
+            .fetch_rollups(ROLLUP_RESOLUTION_1M, bucket, bucket + Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(rollups.len(), 2);
+        assert_eq!(rollups[0].bucket_start, bucket);
+        assert_eq!(rollups[0].sample_count, 3);
+        assert_eq!(rollups[0].fee_sum, 600);
+        assert_eq!(rollups[0].min_fee, 100);
+        assert_eq!(rollups[0].max_fee, 300);
+        assert_eq!(rollups[0].sketch.count(), 3);
+        // Open and close follow timestamps across ticks
+        assert_eq!((rollups[0].open_fee, rollups[0].close_fee), (100, 200));
+        assert_eq!(rollups[1].sample_count, 1);
+
+        assert!(repo.merge_rollups("soon", &[point(100, 0)]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn downsample_builds_the_bucket_straddling_the_cutoff_whole() {
+        let repo = make_repo().await;
+        let bucket = DateTime::from_timestamp(1_700_000_100, 0).unwrap();
+        let cutoff = bucket + Duration::minutes(2);
+        let points: Vec<FeeDataPoint> = [(100, 30), (200, 150), (300, 200)]
+            .iter()
+            .map(|&(fee_amount, offset_secs)| FeeDataPoint {
+                fee_amount,
+                timestamp: bucket + Duration::seconds(offset_secs),
+                transaction_hash: format!("hash_{}", fee_amount),
+                ledger_sequence: 1,
+            })
+            .collect();
+        repo.insert_fee_points(&points).await.unwrap();
+
+        assert_eq!(repo.downsample_before(cutoff, &["5m"]).await.unwrap(), 1);
+        // The bucket has a rollup now, so it is not read again
+        assert_eq!(repo.downsample_before(cutoff, &["5m"]).await.unwrap(), 0);
+        repo.prune_older_than(cutoff).await.unwrap();
+
+        let rollups = repo.fetch_rollups("5m", bucket, bucket + Duration::minutes(5)).await.unwrap();
+        assert_eq!(rollups.len(), 1);
+        assert_eq!(rollups[0].sample_count, 3);
+        assert_eq!((rollups[0].open_fee, rollups[0].close_fee), (100, 300));
+        // Nothing left before the cutoff, so a later pass adds nothing
+        assert_eq!(repo.downsample_before(cutoff, &["5m"]).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn backfill_builds_only_tiers_without_buckets() {
+        let repo = make_repo().await;
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let points: Vec<FeeDataPoint> = (0..6)
+            .map(|i| FeeDataPoint {
+                fee_amount: 100 * (i + 1),
+                timestamp: start + Duration::minutes(i as i64 * 3),
+                transaction_hash: format!("hash_{}", i),
+                ledger_sequence: i,
+            })
+            .collect();
+        repo.insert_fee_points(&points).await.unwrap();
+        // Stored while only the 1m tier was maintained
+        repo.merge_rollups(ROLLUP_RESOLUTION_1M, &points).await.unwrap();
+
+        let created = repo.backfill_empty_rollups(ROLLUP_RESOLUTIONS).await.unwrap();
+        assert!(created > 0);
+
+        let range = (start - Duration::days(1), start + Duration::days(1));
+        for resolution in ROLLUP_RESOLUTIONS {
+            let rollups = repo.fetch_rollups(resolution, range.0, range.1).await.unwrap();
+            let total: u64 = rollups.iter().map(|r| r.sample_count).sum();
+            assert_eq!(total, 6, "{} rollups", resolution);
+        }
+
+        // Every tier is populated now, so a second run is a no-op
+        assert_eq!(repo.backfill_empty_rollups(ROLLUP_RESOLUTIONS).await.unwrap(), 0);
+    }
+
+    fn make_spike(start: DateTime<Utc>, minutes: i64, severity: SpikeSeverity, open: bool) -> FeeSpike {
+        FeeSpike {
+            id: FeeSpike::id_for(start),
+            peak_fee: 900,
+            baseline_fee: 100.0,
+            spike_ratio: 9.0,
+            start_time: start,
+            duration: Duration::minutes(minutes),
+            severity,
+            strategy: "threshold".to_string(),
+            score: 9.0,
+            end_time: (!open).then(|| start + Duration::minutes(minutes)),
+        }
+    }
+
+    #[tokio::test]
+    async fn upsert_spike_keeps_latest_state_per_id() {
+        let repo = make_repo().await;
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        repo.upsert_spike(&make_spike(start, 5, SpikeSeverity::Major, true), start + Duration::minutes(5))
+            .await
+            .unwrap();
+        let ended_at = start + Duration::minutes(20);
+        repo.upsert_spike(&make_spike(start, 20, SpikeSeverity::Critical, false), ended_at)
+            .await
+            .unwrap();
+
+        let spike = repo.get_spike(&FeeSpike::id_for(start)).await.unwrap().unwrap();
+        // Stamped with the caller's time, not the wall clock
+        let updated_at: String = sqlx::query_scalar("SELECT updated_at FROM fee_spikes WHERE id = ?")
+            .bind(&spike.id)
+            .fetch_one(&repo.pool)
+            .await
+            .unwrap();
+        assert_eq!(updated_at, ended_at.to_rfc3339());
+        assert_eq!(spike.duration, Duration::minutes(20));
+        assert_eq!(spike.severity, SpikeSeverity::Critical);
+        assert_eq!(spike.end_time, Some(start + Duration::minutes(20)));
+        assert!(repo.get_spike("spk_missing").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn query_spikes_filters_by_overlap_and_severity() {
+        let repo = make_repo().await;
+        let t0 = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        let early = make_spike(t0, 10, SpikeSeverity::Minor, false);
+        let late = make_spike(t0 + Duration::hours(2), 10, SpikeSeverity::Major, false);
+        let open = make_spike(t0 + Duration::hours(5), 10, SpikeSeverity::Major, true);
+        for spike in [&early, &late, &open] {
+            repo.upsert_spike(spike, spike.start_time).await.unwrap();
+        }
+
+        let all = repo.query_spikes(None, None, None, 100).await.unwrap();
+        let ids: Vec<&str> = all.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec![open.id.as_str(), late.id.as_str(), early.id.as_str()]);
+
+        // Window touching only the tail of the early spike
+        let tail = repo
+            .query_spikes(Some(t0 + Duration::minutes(5)), Some(t0 + Duration::hours(1)), None, 100)
+            .await
+            .unwrap();
+        assert_eq!(tail.len(), 1);
+        assert_eq!(tail[0].id, early.id);
+
+        // Open spikes overlap any later window
+        let after = repo
+            .query_spikes(Some(t0 + Duration::hours(10)), None, Some("Major"), 100)
+            .await
+            .unwrap();
+        assert_eq!(after.len(), 1);
+        assert!(after[0].is_open());
+
+        let major = repo.query_spikes(None, None, Some("Major"), 100).await.unwrap();
+        assert_eq!(major.len(), 2);
+    }
+
+    fn make_extremes(period_start: DateTime<Utc>, min: u64, max: u64) -> FeeExtremes {
+        let value = |fee: u64| ExtremeValue {
+            value: fee,
+            timestamp: period_start + Duration::hours(1),
+            transaction_hash: format!("hash_{}", fee),
+        };
+        FeeExtremes {
+            current_min: value(min),
+            current_max: value(max),
+            period_start,
+            period_end: period_start + Duration::hours(24),
+            lowest: vec![value(min), value(min + 1)],
+            highest: vec![value(max), value(max - 1)],
+        }
+    }
+
+    #[tokio::test]
+    async fn extreme_periods_roundtrip_newest_first() {
+        let repo = make_repo().await;
+        let t0 = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        for day in 0..3 {
+            repo.insert_extreme_period(&make_extremes(t0 + Duration::days(day), 100, 5_000 + day as u64))
+                .await
+                .unwrap();
+        }
+
+        let periods = repo.fetch_extreme_periods(2).await.unwrap();
+        assert_eq!(periods.len(), 2);
+        assert_eq!(periods[0].period_start, t0 + Duration::days(2));
+        assert_eq!(periods[0].current_max.value, 5_002);
+        assert_eq!(periods[0].highest.len(), 2);
+        assert_eq!(periods[1].lowest[1].value, 101);
+    }
+
+    #[tokio::test]
+    async fn engine_snapshot_roundtrip_replaces_previous() {
+        use crate::insights::{FeeInsightsEngine, InsightsConfig};
+
+        let repo = make_repo().await;
+        assert!(repo.load_engine_snapshot().await.unwrap().is_none());
+
+        let mut engine = FeeInsightsEngine::new(InsightsConfig::default());
+        repo.save_engine_snapshot(&engine.snapshot()).await.unwrap();
+
+        let points = vec![make_point(100, 60), make_point(300, 30)];
+        engine.process_fee_data(&points).await.unwrap();
+        repo.save_engine_snapshot(&engine.snapshot()).await.unwrap();
+
+        let loaded = repo.load_engine_snapshot().await.unwrap().unwrap();
+        let long_term = loaded.windows.iter().find(|saved| saved.window.name == "long_term").unwrap();
+        assert_eq!(long_term.state.sample_count(), 2);
+        assert!(loaded.last_insights.is_some());
+        assert_eq!(loaded.last_point_at, engine.get_last_point_at());
+    }
+
     #[tokio::test]
     async fn fetch_since_returns_empty_when_no_data() {
         let repo = make_repo().await;
@@ -711,4 +2020,33 @@ mod alert_event_tests {
         assert!(events[0].id.is_some());
         assert!(events[0].id.unwrap() > 0);
     }
+
+    #[tokio::test]
+    async fn insights_config_changes_are_persisted_and_audited() {
+        let repo = make_repo().await;
+        assert!(repo.load_insights_config().await.unwrap().is_none());
+
+        let original = InsightsConfig::default();
+        let mut updated = original.clone();
+        updated.spike_detection.threshold_multiplier = 3.5;
+
+        repo.save_insights_config(&original, &updated, Some("ops"), Utc::now())
+            .await
+            .unwrap();
+        let mut again = updated.clone();
+        again.spike_detection.threshold_multiplier = 4.0;
+        repo.save_insights_config(&updated, &again, None, Utc::now())
+            .await
+            .unwrap();
+
+        let active = repo.load_insights_config().await.unwrap().unwrap();
+        assert_eq!(active.spike_detection.threshold_multiplier, 4.0);
+
+        let changes = repo.list_insights_config_changes(10).await.unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].new_config.spike_detection.threshold_multiplier, 4.0);
+        assert_eq!(changes[0].actor, None);
+        assert_eq!(changes[1].actor.as_deref(), Some("ops"));
+        assert_eq!(changes[1].previous_config.spike_detection.threshold_multiplier, 2.0);
+    }
 }
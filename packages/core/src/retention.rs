@@ -0,0 +1,213 @@
+//! Tiered retention.
+//!
+//! Raw fee data points are kept for `raw` and each rollup resolution
+//! for its own number of days, where 0 keeps a tier forever. Before raw
+//! points are deleted, any rollup bucket they fall in that does not exist
+//! yet is built from them, so data leaves the raw tier only once it has
+//! reached every rollup tier.
+//!
+//! Queries use [`RetentionPolicy::finest_covering`] to pick the finest
+//! rollup tier that still holds the start of a range.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock as StdRwLock};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::repository::{FeeRepository, ROLLUP_RESOLUTIONS};
+
+/// Days raw fee data points are kept by default.
+pub const DEFAULT_RAW_RETENTION_DAYS: u64 = 7;
+
+/// Default days each rollup resolution is kept; 0 keeps it forever.
+pub const DEFAULT_ROLLUP_RETENTION_DAYS: &[(&str, u64)] =
+    &[("1m", 90), ("5m", 90), ("15m", 90), ("1h", 0), ("1d", 0)];
+
+/// Retention shared with the API so a reload changes tier selection live.
+pub type SharedRetention = Arc<StdRwLock<RetentionPolicy>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// How long raw fee data points are kept
+    pub raw: Duration,
+    /// Days per rollup resolution; 0 or a missing entry keeps it forever
+    pub rollup_days: BTreeMap<String, u64>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self::with_raw_days(DEFAULT_RAW_RETENTION_DAYS)
+    }
+}
+
+impl RetentionPolicy {
+    /// The default rollup tiers with raw points kept for `raw_days`.
+    pub fn with_raw_days(raw_days: u64) -> Self {
+        Self {
+            raw: Duration::days(raw_days as i64),
+            rollup_days: DEFAULT_ROLLUP_RETENTION_DAYS
+                .iter()
+                .map(|(resolution, days)| (resolution.to_string(), *days))
+                .collect(),
+        }
+    }
+
+    pub fn shared(self) -> SharedRetention {
+        Arc::new(StdRwLock::new(self))
+    }
+
+    /// Raw points older than this are pruned.
+    pub fn raw_cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - self.raw
+    }
+
+    /// Rollups at `resolution` starting before this are pruned; `None`
+    /// when the tier is kept forever.
+    pub fn rollup_cutoff(&self, resolution: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.rollup_days.get(resolution) {
+            Some(&days) if days > 0 => Some(now - Duration::days(days as i64)),
+            _ => None,
+        }
+    }
+
+    /// Whether raw points starting at `from` are still kept.
+    pub fn covers_raw(&self, from: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        from >= self.raw_cutoff(now)
+    }
+
+    /// The finest rollup resolution, no finer than `finest`, whose tier
+    /// still holds data from `from`, else the coarsest. `None` when
+    /// `finest` is not a rollup resolution.
+    pub fn finest_covering(
+        &self,
+        finest: &str,
+        from: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Option<&'static str> {
+        let start = ROLLUP_RESOLUTIONS.iter().position(|r| *r == finest)?;
+        let candidates = &ROLLUP_RESOLUTIONS[start..];
+        candidates
+            .iter()
+            .find(|resolution| {
+                self.rollup_cutoff(resolution, now)
+                    .is_none_or(|cutoff| from >= cutoff)
+            })
+            .or(candidates.last())
+            .copied()
+    }
+}
+
+/// Rows removed, or that would be removed, by one retention pass.
+#[derive(Debug, Default, Serialize)]
+pub struct RetentionReport {
+    /// Rollup buckets built from raw points before they were pruned
+    pub downsampled_buckets: usize,
+    pub fee_points: u64,
+    pub insights_snapshots: u64,
+    /// Rollup rows per resolution
+    pub rollups: BTreeMap<String, u64>,
+}
+
+/// Downsample raw points about to expire, then prune every tier.
+pub async fn apply_retention(
+    repository: &FeeRepository,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> Result<RetentionReport, sqlx::Error> {
+    let raw_cutoff = policy.raw_cutoff(now);
+    let mut report = RetentionReport {
+        downsampled_buckets: repository.downsample_before(raw_cutoff, ROLLUP_RESOLUTIONS).await?,
+        fee_points: repository.prune_older_than(raw_cutoff).await?,
+        insights_snapshots: repository.prune_insights_snapshots_older_than(raw_cutoff).await?,
+        ..RetentionReport::default()
+    };
+    for resolution in ROLLUP_RESOLUTIONS {
+        if let Some(cutoff) = policy.rollup_cutoff(resolution, now) {
+            let pruned = repository.prune_rollups_older_than(resolution, cutoff).await?;
+            report.rollups.insert(resolution.to_string(), pruned);
+        }
+    }
+    Ok(report)
+}
+
+/// What [`apply_retention`] would prune, without changing anything.
+/// Downsampling is not counted.
+pub async fn preview_retention(
+    repository: &FeeRepository,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> Result<RetentionReport, sqlx::Error> {
+    let raw_cutoff = policy.raw_cutoff(now);
+    let mut report = RetentionReport {
+        fee_points: repository.count_older_than(raw_cutoff).await? as u64,
+        insights_snapshots: repository.count_insights_snapshots_older_than(raw_cutoff).await? as u64,
+        ..RetentionReport::default()
+    };
+    for resolution in ROLLUP_RESOLUTIONS {
+        if let Some(cutoff) = policy.rollup_cutoff(resolution, now) {
+            let count = repository.count_rollups_older_than(resolution, cutoff).await?;
+            report.rollups.insert(resolution.to_string(), count as u64);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_pool;
+    use crate::insights::FeeDataPoint;
+
+    #[test]
+    fn picks_finest_tier_still_covering_the_range() {
+        let policy = RetentionPolicy::default();
+        let now = Utc::now();
+
+        assert!(policy.covers_raw(now - Duration::days(6), now));
+        assert!(!policy.covers_raw(now - Duration::days(8), now));
+        assert_eq!(policy.finest_covering("1m", now - Duration::days(30), now), Some("1m"));
+        assert_eq!(policy.finest_covering("1m", now - Duration::days(120), now), Some("1h"));
+        assert_eq!(policy.finest_covering("1d", now - Duration::days(900), now), Some("1d"));
+        assert_eq!(policy.finest_covering("2m", now, now), None);
+    }
+
+    #[tokio::test]
+    async fn downsamples_expiring_points_before_pruning_them() {
+        let repository = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let now = Utc::now();
+        // Imported without rollups, and older than raw retention
+        let old = now - Duration::days(10);
+        let points: Vec<FeeDataPoint> = (0..3)
+            .map(|i| FeeDataPoint {
+                fee_amount: 100 * (i + 1),
+                timestamp: old + Duration::seconds(i as i64),
+                transaction_hash: format!("old-{}", i),
+                ledger_sequence: i,
+            })
+            .collect();
+        repository.insert_fee_points(&points).await.unwrap();
+
+        let policy = RetentionPolicy::default();
+        let preview = preview_retention(&repository, &policy, now).await.unwrap();
+        assert_eq!(preview.fee_points, 3);
+
+        let report = apply_retention(&repository, &policy, now).await.unwrap();
+        assert_eq!(report.fee_points, 3);
+        assert!(report.downsampled_buckets >= ROLLUP_RESOLUTIONS.len());
+        assert!(repository.fetch_since(old - Duration::days(1)).await.unwrap().is_empty());
+
+        let hourly = repository
+            .fetch_rollups("1h", old - Duration::hours(1), old + Duration::hours(1))
+            .await
+            .unwrap();
+        let count: u64 = hourly.iter().map(|r| r.sample_count).sum();
+        assert_eq!(count, 3);
+
+        // Past the 1m tier's retention its rollups go too; hourly ones stay
+        let later = now + Duration::days(90);
+        let report = apply_retention(&repository, &policy, later).await.unwrap();
+        assert!(report.rollups["1m"] >= 1);
+        assert!(!report.rollups.contains_key("1h"));
+    }
+}
@@ -4,28 +4,46 @@
 //! Horizon provider, pushes it into the history store, runs the
 //! insights engine, and persists new points to SQLite.
 //!
+//! Spike events are checked against the enabled alert rules and each alert
+//! is dispatched. Each tick's points, insights, spike events and alerts are
+//! published to the live feed when one is given.
+//!
 //! Network errors are retried with exponential backoff + jitter (Issue #10).
 //! Parse errors are not retried — malformed data won't fix itself.
 //! DB write errors are logged but never crash the scheduler.
+//!
+//! Times come from the insights engine's clock, so a simulated clock
+//! drives pruning and baseline refreshes as well as the engine itself.
+//!
+//! The insights engine is snapshotted to SQLite on an interval and at
+//! shutdown, and restored from the latest snapshot at startup. Snapshots
+//! hold each rolling window's bucketed aggregates, not raw points; windows
+//! a snapshot lacks are rebuilt from rollups. Each save also records the
+//! computed insights for point-in-time queries.
 
 use std::sync::Arc;
 use std::time::Duration;
 
-use chrono::Utc;
-use tokio::signal;
-use tokio::sync::RwLock;
+use chrono::{DateTime, DurationRound, Utc};
+use tokio::sync::{watch, RwLock};
 use tokio::time;
 
+use crate::alerts;
 use crate::insights::{
     FeeDataProvider, FeeInsightsEngine,
 };
 use crate::insights::error::ProviderError;
-use crate::insights::types::FeeDataPoint;
-use crate::repository::FeeRepository;
+use crate::insights::seasonality::{rollup_resolution, slot_start, utc_offset, SeasonalityProfile};
+use crate::insights::calculator::WindowBucket;
+use crate::insights::types::{parse_duration, FeeDataPoint, SpikeSeverity, TimeWindow};
+use crate::live::LiveFeed;
+use crate::repository::{FeeRepository, ROLLUP_RESOLUTIONS};
+use crate::retention::{self, RetentionPolicy};
+use crate::shutdown::Shutdown;
 use crate::store::FeeHistoryStore;
 use crate::metrics::AppMetrics;
 
-/// Run the fee polling loop until Ctrl+C is received.
+/// Run the fee polling loop until Ctrl+C or SIGTERM is received.
 /// Uses defaults for retry and retention — prefer `run_fee_polling_with_retry` in production.
 pub async fn run_fee_polling(
     horizon_provider: Arc<dyn FeeDataProvider + Send + Sync>,
@@ -47,6 +65,17 @@ pub async fn run_fee_polling(
     .await
 }
 
+/// Poll settings that can change while the poller runs, e.g. on a config reload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollSettings {
+    pub poll_interval_seconds: u64,
+    pub retention: RetentionPolicy,
+    /// Whether spikes matching the alert rules dispatch alerts
+    pub alerts_enabled: bool,
+    /// Lowest severity any alert rule fires at
+    pub alerts_min_severity: Option<SpikeSeverity>,
+}
+
 /// Full version with configurable retry parameters and optional DB persistence.
 #[allow(clippy::too_many_arguments)]
 pub async fn run_fee_polling_with_retry(
@@ -60,13 +89,52 @@ pub async fn run_fee_polling_with_retry(
     storage_retention_days: u64,
     metrics: Option<Arc<AppMetrics>>,
 ) {
-    let mut interval = time::interval(Duration::from_secs(poll_interval_seconds));
+    let (_settings_tx, settings) = watch::channel(PollSettings {
+        poll_interval_seconds,
+        retention: RetentionPolicy::with_raw_days(storage_retention_days),
+        alerts_enabled: true,
+        alerts_min_severity: None,
+    });
+    run_fee_polling_with_settings(
+        horizon_provider,
+        history_store,
+        insights_engine,
+        settings,
+        max_retry_attempts,
+        base_retry_delay_ms,
+        repository,
+        metrics,
+        None,
+        Shutdown::on_signal(),
+    )
+    .await
+}
+
+/// Like `run_fee_polling_with_retry`, but the poll interval and retention
+/// follow `settings`. A new interval takes effect from the next tick.
+/// Polling stops when `shutdown` is requested.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_fee_polling_with_settings(
+    horizon_provider: Arc<dyn FeeDataProvider + Send + Sync>,
+    history_store: Arc<RwLock<FeeHistoryStore>>,
+    insights_engine: Arc<RwLock<FeeInsightsEngine>>,
+    mut settings: watch::Receiver<PollSettings>,
+    max_retry_attempts: u32,
+    base_retry_delay_ms: u64,
+    repository: Option<Arc<FeeRepository>>,
+    metrics: Option<Arc<AppMetrics>>,
+    live: Option<Arc<LiveFeed>>,
+    mut shutdown: Shutdown,
+) {
+    let mut current = settings.borrow_and_update().clone();
+    let mut interval = time::interval(Duration::from_secs(current.poll_interval_seconds.max(1)));
+    let mut settings_open = true;
 
     tracing::info!(
         "Fee polling started (interval: {}s, max retries: {}, retention: {}d)",
-        poll_interval_seconds,
+        current.poll_interval_seconds,
         max_retry_attempts,
-        storage_retention_days,
+        current.retention.raw.num_days(),
     );
 
     loop {
@@ -79,13 +147,32 @@ pub async fn run_fee_polling_with_retry(
                     max_retry_attempts,
                     base_retry_delay_ms,
                     repository.as_deref(),
-                    storage_retention_days,
+                    &current,
                     metrics.as_deref(),
+                    live.as_deref(),
                 ).await;
             }
 
-            _ = signal::ctrl_c() => {
-                tracing::info!("Shutdown signal received. Stopping polling.");
+            changed = settings.changed(), if settings_open => {
+                if changed.is_err() {
+                    settings_open = false;
+                    continue;
+                }
+                let updated = settings.borrow_and_update().clone();
+                if updated.poll_interval_seconds != current.poll_interval_seconds {
+                    let period = Duration::from_secs(updated.poll_interval_seconds.max(1));
+                    interval = time::interval_at(time::Instant::now() + period, period);
+                }
+                tracing::info!(
+                    "Poll settings updated (interval: {}s, retention: {}d)",
+                    updated.poll_interval_seconds,
+                    updated.retention.raw.num_days(),
+                );
+                current = updated;
+            }
+
+            _ = shutdown.requested() => {
+                tracing::info!("Shutdown requested. Stopping polling.");
                 break;
             }
         }
@@ -95,16 +182,19 @@ pub async fn run_fee_polling_with_retry(
 }
 
 /// Execute a single poll cycle with retry and optional persistence.
-async fn poll_once(
+/// Returns `false` when no fee data could be fetched.
+#[allow(clippy::too_many_arguments)]
+pub async fn poll_once(
     horizon_provider: &Arc<dyn FeeDataProvider + Send + Sync>,
     history_store: &Arc<RwLock<FeeHistoryStore>>,
     insights_engine: &Arc<RwLock<FeeInsightsEngine>>,
     max_retry_attempts: u32,
     base_retry_delay_ms: u64,
     repository: Option<&FeeRepository>,
-    storage_retention_days: u64,
+    settings: &PollSettings,
     metrics: Option<&AppMetrics>,
-) {
+    live: Option<&LiveFeed>,
+) -> bool {
     if let Some(m) = metrics {
         m.polls_total.inc();
     }
@@ -125,13 +215,13 @@ async fn poll_once(
                 "All {} retry attempts exhausted — skipping tick",
                 max_retry_attempts
             );
-            return;
+            return false;
         }
     };
 
     if points.is_empty() {
         tracing::warn!("Provider returned no fee data points this tick");
-        return;
+        return false;
     }
 
     // Push into in-memory store
@@ -147,26 +237,35 @@ async fn poll_once(
         }
     }
 
+    // Keep the hour-of-week spike baseline fresh when it is enabled
+    if let Some(repo) = repository {
+        refresh_seasonal_baseline(insights_engine, repo).await;
+    }
+
     // Run insights engine
-    {
+    let (insights, spike_events, closed_extremes, now) = {
         let mut engine = insights_engine.write().await;
-        match engine.process_fee_data(&points).await {
+        let insights = match engine.process_fee_data(&points).await {
             Ok(update) => {
                 tracing::info!(
                     "Insights updated — {} points processed, short-term avg: {:.1} stroops",
                     update.data_points_processed,
-                    update.insights.rolling_averages.short_term.value,
+                    update.insights.rolling_averages.current_value(),
                 );
                 if let Some(m) = metrics {
-                    m.current_avg_fee.set(update.insights.rolling_averages.short_term.value);
-                    m.spikes_detected_total.inc_by(update.insights.recent_spikes.len() as f64);
+                    m.current_avg_fee.set(update.insights.rolling_averages.current_value());
+                    m.spikes_detected_total.inc_by(update.insights.congestion_trends.recent_spikes.len() as f64);
                 }
+                Some(update.insights)
             }
             Err(err) => {
                 tracing::error!("Insights engine error: {}", err);
+                None
             }
-        }
-    }
+        };
+        let now = engine.clock().now();
+        (insights, engine.take_spike_events(), engine.take_closed_extreme_periods(), now)
+    };
 
     // Persist to DB (non-fatal on error)
     if let Some(repo) = repository {
@@ -179,12 +278,257 @@ async fn poll_once(
             }
         }
 
-        let cutoff = Utc::now() - chrono::Duration::days(storage_retention_days as i64);
-        match repo.prune_older_than(cutoff).await {
-            Ok(n) if n > 0 => tracing::debug!("Pruned {} old fee points from DB", n),
+        for resolution in ROLLUP_RESOLUTIONS {
+            match repo.merge_rollups(resolution, &points).await {
+                Ok(n) => tracing::debug!("Updated {} {} fee rollup buckets", n, resolution),
+                Err(err) => tracing::warn!("Failed to update {} fee rollups: {}", resolution, err),
+            }
+        }
+
+        let rules = if spike_events.is_empty() || !settings.alerts_enabled {
+            Vec::new()
+        } else {
+            let rules = repo.list_alert_configs().await.unwrap_or_else(|err| {
+                tracing::warn!("Failed to load alert rules: {}", err);
+                Vec::new()
+            });
+            alerts::apply_min_severity(rules, settings.alerts_min_severity.as_ref())
+        };
+
+        for event in &spike_events {
+            tracing::info!(
+                "Spike {} {:?} — peak {} stroops, {:?}",
+                event.spike.id,
+                event.kind,
+                event.spike.peak_fee,
+                event.spike.severity,
+            );
+            // The stored severity says which rules have already fired
+            let previous = match repo.get_spike(&event.spike.id).await {
+                Ok(stored) => stored.map(|spike| spike.severity),
+                Err(err) => {
+                    tracing::warn!("Failed to load spike {}: {}", event.spike.id, err);
+                    None
+                }
+            };
+            if let Err(err) = repo.upsert_spike(&event.spike, now).await {
+                tracing::warn!("Failed to persist spike {}: {}", event.spike.id, err);
+            }
+            alerts::alert_spike(&event.spike, previous.as_ref(), &rules, repo, live).await;
+        }
+
+        for period in &closed_extremes {
+            if let Err(err) = repo.insert_extreme_period(period).await {
+                tracing::warn!("Failed to persist extremes period {}: {}", period.period_start, err);
+            }
+        }
+
+        // Expiring raw points are rolled up before they are pruned
+        match retention::apply_retention(repo, &settings.retention, now).await {
+            Ok(report) if report.fee_points > 0 => tracing::debug!(
+                "Pruned {} old fee points from DB after downsampling {} rollup buckets",
+                report.fee_points,
+                report.downsampled_buckets,
+            ),
             Ok(_) => {}
-            Err(err) => tracing::warn!("Failed to prune old fee points: {}", err),
+            Err(err) => tracing::warn!("Failed to apply retention: {}", err),
+        }
+    }
+
+    // Published once persisted, so clients can query what they are told about
+    if let Some(live) = live {
+        live.publish_tick(&points, insights.as_ref(), now).await;
+        live.publish_spike_events(&spike_events, now);
+    }
+
+    true
+}
+
+/// Restore the insights engine at startup.
+///
+/// Loads the latest engine snapshot and replays only the points persisted
+/// after it. Without a usable snapshot, every point since `fallback_since`
+/// is replayed instead. Rolling windows the snapshot does not cover are then
+/// rebuilt from rollups. Returns the number of points replayed.
+pub async fn rehydrate_insights_engine(
+    insights_engine: &Arc<RwLock<FeeInsightsEngine>>,
+    repository: &FeeRepository,
+    fallback_since: DateTime<Utc>,
+) -> usize {
+    let all_windows = insights_engine.read().await.get_config().time_windows.clone();
+
+    // Newest point covered by a restored snapshot, and the windows it lacks
+    let (restored_up_to, uncovered) = match repository.load_engine_snapshot().await {
+        Ok(Some(snapshot)) => {
+            let taken_at = snapshot.taken_at;
+            let last_point_at = snapshot.last_point_at;
+            match insights_engine.write().await.restore_snapshot(snapshot) {
+                Ok(uncovered) => {
+                    tracing::info!("Restored insights engine snapshot taken at {}", taken_at);
+                    (last_point_at, uncovered)
+                }
+                Err(err) => {
+                    tracing::warn!("Ignoring insights engine snapshot: {}", err);
+                    (None, all_windows)
+                }
+            }
+        }
+        Ok(None) => (None, all_windows),
+        Err(err) => {
+            tracing::warn!("Failed to load insights engine snapshot: {}", err);
+            (None, all_windows)
+        }
+    };
+
+    let points = match repository.fetch_since(restored_up_to.unwrap_or(fallback_since)).await {
+        Ok(points) => points,
+        Err(err) => {
+            tracing::warn!("Failed to load fee points to replay: {}", err);
+            return 0;
+        }
+    };
+
+    // Points sharing the snapshot's newest timestamp came from the same
+    // poll and were already processed
+    let delta: Vec<FeeDataPoint> = match restored_up_to {
+        Some(last_point_at) => points.into_iter().filter(|p| p.timestamp > last_point_at).collect(),
+        None => points,
+    };
+
+    let mut engine = insights_engine.write().await;
+    if !delta.is_empty() {
+        if let Err(err) = engine.process_fee_data(&delta).await {
+            tracing::warn!("Insights engine error during rehydration: {}", err);
         }
+        // Spikes replayed here were persisted when they were first detected
+        engine.take_spike_events();
+    }
+    rebuild_windows_from_rollups(&mut engine, repository, &uncovered).await;
+    delta.len()
+}
+
+/// Rebuild `windows` of the insights engine from persisted history: the
+/// rollups at each window's bucket resolution up to the current bucket, and
+/// the raw points since it. Windows whose history fails to load are left
+/// as they are.
+pub async fn rebuild_windows_from_rollups(
+    engine: &mut FeeInsightsEngine,
+    repository: &FeeRepository,
+    windows: &[TimeWindow],
+) {
+    let now = engine.clock().now();
+    for window in windows {
+        let resolution = engine.window_bucket_resolution(window);
+        let Some(width) = parse_duration(resolution) else {
+            continue;
+        };
+        let Ok(current_bucket) = now.duration_trunc(width) else {
+            continue;
+        };
+
+        let history = async {
+            let rollups = repository
+                .fetch_rollups(resolution, now - window.duration - width, current_bucket)
+                .await?;
+            let points = repository.fetch_since(current_bucket).await?;
+            Ok::<_, sqlx::Error>((rollups, points))
+        };
+        match history.await {
+            Ok((rollups, points)) => {
+                let buckets = rollups
+                    .into_iter()
+                    .map(|rollup| WindowBucket { start: rollup.bucket_start, sketch: rollup.sketch })
+                    .collect();
+                engine.rebuild_window(window, buckets, &points);
+                tracing::info!("Rebuilt window '{}' from {} rollups", window.name, resolution);
+            }
+            Err(err) => tracing::warn!("Failed to rebuild window '{}' from rollups: {}", window.name, err),
+        }
+    }
+}
+
+/// Save a snapshot of the insights engine and, once it has processed data,
+/// record its current insights. Errors are logged.
+pub async fn save_engine_snapshot(
+    insights_engine: &Arc<RwLock<FeeInsightsEngine>>,
+    repository: &FeeRepository,
+) {
+    let (snapshot, insights) = {
+        let engine = insights_engine.read().await;
+        (engine.snapshot(), engine.get_last_update().map(|_| engine.get_current_insights()))
+    };
+    match repository.save_engine_snapshot(&snapshot).await {
+        Ok(()) => tracing::debug!("Saved insights engine snapshot ({} windows)", snapshot.windows.len()),
+        Err(err) => tracing::warn!("Failed to save insights engine snapshot: {}", err),
+    }
+    if let Some(insights) = insights {
+        if let Err(err) = repository.insert_insights_snapshot(&insights).await {
+            tracing::warn!("Failed to record insights snapshot: {}", err);
+        }
+    }
+}
+
+/// Snapshot the insights engine every `interval_seconds` until `shutdown` is requested.
+/// The final snapshot at shutdown is left to the caller, after polling has stopped.
+pub async fn run_engine_snapshots(
+    insights_engine: Arc<RwLock<FeeInsightsEngine>>,
+    repository: Arc<FeeRepository>,
+    interval_seconds: u64,
+    mut shutdown: Shutdown,
+) {
+    let period = Duration::from_secs(interval_seconds.max(1));
+    let mut interval = time::interval_at(time::Instant::now() + period, period);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                save_engine_snapshot(&insights_engine, &repository).await;
+            }
+
+            _ = shutdown.requested() => break,
+        }
+    }
+}
+
+/// Rebuild the engine's hour-of-week profile from the hourly rollups when the
+/// seasonal baseline mode is configured and the current profile is stale.
+/// Errors are logged and the engine keeps using its previous baseline.
+async fn refresh_seasonal_baseline(
+    insights_engine: &Arc<RwLock<FeeInsightsEngine>>,
+    repository: &FeeRepository,
+) {
+    let (now, refresh_due) = {
+        let engine = insights_engine.read().await;
+        let now = engine.clock().now();
+        (now, engine.seasonal_refresh_due(now))
+    };
+    let weeks = match refresh_due {
+        Some(weeks) => weeks,
+        None => return,
+    };
+
+    // Stop before the current slot, so a spike in progress is not part of
+    // the baseline it is measured against. Raw points are pruned long before
+    // `weeks` is reached, so the hourly rollups are read instead.
+    let to = slot_start(now, utc_offset());
+    let from = to - chrono::Duration::weeks(weeks as i64);
+    match repository.fetch_rollups(rollup_resolution(utc_offset()), from, to).await {
+        Ok(rollups) => {
+            let profile = SeasonalityProfile::from_rollups(
+                rollups.iter().map(|rollup| (rollup.bucket_start, &rollup.sketch)),
+                utc_offset(),
+                from,
+                to,
+                weeks,
+            );
+            tracing::debug!(
+                "Refreshed seasonal baseline from {} points over {} week(s)",
+                profile.total_transactions,
+                weeks,
+            );
+            insights_engine.write().await.set_seasonal_profile(profile);
+        }
+        Err(err) => tracing::warn!("Failed to refresh seasonal baseline: {}", err),
     }
 }
 
@@ -240,11 +584,12 @@ pub async fn fetch_with_retry(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
+    use chrono::{DateTime, Utc};
 
     use crate::insights::{FeeInsightsEngine, InsightsConfig};
     use crate::insights::error::ProviderError;
     use crate::insights::types::FeeDataPoint;
+    use crate::live::Topic;
     use crate::services::mock_horizon::MockHorizonClient;
     use crate::store::{FeeHistoryStore, DEFAULT_CAPACITY};
 
@@ -265,6 +610,15 @@ mod tests {
         Arc::new(RwLock::new(FeeInsightsEngine::new(InsightsConfig::default())))
     }
 
+    fn default_settings() -> PollSettings {
+        PollSettings {
+            poll_interval_seconds: 60,
+            retention: RetentionPolicy::default(),
+            alerts_enabled: true,
+            alerts_min_severity: None,
+        }
+    }
+
     // ---- poll_once tests ----
 
     #[tokio::test]
@@ -275,7 +629,7 @@ mod tests {
         let store = make_shared_store();
         let engine = make_shared_engine();
 
-        poll_once(&provider, &store, &engine, 3, 0, None, 7, None).await;
+        assert!(poll_once(&provider, &store, &engine, 3, 0, None, &default_settings(), None, None).await);
 
         assert_eq!(store.read().await.len(), 3);
     }
@@ -288,11 +642,28 @@ mod tests {
         let store = make_shared_store();
         let engine = make_shared_engine();
 
-        poll_once(&provider, &store, &engine, 3, 0, None, 7, None).await;
+        poll_once(&provider, &store, &engine, 3, 0, None, &default_settings(), None, None).await;
 
         assert!(engine.read().await.get_last_update().is_some());
     }
 
+    #[tokio::test]
+    async fn poll_once_publishes_tick_to_live_feed() {
+        let points = vec![make_point(100), make_point(150)];
+        let provider: Arc<dyn FeeDataProvider + Send + Sync> =
+            Arc::new(MockHorizonClient::new().with_fees(points));
+        let store = make_shared_store();
+        let engine = make_shared_engine();
+        let live = LiveFeed::new("testnet");
+        let mut updates = live.subscribe();
+
+        poll_once(&provider, &store, &engine, 3, 0, None, &default_settings(), None, Some(&live)).await;
+
+        assert_eq!(updates.recv().await.unwrap().topic, Topic::FeesPoints);
+        assert_eq!(updates.recv().await.unwrap().topic, Topic::Insights);
+        assert_eq!(live.snapshot(Topic::FeesPoints).unwrap().data.as_array().unwrap().len(), 2);
+    }
+
     #[tokio::test]
     async fn poll_once_on_provider_error_does_not_push_to_store() {
         let provider: Arc<dyn FeeDataProvider + Send + Sync> = Arc::new(
@@ -301,7 +672,7 @@ mod tests {
         let store = make_shared_store();
         let engine = make_shared_engine();
 
-        poll_once(&provider, &store, &engine, 1, 0, None, 7, None).await;
+        assert!(!poll_once(&provider, &store, &engine, 1, 0, None, &default_settings(), None, None).await);
 
         assert!(store.read().await.is_empty());
     }
@@ -314,8 +685,8 @@ mod tests {
         let store = make_shared_store();
         let engine = make_shared_engine();
 
-        poll_once(&provider, &store, &engine, 3, 0, None, 7, None).await;
-        poll_once(&provider, &store, &engine, 3, 0, None, 7, None).await;
+        poll_once(&provider, &store, &engine, 3, 0, None, &default_settings(), None, None).await;
+        poll_once(&provider, &store, &engine, 3, 0, None, &default_settings(), None, None).await;
 
         assert_eq!(store.read().await.len(), 4);
     }
@@ -327,11 +698,202 @@ mod tests {
         let store = make_shared_store();
         let engine = make_shared_engine();
 
-        poll_once(&provider, &store, &engine, 3, 0, None, 7, None).await;
+        poll_once(&provider, &store, &engine, 3, 0, None, &default_settings(), None, None).await;
 
         assert!(store.read().await.is_empty());
     }
 
+    #[tokio::test]
+    async fn poll_once_refreshes_seasonal_baseline_from_repository() {
+        use crate::db::create_pool;
+        use crate::insights::config::BaselineMode;
+
+        let repo = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let last_hour = make_point_at(100, Utc::now() - chrono::Duration::hours(1));
+        // Points in the current hour-of-week slot are left out of its baseline
+        let recent = [last_hour, make_point(900)];
+        repo.insert_fee_points(&recent).await.unwrap();
+        repo.merge_rollups("1h", &recent).await.unwrap();
+        // Raw points this old are pruned; only their hourly rollup is left
+        let pruned = make_point_at(300, Utc::now() - chrono::Duration::weeks(3));
+        repo.merge_rollups("1h", &[pruned]).await.unwrap();
+
+        let mut config = InsightsConfig::default();
+        config.spike_detection.baseline_mode = BaselineMode::HourOfWeek { weeks: 4, min_samples: 1 };
+        let engine = Arc::new(RwLock::new(FeeInsightsEngine::new(config)));
+
+        let provider: Arc<dyn FeeDataProvider + Send + Sync> =
+            Arc::new(MockHorizonClient::new().with_fees(vec![make_point(150)]));
+        let store = make_shared_store();
+
+        poll_once(&provider, &store, &engine, 3, 0, Some(&repo), &default_settings(), None, None).await;
+
+        let engine = engine.read().await;
+        let profile = engine.get_seasonal_profile().expect("profile should be loaded");
+        assert_eq!(profile.weeks, 4);
+        assert_eq!(profile.total_transactions, 2);
+    }
+
+    #[tokio::test]
+    async fn poll_once_updates_rollups_in_repository() {
+        use crate::db::create_pool;
+
+        let repo = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let provider: Arc<dyn FeeDataProvider + Send + Sync> = Arc::new(
+            MockHorizonClient::new().with_fees(vec![make_point(100), make_point(200)]),
+        );
+        let store = make_shared_store();
+        let engine = make_shared_engine();
+
+        poll_once(&provider, &store, &engine, 3, 0, Some(&repo), &default_settings(), None, None).await;
+
+        let now = Utc::now();
+        for resolution in ROLLUP_RESOLUTIONS {
+            let rollups = repo
+                .fetch_rollups(
+                    resolution,
+                    now - chrono::Duration::days(2),
+                    now + chrono::Duration::minutes(1),
+                )
+                .await
+                .unwrap();
+            let total: u64 = rollups.iter().map(|r| r.sample_count).sum();
+            assert_eq!(total, 2, "{} rollups", resolution);
+        }
+    }
+
+    #[tokio::test]
+    async fn poll_once_persists_spike_lifecycle_and_alerts_once() {
+        use crate::db::create_pool;
+
+        let repo = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        repo.insert_alert_config("https://hooks.example.com/fees", "Minor").await.unwrap();
+        let live = LiveFeed::new("testnet");
+        let mut config = InsightsConfig::default();
+        config.spike_detection.minimum_spike_duration = chrono::Duration::zero();
+        let engine = Arc::new(RwLock::new(FeeInsightsEngine::new(config)));
+        let store = make_shared_store();
+
+        // Average 400, so the 1000 stroop fees are a spike still open at the end of the tick
+        let spiking: Arc<dyn FeeDataProvider + Send + Sync> = Arc::new(
+            MockHorizonClient::new().with_fees(vec![
+                make_point(100),
+                make_point(100),
+                make_point(100),
+                make_point(100),
+                make_point(1000),
+                make_point(1000),
+            ]),
+        );
+        poll_once(&spiking, &store, &engine, 3, 0, Some(&repo), &default_settings(), None, Some(&live)).await;
+
+        let spikes = repo.query_spikes(None, None, None, 10).await.unwrap();
+        assert_eq!(spikes.len(), 1);
+        assert!(spikes[0].is_open());
+        assert!(live.snapshot(Topic::Alerts).is_some());
+
+        // Fees recover on the next tick, closing the same spike
+        let recovered: Arc<dyn FeeDataProvider + Send + Sync> =
+            Arc::new(MockHorizonClient::new().with_fees(vec![make_point(100)]));
+        poll_once(&recovered, &store, &engine, 3, 0, Some(&repo), &default_settings(), None, Some(&live)).await;
+
+        let spikes = repo.query_spikes(None, None, None, 10).await.unwrap();
+        assert_eq!(spikes.len(), 1);
+        assert!(!spikes[0].is_open());
+        assert_eq!(repo.query_alert_history(10, None, None).await.unwrap().len(), 1);
+    }
+
+    // ---- engine snapshot tests ----
+
+    fn make_point_at(fee_amount: u64, timestamp: DateTime<Utc>) -> FeeDataPoint {
+        FeeDataPoint {
+            timestamp,
+            ..make_point(fee_amount)
+        }
+    }
+
+    #[tokio::test]
+    async fn rehydrate_restores_snapshot_and_replays_only_newer_points() {
+        use crate::db::create_pool;
+
+        let repo = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let now = Utc::now();
+        let seen: Vec<FeeDataPoint> = [100, 200, 300]
+            .into_iter()
+            .map(|fee| make_point_at(fee, now - chrono::Duration::minutes(10)))
+            .collect();
+        repo.insert_fee_points(&seen).await.unwrap();
+
+        let original = make_shared_engine();
+        original.write().await.process_fee_data(&seen).await.unwrap();
+        save_engine_snapshot(&original, &repo).await;
+
+        let newer = vec![make_point_at(400, now - chrono::Duration::minutes(1))];
+        repo.insert_fee_points(&newer).await.unwrap();
+
+        let restored = make_shared_engine();
+        let replayed =
+            rehydrate_insights_engine(&restored, &repo, now - chrono::Duration::hours(24)).await;
+
+        assert_eq!(replayed, 1);
+        let engine = restored.read().await;
+        let averages = engine.get_rolling_averages();
+        let shortest = averages.shortest().unwrap();
+        assert_eq!(shortest.sample_count, 4);
+        assert_eq!(shortest.value, 250.0);
+        assert_eq!(engine.get_last_point_at(), Some(newer[0].timestamp));
+    }
+
+    #[tokio::test]
+    async fn rehydrate_without_snapshot_replays_fallback_window() {
+        use crate::db::create_pool;
+
+        let repo = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let now = Utc::now();
+        repo.insert_fee_points(&[
+            make_point_at(100, now - chrono::Duration::hours(30)),
+            make_point_at(200, now - chrono::Duration::minutes(5)),
+            make_point_at(300, now - chrono::Duration::minutes(2)),
+        ])
+        .await
+        .unwrap();
+
+        let engine = make_shared_engine();
+        let replayed =
+            rehydrate_insights_engine(&engine, &repo, now - chrono::Duration::hours(24)).await;
+
+        assert_eq!(replayed, 2);
+    }
+
+    #[tokio::test]
+    async fn rehydrate_rebuilds_windows_without_a_snapshot_from_rollups() {
+        use crate::db::create_pool;
+
+        let repo = FeeRepository::new(create_pool("sqlite::memory:").await.unwrap());
+        let now = Utc::now();
+        let points = vec![
+            make_point_at(100, now - chrono::Duration::hours(3)),
+            make_point_at(300, now - chrono::Duration::hours(3)),
+            make_point_at(200, now),
+        ];
+        repo.insert_fee_points(&points).await.unwrap();
+        for resolution in ROLLUP_RESOLUTIONS {
+            repo.merge_rollups(resolution, &points).await.unwrap();
+        }
+
+        let engine = make_shared_engine();
+        let replayed =
+            rehydrate_insights_engine(&engine, &repo, now - chrono::Duration::hours(1)).await;
+        assert_eq!(replayed, 1);
+
+        // The 24h window is rebuilt from the older rollups and the newest
+        // raw point, each counted once
+        let averages = engine.read().await.get_rolling_averages();
+        assert_eq!(averages.get("long_term").unwrap().sample_count, 3);
+        assert_eq!(averages.get("long_term").unwrap().value, 200.0);
+        assert_eq!(averages.get("short_term").unwrap().sample_count, 1);
+    }
+
     // ---- fetch_with_retry tests ----
 
     #[tokio::test]
@@ -22,6 +22,19 @@ impl HorizonClient {
         }
     }
 
+    /// Like `new`, but every request fails once `timeout` has elapsed.
+    pub fn with_timeout(base_url: String, timeout: std::time::Duration) -> Self {
+        let http = Client::builder()
+            .no_proxy()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_else(|_| Client::new());
+        Self {
+            base_url,
+            http,
+        }
+    }
+
     pub fn base_url(&self) -> &str {
         &self.base_url
     }
@@ -0,0 +1,93 @@
+//! Process shutdown on Ctrl+C (SIGINT) or SIGTERM.
+//!
+//! One listener is installed per process and every long-running loop holds
+//! a clone of the resulting [`Shutdown`] handle, so the server, poller,
+//! snapshotter and reloader all stop on the same signal.
+
+use tokio::signal;
+use tokio::sync::watch;
+
+/// Cloneable handle that resolves once shutdown has been requested.
+#[derive(Debug, Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    /// Start listening for Ctrl+C and SIGTERM.
+    pub fn on_signal() -> Self {
+        let (trigger, shutdown) = Self::channel();
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            tracing::info!("Shutdown signal received");
+            let _ = trigger.send(true);
+        });
+        shutdown
+    }
+
+    /// A handle fired by sending `true` on the returned sender. Dropping the
+    /// sender without sending leaves the handle pending forever.
+    pub fn channel() -> (watch::Sender<bool>, Self) {
+        let (trigger, receiver) = watch::channel(false);
+        (trigger, Self(receiver))
+    }
+
+    /// Wait until shutdown is requested. Returns immediately once it has been.
+    pub async fn requested(&mut self) {
+        if self.0.wait_for(|stop| *stop).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal as unix_signal, SignalKind};
+
+        match unix_signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(err) => {
+                tracing::warn!("SIGTERM handler unavailable, stopping on Ctrl+C only: {}", err);
+                let _ = signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = signal::ctrl_c().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn every_clone_sees_the_request() {
+        let (trigger, shutdown) = Shutdown::channel();
+        let mut first = shutdown.clone();
+        let mut second = shutdown;
+
+        trigger.send(true).unwrap();
+
+        first.requested().await;
+        second.requested().await;
+        // Already requested, so waiting again returns at once
+        first.requested().await;
+    }
+
+    #[tokio::test]
+    async fn dropped_trigger_does_not_request_shutdown() {
+        let (trigger, mut shutdown) = Shutdown::channel();
+        drop(trigger);
+
+        let waited = tokio::time::timeout(Duration::from_millis(20), shutdown.requested()).await;
+        assert!(waited.is_err());
+    }
+}
@@ -51,6 +51,17 @@ impl FeeHistoryStore {
             .collect()
     }
 
+    /// Return all data points with `from <= timestamp <= to`, oldest first.
+    /// Bounding the end matters when `to` comes from a clock that is
+    /// behind the newest data, such as a replay clock.
+    pub fn get_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<FeeDataPoint> {
+        self.data
+            .iter()
+            .filter(|p| p.timestamp >= from && p.timestamp <= to)
+            .cloned()
+            .collect()
+    }
+
     /// Return the `n` most recent data points, oldest first.
     /// If fewer than `n` points exist, all points are returned.
     pub fn get_last_n(&self, n: usize) -> Vec<FeeDataPoint> {
@@ -58,6 +69,12 @@ impl FeeHistoryStore {
         self.data.iter().skip(skip).cloned().collect()
     }
 
+    /// Timestamp of the oldest data point held, if any. Ranges starting at
+    /// or after it are fully covered by the store.
+    pub fn oldest_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.data.iter().map(|p| p.timestamp).min()
+    }
+
     /// Number of data points currently held.
     pub fn len(&self) -> usize {
         self.data.len()
@@ -176,6 +193,22 @@ mod tests {
         assert_eq!(store.get_since(cutoff).len(), 2);
     }
 
+    // ---- get_between ----
+
+    #[test]
+    fn get_between_excludes_points_after_end() {
+        let mut store = FeeHistoryStore::new(10);
+        store.push(make_point(100, 60));
+        store.push(make_point(200, 30));
+        store.push(make_point(300, 5));
+
+        let now = Utc::now();
+        let result = store.get_between(now - Duration::minutes(61), now - Duration::minutes(10));
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[1].fee_amount, 200);
+    }
+
     // ---- get_last_n ----
 
     #[test]
@@ -214,4 +247,21 @@ mod tests {
         let store = FeeHistoryStore::new(10);
         assert!(store.get_last_n(5).is_empty());
     }
-}
\ No newline at end of file
+
+    // ---- oldest_timestamp ----
+
+    #[test]
+    fn oldest_timestamp_tracks_evictions() {
+        let mut store = FeeHistoryStore::new(2);
+        assert!(store.oldest_timestamp().is_none());
+
+        let oldest = make_point(100, 3);
+        let next = make_point(200, 2);
+        store.push(oldest.clone());
+        store.push(next.clone());
+        assert_eq!(store.oldest_timestamp(), Some(oldest.timestamp));
+
+        store.push(make_point(300, 1));
+        assert_eq!(store.oldest_timestamp(), Some(next.timestamp));
+    }
+}